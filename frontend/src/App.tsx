@@ -15,10 +15,12 @@ import {
   Plus,
   Settings,
 } from 'lucide-react'
-import type { ClipboardItem, Settings as SettingsType, ImageCache, SemanticStatus, Snippet } from './types'
+import type { ClipboardItem, HistoryPreview, Settings as SettingsType, SettingsError, MonitorStopped, DataDirMigrated, ImageCache, SemanticStatus, Snippet, WindowShown } from './types'
 import { isDarwin } from './utils/platform'
 import { useSemanticSearch } from './hooks/useSemanticSearch'
 import { useDebouncedValue } from './hooks/useDebouncedValue'
+import { searchTerms, matchesSearch } from './utils/helpers'
+import { loadFullItem } from './utils/items'
 
 import {
   ResizeHandle,
@@ -34,6 +36,7 @@ import {
   QuickMenu,
   SmartLists,
   PreviewPanel,
+  getPreviewSelection,
   TEXT_ITEM_HEIGHT,
   IMAGE_ITEM_HEIGHT,
   FILE_ITEM_HEIGHT,
@@ -45,15 +48,19 @@ import { Badge } from '@/components/ui/badge'
 function App() {
 
   // State
-  const [items, setItems] = useState<ClipboardItem[]>([])
+  // Rows from get_history_previews, plus full items added by events
+  const [items, setItems] = useState<(ClipboardItem | HistoryPreview)[]>([])
   const [selectedId, setSelectedId] = useState<number | null>(null)
   const [searchQuery, setSearchQuery] = useState('')
   const [imageCache, setImageCache] = useState<ImageCache>({})
   const [showExtensions, setShowExtensions] = useState(false)
+  // Text selected in the preview when the extension bar opened, if any
+  const [extensionInput, setExtensionInput] = useState<string | null>(null)
   const [showPreview, setShowPreview] = useState(false)
   const [semanticMode, setSemanticMode] = useState(false)
   const [semanticStatus, setSemanticStatus] = useState<SemanticStatus | null>(null)
   const [settingsError, setSettingsError] = useState<string | null>(null)
+  const [monitorError, setMonitorError] = useState<string | null>(null)
 
   // Snippets state
   const [viewMode, setViewMode] = useState<'history' | 'snippets'>('history')
@@ -72,31 +79,71 @@ function App() {
   const [settings, setSettings] = useState<SettingsType>({
     auto_cleanup_enabled: false,
     max_items: 100,
+    max_age_days: 0,
     hotkey_modifiers: isDarwin ? 'Meta+Shift' : 'Control+Shift',
     hotkey_key: 'KeyV',
     window_opacity: 0.95,
+    remember_window_size: true,
+    default_width: 450,
+    default_height: 400,
+    window_anchor: 'free',
+    target_monitor: '',
+    remember_selection: false,
     auto_paste_enabled: false,
+    after_select: '',
     extensions: [],
     semantic_search_enabled: false,
     embedding_api_url: '',
     embedding_api_key: '',
     embedding_api_model: '',
     embedding_api_dim: 256,
+    embedding_api_proxy: '',
     add_to_snippets_hotkey_enabled: true,
     add_to_snippets_hotkey_modifiers: isDarwin ? 'Meta+Shift' : 'Control+Shift',
     add_to_snippets_hotkey_key: 'KeyS',
+    copy_latest_hotkey_enabled: false,
+    copy_latest_hotkey_modifiers: isDarwin ? 'Meta+Alt' : 'Control+Alt',
+    copy_latest_hotkey_key: 'KeyV',
+    stack_mode_enabled: false,
+    stack_pop_hotkey_modifiers: isDarwin ? 'Meta+Alt' : 'Control+Alt',
+    stack_pop_hotkey_key: 'KeyN',
+    clear_clipboard_hotkey_enabled: false,
+    clear_clipboard_hotkey_modifiers: isDarwin ? 'Meta+Alt' : 'Control+Alt',
+    clear_clipboard_hotkey_key: 'KeyX',
+    clear_clipboard_delete_recent: 0,
     clipboard_poll_interval_ms: 100,
+    idle_threshold_secs: 120,
+    capture_text: true,
+    capture_images: true,
+    normalize_line_endings: false,
+    capture_transforms: [],
+    capture_rules: [],
+    max_image_megapixels: 40,
+    max_paste_dimension: 0,
+    inline_image_max_kb: 0,
+    prewarm_image_cache: false,
+    auto_backup_enabled: false,
+    backup_keep_count: 7,
     min_similarity_score: 0.2,
     max_embeddings_in_memory: 50000,
+    semantic_unload_idle_minutes: 30,
     content_truncate_length: 50,
+    preview_max_length: 2000,
     image_preview_max_width: 120,
     image_preview_max_height: 80,
     max_history_fetch: 10000,
     focus_delay_ms: 50,
     semantic_search_debounce_ms: 300,
+    theme_override: 'system',
+    tray_left_click: 'toggle_window',
+    tray_icon_theme: 'auto',
+    log_format: 'text',
+    launch_at_login: false,
   })
 
   const listRef = useRef<HTMLUListElement>(null)
+  // Index of the selection restored on window show; the list scrolls there instead of the top
+  const restoredIndexRef = useRef<number | null>(null)
   const inputRef = useRef<HTMLInputElement>(null)
 
   // Refs for global keydown
@@ -106,7 +153,12 @@ function App() {
   viewModeRef.current = viewMode
 
   // Semantic search hook
-  const { results: semanticResults, loading: semanticLoading, error: semanticError } = useSemanticSearch(
+  const {
+    results: semanticResults,
+    loading: semanticLoading,
+    error: semanticError,
+    errorCode: semanticErrorCode,
+  } = useSemanticSearch(
     semanticMode ? searchQuery : '',
     50,
     settings.semantic_search_debounce_ms,
@@ -118,6 +170,8 @@ function App() {
 
   // Debounced search query for filtering (150ms delay)
   const debouncedSearchLower = useDebouncedValue(searchLower, 150)
+  // Every term must match, in any order; "quoted phrases" must appear as written
+  const searchTermList = useMemo(() => searchTerms(debouncedSearchLower), [debouncedSearchLower])
 
   // Get today's date string for filtering
   const todayStr = useMemo(() => new Date().toISOString().split('T')[0], [])
@@ -168,12 +222,12 @@ function App() {
     }
 
     // Then apply search filter (debounced to avoid jank on large lists)
-    if (debouncedSearchLower) {
-      filtered = filtered.filter(item => item.content.toLowerCase().includes(debouncedSearchLower))
+    if (searchTermList.length > 0) {
+      filtered = filtered.filter(item => matchesSearch(searchTermList, item.content))
     }
 
     return filtered
-  }, [items, smartListFilter, debouncedSearchLower, semanticMode, semanticResults, searchQuery, todayStr, weekAgoStr])
+  }, [items, smartListFilter, searchTermList, semanticMode, semanticResults, searchQuery, todayStr, weekAgoStr])
 
   // Calculate counts for smart list badges (single pass)
   const smartListCounts = useMemo(() => {
@@ -199,12 +253,8 @@ function App() {
 
   // Cached filtered snippets - used in list render, empty state, and StatusBar
   const filteredSnippets = useMemo(() =>
-    snippets.filter(s =>
-      debouncedSearchLower.length === 0 ||
-      s.content.toLowerCase().includes(debouncedSearchLower) ||
-      (s.alias && s.alias.toLowerCase().includes(debouncedSearchLower))
-    ),
-    [snippets, debouncedSearchLower]
+    snippets.filter(s => matchesSearch(searchTermList, s.content, s.alias)),
+    [snippets, searchTermList]
   )
 
   // Virtual scrolling for history list - fixed height per item type
@@ -232,15 +282,12 @@ function App() {
   // Copy item to clipboard
   const copyItem = useCallback(async (item: ClipboardItem) => {
     try {
-      await invoke('copy_to_clipboard', { item })
-      await invoke('hide_window')
-      if (settings.auto_paste_enabled) {
-        await invoke('simulate_paste')
-      }
+      // The backend hides and/or pastes according to the after_select setting
+      await invoke('select_item', { id: item.id })
     } catch (error) {
       console.error('Failed to copy:', error)
     }
-  }, [settings.auto_paste_enabled])
+  }, [])
 
   // Delete item
   const deleteItem = useCallback(async (itemId: number) => {
@@ -277,23 +324,11 @@ function App() {
   // Copy snippet to clipboard
   const copySnippet = useCallback(async (snippet: Snippet) => {
     try {
-      // Create a ClipboardItem-like object for the copy_to_clipboard command
-      const item = {
-        id: snippet.id,
-        item_type: 'text',
-        content: snippet.content,
-        hash: '',
-        created_at: snippet.created_at
-      }
-      await invoke('copy_to_clipboard', { item })
-      await invoke('hide_window')
-      if (settings.auto_paste_enabled) {
-        await invoke('simulate_paste')
-      }
+      await invoke('select_snippet', { id: snippet.id })
     } catch (error) {
       console.error('Failed to copy snippet:', error)
     }
-  }, [settings.auto_paste_enabled])
+  }, [])
 
   // Delete snippet
   const deleteSnippet = useCallback(async (snippetId: number) => {
@@ -308,7 +343,9 @@ function App() {
 
   // Add to snippets
   const handleAddToSnippets = useCallback((item: ClipboardItem) => {
-    setAddDialogItem(item)
+    loadFullItem(item)
+      .then(setAddDialogItem)
+      .catch(error => console.error('Failed to load item:', error))
   }, [])
 
   // Confirm add snippet
@@ -398,6 +435,15 @@ function App() {
         return
       }
 
+      // Cmd/Ctrl+1-9 to copy (and auto-paste) the Nth newest history item
+      if (/^[1-9]$/.test(e.key) && (isDarwin ? e.metaKey : e.ctrlKey)) {
+        e.preventDefault()
+        invoke('copy_by_position', { n: parseInt(e.key) }).catch(error => {
+          console.error('Failed to copy by position:', error)
+        })
+        return
+      }
+
       // Esc: Close extensions or hide window
       if (e.key === 'Escape') {
         e.preventDefault()
@@ -572,6 +618,7 @@ function App() {
       case 'Tab':
         e.preventDefault()
         if (selectedId !== null && settings.extensions.length > 0) {
+          setExtensionInput(showPreview ? getPreviewSelection() : null)
           setShowExtensions(true)
         }
         break
@@ -605,7 +652,7 @@ function App() {
         break
       }
     }
-  }, [filteredItems, filteredSnippets, selectedId, selectedSnippetId, settings.extensions.length, copyItem, copySnippet, toggleFavorite, smartListTabs, smartListFilter])
+  }, [filteredItems, filteredSnippets, selectedId, selectedSnippetId, settings.extensions.length, showPreview, copyItem, copySnippet, toggleFavorite, smartListTabs, smartListFilter])
 
   // Load image URLs into cache for a batch of items
   const loadImageUrls = useCallback((imageItems: ClipboardItem[]) => {
@@ -628,10 +675,11 @@ function App() {
     })
   }, [])
 
-  // Load history (returns data without setting state - caller decides when to set)
-  const fetchHistory = useCallback(async (): Promise<ClipboardItem[] | null> => {
+  // Load history (returns data without setting state - caller decides when to set).
+  // Long text comes cut to preview_max_length; see loadFullItem for the rest.
+  const fetchHistory = useCallback(async (): Promise<HistoryPreview[] | null> => {
     try {
-      const result = await invoke<ClipboardItem[]>('get_history', { limit: settings.max_history_fetch })
+      const result = await invoke<HistoryPreview[]>('get_history_previews', { limit: settings.max_history_fetch })
       return result
     } catch (error) {
       console.error('[PowerClip] Failed to load history:', error)
@@ -677,10 +725,69 @@ function App() {
           .catch(() => {})
       }
     }
+    // Existing content copied again: move its row to the top instead of adding a duplicate
+    const onItemUpdated = (e: Event) => {
+      const { id, created_at } = (e as CustomEvent<{ id: number; created_at: string }>).detail
+      setItems(prev => {
+        const existing = prev.find(i => i.id === id)
+        if (!existing) return prev
+        return [{ ...existing, created_at }, ...prev.filter(i => i.id !== id)]
+      })
+    }
+    const onItemCopied = (e: Event) => {
+      const { id, last_used_at } = (e as CustomEvent<{ id: number; last_used_at: string | null }>).detail
+      if (!last_used_at) return
+      setItems(prev => prev.map(i =>
+        i.id === id ? { ...i, last_used_at, use_count: (i.use_count ?? 0) + 1 } : i
+      ))
+    }
     window.addEventListener('powerclip:new-item', onNewItem)
-    return () => window.removeEventListener('powerclip:new-item', onNewItem)
+    window.addEventListener('powerclip:item-updated', onItemUpdated)
+    window.addEventListener('powerclip:item-copied', onItemCopied)
+    return () => {
+      window.removeEventListener('powerclip:new-item', onNewItem)
+      window.removeEventListener('powerclip:item-updated', onItemUpdated)
+      window.removeEventListener('powerclip:item-copied', onItemCopied)
+    }
   }, [loadSettings, loadHistory, loadSemanticStatus, loadSnippets])
 
+  // Offer to remove an image item whose file was deleted outside PowerClip
+  useEffect(() => {
+    const handler = (e: Event) => {
+      const { id, error, missing } = (e as CustomEvent<{ id: number; error: string; missing?: boolean }>).detail
+      if (missing && window.confirm(`${error} Remove it from history?`)) {
+        deleteItem(id)
+      }
+    }
+    window.addEventListener('powerclip:copy-failed', handler)
+    return () => window.removeEventListener('powerclip:copy-failed', handler)
+  }, [deleteItem])
+
+  // Keep keyboard navigation going when the window stays open after a selection
+  useEffect(() => {
+    const handler = (e: Event) => {
+      const { action } = (e as CustomEvent<{ action: string }>).detail
+      if (action === 'stay_open') {
+        listRef.current?.focus()
+      }
+    }
+    window.addEventListener('powerclip:after-select', handler)
+    return () => window.removeEventListener('powerclip:after-select', handler)
+  }, [])
+
+  // Reload history after it was cleared from the tray or hotkey, or changed by another program
+  useEffect(() => {
+    const handler = () => loadHistory()
+    window.addEventListener('powerclip:history-cleared', handler)
+    window.addEventListener('powerclip:clipboard-cleared', handler)
+    window.addEventListener('powerclip:history-changed', handler)
+    return () => {
+      window.removeEventListener('powerclip:history-cleared', handler)
+      window.removeEventListener('powerclip:clipboard-cleared', handler)
+      window.removeEventListener('powerclip:history-changed', handler)
+    }
+  }, [loadHistory])
+
   // Listen for settings file changes
   useEffect(() => {
     const handler = () => loadSettings()
@@ -691,14 +798,46 @@ function App() {
   // Listen for settings errors
   useEffect(() => {
     const handler = (e: Event) => {
-      const error = (e as CustomEvent<string>).detail
-      setSettingsError(error)
+      const error = (e as CustomEvent<SettingsError>).detail
+      setSettingsError(error.line ? `${error.message} (line ${error.line})` : error.message)
       console.error('[PowerClip] Settings error:', error)
     }
     window.addEventListener('powerclip:settings-error', handler)
     return () => window.removeEventListener('powerclip:settings-error', handler)
   }, [])
 
+  // Listen for the clipboard monitor stalling
+  useEffect(() => {
+    const handler = (e: Event) => {
+      const { message, restarting } = (e as CustomEvent<MonitorStopped>).detail
+      console.error('[PowerClip] Clipboard monitor stopped:', message)
+      // A restarted monitor keeps recording, so only a stall waiting for a retry is shown
+      if (!restarting) setMonitorError(message)
+    }
+    window.addEventListener('powerclip:monitor-stopped', handler)
+    return () => window.removeEventListener('powerclip:monitor-stopped', handler)
+  }, [])
+
+  // Recording stays paused after the data folder moves, until PowerClip restarts
+  useEffect(() => {
+    const handler = (e: Event) => {
+      const { path } = (e as CustomEvent<DataDirMigrated>).detail
+      setMonitorError(`Data moved to ${path} - restart PowerClip to use it`)
+    }
+    window.addEventListener('powerclip:data-dir-migrated', handler)
+    return () => window.removeEventListener('powerclip:data-dir-migrated', handler)
+  }, [])
+
+  // New items stop being indexed once the embedding API rejects the key or model
+  useEffect(() => {
+    const handler = (e: Event) => {
+      console.warn('[PowerClip] Semantic indexing paused:', (e as CustomEvent<{ message: string }>).detail.message)
+      loadSemanticStatus()
+    }
+    window.addEventListener('powerclip:semantic-model-missing', handler)
+    return () => window.removeEventListener('powerclip:semantic-model-missing', handler)
+  }, [loadSemanticStatus])
+
   // Listen for add-to-snippets hotkey
   // The backend reads clipboard content and passes it as the event payload,
   // so this works even when the window is hidden and navigator.clipboard is unavailable.
@@ -724,7 +863,9 @@ function App() {
 
   // Reset on window show
   useEffect(() => {
-    const handler = async () => {
+    const handler = async (e: Event) => {
+      const restoreId = (e as CustomEvent<WindowShown | undefined>).detail?.selected_id ?? null
+      restoredIndexRef.current = null
       setShowExtensions(false)
       setShowPreview(false)
       setViewMode('history')
@@ -742,7 +883,13 @@ function App() {
       flushSync(() => {
         if (items && items.length > 0) {
           setItems(items)
-          setSelectedId(items[0].id)
+          const restoredIndex = restoreId === null ? -1 : items.findIndex(i => i.id === restoreId)
+          if (restoredIndex >= 0) {
+            restoredIndexRef.current = restoredIndex
+            setSelectedId(restoreId)
+          } else {
+            setSelectedId(items[0].id)
+          }
         } else {
           setItems([])
           setSelectedId(null)
@@ -766,7 +913,9 @@ function App() {
     if (listKey > 0) {
       // Use multiple attempts to ensure scroll reset works
       const resetScroll = () => {
-        if (listRef.current) {
+        if (restoredIndexRef.current !== null) {
+          historyVirtualizer.scrollToIndex(restoredIndexRef.current, { align: 'auto' })
+        } else if (listRef.current) {
           listRef.current.scrollTop = 0
         }
       }
@@ -775,8 +924,16 @@ function App() {
       setTimeout(resetScroll, 10)
       setTimeout(resetScroll, 50)
     }
+  // eslint-disable-next-line react-hooks/exhaustive-deps
   }, [listKey])
 
+  // Report the selection so it can be restored next time the window opens
+  useEffect(() => {
+    if (settings.remember_selection && viewMode === 'history') {
+      invoke('set_selected_item', { itemId: selectedId }).catch(() => {})
+    }
+  }, [selectedId, viewMode, settings.remember_selection])
+
   // Scroll to selected item when using arrow keys (not on window show)
   useEffect(() => {
     if (viewMode === 'history' && selectedId !== null) {
@@ -800,7 +957,7 @@ function App() {
   }, [settings.focus_delay_ms])
 
   return (
-    <div className="window-wrapper w-full h-full flex flex-col text-white relative" style={{ opacity: settings.window_opacity }}>
+    <div className="window-wrapper w-full h-full flex flex-col text-white relative">
       <WindowDragHandler>
         <div className="flex items-center gap-3 px-4 py-3 bg-secondary">
           {/* Search icon */}
@@ -836,7 +993,7 @@ function App() {
           {semanticError && semanticMode && (
             <Badge variant="destructive" className="flex-shrink-0" title={semanticError}>
               <AlertCircle className="w-3 h-3 mr-1" />
-              Search Error
+              {semanticErrorCode === 'api_not_configured' ? 'API Not Configured' : 'Search Error'}
             </Badge>
           )}
 
@@ -910,6 +1067,7 @@ function App() {
         <ExtensionBar
           extensions={settings.extensions}
           selectedItem={filteredItems.find(i => i.id === selectedId) || null}
+          inputOverride={extensionInput}
           onClose={() => setShowExtensions(false)}
           onCloseWindow={() => { setShowExtensions(false); setSearchQuery(''); invoke('hide_window').catch(() => {}) }}
         />
@@ -1041,6 +1199,7 @@ function App() {
         hotkeyModifiers={settings.hotkey_modifiers}
         hotkeyKey={settings.hotkey_key}
         settingsError={settingsError}
+        monitorError={monitorError}
         hasExtensions={settings.extensions.length > 0}
         hasSelection={selectedId !== null}
       />
@@ -10,6 +10,9 @@
  * - ArrowLeft/Right: Navigate between extensions
  * - Enter: Execute highlighted extension
  * - Esc / Tab: Close the bar
+ *
+ * When text was selected in the preview panel, extensions receive only that
+ * selection instead of the whole item.
  */
 
 import { useState, useEffect, useCallback, useRef } from 'react'
@@ -17,6 +20,8 @@ import { invoke } from '@tauri-apps/api/core'
 import { Loader2, Check, X, Zap } from 'lucide-react'
 import type { Extension, ClipboardItem } from '../types'
 import { logger } from '../utils/logger'
+import { runExtensionCommand } from '../utils/extensions'
+import { loadFullItem } from '../utils/items'
 import { cn } from '@/lib/utils'
 
 type BarState =
@@ -30,11 +35,14 @@ const FEEDBACK_DURATION_MS = 1500
 export function ExtensionBar({
   extensions,
   selectedItem,
+  inputOverride = null,
   onClose,
   onCloseWindow,
 }: {
   extensions: Extension[]
   selectedItem: ClipboardItem | null
+  /** Text to run extensions on instead of the item's content */
+  inputOverride?: string | null
   onClose: () => void
   onCloseWindow: () => void
 }) {
@@ -69,17 +77,13 @@ export function ExtensionBar({
     setBarState({ kind: 'running', index })
 
     try {
-      logger.info('Extension', `Running "${ext.name}" on item ${selectedItem.id}`)
-      const output = await invoke<string>('run_extension', {
-        command: ext.command,
-        content: selectedItem.content,
-        timeout: ext.timeout,
-      })
+      logger.info('Extension', `Running "${ext.name}" on ${inputOverride !== null ? 'selection of ' : ''}item ${selectedItem.id}`)
+      // The list may only hold the start of long text
+      const { content } = await loadFullItem(selectedItem)
+      const output = await runExtensionCommand(ext, content, inputOverride)
 
       if (output.length > 0) {
-        await invoke('copy_to_clipboard', {
-          item: { ...selectedItem, item_type: 'text', content: output },
-        })
+        await invoke('set_clipboard', { text: output })
         logger.info('Extension', `Output copied to clipboard (${output.length} bytes)`)
       }
 
@@ -97,7 +101,7 @@ export function ExtensionBar({
       const shortMsg = msg.length > 50 ? msg.slice(0, 50) + '...' : msg
       showFeedback({ kind: 'error', message: shortMsg }, false)
     }
-  }, [selectedItem, isRunning, onCloseWindow, showFeedback])
+  }, [selectedItem, inputOverride, isRunning, onCloseWindow, showFeedback])
 
   // Keyboard handler (capture phase to intercept before main UI)
   useEffect(() => {
@@ -201,6 +205,15 @@ export function ExtensionBar({
     >
       {/* Label */}
       <Zap className="w-3 h-3 flex-shrink-0 mr-1" style={{ color: 'var(--accent)', opacity: 0.8 }} />
+      {inputOverride !== null && (
+        <span
+          className="text-[10px] flex-shrink-0 mr-1"
+          style={{ color: 'var(--accent)', opacity: 0.8 }}
+          title={inputOverride}
+        >
+          Selection
+        </span>
+      )}
 
       {/* Extension chips */}
       {extensions.map((ext, index) => {
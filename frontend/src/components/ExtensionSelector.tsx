@@ -14,6 +14,8 @@ import { invoke } from '@tauri-apps/api/core'
 import { Loader2 } from 'lucide-react'
 import type { Extension, ClipboardItem } from '../types'
 import { logger } from '../utils/logger'
+import { runExtensionCommand } from '../utils/extensions'
+import { loadFullItem } from '../utils/items'
 import { cn } from '@/lib/utils'
 
 export function ExtensionSelector({
@@ -39,18 +41,14 @@ export function ExtensionSelector({
 
     try {
       logger.info('Extension', `Running "${ext.name}" on item ${selectedItem.id}`)
-      const output = await invoke<string>('run_extension', {
-        command: ext.command,
-        content: selectedItem.content,
-        timeout: ext.timeout,
-      })
+      // The list may only hold the start of long text
+      const { content } = await loadFullItem(selectedItem)
+      const output = await runExtensionCommand(ext, content)
 
       // If the extension produced output, copy it to clipboard as text
       // Extension stdout is always text regardless of the original item type
       if (output.length > 0) {
-        await invoke('copy_to_clipboard', {
-          item: { ...selectedItem, item_type: 'text', content: output },
-        })
+        await invoke('set_clipboard', { text: output })
         logger.info('Extension', `Output copied to clipboard (${output.length} bytes)`)
       }
 
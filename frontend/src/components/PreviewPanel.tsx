@@ -2,13 +2,19 @@
  * Preview Panel - Full content preview for selected clipboard item
  *
  * Shows below the list when Space is held/toggled.
- * Text: scrollable full content with monospace rendering
+ * Text: scrollable full content with monospace rendering (fetched when the
+ *   list only holds the start of it)
  * Image: larger image preview
  * File: full list of file paths
+ *
+ * Text selected in the preview can be passed to extensions instead of the
+ * whole item (see `getPreviewSelection`).
  */
 
+import { useEffect, useState } from 'react'
 import { X } from 'lucide-react'
 import type { ClipboardItem, ImageCache } from '../types'
+import { loadFullItem } from '../utils/items'
 
 export function PreviewPanel({
   item,
@@ -19,6 +25,16 @@ export function PreviewPanel({
   imageCache: ImageCache
   onClose: () => void
 }) {
+  const [full, setFull] = useState<ClipboardItem>(item)
+  useEffect(() => {
+    setFull(item)
+    let cancelled = false
+    loadFullItem(item)
+      .then(loaded => { if (!cancelled) setFull(loaded) })
+      .catch(error => console.error('[PowerClip] Failed to load preview:', error))
+    return () => { cancelled = true }
+  }, [item])
+
   return (
     <div
       className="flex flex-col border-t animate-fade-in"
@@ -49,21 +65,34 @@ export function PreviewPanel({
 
       {/* Content */}
       <div className="flex-1 overflow-auto p-3" style={{ minHeight: 0 }}>
-        {item.item_type === 'image' ? (
-          <ImagePreview item={item} imageCache={imageCache} />
-        ) : item.item_type === 'file' ? (
-          <FilePreview content={item.content} />
+        {full.item_type === 'image' ? (
+          <ImagePreview item={full} imageCache={imageCache} />
+        ) : full.item_type === 'file' ? (
+          <FilePreview content={full.content} />
         ) : (
-          <TextPreview content={item.content} />
+          <TextPreview content={full.content} />
         )}
       </div>
     </div>
   )
 }
 
+/** Text currently selected inside the text preview, or null if there is none */
+export function getPreviewSelection(): string | null {
+  const selection = window.getSelection()
+  const text = selection?.toString() ?? ''
+  if (text.length === 0 || !selection?.anchorNode) return null
+
+  const anchor = selection.anchorNode instanceof Element
+    ? selection.anchorNode
+    : selection.anchorNode.parentElement
+  return anchor?.closest('[data-preview-text]') ? text : null
+}
+
 function TextPreview({ content }: { content: string }) {
   return (
     <pre
+      data-preview-text
       style={{
         margin: 0,
         fontSize: 12,
@@ -81,7 +81,7 @@ export function QuickMenu({ items, imageCache }: QuickMenuProps) {
           e.preventDefault()
           if (displayItems.length > 0) {
             try {
-              await invoke('quick_menu_copy_selected', { items: displayItems })
+              await invoke('quick_menu_copy_selected', { itemIds: displayItems.map(i => i.id) })
               setVisible(false)
             } catch (err) {
               console.error('Failed to copy selected:', err)
@@ -189,7 +189,7 @@ export function QuickMenu({ items, imageCache }: QuickMenuProps) {
                     try {
                       // Sync backend selection to clicked item, then copy
                       const item = displayItems[index]
-                      await invoke('copy_to_clipboard', { item })
+                      await invoke('copy_to_clipboard', { id: item.id })
                       await invoke('hide_quick_menu')
                       setVisible(false)
                       await invoke('simulate_paste')
@@ -6,7 +6,7 @@
 import { useState, useRef, useEffect, useCallback } from 'react'
 import { invoke } from '@tauri-apps/api/core'
 import { Lightbulb, Loader2, Check } from 'lucide-react'
-import type { SemanticStatus } from '../types'
+import type { IndexingProgress, SemanticStatus } from '../types'
 import { cn } from '@/lib/utils'
 import { Button } from '@/components/ui/button'
 
@@ -28,6 +28,11 @@ function getSetupStep(enabled: boolean, status: SemanticStatus | null): SetupSte
   return 'ready'
 }
 
+function formatMegabytes(bytes: number): string {
+  const mb = bytes / (1024 * 1024)
+  return `${mb < 10 ? mb.toFixed(1) : Math.round(mb)}MB`
+}
+
 export function SemanticToggle({
   enabled,
   active,
@@ -36,6 +41,7 @@ export function SemanticToggle({
   onRefreshStatus,
 }: SemanticToggleProps) {
   const [open, setOpen] = useState(false)
+  const [progress, setProgress] = useState<IndexingProgress | null>(null)
   const panelRef = useRef<HTMLDivElement>(null)
   const buttonRef = useRef<HTMLButtonElement>(null)
 
@@ -65,6 +71,15 @@ export function SemanticToggle({
     return () => clearInterval(interval)
   }, [open, step, onRefreshStatus])
 
+  // Live progress pushed from the backend during bulk indexing
+  useEffect(() => {
+    const handler = (e: Event) => {
+      setProgress((e as CustomEvent<IndexingProgress>).detail)
+    }
+    window.addEventListener('powerclip:indexing-progress', handler)
+    return () => window.removeEventListener('powerclip:indexing-progress', handler)
+  }, [])
+
   // Refresh status when enabled changes
   useEffect(() => {
     if (enabled) onRefreshStatus()
@@ -89,6 +104,18 @@ export function SemanticToggle({
       .catch(() => {})
   }, [onRefreshStatus])
 
+  const handleFullRebuild = useCallback(() => {
+    invoke('full_rebuild_index')
+      .then(() => setTimeout(onRefreshStatus, 500))
+      .catch(() => {})
+  }, [onRefreshStatus])
+
+  const handleCancelIndexing = useCallback(() => {
+    invoke('cancel_bulk_indexing')
+      .then(() => setTimeout(onRefreshStatus, 500))
+      .catch(() => {})
+  }, [onRefreshStatus])
+
   // Status dot color and glow
   const getDotStyle = () => {
     switch (step) {
@@ -212,7 +239,12 @@ export function SemanticToggle({
 
             {step === 'indexing' && (
               <div>
-                <StepLabel stepNum={3} title={`Indexing history (${status?.indexed_count ?? 0}/${status?.total_text_count ?? 0})`} />
+                <StepLabel
+                  stepNum={3}
+                  title={progress
+                    ? `Indexing history (${progress.done}/${progress.total})`
+                    : `Indexing history (${status?.indexed_count ?? 0}/${status?.total_text_count ?? 0})`}
+                />
                 <div className="flex items-center gap-2 mt-2 text-xs text-muted-foreground ml-7">
                   <Loader2 className="w-3.5 h-3.5 animate-spin" style={{ color: '#facc15' }} />
                   <span>Indexing runs in background, you can close this</span>
@@ -226,12 +258,22 @@ export function SemanticToggle({
                     className="h-full rounded-full transition-all duration-300"
                     style={{
                       backgroundColor: '#facc15',
-                      width: status?.total_text_count
-                        ? `${((status.indexed_count ?? 0) / status.total_text_count) * 100}%`
-                        : '0%'
+                      width: progress?.total
+                        ? `${(progress.done / progress.total) * 100}%`
+                        : status?.total_text_count
+                          ? `${((status.indexed_count ?? 0) / status.total_text_count) * 100}%`
+                          : '0%'
                     }}
                   />
                 </div>
+                <Button
+                  size="sm"
+                  variant="outline"
+                  onClick={handleCancelIndexing}
+                  className="ml-7 mt-3"
+                >
+                  Cancel
+                </Button>
               </div>
             )}
 
@@ -247,6 +289,32 @@ export function SemanticToggle({
                 <span className="text-xs text-muted-foreground leading-relaxed">
                   {status?.indexed_count ?? 0} text records indexed. Click AI button to toggle search mode.
                 </span>
+                {(status?.index_capacity ?? 0) > 0 && (
+                  <span className="block text-xs text-muted-foreground leading-relaxed mt-1">
+                    Index using {formatMegabytes(status?.index_memory_bytes ?? 0)} of{' '}
+                    {status?.index_capacity?.toLocaleString()}-item capacity.
+                  </span>
+                )}
+                {status?.incremental_paused && (
+                  <div className="flex items-center gap-2 mt-3">
+                    <span className="text-xs leading-relaxed" style={{ color: '#facc15' }}>
+                      The embedding API rejected the key or model, so new items aren't indexed. Check the settings.
+                    </span>
+                    <Button size="sm" variant="outline" onClick={handleOpenSettings}>
+                      Settings
+                    </Button>
+                  </div>
+                )}
+                {(status?.dimension_mismatch_count ?? 0) > 0 && (
+                  <div className="flex items-center gap-2 mt-3">
+                    <span className="text-xs leading-relaxed" style={{ color: '#facc15' }}>
+                      {status?.dimension_mismatch_count} records were indexed with a different model and can't be searched.
+                    </span>
+                    <Button size="sm" variant="outline" onClick={handleFullRebuild}>
+                      Rebuild
+                    </Button>
+                  </div>
+                )}
                 <div className="flex gap-2 mt-3">
                   <Button
                     size="sm"
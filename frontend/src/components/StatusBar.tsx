@@ -15,6 +15,7 @@ export function StatusBar({
   hotkeyModifiers,
   hotkeyKey,
   settingsError,
+  monitorError,
   hasExtensions = false,
   hasSelection = false,
 }: {
@@ -26,6 +27,8 @@ export function StatusBar({
   hotkeyModifiers: string
   hotkeyKey: string
   settingsError?: string | null
+  /** Set when the clipboard monitor stalled and was not restarted */
+  monitorError?: string | null
   hasExtensions?: boolean
   hasSelection?: boolean
 }) {
@@ -50,6 +53,15 @@ export function StatusBar({
             <AlertTriangle className="w-3 h-3" />
             Config error - using defaults
           </span>
+        ) : monitorError ? (
+          <span
+            className="flex items-center gap-1.5"
+            style={{ color: '#f87171' }}
+            title={monitorError}
+          >
+            <AlertTriangle className="w-3 h-3" />
+            Recording stopped - restart PowerClip
+          </span>
         ) : (
           <>
             <span style={{ color: 'var(--muted-foreground)' }}>
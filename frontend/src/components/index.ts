@@ -9,6 +9,6 @@ export { SnippetListItem, SNIPPET_ITEM_HEIGHT } from './SnippetListItem'
 export { AddSnippetDialog } from './AddSnippetDialog'
 export { SnippetDialog } from './SnippetDialog'
 export { QuickMenu } from './QuickMenu'
-export { PreviewPanel } from './PreviewPanel'
+export { PreviewPanel, getPreviewSelection } from './PreviewPanel'
 export { SmartLists } from './SmartLists'
 export type { SmartListFilter } from './SmartLists'
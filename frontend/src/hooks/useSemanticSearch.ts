@@ -1,11 +1,14 @@
 import { useState, useEffect, useCallback, useRef } from 'react'
 import { invoke } from '@tauri-apps/api/core'
 import { SemanticSearchResult } from '../types'
+import { errorMessage, isCommandError } from '../utils/helpers'
 
 interface UseSemanticSearchResult {
   results: SemanticSearchResult[]
   loading: boolean
   error: string | null
+  /// Code of the last error (e.g. "api_not_configured"), null for uncoded errors
+  errorCode: string | null
 }
 
 export function useSemanticSearch(
@@ -17,7 +20,11 @@ export function useSemanticSearch(
   const [results, setResults] = useState<SemanticSearchResult[]>([])
   const [loading, setLoading] = useState(false)
   const [error, setError] = useState<string | null>(null)
+  const [errorCode, setErrorCode] = useState<string | null>(null)
   const timeoutRef = useRef<ReturnType<typeof setTimeout> | null>(null)
+  // Id of the newest search; responses to older ones are dropped
+  const requestIdRef = useRef(0)
+  const inFlightRef = useRef(false)
 
   const performSearch = useCallback(async (searchQuery: string) => {
     if (!searchQuery.trim()) {
@@ -26,21 +33,30 @@ export function useSemanticSearch(
       return
     }
 
+    const requestId = ++requestIdRef.current
     setLoading(true)
     setError(null)
+    setErrorCode(null)
 
+    inFlightRef.current = true
     try {
       const searchResults = await invoke<SemanticSearchResult[]>(
         'semantic_search',
         { query: searchQuery, limit, minScore }
       )
+      if (requestId !== requestIdRef.current) return
       setResults(searchResults)
     } catch (e) {
-      const errorMessage = typeof e === 'string' ? e : String(e)
-      setError(errorMessage)
+      // Superseded searches reject with "cancelled"; the newer one reports its own result
+      if (requestId !== requestIdRef.current || (isCommandError(e) && e.code === 'cancelled')) return
+      setError(errorMessage(e))
+      setErrorCode(isCommandError(e) ? e.code : null)
       setResults([])
     } finally {
-      setLoading(false)
+      if (requestId === requestIdRef.current) {
+        inFlightRef.current = false
+        setLoading(false)
+      }
     }
   }, [limit, minScore])
 
@@ -50,10 +66,18 @@ export function useSemanticSearch(
       clearTimeout(timeoutRef.current)
     }
 
+    // The query changed: stop the backend search for the old one
+    if (inFlightRef.current) {
+      requestIdRef.current++
+      inFlightRef.current = false
+      invoke('cancel_semantic_search').catch(() => {})
+    }
+
     if (!query.trim()) {
       setResults([])
       setLoading(false)
       setError(null)
+      setErrorCode(null)
       return
     }
 
@@ -70,5 +94,5 @@ export function useSemanticSearch(
     }
   }, [query, performSearch, debounceMs])
 
-  return { results, loading, error }
+  return { results, loading, error, errorCode }
 }
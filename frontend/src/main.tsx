@@ -10,8 +10,9 @@ initLogger()
 
 // ============== Tauri Event Listener ==============
 // Set up window shown listener at app startup (before React mounts)
-listen('powerclip:window-shown', () => {
-  window.dispatchEvent(new CustomEvent('powerclip:window-shown'))
+// (payload: WindowShown; selected_id is set when remember_selection is on)
+listen<any>('powerclip:window-shown', (event) => {
+  window.dispatchEvent(new CustomEvent('powerclip:window-shown', { detail: event.payload }))
 }).catch(err => {
   console.error('[PowerClip] Failed to set up window-shown listener:', err)
 })
@@ -23,6 +24,13 @@ listen<any>('powerclip:new-item', (event) => {
   console.error('[PowerClip] Failed to set up new-item listener:', err)
 })
 
+// Set up item-updated listener (payload: { id, created_at }; content already in history was copied again)
+listen<any>('powerclip:item-updated', (event) => {
+  window.dispatchEvent(new CustomEvent('powerclip:item-updated', { detail: event.payload }))
+}).catch(err => {
+  console.error('[PowerClip] Failed to set up item-updated listener:', err)
+})
+
 // Set up settings-changed listener
 listen('powerclip:settings-changed', () => {
   window.dispatchEvent(new CustomEvent('powerclip:settings-changed'))
@@ -30,6 +38,125 @@ listen('powerclip:settings-changed', () => {
   console.error('[PowerClip] Failed to set up settings-changed listener:', err)
 })
 
+// Set up settings-error listener (payload: SettingsError)
+listen<any>('powerclip:settings-error', (event) => {
+  window.dispatchEvent(new CustomEvent('powerclip:settings-error', { detail: event.payload }))
+}).catch(err => {
+  console.error('[PowerClip] Failed to set up settings-error listener:', err)
+})
+
+// Set up first-run listener (show the welcome tour until complete_onboarding is called)
+listen('powerclip:first-run', () => {
+  window.dispatchEvent(new CustomEvent('powerclip:first-run'))
+}).catch(err => {
+  console.error('[PowerClip] Failed to set up first-run listener:', err)
+})
+
+// Set up monitor-stopped listener (payload: MonitorStopped)
+listen<any>('powerclip:monitor-stopped', (event) => {
+  window.dispatchEvent(new CustomEvent('powerclip:monitor-stopped', { detail: event.payload }))
+}).catch(err => {
+  console.error('[PowerClip] Failed to set up monitor-stopped listener:', err)
+})
+
+// Set up history-cleared listener (history was cleared from the tray)
+listen('powerclip:history-cleared', () => {
+  window.dispatchEvent(new CustomEvent('powerclip:history-cleared'))
+}).catch(err => {
+  console.error('[PowerClip] Failed to set up history-cleared listener:', err)
+})
+
+// Set up clipboard-cleared listener (payload: number of history items deleted with it)
+listen<number>('powerclip:clipboard-cleared', (event) => {
+  window.dispatchEvent(new CustomEvent('powerclip:clipboard-cleared', { detail: event.payload }))
+}).catch(err => {
+  console.error('[PowerClip] Failed to set up clipboard-cleared listener:', err)
+})
+
+// Set up history-changed listener (another program wrote to the database)
+listen('powerclip:history-changed', () => {
+  window.dispatchEvent(new CustomEvent('powerclip:history-changed'))
+}).catch(err => {
+  console.error('[PowerClip] Failed to set up history-changed listener:', err)
+})
+
+// Set up indexing-progress listener (payload: IndexingProgress)
+listen<any>('powerclip:indexing-progress', (event) => {
+  window.dispatchEvent(new CustomEvent('powerclip:indexing-progress', { detail: event.payload }))
+}).catch(err => {
+  console.error('[PowerClip] Failed to set up indexing-progress listener:', err)
+})
+
+// Set up indexing-cancelled listener (payload: { indexed })
+listen<any>('powerclip:indexing-cancelled', (event) => {
+  window.dispatchEvent(new CustomEvent('powerclip:indexing-cancelled', { detail: event.payload }))
+}).catch(err => {
+  console.error('[PowerClip] Failed to set up indexing-cancelled listener:', err)
+})
+
+// Set up item-copied listener (payload: { id, last_used_at })
+listen<any>('powerclip:item-copied', (event) => {
+  window.dispatchEvent(new CustomEvent('powerclip:item-copied', { detail: event.payload }))
+}).catch(err => {
+  console.error('[PowerClip] Failed to set up item-copied listener:', err)
+})
+
+// Set up copy-failed listener (payload: { id, error, missing })
+listen<any>('powerclip:copy-failed', (event) => {
+  window.dispatchEvent(new CustomEvent('powerclip:copy-failed', { detail: event.payload }))
+}).catch(err => {
+  console.error('[PowerClip] Failed to set up copy-failed listener:', err)
+})
+
+// Set up after-select listener (payload: { action: "hide" | "hide_and_paste" | "stay_open" })
+listen<any>('powerclip:after-select', (event) => {
+  window.dispatchEvent(new CustomEvent('powerclip:after-select', { detail: event.payload }))
+}).catch(err => {
+  console.error('[PowerClip] Failed to set up after-select listener:', err)
+})
+
+// Set up compact-progress listener (payload: "images" | "vacuum")
+listen<any>('powerclip:compact-progress', (event) => {
+  window.dispatchEvent(new CustomEvent('powerclip:compact-progress', { detail: event.payload }))
+}).catch(err => {
+  console.error('[PowerClip] Failed to set up compact-progress listener:', err)
+})
+
+// Set up compact-finished listener (payload: CompactResult)
+listen<any>('powerclip:compact-finished', (event) => {
+  window.dispatchEvent(new CustomEvent('powerclip:compact-finished', { detail: event.payload }))
+}).catch(err => {
+  console.error('[PowerClip] Failed to set up compact-finished listener:', err)
+})
+
+// Set up compact-failed listener (payload: error message)
+listen<any>('powerclip:compact-failed', (event) => {
+  window.dispatchEvent(new CustomEvent('powerclip:compact-failed', { detail: event.payload }))
+}).catch(err => {
+  console.error('[PowerClip] Failed to set up compact-failed listener:', err)
+})
+
+// Set up data-dir-progress listener (payload: MigrationProgress)
+listen<any>('powerclip:data-dir-progress', (event) => {
+  window.dispatchEvent(new CustomEvent('powerclip:data-dir-progress', { detail: event.payload }))
+}).catch(err => {
+  console.error('[PowerClip] Failed to set up data-dir-progress listener:', err)
+})
+
+// Set up data-dir-migrated listener (payload: DataDirMigrated)
+listen<any>('powerclip:data-dir-migrated', (event) => {
+  window.dispatchEvent(new CustomEvent('powerclip:data-dir-migrated', { detail: event.payload }))
+}).catch(err => {
+  console.error('[PowerClip] Failed to set up data-dir-migrated listener:', err)
+})
+
+// Set up semantic-model-missing listener (payload: { message })
+listen<any>('powerclip:semantic-model-missing', (event) => {
+  window.dispatchEvent(new CustomEvent('powerclip:semantic-model-missing', { detail: event.payload }))
+}).catch(err => {
+  console.error('[PowerClip] Failed to set up semantic-model-missing listener:', err)
+})
+
 // ============== Application ==============
 
 // Set transparent background
@@ -51,28 +51,66 @@ const createMockSnippets = (count: number) =>
 const defaultSettings = {
   auto_cleanup_enabled: false,
   max_items: 100,
+  max_age_days: 0,
   hotkey_modifiers: 'Meta+Shift',
   hotkey_key: 'KeyV',
   window_opacity: 0.95,
+  remember_window_size: true,
+  default_width: 450,
+  default_height: 400,
+  window_anchor: 'free',
+  target_monitor: '',
+  remember_selection: false,
   auto_paste_enabled: false,
+  after_select: '',
   extensions: [],
   semantic_search_enabled: false,
   embedding_api_url: '',
   embedding_api_key: '',
   embedding_api_model: '',
   embedding_api_dim: 256,
+  embedding_api_proxy: '',
   add_to_snippets_hotkey_enabled: true,
   add_to_snippets_hotkey_modifiers: 'Meta+Shift',
   add_to_snippets_hotkey_key: 'KeyS',
+  copy_latest_hotkey_enabled: false,
+  copy_latest_hotkey_modifiers: 'Meta+Alt',
+  copy_latest_hotkey_key: 'KeyV',
+  stack_mode_enabled: false,
+  stack_pop_hotkey_modifiers: 'Meta+Alt',
+  stack_pop_hotkey_key: 'KeyN',
+  clear_clipboard_hotkey_enabled: false,
+  clear_clipboard_hotkey_modifiers: 'Meta+Alt',
+  clear_clipboard_hotkey_key: 'KeyX',
+  clear_clipboard_delete_recent: 0,
   clipboard_poll_interval_ms: 100,
+  idle_threshold_secs: 120,
+  capture_text: true,
+  capture_images: true,
+  normalize_line_endings: false,
+  capture_transforms: [],
+  capture_rules: [],
+  max_image_megapixels: 40,
+  max_paste_dimension: 0,
+  inline_image_max_kb: 0,
+  prewarm_image_cache: false,
+  auto_backup_enabled: false,
+  backup_keep_count: 7,
   min_similarity_score: 0.2,
   max_embeddings_in_memory: 50000,
+  semantic_unload_idle_minutes: 30,
   content_truncate_length: 50,
+  preview_max_length: 2000,
   image_preview_max_width: 120,
   image_preview_max_height: 80,
   max_history_fetch: 10000,
   focus_delay_ms: 50,
   semantic_search_debounce_ms: 300,
+  theme_override: 'system',
+  tray_left_click: 'toggle_window',
+  tray_icon_theme: 'auto',
+  log_format: 'text',
+  launch_at_login: false,
 }
 
 describe('App Scroll Integration', () => {
@@ -85,7 +123,7 @@ describe('App Scroll Integration', () => {
     // Setup default mock responses
     mockInvoke.mockImplementation(async (cmd: string) => {
       switch (cmd) {
-        case 'get_history':
+        case 'get_history_previews':
           return createMockHistoryItems(20)
         case 'get_snippets':
           return createMockSnippets(5)
@@ -144,7 +182,7 @@ describe('App Scroll Integration', () => {
     expect(scrollEvents).toContain(0)
   })
 
-  it('should call get_history when window is shown', async () => {
+  it('should call get_history_previews when window is shown', async () => {
     const { default: App } = await import('../App')
 
     render(<App />)
@@ -158,7 +196,7 @@ describe('App Scroll Integration', () => {
       await vi.runAllTimersAsync()
     })
 
-    // Verify get_history was called
-    expect(mockInvoke).toHaveBeenCalledWith('get_history', { limit: expect.any(Number) })
+    // Verify get_history_previews was called
+    expect(mockInvoke).toHaveBeenCalledWith('get_history_previews', { limit: expect.any(Number) })
   })
 })
@@ -0,0 +1,83 @@
+/**
+ * Tests for running extensions and approving new commands
+ */
+import { describe, it, expect, vi, beforeEach, afterEach } from 'vitest'
+import type { Extension } from '../types'
+
+const mockInvoke = vi.fn()
+vi.mock('@tauri-apps/api/core', () => ({
+  invoke: (...args: unknown[]) => mockInvoke(...args),
+}))
+
+import { runExtensionCommand, NOT_APPROVED_ERROR } from '../utils/extensions'
+
+const ext: Extension = {
+  name: 'Upper',
+  command: 'tr a-z A-Z',
+  timeout: -1,
+  close_on_success: false,
+}
+
+describe('runExtensionCommand', () => {
+  let confirmSpy: ReturnType<typeof vi.spyOn>
+
+  beforeEach(() => {
+    mockInvoke.mockReset()
+    confirmSpy = vi.spyOn(window, 'confirm')
+  })
+
+  afterEach(() => {
+    confirmSpy.mockRestore()
+  })
+
+  it('returns the output of an approved extension without asking', async () => {
+    mockInvoke.mockResolvedValueOnce('HELLO')
+
+    await expect(runExtensionCommand(ext, 'hello')).resolves.toBe('HELLO')
+    expect(mockInvoke).toHaveBeenCalledWith('run_extension', {
+      command: 'tr a-z A-Z',
+      content: 'hello',
+      timeout: -1,
+      inputOverride: null,
+    })
+    expect(confirmSpy).not.toHaveBeenCalled()
+  })
+
+  it('sends the input override along with the full content', async () => {
+    mockInvoke.mockResolvedValueOnce('ELL')
+
+    await expect(runExtensionCommand(ext, 'hello', 'ell')).resolves.toBe('ELL')
+    expect(mockInvoke).toHaveBeenCalledWith('run_extension', expect.objectContaining({
+      content: 'hello',
+      inputOverride: 'ell',
+    }))
+  })
+
+  it('approves and retries an unapproved extension when the user accepts', async () => {
+    confirmSpy.mockReturnValue(true)
+    mockInvoke
+      .mockRejectedValueOnce(`${NOT_APPROVED_ERROR}: confirm it before running`)
+      .mockResolvedValueOnce(undefined)
+      .mockResolvedValueOnce('HELLO')
+
+    await expect(runExtensionCommand(ext, 'hello')).resolves.toBe('HELLO')
+    expect(confirmSpy).toHaveBeenCalledWith(expect.stringContaining('tr a-z A-Z'))
+    expect(mockInvoke).toHaveBeenCalledWith('confirm_extension', { name: 'Upper', command: 'tr a-z A-Z' })
+    expect(mockInvoke).toHaveBeenCalledTimes(3)
+  })
+
+  it('does not run an unapproved extension when the user declines', async () => {
+    confirmSpy.mockReturnValue(false)
+    mockInvoke.mockRejectedValueOnce(`${NOT_APPROVED_ERROR}: confirm it before running`)
+
+    await expect(runExtensionCommand(ext, 'hello')).rejects.toMatch(NOT_APPROVED_ERROR)
+    expect(mockInvoke).toHaveBeenCalledTimes(1)
+  })
+
+  it('passes other errors through without asking', async () => {
+    mockInvoke.mockRejectedValueOnce('Extension timed out')
+
+    await expect(runExtensionCommand(ext, 'hello')).rejects.toBe('Extension timed out')
+    expect(confirmSpy).not.toHaveBeenCalled()
+  })
+})
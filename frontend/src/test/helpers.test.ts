@@ -2,7 +2,7 @@
  * Tests for utility helper functions
  */
 import { describe, it, expect, vi, beforeEach, afterEach } from 'vitest'
-import { formatTime, formatContent, formatFilePaths, getPreview } from '../utils/helpers'
+import { formatTime, formatContent, formatFilePaths, getPreview, isCommandError, errorMessage, searchTerms, matchesSearch } from '../utils/helpers'
 
 // ─── formatTime ────────────────────────────────────────────────────────────
 
@@ -217,3 +217,72 @@ describe('getPreview', () => {
     expect(getPreview(text).endsWith('...')).toBe(false)
   })
 })
+
+// ─── errorMessage ──────────────────────────────────────────────────────────
+
+describe('errorMessage', () => {
+  it('returns the message of a coded command error', () => {
+    const e = { code: 'api_not_configured', message: 'Embedding API is not configured' }
+    expect(isCommandError(e)).toBe(true)
+    expect(errorMessage(e)).toBe('Embedding API is not configured')
+  })
+
+  it('returns plain string errors unchanged', () => {
+    expect(isCommandError('Database is locked')).toBe(false)
+    expect(errorMessage('Database is locked')).toBe('Database is locked')
+  })
+
+  it('stringifies anything else', () => {
+    expect(isCommandError(null)).toBe(false)
+    expect(errorMessage(new Error('boom'))).toBe('Error: boom')
+    expect(errorMessage({ code: 1 })).toBe('[object Object]')
+  })
+})
+
+// ─── searchTerms / matchesSearch ───────────────────────────────────────────
+
+describe('searchTerms', () => {
+  it('splits on whitespace and lowercases', () => {
+    expect(searchTerms('  Meeting   NOTES ')).toEqual(['meeting', 'notes'])
+  })
+
+  it('keeps quoted phrases whole', () => {
+    expect(searchTerms('draft "meeting notes" v2')).toEqual(['draft', 'meeting notes', 'v2'])
+    expect(searchTerms('say"hi there')).toEqual(['say', 'hi there'])
+  })
+
+  it('drops empty and blank phrases', () => {
+    expect(searchTerms('"" "  " x')).toEqual(['x'])
+    expect(searchTerms('   ')).toEqual([])
+  })
+})
+
+describe('matchesSearch', () => {
+  it('matches partly typed words', () => {
+    expect(matchesSearch(searchTerms('conf'), 'Configuration file')).toBe(true)
+    expect(matchesSearch(searchTerms('conf fil'), 'Configuration file')).toBe(true)
+    expect(matchesSearch(searchTerms('conf dir'), 'Configuration file')).toBe(false)
+  })
+
+  it('matches terms in any order but phrases as written', () => {
+    expect(matchesSearch(searchTerms('team meeting'), 'Meeting notes for the team')).toBe(true)
+    expect(matchesSearch(searchTerms('"team meeting"'), 'Meeting notes for the team')).toBe(false)
+    expect(matchesSearch(searchTerms('"meeting notes" team'), 'Meeting notes for the team')).toBe(true)
+  })
+
+  it('matches Chinese and Japanese text without word boundaries', () => {
+    expect(matchesSearch(searchTerms('会议'), '今天的会议记录：讨论配置文件')).toBe(true)
+    expect(matchesSearch(searchTerms('会议\u3000配置'), '今天的会议记录：讨论配置文件')).toBe(true)
+    expect(matchesSearch(searchTerms('"会议配置"'), '今天的会议记录：讨论配置文件')).toBe(false)
+    expect(matchesSearch(searchTerms('東京 あります'), '東京で会議があります')).toBe(true)
+  })
+
+  it('lets each term match any field', () => {
+    expect(matchesSearch(searchTerms('sig mail'), 'Best regards', 'email-signature')).toBe(true)
+    expect(matchesSearch(searchTerms('regards'), 'Best regards', null)).toBe(true)
+  })
+
+  it('matches everything without terms', () => {
+    expect(matchesSearch([], 'anything')).toBe(true)
+  })
+})
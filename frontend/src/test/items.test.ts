@@ -0,0 +1,47 @@
+/**
+ * Tests for loading the full content of list items
+ */
+import { describe, it, expect, vi, beforeEach } from 'vitest'
+import type { ClipboardItem, HistoryPreview } from '../types'
+
+const mockInvoke = vi.fn()
+vi.mock('@tauri-apps/api/core', () => ({
+  invoke: (...args: unknown[]) => mockInvoke(...args),
+}))
+
+import { loadFullItem } from '../utils/items'
+
+const item: ClipboardItem = {
+  id: 7,
+  item_type: 'text',
+  content: 'full text',
+  hash: 'abc',
+  created_at: '2024-03-01T08:30:00',
+}
+
+describe('loadFullItem', () => {
+  beforeEach(() => {
+    mockInvoke.mockReset()
+  })
+
+  it('returns items that were not cut without asking the backend', async () => {
+    const preview: HistoryPreview = { ...item, truncated: false }
+
+    await expect(loadFullItem(item)).resolves.toBe(item)
+    await expect(loadFullItem(preview)).resolves.toBe(preview)
+    expect(mockInvoke).not.toHaveBeenCalled()
+  })
+
+  it('fetches the full item for a cut preview', async () => {
+    mockInvoke.mockResolvedValueOnce(item)
+
+    await expect(loadFullItem({ ...item, content: 'full', truncated: true })).resolves.toEqual(item)
+    expect(mockInvoke).toHaveBeenCalledWith('get_item', { id: 7 })
+  })
+
+  it('fails when the item was deleted', async () => {
+    mockInvoke.mockResolvedValueOnce(null)
+
+    await expect(loadFullItem({ ...item, truncated: true })).rejects.toThrow('no longer exists')
+  })
+})
@@ -50,28 +50,66 @@ describe('types', () => {
       const settings: Settings = {
         auto_cleanup_enabled: true,
         max_items: 100,
+        max_age_days: 0,
         hotkey_modifiers: 'Meta+Shift',
         hotkey_key: 'KeyV',
         window_opacity: 0.95,
+        remember_window_size: true,
+        default_width: 450,
+        default_height: 400,
+        window_anchor: 'free',
+        target_monitor: '',
+        remember_selection: false,
         auto_paste_enabled: false,
+        after_select: '',
         extensions: [],
         semantic_search_enabled: false,
         embedding_api_url: 'https://api.openai.com/v1',
         embedding_api_key: '',
         embedding_api_model: 'text-embedding-3-small',
         embedding_api_dim: 1536,
+        embedding_api_proxy: '',
         add_to_snippets_hotkey_enabled: true,
         add_to_snippets_hotkey_modifiers: 'Meta+Shift',
         add_to_snippets_hotkey_key: 'KeyS',
+        copy_latest_hotkey_enabled: false,
+        copy_latest_hotkey_modifiers: 'Meta+Alt',
+        copy_latest_hotkey_key: 'KeyV',
+        stack_mode_enabled: false,
+        stack_pop_hotkey_modifiers: 'Meta+Alt',
+        stack_pop_hotkey_key: 'KeyN',
+        clear_clipboard_hotkey_enabled: false,
+        clear_clipboard_hotkey_modifiers: 'Meta+Alt',
+        clear_clipboard_hotkey_key: 'KeyX',
+        clear_clipboard_delete_recent: 0,
         clipboard_poll_interval_ms: 100,
+        idle_threshold_secs: 120,
+        capture_text: true,
+        capture_images: true,
+        normalize_line_endings: false,
+        capture_transforms: [],
+        capture_rules: [],
+        max_image_megapixels: 40,
+        max_paste_dimension: 0,
+        inline_image_max_kb: 0,
+        prewarm_image_cache: false,
+        auto_backup_enabled: false,
+        backup_keep_count: 7,
         min_similarity_score: 0.2,
         max_embeddings_in_memory: 50000,
+        semantic_unload_idle_minutes: 30,
         content_truncate_length: 50,
+        preview_max_length: 2000,
         image_preview_max_width: 120,
         image_preview_max_height: 80,
         max_history_fetch: 10000,
         focus_delay_ms: 50,
         semantic_search_debounce_ms: 300,
+        theme_override: 'system',
+        tray_left_click: 'toggle_window',
+        tray_icon_theme: 'auto',
+        log_format: 'text',
+        launch_at_login: false,
       }
 
       expect(settings.auto_cleanup_enabled).toBe(true)
@@ -79,6 +117,8 @@ describe('types', () => {
       expect(settings.hotkey_modifiers).toBe('Meta+Shift')
       expect(settings.hotkey_key).toBe('KeyV')
       expect(settings.window_opacity).toBeCloseTo(0.95)
+      expect(settings.remember_window_size).toBe(true)
+      expect(settings.default_width).toBe(450)
       expect(settings.auto_paste_enabled).toBe(false)
       expect(settings.extensions).toEqual([])
       expect(settings.semantic_search_enabled).toBe(false)
@@ -112,6 +152,21 @@ describe('types', () => {
       expect(status.api_configured).toBe(true)
     })
 
+    it('should accept index memory and capacity', () => {
+      const status: SemanticStatus = {
+        indexed_count: 100,
+        total_text_count: 200,
+        indexing_in_progress: false,
+        enabled: true,
+        api_configured: true,
+        index_memory_bytes: 38 * 1024 * 1024,
+        index_capacity: 50000,
+      }
+
+      expect(status.index_memory_bytes).toBe(39845888)
+      expect(status.index_capacity).toBe(50000)
+    })
+
     it('should allow api_configured false', () => {
       const status: SemanticStatus = {
         indexed_count: 0,
@@ -9,6 +9,21 @@ export interface ClipboardItem {
   hash: string;
   created_at: string;
   is_favorited?: boolean;
+  is_concealed?: boolean;
+  // Guessed language when the text looks like code ("unknown" if unclear)
+  code_language?: string | null;
+  // Times the item was copied back from history (drives the "frecency" sort)
+  use_count?: number;
+  // When the item was last copied back from history (null if never)
+  last_used_at?: string | null;
+  // Age of created_at as computed by the backend when the item was read ("5 min ago")
+  created_at_relative?: string;
+}
+
+// History item from get_history_previews: long text is cut to preview_max_length
+// characters, and `truncated` tells whether get_item is needed for the full content
+export interface HistoryPreview extends ClipboardItem {
+  truncated: boolean;
 }
 
 export interface Extension {
@@ -18,13 +33,34 @@ export interface Extension {
   close_on_success: boolean;
 }
 
+/// Per-app exception to capture_text / capture_images
+export interface CaptureRule {
+  /// Executable name on Windows, bundle identifier on macOS
+  app: string;
+  capture_text: boolean;
+  capture_images: boolean;
+}
+
 export interface Settings {
   auto_cleanup_enabled: boolean;
   max_items: number;
+  max_age_days: number;
   hotkey_modifiers: string;
   hotkey_key: string;
   window_opacity: number;
+  /// When false, the window always opens at default_width x default_height
+  remember_window_size: boolean;
+  default_width: number;
+  default_height: number;
+  /// "free", "center", "top_left", "top_right", "bottom_left" or "bottom_right"
+  window_anchor: string;
+  /// Display to always open on (a list_monitors name); empty follows the cursor
+  target_monitor: string;
+  /// Reopen the window with the last selected item still selected
+  remember_selection: boolean;
   auto_paste_enabled: boolean;
+  /// "hide", "hide_and_paste" or "stay_open"; empty follows auto_paste_enabled
+  after_select: string;
   extensions: Extension[];
   semantic_search_enabled: boolean;
   // Embedding API settings
@@ -32,20 +68,69 @@ export interface Settings {
   embedding_api_key: string;
   embedding_api_model: string;
   embedding_api_dim: number;
+  embedding_api_proxy: string;
   // Add to snippets hotkey settings
   add_to_snippets_hotkey_enabled: boolean;
   add_to_snippets_hotkey_modifiers: string;
   add_to_snippets_hotkey_key: string;
+  // Copy latest item hotkey settings
+  copy_latest_hotkey_enabled: boolean;
+  copy_latest_hotkey_modifiers: string;
+  copy_latest_hotkey_key: string;
+  // Paste stack settings
+  stack_mode_enabled: boolean;
+  stack_pop_hotkey_modifiers: string;
+  stack_pop_hotkey_key: string;
+  // Clear clipboard hotkey settings
+  clear_clipboard_hotkey_enabled: boolean;
+  clear_clipboard_hotkey_modifiers: string;
+  clear_clipboard_hotkey_key: string;
+  clear_clipboard_delete_recent: number;
   // Advanced settings
   clipboard_poll_interval_ms: number;
+  idle_threshold_secs: number;
+  capture_text: boolean;
+  capture_images: boolean;
+  normalize_line_endings: boolean;
+  capture_transforms: string[];
+  /// First rule matching the source app applies; can only turn capture off
+  capture_rules: CaptureRule[];
+  max_image_megapixels: number;
+  /// Longest side of images copied back from history (0 = no limit)
+  max_paste_dimension: number;
+  inline_image_max_kb: number;
+  prewarm_image_cache: boolean;
+  auto_backup_enabled: boolean;
+  backup_keep_count: number;
   min_similarity_score: number;
   max_embeddings_in_memory: number;
+  semantic_unload_idle_minutes: number;
   content_truncate_length: number;
+  preview_max_length: number;
   image_preview_max_width: number;
   image_preview_max_height: number;
   max_history_fetch: number;
   focus_delay_ms: number;
   semantic_search_debounce_ms: number;
+  theme_override: 'system' | 'dark' | 'light';
+  tray_left_click: 'toggle_window' | 'show_menu' | 'none';
+  tray_icon_theme: 'auto' | 'light' | 'dark';
+  log_format: 'text' | 'json';
+  launch_at_login: boolean;
+}
+
+/// Settings load problem reported by the backend
+export interface SettingsError {
+  message: string;
+  line: number | null;
+  column: number | null;
+}
+
+/// Payload of powerclip:monitor-stopped (the clipboard monitor stalled)
+export interface MonitorStopped {
+  message: string;
+  /// Whether the monitor is being restarted now; if not, nothing is recorded until a retry a few minutes later succeeds
+  restarting: boolean;
 }
 
 /// Status of the semantic search feature
@@ -55,12 +140,57 @@ export interface SemanticStatus {
   indexing_in_progress: boolean;
   enabled: boolean;
   api_configured: boolean;
+  /// Stored embeddings with the wrong dimension (need a full rebuild)
+  dimension_mismatch_count?: number;
+  /// Approximate bytes used by the in-memory index (0 while unloaded)
+  index_memory_bytes?: number;
+  /// Maximum embeddings kept in memory before the oldest are evicted
+  index_capacity?: number;
+  /// New items aren't indexed because the API rejected the key or model
+  incremental_paused?: boolean;
+}
+
+/// Payload of the powerclip:indexing-progress event
+export interface IndexingProgress {
+  done: number;
+  total: number;
+}
+
+/// Payload of the powerclip:compact-finished event (also returned by compact_database)
+export interface CompactResult {
+  bytes_reclaimed: number;
+  images_removed: number;
+}
+
+/// Payload of the powerclip:data-dir-progress event
+export interface MigrationProgress {
+  copied_bytes: number;
+  total_bytes: number;
+}
+
+/// Payload of the powerclip:data-dir-migrated event (also returned by migrate_data_dir)
+export interface DataDirMigrated {
+  path: string;
+  /// Files copied besides the database
+  files_copied: number;
+  bytes_copied: number;
+  items_copied: number;
 }
 
 /// Result item from semantic search
 export interface SemanticSearchResult {
   item: ClipboardItem;
   score: number;
+  /// Best-matching sentence of a long text (when requested with withSnippets)
+  snippet?: string;
+}
+
+/// Error returned by commands that report a machine-readable kind (the semantic commands).
+/// Other commands reject with a plain message string.
+export interface CommandError {
+  /// e.g. "semantic_disabled", "api_not_configured", "api_error", "database_error"
+  code: string;
+  message: string;
 }
 
 export interface LogEntry {
@@ -79,6 +209,41 @@ export interface PowerClipLogger {
   clearLogs: () => void;
 }
 
+/// Filters for the query_history command (all optional)
+export interface HistoryQuery {
+  text?: string;
+  item_type?: 'text' | 'image' | 'file';
+  tag?: 'favorite' | 'concealed' | 'code';
+  after?: string;
+  before?: string;
+  limit?: number;
+  offset?: number;
+  sort?: 'default' | 'newest' | 'oldest' | 'frecency';
+}
+
+/// Group returned by find_duplicates and group_similar_images (item_ids newest first)
+export interface DuplicateGroup {
+  group_id: number;
+  item_ids: number[];
+}
+
+/// Payload of powerclip:window-shown
+export interface WindowShown {
+  /// Item to select instead of the newest (remember_selection); may no longer be in the list
+  selected_id: number | null;
+}
+
+/// Display returned by list_monitors (logical pixels); `name` is the value for target_monitor
+export interface MonitorInfo {
+  name: string;
+  x: number;
+  y: number;
+  width: number;
+  height: number;
+  scale_factor: number;
+  is_primary: boolean;
+}
+
 /// Snippet item for quick commands
 export interface Snippet {
   id: number;
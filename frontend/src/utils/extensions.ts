@@ -0,0 +1,40 @@
+/**
+ * Running extensions, including the one-time approval of new commands
+ */
+
+import { invoke } from '@tauri-apps/api/core'
+import type { Extension } from '../types'
+import { logger } from './logger'
+
+/** Start of the backend error for commands that haven't been approved yet */
+export const NOT_APPROVED_ERROR = 'Extension not approved'
+
+/**
+ * Run `ext` with `content` on stdin and return its output. A non-null
+ * `inputOverride` (such as text selected in the preview) is sent instead.
+ *
+ * New or edited commands must be approved once before they run: the user is
+ * asked to confirm the command, and the extension is retried if they accept.
+ */
+export async function runExtensionCommand(
+  ext: Extension,
+  content: string,
+  inputOverride: string | null = null,
+): Promise<string> {
+  const args = { command: ext.command, content, timeout: ext.timeout, inputOverride }
+  try {
+    return await invoke<string>('run_extension', args)
+  } catch (error) {
+    if (!String(error).startsWith(NOT_APPROVED_ERROR)) throw error
+
+    const allowed = window.confirm(
+      `Allow the extension "${ext.name}" to run this command?\n\n${ext.command}`,
+    )
+    if (!allowed) throw error
+
+    // Only the command shown above is approved, even if settings changed meanwhile
+    await invoke('confirm_extension', { name: ext.name, command: ext.command })
+    logger.info('Extension', `Approved "${ext.name}"`)
+    return await invoke<string>('run_extension', args)
+  }
+}
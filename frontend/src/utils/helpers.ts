@@ -2,6 +2,8 @@
  * Helper functions collection
  */
 
+import type { CommandError } from '../types'
+
 /** Format time as relative time */
 export function formatTime(createdAt: string): string {
   try {
@@ -71,3 +73,37 @@ export function getPreview(content: string, maxLength: number = 200): string {
     ? content.slice(0, maxLength) + '...'
     : content
 }
+
+/**
+ * Split search text into lowercase terms: words separated by whitespace, or a
+ * "quoted phrase" kept whole (an unclosed quote runs to the end). Mirrors
+ * `search_terms` in the backend so keyword search behaves the same everywhere.
+ */
+export function searchTerms(text: string): string[] {
+  const terms: string[] = []
+  const pattern = /"([^"]*)"?|[^\s"]+/g
+  for (const match of text.toLowerCase().matchAll(pattern)) {
+    const term = match[1] ?? match[0]
+    if (term.trim()) terms.push(term)
+  }
+  return terms
+}
+
+/** Whether every term appears in one of `fields` (terms from searchTerms, so already lowercase) */
+export function matchesSearch(terms: string[], ...fields: (string | null | undefined)[]): boolean {
+  const haystacks = fields.filter((f): f is string => !!f).map(f => f.toLowerCase())
+  return terms.every(term => haystacks.some(h => h.includes(term)))
+}
+
+/** Whether a rejected invoke() value is a coded CommandError */
+export function isCommandError(e: unknown): e is CommandError {
+  return typeof e === 'object' && e !== null
+    && typeof (e as CommandError).code === 'string'
+    && typeof (e as CommandError).message === 'string'
+}
+
+/** Human-readable message of a rejected invoke() value (coded error or plain string) */
+export function errorMessage(e: unknown): string {
+  if (isCommandError(e)) return e.message
+  return typeof e === 'string' ? e : String(e)
+}
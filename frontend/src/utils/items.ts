@@ -0,0 +1,18 @@
+/**
+ * History items whose content may have been cut for the list
+ */
+
+import { invoke } from '@tauri-apps/api/core'
+import type { ClipboardItem, HistoryPreview } from '../types'
+
+/**
+ * `item` with its full content. Rows from get_history_previews may only hold
+ * the start of long text; those are fetched again with get_item.
+ */
+export async function loadFullItem(item: ClipboardItem | HistoryPreview): Promise<ClipboardItem> {
+  if (!('truncated' in item) || !item.truncated) return item
+
+  const full = await invoke<ClipboardItem | null>('get_item', { id: item.id })
+  if (!full) throw new Error(`Item ${item.id} no longer exists`)
+  return full
+}
@@ -0,0 +1,252 @@
+//! Autostart module - Launch PowerClip at login
+//!
+//! Native per-platform login items, so no extra plugin is needed:
+//! - macOS: a LaunchAgent plist in `~/Library/LaunchAgents`
+//! - Windows: a value under `HKCU\Software\Microsoft\Windows\CurrentVersion\Run`
+//! - Linux: an XDG autostart `.desktop` entry in `~/.config/autostart`
+//!
+//! The OS state is the source of truth: `is_enabled` checks whether the login
+//! item is actually registered, even if it was changed outside the app.
+
+use std::path::PathBuf;
+
+use crate::config::APP_NAME;
+use crate::logger;
+
+/// Bundle identifier (matches `identifier` in tauri.conf.json)
+#[cfg(target_os = "macos")]
+const APP_IDENTIFIER: &str = "com.powerclip";
+
+/// Path of the executable to launch at login.
+fn executable_path() -> Result<PathBuf, String> {
+    // AppImages run from a temporary mount; the login item must point at the image itself
+    #[cfg(target_os = "linux")]
+    if let Some(appimage) = std::env::var_os("APPIMAGE") {
+        return Ok(PathBuf::from(appimage));
+    }
+
+    std::env::current_exe().map_err(|e| format!("Failed to resolve executable path: {}", e))
+}
+
+/// Register or unregister the login item.
+pub fn set_enabled(enabled: bool) -> Result<(), String> {
+    if enabled {
+        enable()?;
+    } else {
+        disable()?;
+    }
+    logger::info(
+        "Autostart",
+        &format!("Launch at login {}", if enabled { "enabled" } else { "disabled" }),
+    );
+    Ok(())
+}
+
+// ============================================================================
+// macOS
+// ============================================================================
+
+#[cfg(target_os = "macos")]
+fn launch_agent_path() -> Result<PathBuf, String> {
+    let home = dirs::home_dir().ok_or("Failed to resolve home directory")?;
+    Ok(home
+        .join("Library/LaunchAgents")
+        .join(format!("{}.plist", APP_IDENTIFIER)))
+}
+
+#[cfg(target_os = "macos")]
+fn launch_agent_plist(exe: &str) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+    <key>Label</key>
+    <string>{}</string>
+    <key>ProgramArguments</key>
+    <array>
+        <string>{}</string>
+    </array>
+    <key>RunAtLoad</key>
+    <true/>
+</dict>
+</plist>
+"#,
+        APP_IDENTIFIER,
+        xml_escape(exe)
+    )
+}
+
+#[cfg(target_os = "macos")]
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(target_os = "macos")]
+pub fn is_enabled() -> bool {
+    launch_agent_path().map(|p| p.exists()).unwrap_or(false)
+}
+
+#[cfg(target_os = "macos")]
+fn enable() -> Result<(), String> {
+    let path = launch_agent_path()?;
+    let exe = executable_path()?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    std::fs::write(&path, launch_agent_plist(&exe.to_string_lossy()))
+        .map_err(|e| format!("Failed to write launch agent: {}", e))
+}
+
+#[cfg(target_os = "macos")]
+fn disable() -> Result<(), String> {
+    remove_if_exists(&launch_agent_path()?)
+}
+
+// ============================================================================
+// Windows
+// ============================================================================
+
+#[cfg(target_os = "windows")]
+const RUN_KEY: &str = r"HKCU\Software\Microsoft\Windows\CurrentVersion\Run";
+
+/// Run `reg.exe` without flashing a console window.
+#[cfg(target_os = "windows")]
+fn reg(args: &[&str]) -> std::io::Result<std::process::Output> {
+    use std::os::windows::process::CommandExt;
+    const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+
+    std::process::Command::new("reg")
+        .args(args)
+        .creation_flags(CREATE_NO_WINDOW)
+        .output()
+}
+
+#[cfg(target_os = "windows")]
+pub fn is_enabled() -> bool {
+    reg(&["query", RUN_KEY, "/v", APP_NAME])
+        .map(|out| out.status.success())
+        .unwrap_or(false)
+}
+
+#[cfg(target_os = "windows")]
+fn enable() -> Result<(), String> {
+    let exe = executable_path()?;
+    let command = format!("\"{}\"", exe.to_string_lossy());
+    let out = reg(&["add", RUN_KEY, "/v", APP_NAME, "/t", "REG_SZ", "/d", &command, "/f"])
+        .map_err(|e| format!("Failed to run reg: {}", e))?;
+    if !out.status.success() {
+        return Err(format!(
+            "Failed to register login item: {}",
+            String::from_utf8_lossy(&out.stderr).trim()
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "windows")]
+fn disable() -> Result<(), String> {
+    if !is_enabled() {
+        return Ok(());
+    }
+    let out = reg(&["delete", RUN_KEY, "/v", APP_NAME, "/f"])
+        .map_err(|e| format!("Failed to run reg: {}", e))?;
+    if !out.status.success() {
+        return Err(format!(
+            "Failed to unregister login item: {}",
+            String::from_utf8_lossy(&out.stderr).trim()
+        ));
+    }
+    Ok(())
+}
+
+// ============================================================================
+// Linux
+// ============================================================================
+
+#[cfg(target_os = "linux")]
+fn desktop_entry_path() -> Result<PathBuf, String> {
+    let config = dirs::config_dir().ok_or("Failed to resolve config directory")?;
+    Ok(config
+        .join("autostart")
+        .join(format!("{}.desktop", APP_NAME.to_lowercase())))
+}
+
+#[cfg(target_os = "linux")]
+fn desktop_entry(exe: &str) -> String {
+    // Exec values with spaces or quotes must be quoted (Desktop Entry spec)
+    let exec = if exe.contains([' ', '"', '\\']) {
+        format!("\"{}\"", exe.replace('\\', "\\\\").replace('"', "\\\""))
+    } else {
+        exe.to_string()
+    };
+
+    format!(
+        "[Desktop Entry]\nType=Application\nName={}\nExec={}\nTerminal=false\nX-GNOME-Autostart-enabled=true\n",
+        APP_NAME, exec
+    )
+}
+
+#[cfg(target_os = "linux")]
+pub fn is_enabled() -> bool {
+    desktop_entry_path().map(|p| p.exists()).unwrap_or(false)
+}
+
+#[cfg(target_os = "linux")]
+fn enable() -> Result<(), String> {
+    let path = desktop_entry_path()?;
+    let exe = executable_path()?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    std::fs::write(&path, desktop_entry(&exe.to_string_lossy()))
+        .map_err(|e| format!("Failed to write autostart entry: {}", e))
+}
+
+#[cfg(target_os = "linux")]
+fn disable() -> Result<(), String> {
+    remove_if_exists(&desktop_entry_path()?)
+}
+
+#[cfg(any(target_os = "macos", target_os = "linux"))]
+fn remove_if_exists(path: &std::path::Path) -> Result<(), String> {
+    match std::fs::remove_file(path) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(format!("Failed to remove login item: {}", e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_executable_path() {
+        assert!(executable_path().is_ok());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_desktop_entry() {
+        let entry = desktop_entry("/usr/bin/powerclip");
+        assert!(entry.starts_with("[Desktop Entry]\n"));
+        assert!(entry.contains("Name=PowerClip\n"));
+        assert!(entry.contains("Exec=/usr/bin/powerclip\n"));
+
+        let entry = desktop_entry("/opt/Power Clip/powerclip");
+        assert!(entry.contains("Exec=\"/opt/Power Clip/powerclip\"\n"));
+    }
+
+    #[cfg(target_os = "macos")]
+    #[test]
+    fn test_launch_agent_plist() {
+        let plist = launch_agent_plist("/Applications/A&B.app/Contents/MacOS/PowerClip");
+        assert!(plist.contains("<string>com.powerclip</string>"));
+        assert!(plist.contains("<string>/Applications/A&amp;B.app/Contents/MacOS/PowerClip</string>"));
+        assert!(plist.contains("<key>RunAtLoad</key>"));
+    }
+}
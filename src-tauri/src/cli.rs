@@ -0,0 +1,265 @@
+//! Command-line interface - Headless history operations for scripts
+//!
+//! `powerclip --copy-latest`, `--search <text>` and `--export <file>` work
+//! directly on the history database and exit without opening a window.
+//! Without any of these flags the app starts normally.
+
+use std::path::{Path, PathBuf};
+
+use rusqlite::Connection;
+
+use crate::commands::history::write_text_or_files;
+use crate::commands::image::{copy_image_from_bytes, load_stored_image, set_max_paste_dimension};
+use crate::config::{self, CLI_SEARCH_LIMIT};
+use crate::db::{self, ClipboardItem, DatabaseState, HistoryQuery};
+use crate::{app_settings, logger, monitor};
+
+const USAGE: &str = "\
+Usage: powerclip [--copy-latest | --search <text> | --export <file>]
+
+  --copy-latest     Copy the newest history item to the clipboard
+  --search <text>   Print history items containing <text>, newest first
+  --export <file>   Write the whole history to <file> as JSON
+  --help            Show this message
+
+Without options, PowerClip starts normally.";
+
+/// Exit code for invalid arguments
+const EXIT_USAGE: i32 = 2;
+
+/// A headless operation requested on the command line.
+#[derive(Debug, PartialEq)]
+pub enum CliCommand {
+    CopyLatest,
+    Search(String),
+    Export(PathBuf),
+    Help,
+}
+
+/// Parse command-line arguments (without the program name).
+///
+/// Returns `None` when no headless operation was requested. Arguments that
+/// aren't ours are ignored, since launchers and the OS can add their own.
+pub fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Option<CliCommand>, String> {
+    let mut args = args.into_iter();
+    let mut command = None;
+
+    while let Some(arg) = args.next() {
+        let parsed = match arg.as_str() {
+            "--copy-latest" => CliCommand::CopyLatest,
+            "--search" => CliCommand::Search(args.next().ok_or("--search needs a search text")?),
+            "--export" => CliCommand::Export(args.next().ok_or("--export needs an output file")?.into()),
+            "-h" | "--help" => CliCommand::Help,
+            _ => continue,
+        };
+        if command.is_some() {
+            return Err("Only one of --copy-latest, --search and --export can be given".to_string());
+        }
+        command = Some(parsed);
+    }
+
+    Ok(command)
+}
+
+/// Run a headless operation if the arguments ask for one.
+///
+/// Returns the process exit code, or `None` to start the GUI.
+pub fn run_from_args(args: impl IntoIterator<Item = String>) -> Option<i32> {
+    let command = match parse_args(args) {
+        Ok(command) => command?,
+        Err(e) => {
+            attach_console();
+            eprintln!("powerclip: {}\n\n{}", e, USAGE);
+            return Some(EXIT_USAGE);
+        }
+    };
+
+    attach_console();
+    if command == CliCommand::Help {
+        println!("{}", USAGE);
+        return Some(0);
+    }
+
+    match execute(command) {
+        Ok(()) => Some(0),
+        Err(e) => {
+            logger::error("CLI", &e);
+            eprintln!("powerclip: {}", e);
+            Some(1)
+        }
+    }
+}
+
+fn execute(command: CliCommand) -> Result<(), String> {
+    config::ensure_dirs();
+    let state = DatabaseState::new().map_err(|e| format!("Failed to open history database: {}", e))?;
+    let conn = state.conn.lock().map_err(|e| e.to_string())?;
+
+    match command {
+        CliCommand::CopyLatest => copy_latest(&conn),
+        CliCommand::Search(text) => {
+            for item in search(&conn, &text)? {
+                println!("{}", search_line(&item));
+            }
+            Ok(())
+        }
+        CliCommand::Export(path) => {
+            let count = export_history(&conn, &path)?;
+            println!("Exported {} items to {}", count, path.display());
+            Ok(())
+        }
+        CliCommand::Help => Ok(()),
+    }
+}
+
+/// Copy the newest history item (ignoring favorites) to the clipboard.
+fn copy_latest(conn: &Connection) -> Result<(), String> {
+    let query = HistoryQuery {
+        sort: Some("newest".to_string()),
+        limit: Some(1),
+        ..Default::default()
+    };
+    let item = db::query_history(conn, &query)
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .next()
+        .ok_or("History is empty")?;
+
+    let settings = app_settings::load_settings_simple()?;
+    if item.item_type == "image" {
+        set_max_paste_dimension(settings.max_paste_dimension);
+        copy_image_from_bytes(&load_stored_image(Some(conn), &item)?)?;
+    } else {
+        monitor::set_normalize_line_endings(settings.normalize_line_endings);
+        write_text_or_files(&item)?;
+    }
+
+    logger::info("CLI", &format!("Copied item {} to clipboard", item.id));
+    Ok(())
+}
+
+/// History items containing `text` (case-insensitive), newest first.
+fn search(conn: &Connection, text: &str) -> Result<Vec<ClipboardItem>, String> {
+    let query = HistoryQuery {
+        text: Some(text.to_string()),
+        sort: Some("newest".to_string()),
+        limit: Some(CLI_SEARCH_LIMIT),
+        ..Default::default()
+    };
+    db::query_history(conn, &query).map_err(|e| e.to_string())
+}
+
+/// One tab-separated output line: id, type, and content with line breaks,
+/// tabs and backslashes escaped. Concealed content is not printed.
+fn search_line(item: &ClipboardItem) -> String {
+    let content = if item.is_concealed {
+        "[Concealed]".to_string()
+    } else {
+        escape_line(&item.content)
+    };
+    format!("{}\t{}\t{}", item.id, item.item_type, content)
+}
+
+fn escape_line(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Write every history item to `path` as a JSON array, newest first.
+///
+/// Returns the number of items written.
+fn export_history(conn: &Connection, path: &Path) -> Result<usize, String> {
+    let query = HistoryQuery {
+        sort: Some("newest".to_string()),
+        limit: Some(-1),
+        ..Default::default()
+    };
+    let items = db::query_history(conn, &query).map_err(|e| e.to_string())?;
+    let json = serde_json::to_vec_pretty(&items).map_err(|e| e.to_string())?;
+    config::write_atomic(path, &json).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    Ok(items.len())
+}
+
+/// Release builds on Windows use the GUI subsystem and start without a
+/// console, so output would be lost unless we attach to the caller's.
+#[cfg(target_os = "windows")]
+fn attach_console() {
+    use windows::Win32::System::Console::{AttachConsole, ATTACH_PARENT_PROCESS};
+
+    unsafe {
+        let _ = AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+}
+
+#[cfg(not(target_os = "windows"))]
+fn attach_console() {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_args() {
+        assert_eq!(parse_args(args(&[])), Ok(None));
+        assert_eq!(parse_args(args(&["--copy-latest"])), Ok(Some(CliCommand::CopyLatest)));
+        assert_eq!(parse_args(args(&["--search", "foo bar"])), Ok(Some(CliCommand::Search("foo bar".to_string()))));
+        assert_eq!(parse_args(args(&["--export", "out.json"])), Ok(Some(CliCommand::Export(PathBuf::from("out.json")))));
+        assert_eq!(parse_args(args(&["--help"])), Ok(Some(CliCommand::Help)));
+
+        // Arguments added by launchers don't turn on headless mode
+        assert_eq!(parse_args(args(&["-psn_0_12345"])), Ok(None));
+        assert_eq!(parse_args(args(&["-psn_0_12345", "--copy-latest"])), Ok(Some(CliCommand::CopyLatest)));
+    }
+
+    #[test]
+    fn test_parse_args_errors() {
+        assert!(parse_args(args(&["--search"])).is_err());
+        assert!(parse_args(args(&["--export"])).is_err());
+        assert!(parse_args(args(&["--copy-latest", "--search", "foo"])).is_err());
+    }
+
+    #[test]
+    fn test_search_line() {
+        let conn = Connection::open_in_memory().unwrap();
+        db::create_history_table(&conn).unwrap();
+        db::save_item(&conn, "text", "first\tline\nsecond \\ line", "h1").unwrap();
+        db::save_item(&conn, "text", "secret", "h2").unwrap();
+        conn.execute("UPDATE history SET is_concealed = 1 WHERE hash = 'h2'", []).unwrap();
+
+        let items = search(&conn, "line").unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(search_line(&items[0]), format!("{}\ttext\tfirst\\tline\\nsecond \\\\ line", items[0].id));
+
+        let concealed = search(&conn, "secret").unwrap();
+        assert_eq!(search_line(&concealed[0]), format!("{}\ttext\t[Concealed]", concealed[0].id));
+    }
+
+    #[test]
+    fn test_export_history() {
+        let conn = Connection::open_in_memory().unwrap();
+        db::create_history_table(&conn).unwrap();
+        db::save_item(&conn, "text", "older", "h1").unwrap();
+        db::save_item(&conn, "text", "newer", "h2").unwrap();
+
+        let path = std::env::temp_dir().join(format!("powerclip_cli_export_{}.json", std::process::id()));
+        assert_eq!(export_history(&conn, &path).unwrap(), 2);
+
+        let exported: Vec<ClipboardItem> = serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+        let _ = std::fs::remove_file(&path);
+        let contents: Vec<&str> = exported.iter().map(|item| item.content.as_str()).collect();
+        assert_eq!(contents, vec!["newer", "older"]);
+    }
+}
@@ -0,0 +1,206 @@
+//! Code detection - Guess whether copied text is source code, and in which language
+//!
+//! A cheap line-based heuristic, not a parser: it only needs to tell likely
+//! code from prose so the UI can render it monospace with a highlighting hint.
+
+/// Only the first part of large texts is inspected.
+const MAX_SCAN_CHARS: usize = 4_000;
+
+/// Minimum share of code-looking lines for untagged text to count as code.
+const MIN_CODE_LINE_RATIO: f32 = 0.5;
+
+/// Language returned for text that looks like code but matches no signature.
+pub const UNKNOWN_LANGUAGE: &str = "unknown";
+
+/// Per-language markers; a language needs at least two hits (or one strong marker).
+struct Signature {
+    language: &'static str,
+    /// A single match is enough
+    strong: &'static [&'static str],
+    /// Each match counts once
+    weak: &'static [&'static str],
+}
+
+const SIGNATURES: &[Signature] = &[
+    Signature {
+        language: "rust",
+        strong: &["fn main()", "impl ", "pub fn ", "#[derive(", "let mut "],
+        weak: &["fn ", "->", "::", "let ", "match ", "use ", "&self", "Option<", "Result<"],
+    },
+    Signature {
+        language: "python",
+        strong: &["def __init__(", "if __name__ == ", "elif "],
+        weak: &["def ", "import ", "from ", "self.", "print(", "None", "True:", "):"],
+    },
+    Signature {
+        language: "typescript",
+        strong: &["interface ", ": string", ": number", ": boolean", "export type "],
+        weak: &["const ", "=>", "import ", "export "],
+    },
+    Signature {
+        language: "javascript",
+        strong: &["console.log(", "function ", "module.exports", "require("],
+        weak: &["const ", "let ", "=>", "===", "document.", "async ", "await "],
+    },
+    Signature {
+        language: "go",
+        strong: &["package main", "func main()", "fmt."],
+        weak: &["func ", ":=", "package ", "err != nil"],
+    },
+    Signature {
+        language: "java",
+        strong: &["public static void main", "System.out.", "public class "],
+        weak: &["private ", "public ", "void ", "new ", "import java"],
+    },
+    Signature {
+        language: "cpp",
+        strong: &["std::", "#include <iostream>", "template<", "nullptr"],
+        weak: &["#include", "int main(", "->", "::"],
+    },
+    Signature {
+        language: "c",
+        strong: &["#include <stdio.h>", "printf(", "malloc("],
+        weak: &["#include", "int main(", "void ", "struct "],
+    },
+    Signature {
+        language: "shell",
+        strong: &["#!/bin/", "#!/usr/bin/env bash", "#!/usr/bin/env sh"],
+        weak: &["echo ", "sudo ", "export ", "; then", "; do", "$(", "&& "],
+    },
+    Signature {
+        language: "sql",
+        strong: &["SELECT ", "INSERT INTO ", "CREATE TABLE ", "UPDATE ", "DELETE FROM "],
+        weak: &[" FROM ", " WHERE ", " JOIN ", "GROUP BY", "ORDER BY"],
+    },
+    Signature {
+        language: "html",
+        strong: &["<!DOCTYPE html", "<html", "<div", "</div>", "<script"],
+        weak: &["</", "class=\"", "<span", "<p>"],
+    },
+    Signature {
+        language: "css",
+        strong: &["@media ", "!important"],
+        weak: &["px;", "color:", "margin:", "padding:", "display:"],
+    },
+];
+
+/// Guess the language of `text`.
+///
+/// Returns `None` for prose, a language name when a signature matches, or
+/// `UNKNOWN_LANGUAGE` for text that looks like code without a clear language.
+pub fn detect_language(text: &str) -> Option<&'static str> {
+    let text = match text.char_indices().nth(MAX_SCAN_CHARS) {
+        Some((end, _)) => &text[..end],
+        None => text,
+    };
+    let trimmed = text.trim();
+    if trimmed.len() < 8 {
+        return None;
+    }
+
+    if is_json(trimmed) {
+        return Some("json");
+    }
+
+    let lines: Vec<&str> = trimmed.lines().filter(|l| !l.trim().is_empty()).collect();
+    let code_ratio = lines.iter().filter(|l| looks_like_code_line(l)).count() as f32 / lines.len() as f32;
+
+    // Keywords alone aren't enough: prose like "import the files from..." has no code structure
+    if code_ratio > 0.0 {
+        if let Some(language) = best_signature(trimmed) {
+            return Some(language);
+        }
+    }
+
+    (lines.len() > 1 && code_ratio >= MIN_CODE_LINE_RATIO).then_some(UNKNOWN_LANGUAGE)
+}
+
+fn is_json(text: &str) -> bool {
+    let object = text.starts_with('{') && text.ends_with('}');
+    let array = text.starts_with('[') && text.ends_with(']');
+    (object || array) && serde_json::from_str::<serde_json::Value>(text).is_ok()
+}
+
+/// Pick the language with the highest score, if any qualifies.
+fn best_signature(text: &str) -> Option<&'static str> {
+    SIGNATURES
+        .iter()
+        .filter_map(|sig| {
+            let strong = sig.strong.iter().filter(|m| text.contains(*m)).count();
+            let weak = sig.weak.iter().filter(|m| text.contains(*m)).count();
+            let score = strong * 3 + weak;
+            (strong > 0 || weak >= 2).then_some((sig.language, score))
+        })
+        // On ties the earlier (more specific) signature wins
+        .fold(None, |best: Option<(&str, usize)>, (language, score)| match best {
+            Some((_, best_score)) if best_score >= score => best,
+            _ => Some((language, score)),
+        })
+        .map(|(language, _)| language)
+}
+
+/// Structural hints: statement terminators, braces, or indentation.
+fn looks_like_code_line(line: &str) -> bool {
+    let trimmed = line.trim_end();
+    let indented = line.starts_with("    ") || line.starts_with('\t');
+    let ends_like_code = trimmed.ends_with(';')
+        || trimmed.ends_with('{')
+        || trimmed.ends_with('}')
+        || trimmed.ends_with(')')
+        || (trimmed.ends_with(':') && indented);
+    let starts_like_code = matches!(trimmed.trim_start().chars().next(), Some('}' | ')' | ']' | '#' | '<'))
+        || trimmed.trim_start().starts_with("//");
+
+    ends_like_code || starts_like_code || indented
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_rust() {
+        let code = "fn main() {\n    let mut items = Vec::new();\n    items.push(1);\n}";
+        assert_eq!(detect_language(code), Some("rust"));
+    }
+
+    #[test]
+    fn test_detect_python() {
+        let code = "def greet(name):\n    if name:\n        print(name)\n    return None";
+        assert_eq!(detect_language(code), Some("python"));
+    }
+
+    #[test]
+    fn test_detect_javascript_and_sql() {
+        let js = "function add(a, b) {\n  console.log(a);\n  return a + b;\n}";
+        assert_eq!(detect_language(js), Some("javascript"));
+
+        assert_eq!(detect_language("SELECT id, name FROM users WHERE id = 1;"), Some("sql"));
+    }
+
+    #[test]
+    fn test_detect_json() {
+        assert_eq!(detect_language(r#"{"name": "PowerClip", "tags": [1, 2]}"#), Some("json"));
+        assert_ne!(detect_language("{not json at all}"), Some("json"));
+    }
+
+    #[test]
+    fn test_detect_unknown_code() {
+        let code = "foo {\n    bar = 1;\n    baz = 2;\n}";
+        assert_eq!(detect_language(code), Some(UNKNOWN_LANGUAGE));
+    }
+
+    #[test]
+    fn test_prose_is_not_code() {
+        assert_eq!(detect_language("Hello there"), None);
+        assert_eq!(
+            detect_language("Let me know if the meeting works for you.\nI will bring the notes from last week."),
+            None
+        );
+        assert_eq!(
+            detect_language("Please import the files from the shared drive.\nThanks for your help today."),
+            None
+        );
+        assert_eq!(detect_language("Quick update: import the data from the old server"), None);
+    }
+}
@@ -0,0 +1,192 @@
+//! Command-line image fallback for Linux
+//!
+//! Some Wayland compositors (and a few X11 setups) don't give arboard a usable
+//! image, so reads fail and images are never captured. When that happens we
+//! ask `wl-paste` or `xclip` for `image/png` instead. The same tools fetch the
+//! original of a copied GIF (`image/gif`), which arboard can't read.
+
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+
+use crate::logger;
+
+use super::ImageData;
+
+/// External tool used to read images from the clipboard.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Backend {
+    WlPaste,
+    Xclip,
+}
+
+impl Backend {
+    fn program(self) -> &'static str {
+        match self {
+            Backend::WlPaste => "wl-paste",
+            Backend::Xclip => "xclip",
+        }
+    }
+
+    fn list_types_args(self) -> &'static [&'static str] {
+        match self {
+            Backend::WlPaste => &["--list-types"],
+            Backend::Xclip => &["-selection", "clipboard", "-t", "TARGETS", "-o"],
+        }
+    }
+
+    fn read_png_args(self) -> &'static [&'static str] {
+        match self {
+            Backend::WlPaste => &["--no-newline", "--type", "image/png"],
+            Backend::Xclip => &["-selection", "clipboard", "-t", "image/png", "-o"],
+        }
+    }
+
+    fn read_gif_args(self) -> &'static [&'static str] {
+        match self {
+            Backend::WlPaste => &["--no-newline", "--type", "image/gif"],
+            Backend::Xclip => &["-selection", "clipboard", "-t", "image/gif", "-o"],
+        }
+    }
+}
+
+/// Backend for this session, detected once. `None` when neither tool applies.
+static BACKEND: OnceLock<Option<Backend>> = OnceLock::new();
+
+/// Set after the first image captured through the fallback has been logged.
+static FALLBACK_LOGGED: AtomicBool = AtomicBool::new(false);
+
+/// Pick a backend from the display server in use and the tools installed.
+fn choose_backend(wayland: bool, x11: bool, installed: impl Fn(&str) -> bool) -> Option<Backend> {
+    if wayland && installed(Backend::WlPaste.program()) {
+        return Some(Backend::WlPaste);
+    }
+    // XWayland sessions also set DISPLAY, so xclip works on Wayland too
+    if x11 && installed(Backend::Xclip.program()) {
+        return Some(Backend::Xclip);
+    }
+    None
+}
+
+fn detect_backend() -> Option<Backend> {
+    let backend = choose_backend(
+        std::env::var_os("WAYLAND_DISPLAY").is_some(),
+        std::env::var_os("DISPLAY").is_some(),
+        is_on_path,
+    );
+    match backend {
+        Some(backend) => logger::info(
+            "Clipboard",
+            &format!("Image fallback available via {}", backend.program()),
+        ),
+        None => logger::debug("Clipboard", "No image fallback available (install wl-clipboard or xclip)"),
+    }
+    backend
+}
+
+fn is_on_path(program: &str) -> bool {
+    std::env::var_os("PATH")
+        .map(|paths| std::env::split_paths(&paths).any(|dir| dir.join(program).is_file()))
+        .unwrap_or(false)
+}
+
+/// Whether a type listing (one MIME type per line) includes PNG.
+fn offers_png(types: &str) -> bool {
+    types.lines().any(|line| line.trim() == "image/png")
+}
+
+/// Whether a type listing (one MIME type per line) includes GIF.
+fn offers_gif(types: &str) -> bool {
+    types.lines().any(|line| line.trim() == "image/gif")
+}
+
+fn run(backend: Backend, args: &[&str]) -> Option<Vec<u8>> {
+    let output = Command::new(backend.program())
+        .args(args)
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    output.status.success().then_some(output.stdout)
+}
+
+/// MIME types currently offered on the clipboard, one per line.
+///
+/// Returns `None` when no backend is available.
+pub(super) fn offered_types() -> Option<String> {
+    let backend = (*BACKEND.get_or_init(detect_backend))?;
+    let types = run(backend, backend.list_types_args())?;
+    Some(String::from_utf8_lossy(&types).into_owned())
+}
+
+/// Read a PNG image from the clipboard with `wl-paste` or `xclip`.
+///
+/// Returns `None` when no backend is available or the clipboard holds no image.
+pub(super) fn read_image() -> Option<ImageData> {
+    let backend = (*BACKEND.get_or_init(detect_backend))?;
+
+    // Checking the offered types first avoids reading large non-image payloads
+    if !offers_png(&offered_types()?) {
+        return None;
+    }
+
+    let png = run(backend, backend.read_png_args())?;
+    let image = match image::load_from_memory_with_format(&png, image::ImageFormat::Png) {
+        Ok(image) => image.to_rgba8(),
+        Err(e) => {
+            logger::debug("Clipboard", &format!("{} returned an unreadable PNG: {}", backend.program(), e));
+            return None;
+        }
+    };
+
+    if !FALLBACK_LOGGED.swap(true, Ordering::Relaxed) {
+        logger::info("Clipboard", &format!("Captured image via {} fallback", backend.program()));
+    }
+
+    let (width, height) = image.dimensions();
+    Some(ImageData { bytes: image.into_raw(), width, height, gif: None })
+}
+
+/// Read the clipboard's `image/gif` data with `wl-paste` or `xclip`.
+///
+/// Returns `None` when no backend is available or the clipboard offers no GIF.
+pub(super) fn read_gif() -> Option<Vec<u8>> {
+    let backend = (*BACKEND.get_or_init(detect_backend))?;
+    if !offers_gif(&offered_types()?) {
+        return None;
+    }
+    run(backend, backend.read_gif_args())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_choose_backend() {
+        let all = |_: &str| true;
+        let none = |_: &str| false;
+        let only_xclip = |p: &str| p == "xclip";
+
+        assert_eq!(choose_backend(true, true, all), Some(Backend::WlPaste));
+        assert_eq!(choose_backend(true, true, only_xclip), Some(Backend::Xclip));
+        assert_eq!(choose_backend(true, false, only_xclip), None);
+        assert_eq!(choose_backend(false, true, all), Some(Backend::Xclip));
+        assert_eq!(choose_backend(true, true, none), None);
+        assert_eq!(choose_backend(false, false, all), None);
+    }
+
+    #[test]
+    fn test_offers_png() {
+        assert!(offers_png("text/plain\nimage/png\nTARGETS\n"));
+        assert!(offers_png("image/png"));
+        assert!(!offers_png("text/plain;charset=utf-8\nimage/jpeg\n"));
+        assert!(!offers_png(""));
+    }
+
+    #[test]
+    fn test_offers_gif() {
+        assert!(offers_gif("text/html\nimage/gif\nimage/png\n"));
+        assert!(!offers_gif("image/png\n"));
+    }
+}
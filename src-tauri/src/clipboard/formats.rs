@@ -0,0 +1,126 @@
+//! Formats currently offered on the clipboard, for diagnostics
+//!
+//! Each platform names formats its own way: pasteboard types on macOS
+//! (e.g. "public.utf8-plain-text"), format names on Windows (e.g.
+//! "CF_UNICODETEXT", "HTML Format") and MIME types on Linux. Listing them
+//! doesn't read or consume the content.
+
+use super::ClipboardContent;
+
+/// X11 selection targets that describe the selection itself rather than a format
+#[cfg(target_os = "linux")]
+const X11_META_TARGETS: &[&str] = &["TARGETS", "TIMESTAMP", "MULTIPLE", "SAVE_TARGETS", "DELETE"];
+
+/// Formats offered on the clipboard, or `None` where they can't be listed.
+#[cfg(target_os = "macos")]
+pub(super) fn offered_formats() -> Option<Vec<String>> {
+    use objc2_app_kit::NSPasteboard;
+
+    let pasteboard = NSPasteboard::generalPasteboard();
+    let types = pasteboard.types()?;
+    Some(types.iter().map(|t| t.to_string()).collect())
+}
+
+#[cfg(target_os = "windows")]
+pub(super) fn offered_formats() -> Option<Vec<String>> {
+    use windows::Win32::Foundation::HWND;
+    use windows::Win32::System::DataExchange::{CloseClipboard, EnumClipboardFormats, OpenClipboard};
+
+    unsafe {
+        OpenClipboard(HWND(std::ptr::null_mut())).ok()?;
+
+        let mut formats = Vec::new();
+        let mut format = EnumClipboardFormats(0);
+        while format != 0 {
+            formats.push(windows_format_name(format));
+            format = EnumClipboardFormats(format);
+        }
+
+        let _ = CloseClipboard();
+        Some(formats)
+    }
+}
+
+#[cfg(target_os = "linux")]
+pub(super) fn offered_formats() -> Option<Vec<String>> {
+    super::fallback::offered_types().map(|types| parse_type_listing(&types))
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows", target_os = "linux")))]
+pub(super) fn offered_formats() -> Option<Vec<String>> {
+    None
+}
+
+/// Name of a Windows clipboard format: the CF_ constant for predefined
+/// formats, the registered name for the rest.
+#[cfg(target_os = "windows")]
+fn windows_format_name(format: u32) -> String {
+    use windows::Win32::System::DataExchange::GetClipboardFormatNameW;
+
+    const STANDARD: &[&str] = &[
+        "CF_TEXT", "CF_BITMAP", "CF_METAFILEPICT", "CF_SYLK", "CF_DIF", "CF_TIFF", "CF_OEMTEXT",
+        "CF_DIB", "CF_PALETTE", "CF_PENDATA", "CF_RIFF", "CF_WAVE", "CF_UNICODETEXT",
+        "CF_ENHMETAFILE", "CF_HDROP", "CF_LOCALE", "CF_DIBV5",
+    ];
+    if let Some(name) = (format as usize).checked_sub(1).and_then(|i| STANDARD.get(i)) {
+        return name.to_string();
+    }
+
+    let mut name = [0u16; 256];
+    let len = unsafe { GetClipboardFormatNameW(format, &mut name) };
+    if len > 0 {
+        String::from_utf16_lossy(&name[..len as usize])
+    } else {
+        format!("#{}", format)
+    }
+}
+
+/// Formats from a type listing (one per line), without X11 meta targets,
+/// blank lines or repeats.
+#[cfg(target_os = "linux")]
+fn parse_type_listing(types: &str) -> Vec<String> {
+    let mut formats: Vec<String> = Vec::new();
+    for line in types.lines().map(str::trim) {
+        if line.is_empty() || X11_META_TARGETS.contains(&line) || formats.iter().any(|f| f == line) {
+            continue;
+        }
+        formats.push(line.to_string());
+    }
+    formats
+}
+
+/// Best-effort formats for content read through arboard, used where the
+/// offered formats can't be listed.
+pub(super) fn content_formats(content: &ClipboardContent) -> Vec<String> {
+    let format = match content {
+        ClipboardContent::Text(_) => "text/plain",
+        ClipboardContent::Image(image) if image.gif.is_some() => "image/gif",
+        ClipboardContent::Image(_) => "image/png",
+        ClipboardContent::Files(_) => "text/uri-list",
+    };
+    vec![format.to_string()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clipboard::{FileData, ImageData};
+
+    #[test]
+    fn test_content_formats() {
+        assert_eq!(content_formats(&ClipboardContent::Text("hi".to_string())), vec!["text/plain"]);
+        let image = ImageData { bytes: vec![0; 4], width: 1, height: 1, gif: None };
+        assert_eq!(content_formats(&ClipboardContent::Image(image)), vec!["image/png"]);
+        let files = FileData { paths: vec!["/tmp/a".to_string()] };
+        assert_eq!(content_formats(&ClipboardContent::Files(files)), vec!["text/uri-list"]);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_parse_type_listing() {
+        let xclip = "TIMESTAMP\nTARGETS\nMULTIPLE\ntext/html\ntext/plain\nUTF8_STRING\ntext/plain\n";
+        assert_eq!(parse_type_listing(xclip), vec!["text/html", "text/plain", "UTF8_STRING"]);
+        assert_eq!(parse_type_listing("image/png\n\n"), vec!["image/png"]);
+        assert!(parse_type_listing("").is_empty());
+    }
+}
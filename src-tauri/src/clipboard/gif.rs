@@ -0,0 +1,181 @@
+//! GIF clipboard data - Keeping animated GIFs intact
+//!
+//! Apps that copy a GIF usually offer a bitmap of its first frame next to the
+//! original file. The bitmap is what arboard reads, so the original bytes are
+//! looked up separately and stored as is. Copying back offers both again:
+//! apps that understand GIFs get the animation, the rest paste the bitmap.
+//!
+//! Windows uses the registered "GIF" format and macOS the `com.compuserve.gif`
+//! pasteboard type. On Linux GIFs can be read (`image/gif`) but only the
+//! bitmap is written, since the clipboard owner offers a single image format.
+
+/// Signatures of the two GIF versions.
+const GIF_SIGNATURES: &[&[u8]] = &[b"GIF87a", b"GIF89a"];
+
+/// Pasteboard type of GIF data on macOS
+#[cfg(target_os = "macos")]
+const MACOS_GIF_TYPE: &str = "com.compuserve.gif";
+
+/// Whether `data` is a GIF file.
+pub fn is_gif(data: &[u8]) -> bool {
+    GIF_SIGNATURES.iter().any(|signature| data.starts_with(signature))
+}
+
+/// The original GIF bytes of the image on the clipboard, if it offers them.
+///
+/// `fingerprint` identifies the current content; platforms where checking is
+/// expensive only check again when it changes.
+#[cfg(target_os = "macos")]
+pub(super) fn read_gif(_fingerprint: u64) -> Option<Vec<u8>> {
+    use objc2_app_kit::NSPasteboard;
+    use objc2_foundation::NSString;
+
+    let pasteboard = NSPasteboard::generalPasteboard();
+    let data = pasteboard.dataForType(&NSString::from_str(MACOS_GIF_TYPE))?;
+    Some(data.to_vec()).filter(|bytes| is_gif(bytes))
+}
+
+#[cfg(target_os = "windows")]
+pub(super) fn read_gif(_fingerprint: u64) -> Option<Vec<u8>> {
+    use windows::core::w;
+    use windows::Win32::Foundation::{HGLOBAL, HWND};
+    use windows::Win32::System::DataExchange::{
+        CloseClipboard, GetClipboardData, IsClipboardFormatAvailable, OpenClipboard, RegisterClipboardFormatW,
+    };
+    use windows::Win32::System::Memory::{GlobalLock, GlobalSize, GlobalUnlock};
+
+    unsafe {
+        let format = RegisterClipboardFormatW(w!("GIF"));
+        if format == 0 || IsClipboardFormatAvailable(format).is_err() {
+            return None;
+        }
+        OpenClipboard(HWND(std::ptr::null_mut())).ok()?;
+
+        let mut bytes = None;
+        if let Ok(handle) = GetClipboardData(format) {
+            let h_global = HGLOBAL(handle.0);
+            let ptr = GlobalLock(h_global);
+            if !ptr.is_null() {
+                bytes = Some(std::slice::from_raw_parts(ptr as *const u8, GlobalSize(h_global)).to_vec());
+                let _ = GlobalUnlock(h_global);
+            }
+        }
+
+        let _ = CloseClipboard();
+        bytes.map(trim_padding).filter(|bytes| is_gif(bytes))
+    }
+}
+
+/// Last content checked on Linux and its GIF. Listing the offered types
+/// spawns a process, so it's only done when the content changes.
+#[cfg(target_os = "linux")]
+static LAST_CHECK: std::sync::Mutex<Option<(u64, Option<Vec<u8>>)>> = std::sync::Mutex::new(None);
+
+#[cfg(target_os = "linux")]
+pub(super) fn read_gif(fingerprint: u64) -> Option<Vec<u8>> {
+    let mut last = LAST_CHECK.lock().ok()?;
+    if let Some((checked, gif)) = &*last {
+        if *checked == fingerprint {
+            return gif.clone();
+        }
+    }
+
+    let gif = super::fallback::read_gif().filter(|bytes| is_gif(bytes));
+    *last = Some((fingerprint, gif.clone()));
+    gif
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows", target_os = "linux")))]
+pub(super) fn read_gif(_fingerprint: u64) -> Option<Vec<u8>> {
+    None
+}
+
+/// Offer `gif` on the clipboard next to the bitmap just written.
+///
+/// Must run right after the bitmap is set, without clearing the clipboard.
+#[cfg(target_os = "macos")]
+pub(super) fn add_gif(gif: &[u8]) -> Result<(), String> {
+    use objc2_app_kit::NSPasteboard;
+    use objc2_foundation::{NSData, NSString};
+
+    let pasteboard = NSPasteboard::generalPasteboard();
+    let data = NSData::with_bytes(gif);
+    if pasteboard.setData_forType(Some(&data), &NSString::from_str(MACOS_GIF_TYPE)) {
+        Ok(())
+    } else {
+        Err("The pasteboard refused the GIF data".to_string())
+    }
+}
+
+#[cfg(target_os = "windows")]
+pub(super) fn add_gif(gif: &[u8]) -> Result<(), String> {
+    use windows::core::w;
+    use windows::Win32::Foundation::{GlobalFree, HANDLE, HWND};
+    use windows::Win32::System::DataExchange::{CloseClipboard, OpenClipboard, RegisterClipboardFormatW, SetClipboardData};
+    use windows::Win32::System::Memory::{GlobalAlloc, GlobalLock, GlobalUnlock, GMEM_MOVEABLE};
+
+    unsafe {
+        let format = RegisterClipboardFormatW(w!("GIF"));
+        if format == 0 {
+            return Err("Failed to register the GIF clipboard format".to_string());
+        }
+
+        let h_mem = GlobalAlloc(GMEM_MOVEABLE, gif.len()).map_err(|e| format!("Failed to allocate memory: {}", e))?;
+        let ptr = GlobalLock(h_mem) as *mut u8;
+        if ptr.is_null() {
+            let _ = GlobalFree(h_mem);
+            return Err("Failed to lock memory".to_string());
+        }
+        std::ptr::copy_nonoverlapping(gif.as_ptr(), ptr, gif.len());
+        let _ = GlobalUnlock(h_mem);
+
+        if OpenClipboard(HWND(std::ptr::null_mut())).is_err() {
+            let _ = GlobalFree(h_mem);
+            return Err("Failed to open clipboard".to_string());
+        }
+        // Not emptied: the bitmap set just before stays alongside
+        let result = SetClipboardData(format, HANDLE(h_mem.0));
+        let _ = CloseClipboard();
+
+        // On success the clipboard owns the memory
+        if result.is_err() {
+            let _ = GlobalFree(h_mem);
+            return Err("Failed to set clipboard data".to_string());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+pub(super) fn add_gif(_gif: &[u8]) -> Result<(), String> {
+    Err("GIF clipboard data is only written on macOS and Windows".to_string())
+}
+
+/// `bytes` without the zero padding Windows may add after the GIF trailer.
+#[cfg(any(target_os = "windows", test))]
+fn trim_padding(mut bytes: Vec<u8>) -> Vec<u8> {
+    let len = bytes.iter().rposition(|&b| b != 0).map_or(0, |last| last + 1);
+    bytes.truncate(len);
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_gif() {
+        assert!(is_gif(b"GIF89a\x01\x00\x01\x00"));
+        assert!(is_gif(b"GIF87a"));
+        assert!(!is_gif(b"GIF8"));
+        assert!(!is_gif(&[0x89, 0x50, 0x4E, 0x47]));
+        assert!(!is_gif(b""));
+    }
+
+    #[test]
+    fn test_trim_padding() {
+        assert_eq!(trim_padding(b"GIF89a\x3B\0\0\0".to_vec()), b"GIF89a\x3B");
+        assert_eq!(trim_padding(b"GIF89a\x3B".to_vec()), b"GIF89a\x3B");
+        assert!(trim_padding(vec![0, 0]).is_empty());
+    }
+}
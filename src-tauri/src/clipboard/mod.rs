@@ -3,8 +3,30 @@
 //! Provides a cross-platform interface for reading and writing clipboard content.
 //! On macOS, clipboard operations must run on the main thread due to AppKit requirements.
 
+use std::borrow::Cow;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::time::Duration;
+
 use arboard::Clipboard;
 
+use crate::config::{
+    BINARY_TEXT_CONTROL_RATIO, CLIPBOARD_FAILURE_WARN_THRESHOLD, CLIPBOARD_READ_RETRIES,
+    CLIPBOARD_RETRY_BACKOFF_MS, GARBLED_TEXT_REPLACEMENT_RATIO,
+};
+use crate::logger;
+
+pub mod code;
+#[cfg(target_os = "linux")]
+mod fallback;
+mod formats;
+pub mod gif;
+mod sensitive;
+mod source;
+pub mod transforms;
+
+pub use source::source_app;
+
 /// Clipboard content variants.
 #[derive(Debug, Clone)]
 pub enum ClipboardContent {
@@ -16,9 +38,12 @@ pub enum ClipboardContent {
 /// Image data extracted from clipboard.
 #[derive(Debug, Clone)]
 pub struct ImageData {
+    /// RGBA pixels (the first frame of a GIF)
     pub bytes: Vec<u8>,
     pub width: u32,
     pub height: u32,
+    /// The original file when the clipboard also offered the image as a GIF
+    pub gif: Option<Vec<u8>>,
 }
 
 /// File data extracted from clipboard.
@@ -28,6 +53,23 @@ pub struct FileData {
     pub paths: Vec<String>,
 }
 
+/// Convert `\r\n` line endings to `\n`.
+pub fn normalize_line_endings(text: &str) -> Cow<'_, str> {
+    if text.contains("\r\n") {
+        Cow::Owned(text.replace("\r\n", "\n"))
+    } else {
+        Cow::Borrowed(text)
+    }
+}
+
+/// Convert `\n` line endings to `\r\n`, leaving existing `\r\n` alone.
+pub fn to_crlf(text: &str) -> Cow<'_, str> {
+    if !text.contains('\n') {
+        return Cow::Borrowed(text);
+    }
+    Cow::Owned(normalize_line_endings(text).replace('\n', "\r\n"))
+}
+
 /// Get current clipboard content (image has priority over text).
 ///
 /// Returns `None` if clipboard is empty or unavailable.
@@ -57,9 +99,202 @@ pub fn get_clipboard_content() -> Option<ClipboardContent> {
     get_clipboard_content_impl()
 }
 
+/// Format identifiers the clipboard currently offers, without consuming them.
+///
+/// Where formats can't be listed, reports what `get_clipboard_content` can
+/// read instead ("text/plain", "image/png" or "text/uri-list").
+///
+/// On macOS, this function dispatches to the main thread for AppKit compatibility.
+#[cfg(target_os = "macos")]
+pub fn list_clipboard_formats() -> Vec<String> {
+    use std::sync::{Arc, Mutex};
+
+    // Dispatching synchronously from the main thread itself would deadlock
+    if objc2::MainThreadMarker::new().is_some() {
+        return list_clipboard_formats_impl();
+    }
+
+    let result = Arc::new(Mutex::new(Vec::new()));
+    let result_clone = result.clone();
+
+    dispatch::Queue::main().exec_sync(move || {
+        *result_clone.lock().unwrap() = list_clipboard_formats_impl();
+    });
+
+    let guard = result.lock().unwrap();
+    guard.clone()
+}
+
+/// Format identifiers the clipboard currently offers, without consuming them.
+///
+/// Where formats can't be listed, reports what `get_clipboard_content` can
+/// read instead ("text/plain", "image/png" or "text/uri-list").
+#[cfg(not(target_os = "macos"))]
+pub fn list_clipboard_formats() -> Vec<String> {
+    list_clipboard_formats_impl()
+}
+
+fn list_clipboard_formats_impl() -> Vec<String> {
+    if let Some(formats) = formats::offered_formats() {
+        return formats;
+    }
+    logger::debug("Clipboard", "Can't list clipboard formats here, reporting readable content only");
+    read_clipboard_content()
+        .map(|content| formats::content_formats(&content))
+        .unwrap_or_default()
+}
+
+/// Number of clipboard reads in a row that failed (not merely empty).
+static CONSECUTIVE_READ_FAILURES: AtomicU32 = AtomicU32::new(0);
+
+/// Whether an arboard error is likely to go away on a retry.
+fn is_transient(err: &arboard::Error) -> bool {
+    matches!(err, arboard::Error::ClipboardOccupied | arboard::Error::Unknown { .. })
+}
+
+/// Run a clipboard operation, retrying transient errors with exponential backoff.
+fn with_retry<T>(mut op: impl FnMut() -> Result<T, arboard::Error>) -> Result<T, arboard::Error> {
+    let mut delay = CLIPBOARD_RETRY_BACKOFF_MS;
+    let mut attempt = 0;
+    loop {
+        match op() {
+            Err(e) if is_transient(&e) && attempt < CLIPBOARD_READ_RETRIES => {
+                attempt += 1;
+                std::thread::sleep(Duration::from_millis(delay));
+                delay *= 2;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Read image or text via arboard.
+///
+/// `Ok(None)` means the clipboard is empty (or holds nothing we record);
+/// `Err` means the clipboard could not be read at all.
+fn read_arboard_content() -> Result<Option<ClipboardContent>, arboard::Error> {
+    let mut clipboard = with_retry(Clipboard::new)?;
+
+    match with_retry(|| clipboard.get_image()) {
+        Ok(image) if !image.bytes.is_empty() => {
+            return Ok(Some(with_original_gif(ImageData {
+                bytes: image.bytes.to_vec(),
+                width: image.width as u32,
+                height: image.height as u32,
+                gif: None,
+            })));
+        }
+        Ok(_) | Err(arboard::Error::ContentNotAvailable) => {}
+        Err(e) => {
+            if let Some(image) = read_image_fallback() {
+                return Ok(Some(with_original_gif(image)));
+            }
+            // An image we can't convert: fall through to text
+            if !matches!(e, arboard::Error::ConversionFailure) {
+                return Err(e);
+            }
+        }
+    }
+
+    match with_retry(|| clipboard.get_text()) {
+        Ok(text) if is_garbled(&text) => {
+            log_rejected_text(&text, "failed to decode");
+            Ok(None)
+        }
+        Ok(text) if is_binary(&text) => {
+            log_rejected_text(&text, "looks like binary data");
+            Ok(None)
+        }
+        Ok(text) if !text.is_empty() && !text.contains('\0') => Ok(Some(ClipboardContent::Text(text))),
+        Ok(_) | Err(arboard::Error::ContentNotAvailable | arboard::Error::ConversionFailure) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Read an image with an external tool after arboard failed to (Linux only).
+#[cfg(target_os = "linux")]
+fn read_image_fallback() -> Option<ImageData> {
+    fallback::read_image()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn read_image_fallback() -> Option<ImageData> {
+    None
+}
+
+/// Fingerprint of the last rejected text, so each one is logged once.
+static LAST_REJECTED_TEXT: AtomicU64 = AtomicU64::new(0);
+
+/// Whether text is mostly U+FFFD replacement characters, i.e. the source app
+/// put it on the clipboard in an encoding that didn't survive decoding.
+fn is_garbled(text: &str) -> bool {
+    let total = text.chars().count();
+    if total == 0 {
+        return false;
+    }
+    let replaced = text.chars().filter(|&c| c == char::REPLACEMENT_CHARACTER).count();
+    replaced as f64 / total as f64 > GARBLED_TEXT_REPLACEMENT_RATIO
+}
+
+/// Whether text has too many control characters to be real text, e.g. a
+/// binary payload that an app offered as a text format.
+///
+/// Only C0/C1 controls count, so scripts and emoji of any language pass.
+fn is_binary(text: &str) -> bool {
+    let total = text.chars().count();
+    if total == 0 {
+        return false;
+    }
+    let control = text
+        .chars()
+        .filter(|&c| c.is_control() && !matches!(c, '\t' | '\n' | '\r' | '\x0C'))
+        .count();
+    control as f64 / total as f64 > BINARY_TEXT_CONTROL_RATIO
+}
+
+fn log_rejected_text(text: &str, reason: &str) {
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    text.hash(&mut hasher);
+    let fingerprint = hasher.finish();
+    if LAST_REJECTED_TEXT.swap(fingerprint, Ordering::Relaxed) != fingerprint {
+        logger::info(
+            "Clipboard",
+            &format!("Skipped clipboard text that {} ({} chars)", reason, text.chars().count()),
+        );
+    }
+}
+
+/// Track a failed read, logging the start of a failure streak and warning once it persists.
+fn record_read_failure(err: &arboard::Error) {
+    let failures = CONSECUTIVE_READ_FAILURES.fetch_add(1, Ordering::Relaxed) + 1;
+    if failures == 1 {
+        logger::debug("Clipboard", &format!("Clipboard unavailable: {}", err));
+    } else if failures == CLIPBOARD_FAILURE_WARN_THRESHOLD {
+        logger::warning(
+            "Clipboard",
+            &format!("Clipboard unavailable for {} consecutive reads, capture is not working: {}", failures, err),
+        );
+    }
+}
+
+/// Reset the failure streak after a successful (possibly empty) read.
+fn record_read_success() {
+    let failures = CONSECUTIVE_READ_FAILURES.swap(0, Ordering::Relaxed);
+    if failures >= CLIPBOARD_FAILURE_WARN_THRESHOLD {
+        logger::info("Clipboard", &format!("Clipboard access recovered after {} failed reads", failures));
+    }
+}
+
 /// Internal implementation for getting clipboard content.
-/// Priority: files > image > text
+///
+/// Content the source app marked as not to be recorded is dropped.
 fn get_clipboard_content_impl() -> Option<ClipboardContent> {
+    read_clipboard_content().filter(|content| !is_marked_excluded(content))
+}
+
+/// Read whatever is on the clipboard.
+/// Priority: files > image > text
+fn read_clipboard_content() -> Option<ClipboardContent> {
     // Check for files first (platform-specific)
     #[cfg(target_os = "macos")]
     {
@@ -80,26 +315,60 @@ fn get_clipboard_content_impl() -> Option<ClipboardContent> {
         }
     }
 
-    let mut clipboard = Clipboard::new().ok()?;
-
-    if let Ok(image) = clipboard.get_image() {
-        let bytes = image.bytes.to_vec();
-        if !bytes.is_empty() {
-            return Some(ClipboardContent::Image(ImageData {
-                bytes,
-                width: image.width as u32,
-                height: image.height as u32,
-            }));
+    match read_arboard_content() {
+        Ok(content) => {
+            record_read_success();
+            content
+        }
+        Err(e) => {
+            record_read_failure(&e);
+            None
         }
     }
+}
 
-    if let Ok(text) = clipboard.get_text() {
-        if !text.is_empty() && !text.contains('\0') {
-            return Some(ClipboardContent::Text(text));
+/// Image content, with the original GIF attached when the clipboard offers one.
+fn with_original_gif(image: ImageData) -> ClipboardContent {
+    let mut content = ClipboardContent::Image(image);
+    let fingerprint = content_fingerprint(&content);
+    if let ClipboardContent::Image(image) = &mut content {
+        image.gif = gif::read_gif(fingerprint);
+    }
+    content
+}
+
+/// Fingerprint of the last content skipped for a do-not-record marker, so each is logged once.
+static LAST_EXCLUDED: AtomicU64 = AtomicU64::new(0);
+
+/// Cheap identity of clipboard content, used to notice when it changes.
+///
+/// Large images are sampled rather than hashed in full, since this runs on every poll.
+fn content_fingerprint(content: &ClipboardContent) -> u64 {
+    const SAMPLE: usize = 4096;
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    match content {
+        ClipboardContent::Text(text) => text.hash(&mut hasher),
+        ClipboardContent::Image(image) => {
+            (image.width, image.height, image.bytes.len()).hash(&mut hasher);
+            image.bytes[..image.bytes.len().min(SAMPLE)].hash(&mut hasher);
+            image.bytes[image.bytes.len().saturating_sub(SAMPLE)..].hash(&mut hasher);
         }
+        ClipboardContent::Files(files) => files.paths.hash(&mut hasher),
     }
+    hasher.finish()
+}
 
-    None
+/// Whether the source app marked the content as not to be recorded
+/// (transient, auto-generated, or a password).
+fn is_marked_excluded(content: &ClipboardContent) -> bool {
+    let fingerprint = content_fingerprint(content);
+    let Some(marker) = sensitive::excluded_marker(fingerprint) else {
+        return false;
+    };
+    if LAST_EXCLUDED.swap(fingerprint, Ordering::Relaxed) != fingerprint {
+        logger::info("Clipboard", &format!("Skipped clipboard content marked {}", marker));
+    }
+    true
 }
 
 /// Get file paths from macOS clipboard using NSPasteboard.
@@ -151,6 +420,11 @@ fn get_clipboard_files_macos() -> Option<FileData> {
 pub fn set_clipboard_text(text: &str) -> Result<(), String> {
     use std::sync::{Arc, Mutex};
 
+    // Dispatching synchronously from the main thread itself would deadlock
+    if objc2::MainThreadMarker::new().is_some() {
+        return set_clipboard_text_impl(text);
+    }
+
     let text = text.to_string();
     let result = Arc::new(Mutex::new(Err("Not executed".to_string())));
     let result_clone = result.clone();
@@ -175,6 +449,39 @@ fn set_clipboard_text_impl(text: &str) -> Result<(), String> {
     clipboard.set_text(text).map_err(|e| e.to_string())
 }
 
+/// Remove all content from the clipboard.
+#[cfg(target_os = "macos")]
+pub fn clear_clipboard() -> Result<(), String> {
+    use std::sync::{Arc, Mutex};
+
+    // Dispatching synchronously from the main thread itself would deadlock
+    if objc2::MainThreadMarker::new().is_some() {
+        return clear_clipboard_impl();
+    }
+
+    let result = Arc::new(Mutex::new(Err("Not executed".to_string())));
+    let result_clone = result.clone();
+
+    dispatch::Queue::main().exec_sync(move || {
+        *result_clone.lock().unwrap() = clear_clipboard_impl();
+    });
+
+    let guard = result.lock().unwrap();
+    guard.clone()
+}
+
+/// Remove all content from the clipboard.
+#[cfg(not(target_os = "macos"))]
+pub fn clear_clipboard() -> Result<(), String> {
+    clear_clipboard_impl()
+}
+
+/// Internal implementation for clearing the clipboard.
+fn clear_clipboard_impl() -> Result<(), String> {
+    let mut clipboard = Clipboard::new().map_err(|e| e.to_string())?;
+    clipboard.clear().map_err(|e| e.to_string())
+}
+
 /// Set image to clipboard from raw RGBA pixels.
 ///
 /// On macOS, this function dispatches to the main thread for AppKit compatibility.
@@ -182,6 +489,11 @@ fn set_clipboard_text_impl(text: &str) -> Result<(), String> {
 pub fn set_clipboard_image(width: u32, height: u32, pixels: &[u8]) -> Result<(), String> {
     use std::sync::{Arc, Mutex};
 
+    // Dispatching synchronously from the main thread itself would deadlock
+    if objc2::MainThreadMarker::new().is_some() {
+        return set_clipboard_image_impl(width, height, pixels);
+    }
+
     let pixels = pixels.to_vec();
     let result = Arc::new(Mutex::new(Err("Not executed".to_string())));
     let result_clone = result.clone();
@@ -213,6 +525,48 @@ fn set_clipboard_image_impl(width: u32, height: u32, pixels: &[u8]) -> Result<()
     clipboard.set_image(image).map_err(|e| e.to_string())
 }
 
+/// Set a GIF to clipboard: its first frame as a bitmap (raw RGBA `pixels`),
+/// plus the original bytes where the platform can offer them (see `gif`).
+///
+/// On macOS, this function dispatches to the main thread for AppKit compatibility.
+#[cfg(target_os = "macos")]
+pub fn set_clipboard_gif(gif: &[u8], width: u32, height: u32, pixels: &[u8]) -> Result<(), String> {
+    use std::sync::{Arc, Mutex};
+
+    // Dispatching synchronously from the main thread itself would deadlock
+    if objc2::MainThreadMarker::new().is_some() {
+        return set_clipboard_gif_impl(gif, width, height, pixels);
+    }
+
+    let gif = gif.to_vec();
+    let pixels = pixels.to_vec();
+    let result = Arc::new(Mutex::new(Err("Not executed".to_string())));
+    let result_clone = result.clone();
+
+    dispatch::Queue::main().exec_sync(move || {
+        *result_clone.lock().unwrap() = set_clipboard_gif_impl(&gif, width, height, &pixels);
+    });
+
+    let guard = result.lock().unwrap();
+    guard.clone()
+}
+
+/// Set a GIF to clipboard: its first frame as a bitmap (raw RGBA `pixels`),
+/// plus the original bytes where the platform can offer them (see `gif`).
+#[cfg(not(target_os = "macos"))]
+pub fn set_clipboard_gif(gif: &[u8], width: u32, height: u32, pixels: &[u8]) -> Result<(), String> {
+    set_clipboard_gif_impl(gif, width, height, pixels)
+}
+
+/// Internal implementation for setting a GIF; only the bitmap is required to succeed.
+fn set_clipboard_gif_impl(gif: &[u8], width: u32, height: u32, pixels: &[u8]) -> Result<(), String> {
+    set_clipboard_image_impl(width, height, pixels)?;
+    if let Err(e) = gif::add_gif(gif) {
+        logger::debug("Clipboard", &format!("Pasting the GIF as a still image: {}", e));
+    }
+    Ok(())
+}
+
 /// Set files to clipboard.
 ///
 /// On macOS, this function dispatches to the main thread for AppKit compatibility.
@@ -220,6 +574,11 @@ fn set_clipboard_image_impl(width: u32, height: u32, pixels: &[u8]) -> Result<()
 pub fn set_clipboard_files(paths: &[String]) -> Result<(), String> {
     use std::sync::{Arc, Mutex};
 
+    // Dispatching synchronously from the main thread itself would deadlock
+    if objc2::MainThreadMarker::new().is_some() {
+        return set_clipboard_files_impl(paths);
+    }
+
     let paths = paths.to_vec();
     let result = Arc::new(Mutex::new(Err("Not executed".to_string())));
     let result_clone = result.clone();
@@ -501,3 +860,109 @@ fn get_clipboard_files_windows() -> Option<FileData> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_transient() {
+        assert!(is_transient(&arboard::Error::ClipboardOccupied));
+        assert!(is_transient(&arboard::Error::Unknown { description: "busy".to_string() }));
+        assert!(!is_transient(&arboard::Error::ContentNotAvailable));
+        assert!(!is_transient(&arboard::Error::ClipboardNotSupported));
+    }
+
+    #[test]
+    fn test_line_ending_conversion() {
+        assert_eq!(normalize_line_endings("a\r\nb\r\n"), "a\nb\n");
+        assert_eq!(normalize_line_endings("a\nb"), "a\nb");
+        assert!(matches!(normalize_line_endings("plain"), Cow::Borrowed(_)));
+
+        assert_eq!(to_crlf("a\nb\n"), "a\r\nb\r\n");
+        assert_eq!(to_crlf("a\r\nb\nc"), "a\r\nb\r\nc");
+        assert_eq!(normalize_line_endings(&to_crlf("x\ny")), "x\ny");
+    }
+
+    #[test]
+    fn test_is_garbled() {
+        // Invalid UTF-16 (lone surrogates) and UTF-8 decode to replacement characters
+        let bad_utf16 = String::from_utf16_lossy(&[0xDC00, 0xDC01, 0xD800, 0x0041]);
+        assert!(is_garbled(&bad_utf16), "{:?}", bad_utf16);
+        let bad_utf8 = String::from_utf8_lossy(&[0xC3, 0x28, 0xFF, 0xFE, 0x80]).into_owned();
+        assert!(is_garbled(&bad_utf8), "{:?}", bad_utf8);
+
+        // Legitimate non-ASCII text is kept
+        assert!(!is_garbled("Hello, 世界! 🎉 Ünïcödé"));
+        assert!(!is_garbled("コピー"));
+        assert!(!is_garbled(""));
+        // An occasional replacement character in real text is not enough
+        assert!(!is_garbled("price: 10\u{FFFD} per unit"));
+    }
+
+    #[test]
+    fn test_content_fingerprint() {
+        let text = |t: &str| ClipboardContent::Text(t.to_string());
+        assert_eq!(content_fingerprint(&text("a")), content_fingerprint(&text("a")));
+        assert_ne!(content_fingerprint(&text("a")), content_fingerprint(&text("b")));
+
+        // Images differing only past the sampled head still differ at the tail
+        let image = |last: u8| {
+            let mut bytes = vec![0u8; 64 * 1024];
+            *bytes.last_mut().unwrap() = last;
+            ClipboardContent::Image(ImageData { bytes, width: 128, height: 128, gif: None })
+        };
+        assert_ne!(content_fingerprint(&image(1)), content_fingerprint(&image(2)));
+    }
+
+    #[test]
+    fn test_is_binary() {
+        let blob: String = [0x89u8, b'P', b'N', b'G', 0x1A, 0x0A, 0x01, 0x02, 0x7F, 0x03]
+            .iter()
+            .map(|&b| b as char)
+            .collect();
+        assert!(is_binary(&blob));
+        assert!(is_binary("\u{1}\u{2}\u{3}abc"));
+
+        // Whitespace controls and non-Latin scripts are ordinary text
+        assert!(!is_binary("line one\r\n\tindented\x0Cnext page"));
+        assert!(!is_binary("Привет, мир! مرحبا 你好 🎉"));
+        assert!(!is_binary(""));
+        // A stray escape sequence in terminal output is tolerated
+        assert!(!is_binary("\x1b[32mok\x1b[0m: all tests passed"));
+    }
+
+    #[test]
+    fn test_with_retry_recovers_from_transient_errors() {
+        let mut calls = 0;
+        let result = with_retry(|| {
+            calls += 1;
+            if calls < 3 {
+                Err(arboard::Error::ClipboardOccupied)
+            } else {
+                Ok(calls)
+            }
+        });
+        assert_eq!(result.ok(), Some(3));
+    }
+
+    #[test]
+    fn test_with_retry_gives_up() {
+        let mut calls = 0;
+        let result: Result<(), _> = with_retry(|| {
+            calls += 1;
+            Err(arboard::Error::ClipboardOccupied)
+        });
+        assert!(result.is_err());
+        assert_eq!(calls, CLIPBOARD_READ_RETRIES + 1);
+
+        // Non-transient errors are not retried
+        let mut calls = 0;
+        let result: Result<(), _> = with_retry(|| {
+            calls += 1;
+            Err(arboard::Error::ContentNotAvailable)
+        });
+        assert!(result.is_err());
+        assert_eq!(calls, 1);
+    }
+}
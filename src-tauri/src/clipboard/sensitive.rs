@@ -0,0 +1,116 @@
+//! Markers that ask clipboard history tools not to record content
+//!
+//! Password managers and other apps tag sensitive or short-lived clipboard
+//! content with a platform-specific format. When one is present the content
+//! is skipped entirely.
+
+/// macOS pasteboard types from the nspasteboard.org conventions
+#[cfg(target_os = "macos")]
+const MACOS_MARKERS: &[&str] = &["org.nspasteboard.TransientType", "org.nspasteboard.AutoGeneratedType"];
+
+/// Type KDE's Klipper convention uses for password manager content
+#[cfg(target_os = "linux")]
+const KDE_PASSWORD_HINT: &str = "x-kde-passwordManagerHint";
+
+/// Name of the do-not-record marker on the clipboard, if any.
+///
+/// `fingerprint` identifies the current content; platforms where checking is
+/// expensive only check again when it changes.
+#[cfg(target_os = "macos")]
+pub(super) fn excluded_marker(_fingerprint: u64) -> Option<&'static str> {
+    use objc2_app_kit::NSPasteboard;
+
+    let pasteboard = NSPasteboard::generalPasteboard();
+    let types = pasteboard.types()?;
+    let types: Vec<String> = types.iter().map(|t| t.to_string()).collect();
+    MACOS_MARKERS.iter().copied().find(|marker| types.iter().any(|t| t == marker))
+}
+
+#[cfg(target_os = "windows")]
+pub(super) fn excluded_marker(_fingerprint: u64) -> Option<&'static str> {
+    use windows::core::w;
+    use windows::Win32::System::DataExchange::{IsClipboardFormatAvailable, RegisterClipboardFormatW};
+
+    unsafe {
+        // Present at all means "don't record" (used by most password managers)
+        let exclude = RegisterClipboardFormatW(w!("ExcludeClipboardContentFromMonitorProcessing"));
+        if exclude != 0 && IsClipboardFormatAvailable(exclude).is_ok() {
+            return Some("ExcludeClipboardContentFromMonitorProcessing");
+        }
+
+        // A DWORD; 0 keeps the content out of Windows' own clipboard history
+        let history = RegisterClipboardFormatW(w!("CanIncludeInClipboardHistory"));
+        if history != 0 && IsClipboardFormatAvailable(history).is_ok() && read_format_dword(history) == Some(0) {
+            return Some("CanIncludeInClipboardHistory");
+        }
+    }
+    None
+}
+
+#[cfg(target_os = "windows")]
+unsafe fn read_format_dword(format: u32) -> Option<u32> {
+    use windows::Win32::Foundation::{HGLOBAL, HWND};
+    use windows::Win32::System::DataExchange::{CloseClipboard, GetClipboardData, OpenClipboard};
+    use windows::Win32::System::Memory::{GlobalLock, GlobalSize, GlobalUnlock};
+
+    OpenClipboard(HWND(std::ptr::null_mut())).ok()?;
+
+    let mut value = None;
+    if let Ok(handle) = GetClipboardData(format) {
+        let h_global = HGLOBAL(handle.0);
+        let ptr = GlobalLock(h_global);
+        if !ptr.is_null() {
+            if GlobalSize(h_global) >= std::mem::size_of::<u32>() {
+                value = Some(std::ptr::read_unaligned(ptr as *const u32));
+            }
+            let _ = GlobalUnlock(h_global);
+        }
+    }
+
+    let _ = CloseClipboard();
+    value
+}
+
+/// Last content checked on Linux and its result. Listing the offered types
+/// spawns a process, so it's only done when the content changes.
+#[cfg(target_os = "linux")]
+static LAST_CHECK: std::sync::Mutex<Option<(u64, Option<&'static str>)>> = std::sync::Mutex::new(None);
+
+#[cfg(target_os = "linux")]
+pub(super) fn excluded_marker(fingerprint: u64) -> Option<&'static str> {
+    let mut last = LAST_CHECK.lock().ok()?;
+    if let Some((checked, marker)) = *last {
+        if checked == fingerprint {
+            return marker;
+        }
+    }
+
+    let marker = super::fallback::offered_types()
+        .filter(|types| offers_type(types, KDE_PASSWORD_HINT))
+        .map(|_| KDE_PASSWORD_HINT);
+    *last = Some((fingerprint, marker));
+    marker
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows", target_os = "linux")))]
+pub(super) fn excluded_marker(_fingerprint: u64) -> Option<&'static str> {
+    None
+}
+
+/// Whether a type listing (one MIME type per line) includes `wanted`.
+#[cfg(target_os = "linux")]
+fn offers_type(types: &str, wanted: &str) -> bool {
+    types.lines().any(|line| line.trim() == wanted)
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_offers_type() {
+        assert!(offers_type("text/plain\nx-kde-passwordManagerHint\n", KDE_PASSWORD_HINT));
+        assert!(!offers_type("text/plain\nUTF8_STRING\n", KDE_PASSWORD_HINT));
+        assert!(!offers_type("", KDE_PASSWORD_HINT));
+    }
+}
@@ -0,0 +1,70 @@
+//! Source app - Which application put the current content on the clipboard
+//!
+//! Used to apply per-app `capture_rules`. Windows reports the clipboard
+//! owner's executable name ("SnippingTool.exe"); macOS has no owner API, so
+//! the frontmost app's bundle identifier stands in ("com.apple.screencaptureui").
+//! Other platforms report nothing and capture rules never match there.
+
+use std::sync::Mutex;
+
+use super::{content_fingerprint, ClipboardContent};
+
+/// Fingerprint of the last content looked up and its source, so the app is
+/// identified once per clipboard change rather than on every poll (on macOS
+/// the frontmost app may change while the content stays the same).
+static LAST_SOURCE: Mutex<Option<(u64, Option<String>)>> = Mutex::new(None);
+
+/// The app that put `content` on the clipboard, if it can be told.
+pub fn source_app(content: &ClipboardContent) -> Option<String> {
+    let fingerprint = content_fingerprint(content);
+    let mut last = LAST_SOURCE.lock().ok()?;
+    if let Some((checked, source)) = &*last {
+        if *checked == fingerprint {
+            return source.clone();
+        }
+    }
+
+    let source = current_source_app();
+    *last = Some((fingerprint, source.clone()));
+    source
+}
+
+#[cfg(target_os = "windows")]
+fn current_source_app() -> Option<String> {
+    use windows::core::PWSTR;
+    use windows::Win32::Foundation::CloseHandle;
+    use windows::Win32::System::DataExchange::GetClipboardOwner;
+    use windows::Win32::System::Threading::{
+        OpenProcess, QueryFullProcessImageNameW, PROCESS_NAME_WIN32, PROCESS_QUERY_LIMITED_INFORMATION,
+    };
+    use windows::Win32::UI::WindowsAndMessaging::GetWindowThreadProcessId;
+
+    unsafe {
+        let owner = GetClipboardOwner().ok()?;
+        let mut pid = 0u32;
+        GetWindowThreadProcessId(owner, Some(&mut pid));
+        if pid == 0 {
+            return None;
+        }
+
+        let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid).ok()?;
+        let mut buffer = [0u16; 1024];
+        let mut len = buffer.len() as u32;
+        let queried = QueryFullProcessImageNameW(process, PROCESS_NAME_WIN32, PWSTR(buffer.as_mut_ptr()), &mut len);
+        let _ = CloseHandle(process);
+        queried.ok()?;
+
+        let path = String::from_utf16_lossy(&buffer[..len as usize]);
+        std::path::Path::new(&path).file_name().map(|name| name.to_string_lossy().into_owned())
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn current_source_app() -> Option<String> {
+    crate::window::macos::get_frontmost_bundle_id()
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn current_source_app() -> Option<String> {
+    None
+}
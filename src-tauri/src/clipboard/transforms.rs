@@ -0,0 +1,167 @@
+//! Capture transforms - Named rewrites applied to copied text before it is stored
+//!
+//! Unlike the do-not-record markers, these never reject content: they only
+//! change what ends up in history. Each transform is a pure function of the
+//! text. Lines may end in `\n` or `\r\n`; the line endings are kept.
+
+/// Removes leading and trailing whitespace (including line breaks) from the whole text.
+pub const TRIM: &str = "trim";
+/// Removes spaces and tabs at the end of every line.
+pub const TRIM_TRAILING_WHITESPACE: &str = "trim_trailing_whitespace";
+/// Replaces each run of two or more blank (empty or whitespace-only) lines with one empty line.
+pub const COLLAPSE_BLANK_LINES: &str = "collapse_blank_lines";
+/// Removes the longest run of leading spaces and tabs shared by every non-blank line.
+pub const UNINDENT: &str = "unindent";
+
+/// Every transform name accepted in `capture_transforms`.
+pub const TRANSFORMS: &[&str] = &[TRIM, TRIM_TRAILING_WHITESPACE, COLLAPSE_BLANK_LINES, UNINDENT];
+
+/// Apply the named transforms to `text` in order. Unknown names are skipped.
+pub fn apply(text: &str, transforms: &[String]) -> String {
+    let mut text = text.to_string();
+    for name in transforms {
+        text = match name.as_str() {
+            TRIM => text.trim().to_string(),
+            TRIM_TRAILING_WHITESPACE => trim_trailing_whitespace(&text),
+            COLLAPSE_BLANK_LINES => collapse_blank_lines(&text),
+            UNINDENT => unindent(&text),
+            _ => continue,
+        };
+    }
+    text
+}
+
+/// Names in `transforms` that aren't known transforms.
+pub fn unknown(transforms: &[String]) -> Vec<&str> {
+    transforms.iter().map(String::as_str).filter(|name| !TRANSFORMS.contains(name)).collect()
+}
+
+/// Split `text` into lines, each paired with its line ending ("", "\n" or "\r\n").
+fn lines_with_endings(text: &str) -> impl Iterator<Item = (&str, &str)> {
+    text.split_inclusive('\n').map(|line| {
+        if let Some(body) = line.strip_suffix("\r\n") {
+            (body, "\r\n")
+        } else if let Some(body) = line.strip_suffix('\n') {
+            (body, "\n")
+        } else {
+            (line, "")
+        }
+    })
+}
+
+fn is_blank(line: &str) -> bool {
+    line.trim().is_empty()
+}
+
+fn trim_trailing_whitespace(text: &str) -> String {
+    lines_with_endings(text)
+        .map(|(line, ending)| format!("{}{}", line.trim_end_matches([' ', '\t']), ending))
+        .collect()
+}
+
+fn collapse_blank_lines(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut blank_run = 0;
+    for (line, ending) in lines_with_endings(text) {
+        if !is_blank(line) {
+            blank_run = 0;
+            result.push_str(line);
+            result.push_str(ending);
+            continue;
+        }
+        blank_run += 1;
+        if blank_run == 1 {
+            result.push_str(ending);
+        }
+    }
+    result
+}
+
+fn unindent(text: &str) -> String {
+    let indents = lines_with_endings(text)
+        .map(|(line, _)| line)
+        .filter(|line| !is_blank(line))
+        .map(|line| &line[..line.len() - line.trim_start_matches([' ', '\t']).len()]);
+
+    // Longest prefix shared by every indent, compared byte for byte so tabs and spaces don't mix
+    let mut common: Option<&str> = None;
+    for indent in indents {
+        common = Some(match common {
+            None => indent,
+            Some(prefix) => {
+                let shared = prefix.bytes().zip(indent.bytes()).take_while(|(a, b)| a == b).count();
+                &prefix[..shared]
+            }
+        });
+    }
+    let Some(common) = common.filter(|prefix| !prefix.is_empty()) else {
+        return text.to_string();
+    };
+
+    lines_with_endings(text)
+        .map(|(line, ending)| {
+            // Blank lines may have less indentation than the common prefix
+            let line = line.strip_prefix(common).unwrap_or_else(|| line.trim_start_matches([' ', '\t']));
+            format!("{}{}", line, ending)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|n| n.to_string()).collect()
+    }
+
+    #[test]
+    fn test_trim() {
+        assert_eq!(apply("\n  hello world \t\n\n", &names(&[TRIM])), "hello world");
+    }
+
+    #[test]
+    fn test_trim_trailing_whitespace() {
+        let text = "fn main() {  \r\n    body();\t\n}   ";
+        assert_eq!(apply(text, &names(&[TRIM_TRAILING_WHITESPACE])), "fn main() {\r\n    body();\n}");
+        // Leading indentation is kept
+        assert_eq!(apply("  a  \n", &names(&[TRIM_TRAILING_WHITESPACE])), "  a\n");
+    }
+
+    #[test]
+    fn test_collapse_blank_lines() {
+        let text = "a\n\n\n  \nb\n\nc\r\n\r\n\r\nd";
+        assert_eq!(apply(text, &names(&[COLLAPSE_BLANK_LINES])), "a\n\nb\n\nc\r\n\r\nd");
+        assert_eq!(apply("a\nb", &names(&[COLLAPSE_BLANK_LINES])), "a\nb");
+    }
+
+    #[test]
+    fn test_unindent() {
+        let code = "    if x {\n        y();\n\n    }\n";
+        assert_eq!(apply(code, &names(&[UNINDENT])), "if x {\n    y();\n\n}\n");
+
+        // Only whitespace shared by every non-blank line is removed
+        assert_eq!(apply("\t\ta\n\tb", &names(&[UNINDENT])), "\ta\nb");
+        assert_eq!(apply("  a\n\tb", &names(&[UNINDENT])), "  a\n\tb");
+        assert_eq!(apply("a\n  b", &names(&[UNINDENT])), "a\n  b");
+        // Blank lines with less indentation lose what they have
+        assert_eq!(apply("    a\n  \n    b", &names(&[UNINDENT])), "a\n\nb");
+    }
+
+    #[test]
+    fn test_apply_in_order() {
+        let text = "\n    a  \n\n\n    b\n";
+        let all = names(&[TRIM_TRAILING_WHITESPACE, COLLAPSE_BLANK_LINES, UNINDENT, TRIM]);
+        assert_eq!(apply(text, &all), "a\n\nb");
+
+        // Trimming first removes the first line's indent, so nothing is shared afterwards
+        assert_eq!(apply("  a\n  b", &names(&[TRIM, UNINDENT])), "a\n  b");
+    }
+
+    #[test]
+    fn test_unknown_transforms() {
+        let transforms = names(&["trim", "shout", "unindent", ""]);
+        assert_eq!(unknown(&transforms), vec!["shout", ""]);
+        assert_eq!(apply(" x ", &names(&["shout"])), " x ");
+    }
+}
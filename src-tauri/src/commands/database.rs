@@ -0,0 +1,315 @@
+//! Database commands - Backups and maintenance
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::Serialize;
+use tauri::{Emitter, Manager};
+
+use crate::config::{
+    self, backups_dir, data_dir, db_path, images_dir, AUTO_BACKUP_CHECK_INTERVAL_SECS, AUTO_BACKUP_INTERVAL_HOURS,
+};
+use crate::db::{self, backup, relocate};
+use crate::{app_settings, logger, monitor};
+
+/// Result of `compact_database`, also the `powerclip:compact-finished` payload.
+#[derive(Debug, Clone, Serialize)]
+pub struct CompactResult {
+    /// Database shrinkage plus the size of deleted orphan images
+    pub bytes_reclaimed: u64,
+    pub images_removed: usize,
+}
+
+/// Payload of `powerclip:data-dir-progress`.
+#[derive(Debug, Clone, Serialize)]
+pub struct MigrationProgress {
+    pub copied_bytes: u64,
+    pub total_bytes: u64,
+}
+
+/// Result of `migrate_data_dir`, also the `powerclip:data-dir-migrated` payload.
+#[derive(Debug, Clone, Serialize)]
+pub struct DataDirMigrated {
+    pub path: String,
+    /// Files copied besides the database
+    pub files_copied: usize,
+    pub bytes_copied: u64,
+    pub items_copied: i64,
+}
+
+/// Back up the database, then delete backups beyond `backup_keep_count`.
+///
+/// The connection stays locked for the copy, so writes from the monitor wait
+/// rather than interleave with it.
+fn run_backup(app: &tauri::AppHandle) -> Result<String, String> {
+    config::check_data_dir_current()?;
+    let keep = app_settings::load_settings_simple()?.backup_keep_count;
+    let dir = backups_dir();
+
+    let path = {
+        let state = app.state::<crate::DatabaseState>();
+        let conn = state.conn.lock().map_err(|e| e.to_string())?;
+        backup::create_backup(&conn, &dir, chrono::Local::now().naive_local())?
+    };
+
+    let pruned = backup::prune_backups(&dir, keep);
+    if pruned > 0 {
+        logger::info("Database", &format!("Deleted {} old backups", pruned));
+    }
+    Ok(path.to_string_lossy().into_owned())
+}
+
+/// Back up `clipboard.db` to `backups/clipboard-<timestamp>.db`.
+///
+/// Returns the path of the new backup for display (lossy if it isn't valid UTF-8).
+#[tauri::command]
+pub async fn backup_database(app: tauri::AppHandle) -> Result<String, String> {
+    tokio::task::spawn_blocking(move || run_backup(&app))
+        .await
+        .map_err(|e| format!("Task join error: {}", e))?
+}
+
+/// Start the background thread that backs up the database once a day
+/// while `auto_backup_enabled` is set.
+pub fn start_auto_backup(app: tauri::AppHandle) {
+    std::thread::spawn(move || loop {
+        let enabled = app_settings::load_settings_simple()
+            .map(|s| s.auto_backup_enabled)
+            .unwrap_or(false);
+
+        if enabled && backup_due(backup::latest_backup_time(&backups_dir())) {
+            if let Err(e) = run_backup(&app) {
+                logger::error("Database", &format!("Automatic backup failed: {}", e));
+            }
+        }
+
+        std::thread::sleep(Duration::from_secs(AUTO_BACKUP_CHECK_INTERVAL_SECS));
+    });
+}
+
+/// Whether enough time has passed since the last backup.
+fn backup_due(last_backup: Option<chrono::NaiveDateTime>) -> bool {
+    match last_backup {
+        Some(last) => {
+            chrono::Local::now().naive_local() - last >= chrono::Duration::hours(AUTO_BACKUP_INTERVAL_HOURS)
+        }
+        None => true,
+    }
+}
+
+/// Reclaim disk space: delete orphan image files, then `VACUUM` the database.
+///
+/// Runs off the main thread and reports through `powerclip:compact-progress`
+/// (`"images"`, then `"vacuum"`), then `powerclip:compact-finished` or `powerclip:compact-failed`.
+#[tauri::command]
+pub async fn compact_database(app: tauri::AppHandle) -> Result<CompactResult, String> {
+    let worker_app = app.clone();
+    let result = tokio::task::spawn_blocking(move || run_compact(&worker_app))
+        .await
+        .map_err(|e| format!("Task join error: {}", e))?;
+
+    match &result {
+        Ok(compacted) => {
+            let _ = app.emit("powerclip:compact-finished", compacted);
+        }
+        Err(e) => {
+            logger::error("Database", &format!("Compaction failed: {}", e));
+            let _ = app.emit("powerclip:compact-failed", e);
+        }
+    }
+    result
+}
+
+fn run_compact(app: &tauri::AppHandle) -> Result<CompactResult, String> {
+    config::check_data_dir_current()?;
+    let db_file = db_path();
+    let size_before = file_size(&db_file);
+
+    // VACUUM builds a full temporary copy of the database before replacing it
+    if let Some(available) = available_space(data_dir()) {
+        if available < size_before {
+            let message = format!(
+                "Not enough free disk space to compact the database ({} MB needed, {} MB available)",
+                size_before / 1_000_000,
+                available / 1_000_000
+            );
+            logger::warning("Database", &message);
+            return Err(message);
+        }
+    }
+
+    let state = app.state::<crate::DatabaseState>();
+    // VACUUM needs the database to itself; holding the lock keeps the monitor out
+    let conn = state.conn.lock().map_err(|e| e.to_string())?;
+
+    let _ = app.emit("powerclip:compact-progress", "images");
+    let (images_removed, image_bytes) = db::prune_orphan_images(&conn, &images_dir()).map_err(|e| e.to_string())?;
+
+    let _ = app.emit("powerclip:compact-progress", "vacuum");
+    conn.execute_batch("VACUUM").map_err(|e| format!("Failed to vacuum database: {}", e))?;
+    drop(conn);
+
+    let bytes_reclaimed = size_before.saturating_sub(file_size(&db_file)) + image_bytes;
+    logger::info(
+        "Database",
+        &format!("Compacted database: reclaimed {} bytes, removed {} orphan images", bytes_reclaimed, images_removed),
+    );
+    Ok(CompactResult { bytes_reclaimed, images_removed })
+}
+
+/// Copy all data (database, images, settings, models, ...) to `new_path` and use it from the next start.
+///
+/// The folder must be empty or not exist yet. The database copy is checked
+/// with `PRAGMA integrity_check` before the switch. Reports through
+/// `powerclip:data-dir-progress`, then `powerclip:data-dir-migrated`.
+///
+/// The data folder is resolved once per run, so PowerClip must restart to use
+/// the new folder. Until then recording stays paused and the database is
+/// read-only, so nothing is saved only to the old one. With `remove_old`, the
+/// old files are deleted at the next start.
+#[tauri::command]
+pub async fn migrate_data_dir(
+    app: tauri::AppHandle,
+    new_path: String,
+    remove_old: Option<bool>,
+) -> Result<DataDirMigrated, String> {
+    let worker_app = app.clone();
+    let target = PathBuf::from(new_path.trim());
+    let result = tokio::task::spawn_blocking(move || run_migration(&worker_app, &target, remove_old.unwrap_or(false)))
+        .await
+        .map_err(|e| format!("Task join error: {}", e))?;
+
+    match &result {
+        Ok(migrated) => {
+            // Resuming recording is no longer offered
+            crate::tray::refresh_menu(&app);
+            let _ = app.emit("powerclip:data-dir-migrated", migrated);
+        }
+        Err(e) => logger::error("Database", &format!("Data folder move failed: {}", e)),
+    }
+    result
+}
+
+fn run_migration(app: &tauri::AppHandle, target: &Path, remove_old: bool) -> Result<DataDirMigrated, String> {
+    config::check_data_dir_current()?;
+    let source = data_dir();
+    relocate::check_target(source, target)?;
+    let created = !target.exists();
+    std::fs::create_dir_all(target).map_err(|e| format!("Can't create {}: {}", target.display(), e))?;
+
+    let was_paused = monitor::is_paused();
+    let result = copy_data_dir(app, source, target);
+    if result.is_err() {
+        // The folder was empty before, so everything in it is a partial copy
+        if created {
+            let _ = std::fs::remove_dir_all(target);
+        } else if let Ok(entries) = std::fs::read_dir(target) {
+            for entry in entries.flatten() {
+                let path = entry.path();
+                let _ = if path.is_dir() { std::fs::remove_dir_all(&path) } else { std::fs::remove_file(&path) };
+            }
+        }
+        if !was_paused {
+            monitor::set_paused(false);
+        }
+        return result;
+    }
+
+    if remove_old {
+        relocate::schedule_previous_removal(target, source)?;
+    }
+    config::write_data_dir_override(&config::default_data_dir(), target)
+        .map_err(|e| format!("Failed to record the new data folder: {}", e))?;
+
+    // Anything written from here on would only reach the old folder
+    config::set_data_dir_moved();
+    let state = app.state::<crate::DatabaseState>();
+    let conn = state.conn.lock().map_err(|e| e.to_string())?;
+    if let Err(e) = conn.pragma_update(None, "query_only", true) {
+        logger::warning("Database", &format!("Failed to make the old database read-only: {}", e));
+    }
+    drop(conn);
+    logger::info("Database", &format!("Data moved to {:?}; it will be used after a restart", target));
+    result
+}
+
+fn copy_data_dir(app: &tauri::AppHandle, source: &Path, target: &Path) -> Result<DataDirMigrated, String> {
+    let state = app.state::<crate::DatabaseState>();
+    // From here on, new items would only reach the old folder. Capture must stop
+    // before the listing: an image saved after it would get a row in the
+    // database copy, but its file would never reach the new folder.
+    monitor::set_paused(true);
+    // Wait for a capture in progress; later ones see the pause
+    drop(state.conn.lock());
+    let files = relocate::list_data_files(source)
+        .map_err(|e| format!("Can't read the data folder: {}", e))?;
+    let files_bytes: u64 = files.iter().map(|f| f.size).sum();
+    let total_bytes = files_bytes + file_size(&db_path());
+
+    if let Some(available) = available_space(target) {
+        if available < total_bytes {
+            return Err(format!(
+                "Not enough free disk space in {} ({} MB needed, {} MB available)",
+                target.display(),
+                total_bytes.div_ceil(1_000_000),
+                available / 1_000_000
+            ));
+        }
+    }
+
+    let report = |copied_bytes: u64| {
+        let _ = app.emit("powerclip:data-dir-progress", MigrationProgress { copied_bytes, total_bytes });
+    };
+    let mut last_percent = None;
+    let bytes_copied = relocate::copy_data_files(source, target, &files, |copied| {
+        let percent = copied * 100 / total_bytes.max(1);
+        if last_percent != Some(percent) {
+            last_percent = Some(percent);
+            report(copied);
+        }
+    })?;
+
+    let items_copied = {
+        let conn = state.conn.lock().map_err(|e| e.to_string())?;
+        relocate::copy_database(&conn, target)?
+    };
+    let db_bytes = file_size(&target.join("clipboard.db"));
+    report(total_bytes);
+
+    Ok(DataDirMigrated {
+        path: target.to_string_lossy().into_owned(),
+        files_copied: files.len(),
+        bytes_copied: bytes_copied + db_bytes,
+        items_copied,
+    })
+}
+
+fn file_size(path: &Path) -> u64 {
+    std::fs::metadata(path).map(|m| m.len()).unwrap_or(0)
+}
+
+/// Free disk space available to the current user on the volume holding `path`.
+#[cfg(unix)]
+fn available_space(path: &Path) -> Option<u64> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_path = std::ffi::CString::new(path.as_os_str().as_bytes()).ok()?;
+    // SAFETY: statvfs only writes into the zeroed struct we pass it
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(c_path.as_ptr(), &mut stat) } != 0 {
+        return None;
+    }
+    #[allow(clippy::unnecessary_cast)] // field types differ between platforms
+    Some(stat.f_bavail as u64 * stat.f_frsize as u64)
+}
+
+/// Free disk space available to the current user on the volume holding `path`.
+#[cfg(windows)]
+fn available_space(path: &Path) -> Option<u64> {
+    use windows::core::HSTRING;
+    use windows::Win32::Storage::FileSystem::GetDiskFreeSpaceExW;
+
+    let mut available = 0u64;
+    unsafe { GetDiskFreeSpaceExW(&HSTRING::from(path), Some(&mut available), None, None) }.ok()?;
+    Some(available)
+}
@@ -0,0 +1,174 @@
+//! Diagnostics commands - One-shot self-test of the app's moving parts
+
+use std::fs;
+use std::path::Path;
+
+use rusqlite::Connection;
+use serde::Serialize;
+use tauri::Manager;
+
+use crate::config::{data_dir, images_dir, TEMP_FILE_SUFFIX};
+use crate::{app_settings, logger, monitor};
+
+/// Outcome of one diagnostic check.
+#[derive(Debug, Clone, Serialize)]
+pub struct DiagnosticCheck {
+    pub name: String,
+    pub passed: bool,
+    /// What was found, or why the check failed
+    pub message: String,
+}
+
+/// Result of `run_diagnostics`.
+#[derive(Debug, Clone, Serialize)]
+pub struct DiagnosticsReport {
+    pub checks: Vec<DiagnosticCheck>,
+    /// Whether every check passed
+    pub passed: bool,
+}
+
+impl DiagnosticCheck {
+    fn new(name: &str, result: Result<String, String>) -> Self {
+        let (passed, message) = match result {
+            Ok(message) => (true, message),
+            Err(message) => (false, message),
+        };
+        Self { name: name.to_string(), passed, message }
+    }
+}
+
+/// Check that the database can be read and that a write transaction can start.
+fn check_database(conn: &Connection) -> Result<String, String> {
+    let count: i64 = conn
+        .query_row("SELECT COUNT(*) FROM history", [], |row| row.get(0))
+        .map_err(|e| format!("Database is not readable: {}", e))?;
+    // Takes the write lock without changing anything
+    conn.execute_batch("BEGIN IMMEDIATE; ROLLBACK;")
+        .map_err(|e| format!("Database is not writable: {}", e))?;
+    Ok(format!("Readable and writable ({} history items)", count))
+}
+
+/// Check that `dir` exists and a file can be created in it.
+fn check_dir_writable(dir: &Path) -> Result<String, String> {
+    if !dir.is_dir() {
+        return Err(format!("{} does not exist", dir.display()));
+    }
+    let probe = dir.join(format!("diagnostics{}", TEMP_FILE_SUFFIX));
+    fs::write(&probe, b"powerclip").map_err(|e| format!("{} is not writable: {}", dir.display(), e))?;
+    let _ = fs::remove_file(&probe);
+    Ok(format!("{} is writable", dir.display()))
+}
+
+/// Check that the main window hotkey is registered.
+fn check_hotkey(app: &tauri::AppHandle) -> Result<String, String> {
+    let state = app.try_state::<crate::HotkeyState>().ok_or("Hotkey manager is not available")?;
+    let registered = state.current_hotkey.lock().map_err(|e| e.to_string())?.is_some();
+    if registered {
+        Ok("Main hotkey is registered".to_string())
+    } else {
+        Err("Main hotkey is not registered; it may be taken by another app".to_string())
+    }
+}
+
+/// Check that the clipboard monitor is polling.
+fn check_monitor() -> Result<String, String> {
+    match (monitor::is_running(), monitor::is_paused()) {
+        (false, _) => Err("Clipboard monitor is not running".to_string()),
+        (true, true) => Ok("Clipboard monitor is running (recording paused)".to_string()),
+        (true, false) => Ok("Clipboard monitor is running".to_string()),
+    }
+}
+
+/// Check that semantic search, when enabled, has an embeddings API to call.
+fn check_semantic(settings: &app_settings::AppSettings) -> Result<String, String> {
+    if !settings.semantic_search_enabled {
+        return Ok("Semantic search is disabled".to_string());
+    }
+    if settings.embedding_api_url.is_empty() || settings.embedding_api_key.is_empty() {
+        return Err("Semantic search is enabled but embedding_api_url or embedding_api_key is empty".to_string());
+    }
+    Ok(format!("Semantic search uses {} at {}", settings.embedding_api_model, settings.embedding_api_url))
+}
+
+/// Run every self-test and report each result, so problems can be found
+/// without reading the log.
+#[tauri::command]
+pub async fn run_diagnostics(app: tauri::AppHandle) -> Result<DiagnosticsReport, String> {
+    let database = match app.try_state::<crate::DatabaseState>() {
+        Some(state) => match state.conn.lock() {
+            Ok(conn) => check_database(&conn),
+            Err(e) => Err(format!("Database connection is unusable: {}", e)),
+        },
+        None => Err("Database is not open".to_string()),
+    };
+
+    let settings = app_settings::load_settings();
+    let semantic = match &settings {
+        Ok((settings, _)) => check_semantic(settings),
+        Err(_) => Err("Skipped: settings could not be loaded".to_string()),
+    };
+    let settings = match settings {
+        Ok((_, None)) => Ok("Settings file is valid".to_string()),
+        Ok((_, Some(e))) => Err(format!("Settings file has an error, defaults are in use: {}", e)),
+        Err(e) => Err(format!("Settings could not be loaded: {}", e)),
+    };
+
+    let checks = vec![
+        DiagnosticCheck::new("database", database),
+        DiagnosticCheck::new("data_dir", check_dir_writable(data_dir())),
+        DiagnosticCheck::new("images_dir", check_dir_writable(&images_dir())),
+        DiagnosticCheck::new("settings", settings),
+        DiagnosticCheck::new("hotkey", check_hotkey(&app)),
+        DiagnosticCheck::new("monitor", check_monitor()),
+        DiagnosticCheck::new("semantic_search", semantic),
+    ];
+
+    let failed: Vec<&str> = checks.iter().filter(|c| !c.passed).map(|c| c.name.as_str()).collect();
+    if failed.is_empty() {
+        logger::info("Diagnostics", "All checks passed");
+    } else {
+        logger::warning("Diagnostics", &format!("Failed checks: {}", failed.join(", ")));
+    }
+
+    Ok(DiagnosticsReport { passed: failed.is_empty(), checks })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_database() {
+        let conn = Connection::open_in_memory().unwrap();
+        assert!(check_database(&conn).unwrap_err().contains("not readable"));
+
+        crate::db::create_history_table(&conn).unwrap();
+        assert_eq!(check_database(&conn).unwrap(), "Readable and writable (0 history items)");
+    }
+
+    #[test]
+    fn test_check_dir_writable() {
+        let dir = std::env::temp_dir().join(format!("powerclip-diagnostics-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        assert!(check_dir_writable(&dir).unwrap_err().contains("does not exist"));
+
+        fs::create_dir_all(&dir).unwrap();
+        assert!(check_dir_writable(&dir).is_ok());
+        // The probe file is cleaned up
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_check_semantic() {
+        let mut settings = app_settings::AppSettings::default();
+        assert!(check_semantic(&settings).is_ok());
+
+        settings.semantic_search_enabled = true;
+        assert!(check_semantic(&settings).is_err());
+
+        settings.embedding_api_key = "sk-test".to_string();
+        assert!(check_semantic(&settings).unwrap().contains("text-embedding-3-small"));
+    }
+}
@@ -1,18 +1,171 @@
 //! Extension commands - Run external commands with clipboard content
-
-use std::process::Stdio;
+//!
+//! An extension only runs once the user has approved its command with
+//! `confirm_extension`, so a command slipped into settings.json (or an
+//! existing one edited) can't run silently. Approvals are stored as command
+//! hashes next to the settings file.
+
+use std::collections::HashSet;
+use std::path::Path;
+use std::process::{ExitStatus, Stdio};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
-use tokio::process::Command;
+use tokio::process::{Child, Command};
+use tokio::sync::oneshot;
 
 #[cfg(target_os = "windows")]
 use std::os::windows::process::CommandExt;
 
+use crate::config::{self, approved_extensions_path, EXTENSION_DETACHED_MAX_SECS};
+use crate::app_settings::{self, Extension};
 use crate::logger;
 
 /// CREATE_NO_WINDOW flag to prevent console window from appearing
 #[cfg(target_os = "windows")]
 const CREATE_NO_WINDOW: u32 = 0x08000000;
 
+/// Start of the error returned for unapproved commands (the frontend matches on it)
+const NOT_APPROVED_ERROR: &str = "Extension not approved";
+
+/// Extensions still running, each with a channel that asks it to stop
+static RUNNING: Mutex<Vec<(u64, oneshot::Sender<()>)>> = Mutex::new(Vec::new());
+static NEXT_RUN_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Registration in `RUNNING`, removed again when dropped.
+struct Tracked(u64);
+
+impl Tracked {
+    fn register() -> (Self, oneshot::Receiver<()>) {
+        let id = NEXT_RUN_ID.fetch_add(1, Ordering::Relaxed);
+        let (stop_tx, stop_rx) = oneshot::channel();
+        if let Ok(mut running) = RUNNING.lock() {
+            running.push((id, stop_tx));
+        }
+        (Self(id), stop_rx)
+    }
+}
+
+impl Drop for Tracked {
+    fn drop(&mut self) {
+        if let Ok(mut running) = RUNNING.lock() {
+            running.retain(|(id, _)| *id != self.0);
+        }
+    }
+}
+
+/// How waiting for an extension process ended
+#[derive(Debug)]
+enum Ended {
+    Exited(ExitStatus),
+    /// Ran past its time limit and was killed
+    TimedOut,
+    /// Killed by `kill_all_extensions`
+    Stopped,
+}
+
+/// Wait for `child` to exit, killing (and reaping) it when `limit` passes
+/// or `stop` fires first.
+async fn wait_for_exit(
+    child: &mut Child,
+    limit: Option<Duration>,
+    stop: oneshot::Receiver<()>,
+) -> Result<Ended, String> {
+    let deadline = async {
+        match limit {
+            Some(limit) => tokio::time::sleep(limit).await,
+            None => std::future::pending().await,
+        }
+    };
+
+    let ended = tokio::select! {
+        status = child.wait() => {
+            return status.map(Ended::Exited).map_err(|e| format!("Failed to wait for process: {}", e));
+        }
+        _ = deadline => Ended::TimedOut,
+        Ok(()) = stop => Ended::Stopped,
+    };
+    let _ = child.kill().await;
+    Ok(ended)
+}
+
+/// Ask every running extension to stop. Returns how many were signalled.
+pub fn kill_all() -> usize {
+    let Ok(mut running) = RUNNING.lock() else {
+        return 0;
+    };
+    let count = running
+        .drain(..)
+        .map(|(_, stop_tx)| stop_tx.send(()))
+        .filter(Result::is_ok)
+        .count();
+    if count > 0 {
+        logger::info("Extension", &format!("Stopping {} running extension(s)", count));
+    }
+    count
+}
+
+/// Kill all running extensions, including fire-and-forget ones.
+#[tauri::command]
+pub async fn kill_all_extensions() -> Result<usize, String> {
+    Ok(kill_all())
+}
+
+fn command_hash(command: &str) -> String {
+    format!("{:x}", md5::compute(command))
+}
+
+/// Approved command hashes; a missing or unreadable file approves nothing.
+fn load_approved(path: &Path) -> HashSet<String> {
+    std::fs::read(path)
+        .ok()
+        .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+        .unwrap_or_default()
+}
+
+fn is_approved(path: &Path, command: &str) -> bool {
+    load_approved(path).contains(&command_hash(command))
+}
+
+fn approve(path: &Path, command: &str) -> Result<(), String> {
+    let mut approved = load_approved(path);
+    if !approved.insert(command_hash(command)) {
+        return Ok(());
+    }
+    let mut hashes: Vec<String> = approved.into_iter().collect();
+    hashes.sort();
+    let json = serde_json::to_vec_pretty(&hashes).map_err(|e| e.to_string())?;
+    config::write_atomic(path, &json).map_err(|e| format!("Failed to save extension approval: {}", e))
+}
+
+/// Approve `command`, the command of the extension called `name` that the
+/// user was shown, letting `run_extension` execute it. Editing the command
+/// requires approving it again.
+///
+/// Nothing is approved if settings.json changed the command since it was shown.
+#[tauri::command]
+pub fn confirm_extension(name: String, command: String) -> Result<(), String> {
+    let settings = app_settings::load_settings_simple()?;
+    let extension = shown_extension(&settings.extensions, &name, &command)?;
+
+    approve(&approved_extensions_path(), &extension.command)?;
+    logger::info("Extension", &format!("Approved \"{}\": {}", name, extension.command));
+    Ok(())
+}
+
+/// The extension called `name`, as long as its command is still `command`.
+fn shown_extension<'a>(extensions: &'a [Extension], name: &str, command: &str) -> Result<&'a Extension, String> {
+    let extension = extensions
+        .iter()
+        .find(|ext| ext.name == name)
+        .ok_or_else(|| format!("No extension named \"{}\"", name))?;
+    if extension.command != command {
+        return Err(format!("The command of \"{}\" changed in settings; run it again to review it", name));
+    }
+    Ok(extension)
+}
+
 /// Check if the command is a PowerShell command that should be run directly.
 /// PowerShell commands with pipes or special characters don't work well through cmd /C.
 #[cfg(target_os = "windows")]
@@ -54,11 +207,35 @@ fn parse_command_simple(command: &str) -> Vec<String> {
     parts
 }
 
-/// Run an extension command, piping `content` to its stdin.
+/// Run an extension command, piping `content` to its stdin, or
+/// `input_override` instead when given (e.g. text selected in the preview).
 ///
-/// Returns `Ok(stdout_output)` on success, `Err(message)` on failure/timeout.
+/// Returns `Ok(stdout_output)` on success, `Err(message)` on failure/timeout,
+/// or an error starting with "Extension not approved" if the command still
+/// needs `confirm_extension`.
 #[tauri::command]
-pub async fn run_extension(command: String, content: String, timeout: i64) -> Result<String, String> {
+pub async fn run_extension(
+    command: String,
+    content: String,
+    timeout: i64,
+    input_override: Option<String>,
+) -> Result<String, String> {
+    if !is_approved(&approved_extensions_path(), &command) {
+        logger::warning("Extension", &format!("Refusing to run unapproved command: {}", command));
+        return Err(format!("{}: confirm it before running", NOT_APPROVED_ERROR));
+    }
+
+    let input = match input_override {
+        Some(input) => {
+            logger::debug("Extension", &format!("Using input override ({} bytes)", input.len()));
+            input
+        }
+        None => content,
+    };
+    spawn_extension(command, input, timeout).await
+}
+
+async fn spawn_extension(command: String, content: String, timeout: i64) -> Result<String, String> {
     logger::info("Extension", &format!("Running: {}", command));
 
     #[cfg(target_os = "windows")]
@@ -114,64 +291,76 @@ pub async fn run_extension(command: String, content: String, timeout: i64) -> Re
 
     // Write content to stdin then close it
     if let Some(mut stdin) = child.stdin.take() {
-        stdin
-            .write_all(content.as_bytes())
-            .await
-            .map_err(|e| format!("Failed to write to stdin: {}", e))?;
+        match stdin.write_all(content.as_bytes()).await {
+            // Commands that don't read their input (e.g. `echo`) may exit first
+            Err(e) if e.kind() == std::io::ErrorKind::BrokenPipe => {
+                logger::debug("Extension", "Process exited without reading stdin");
+            }
+            result => result.map_err(|e| format!("Failed to write to stdin: {}", e))?,
+        }
         // stdin is dropped here, closing the pipe
     }
 
-    match timeout {
-        0 => {
-            // Fire and forget
-            logger::info("Extension", "Fire-and-forget mode, returning immediately");
-            Ok(String::new())
-        }
-        t if t < 0 => {
-            // Wait indefinitely
-            let output = child
-                .wait_with_output()
-                .await
-                .map_err(|e| format!("Failed to wait for process: {}", e))?;
-            if output.status.success() {
-                let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-                logger::info("Extension", &format!("Process succeeded, stdout {} bytes", stdout.len()));
-                Ok(stdout)
+    let (tracked, stop) = Tracked::register();
+
+    if timeout == 0 {
+        // Fire and forget: reap the process in the background, killing it if it
+        // runs past the ceiling so misbehaving extensions don't pile up
+        logger::info("Extension", "Fire-and-forget mode, returning immediately");
+        drop(child.stdout.take());
+        tokio::spawn(async move {
+            let _tracked = tracked;
+            let limit = Some(Duration::from_secs(EXTENSION_DETACHED_MAX_SECS));
+            match wait_for_exit(&mut child, limit, stop).await {
+                Ok(Ended::Exited(status)) => {
+                    logger::debug("Extension", &format!("Fire-and-forget process exited with code {:?}", status.code()));
+                }
+                Ok(Ended::TimedOut) => logger::warning("Extension", &format!(
+                    "Fire-and-forget process still running after {}s, killed: {}",
+                    EXTENSION_DETACHED_MAX_SECS, command
+                )),
+                Ok(Ended::Stopped) => logger::info("Extension", "Fire-and-forget process stopped"),
+                Err(e) => logger::warning("Extension", &e),
+            }
+        });
+        return Ok(String::new());
+    }
+
+    // Read stdout while waiting so a chatty process can't block on a full pipe.
+    // -1 = wait indefinitely (but still stoppable), >0 = timeout in ms
+    let stdout_reader = child.stdout.take().map(|mut out| {
+        tokio::spawn(async move {
+            let mut stdout_buf = Vec::new();
+            let _ = out.read_to_end(&mut stdout_buf).await;
+            stdout_buf
+        })
+    });
+    let limit = (timeout > 0).then(|| Duration::from_millis(timeout as u64));
+
+    let ended = wait_for_exit(&mut child, limit, stop).await;
+    drop(tracked);
+
+    match ended? {
+        Ended::Exited(status) => {
+            let stdout_buf = match stdout_reader {
+                Some(reader) => reader.await.unwrap_or_default(),
+                None => Vec::new(),
+            };
+            if status.success() {
+                let output = String::from_utf8_lossy(&stdout_buf).to_string();
+                logger::info("Extension", &format!("Process succeeded, stdout {} bytes", output.len()));
+                Ok(output)
             } else {
-                Err(format!("Process exited with code {:?}", output.status.code()))
+                Err(format!("Process exited with code {:?}", status.code()))
             }
         }
-        t => {
-            // Wait with timeout
-            // Take stdout before timeout so we can kill the process if needed
-            let mut stdout_reader = child.stdout.take();
-            let duration = std::time::Duration::from_millis(t as u64);
-
-            let wait_result = tokio::time::timeout(duration, child.wait()).await;
-
-            match wait_result {
-                Ok(Ok(status)) => {
-                    // Process completed within timeout, read stdout
-                    let mut stdout_buf = Vec::new();
-                    if let Some(ref mut out) = stdout_reader {
-                        let _ = out.read_to_end(&mut stdout_buf).await;
-                    }
-                    if status.success() {
-                        let output = String::from_utf8_lossy(&stdout_buf).to_string();
-                        logger::info("Extension", &format!("Process succeeded, stdout {} bytes", output.len()));
-                        Ok(output)
-                    } else {
-                        Err(format!("Process exited with code {:?}", status.code()))
-                    }
-                }
-                Ok(Err(e)) => Err(format!("Failed to wait for process: {}", e)),
-                Err(_) => {
-                    // Timeout - kill the process
-                    logger::info("Extension", "Process timed out, killing");
-                    let _ = child.kill().await;
-                    Err("Extension timed out".to_string())
-                }
-            }
+        Ended::TimedOut => {
+            logger::info("Extension", "Process timed out, killed");
+            Err("Extension timed out".to_string())
+        }
+        Ended::Stopped => {
+            logger::info("Extension", "Process stopped");
+            Err("Extension was stopped".to_string())
         }
     }
 }
@@ -233,7 +422,7 @@ mod tests {
         #[cfg(not(target_os = "windows"))]
         let cmd = "echo test";
 
-        let result = run_extension(cmd.to_string(), "input".to_string(), 0).await;
+        let result = spawn_extension(cmd.to_string(), "input".to_string(), 0).await;
         assert!(result.is_ok());
         assert_eq!(result.unwrap(), "");
     }
@@ -245,7 +434,7 @@ mod tests {
         #[cfg(not(target_os = "windows"))]
         let cmd = "echo hello";
 
-        let result = run_extension(cmd.to_string(), "".to_string(), 5000).await;
+        let result = spawn_extension(cmd.to_string(), "".to_string(), 5000).await;
         assert!(result.is_ok());
         let output = result.unwrap();
         assert!(output.contains("hello") || output.contains("hello\r\n") || output == "hello\n");
@@ -259,7 +448,7 @@ mod tests {
         #[cfg(not(target_os = "windows"))]
         let cmd = "cat";
 
-        let result = run_extension(cmd.to_string(), "test input".to_string(), 5000).await;
+        let result = spawn_extension(cmd.to_string(), "test input".to_string(), 5000).await;
         assert!(result.is_ok());
         let output = result.unwrap();
         assert!(output.contains("test input"), "Expected output to contain 'test input', got: {}", output);
@@ -272,11 +461,90 @@ mod tests {
         #[cfg(not(target_os = "windows"))]
         let cmd = "sleep 10";
 
-        let result = run_extension(cmd.to_string(), "".to_string(), 100).await; // 100ms timeout
+        let result = spawn_extension(cmd.to_string(), "".to_string(), 100).await; // 100ms timeout
         assert!(result.is_err());
         assert!(result.unwrap_err().contains("timed out"));
     }
 
+    fn spawn_sleeper() -> Child {
+        #[cfg(target_os = "windows")]
+        let child = Command::new("powershell").args(["-Command", "Start-Sleep 10"]).spawn();
+        #[cfg(not(target_os = "windows"))]
+        let child = Command::new("sleep").arg("10").spawn();
+        child.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_exit_limit_and_stop() {
+        // Past its limit: killed and reaped
+        let mut child = spawn_sleeper();
+        let (_stop_tx, stop_rx) = oneshot::channel();
+        let ended = wait_for_exit(&mut child, Some(Duration::from_millis(100)), stop_rx).await;
+        assert!(matches!(ended, Ok(Ended::TimedOut)));
+        assert!(child.try_wait().unwrap().is_some());
+
+        // Asked to stop while waiting without a limit
+        let mut child = spawn_sleeper();
+        let (stop_tx, stop_rx) = oneshot::channel();
+        stop_tx.send(()).unwrap();
+        let ended = wait_for_exit(&mut child, None, stop_rx).await;
+        assert!(matches!(ended, Ok(Ended::Stopped)));
+        assert!(child.try_wait().unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_exit_reaps_finished_process() {
+        #[cfg(target_os = "windows")]
+        let mut child = Command::new("cmd").args(["/C", "exit 3"]).spawn().unwrap();
+        #[cfg(not(target_os = "windows"))]
+        let mut child = Command::new("sh").args(["-c", "exit 3"]).spawn().unwrap();
+
+        let (_stop_tx, stop_rx) = oneshot::channel();
+        let ended = wait_for_exit(&mut child, Some(Duration::from_secs(5)), stop_rx).await;
+        let Ok(Ended::Exited(status)) = ended else {
+            panic!("Expected the process to exit, got {:?}", ended);
+        };
+        assert_eq!(status.code(), Some(3));
+    }
+
+    #[test]
+    fn test_approval_follows_command() {
+        let path = std::env::temp_dir().join(format!("powerclip_approved_{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        assert!(!is_approved(&path, "cat"));
+        approve(&path, "cat").unwrap();
+        approve(&path, "cat").unwrap();
+        assert!(is_approved(&path, "cat"));
+        // An edited command needs approving again
+        assert!(!is_approved(&path, "cat | sh"));
+        assert_eq!(load_approved(&path).len(), 1);
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_shown_extension() {
+        let extensions = vec![Extension {
+            name: "Upper".to_string(),
+            command: "tr a-z A-Z".to_string(),
+            timeout: -1,
+            close_on_success: false,
+        }];
+
+        assert!(shown_extension(&extensions, "Upper", "tr a-z A-Z").is_ok());
+        // Edited after the prompt was shown: the new command isn't approved blindly
+        assert!(shown_extension(&extensions, "Upper", "cat").unwrap_err().contains("changed"));
+        assert!(shown_extension(&extensions, "Lower", "tr a-z A-Z").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_run_extension_requires_approval() {
+        let cmd = format!("echo unapproved {}", std::process::id());
+        let result = run_extension(cmd, "".to_string(), 5000, None).await;
+        assert!(result.unwrap_err().starts_with(NOT_APPROVED_ERROR));
+    }
+
     #[tokio::test]
     async fn test_run_extension_invalid_command() {
         #[cfg(target_os = "windows")]
@@ -284,7 +552,7 @@ mod tests {
         #[cfg(not(target_os = "windows"))]
         let cmd = "nonexistent_command_12345_xyz";
 
-        let result = run_extension(cmd.to_string(), "".to_string(), 5000).await;
+        let result = spawn_extension(cmd.to_string(), "".to_string(), 5000).await;
         assert!(result.is_err());
     }
 }
@@ -1,25 +1,161 @@
 //! History commands - Clipboard history retrieval, saving, and monitoring
 
-use std::fs;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
 
-use image::{ImageFormat, RgbaImage};
+use serde::Serialize;
 use tauri::{Emitter, Manager};
 
 use crate::clipboard::ClipboardContent;
-use crate::db::{self, ClipboardItem};
-use crate::config::{data_dir, images_dir};
-use crate::{clipboard, logger, app_settings};
+use crate::db::{self, ClipboardItem, HistoryPreview, SaveOutcome};
+use crate::config::{
+    images_dir, DUPLICATE_SCAN_MAX_ITEMS, HOTKEY_PASTE_DELAY_MS, QUICK_SELECT_POSITIONS, SIMILAR_IMAGE_MAX_DISTANCE,
+};
+use crate::app_settings::AfterSelect;
+use crate::{clipboard, logger, app_settings, monitor};
+
+use super::image::{
+    canonicalize_clipboard_image, copy_image_from_bytes, hash_missing_images, image_extension, load_image_bytes,
+    save_clipboard_gif, save_clipboard_image, IMAGE_UNAVAILABLE,
+};
+
+/// Longest text returned by `get_history_previews` in characters, 0 = no limit
+/// (hot-reloadable from settings).
+static PREVIEW_MAX_LENGTH: AtomicUsize = AtomicUsize::new(0);
+
+/// Update the preview length limit (called when settings change).
+pub fn set_preview_max_length(chars: usize) {
+    PREVIEW_MAX_LENGTH.store(chars, Ordering::Relaxed);
+}
+
+/// Hash of the last capture skipped for a disabled type, so each clipboard
+/// change is logged once rather than on every poll.
+static LAST_SKIPPED_HASH: Mutex<String> = Mutex::new(String::new());
+
+fn log_skipped_capture(item_type: &str, hash: &str, reason: &str) {
+    let Ok(mut last) = LAST_SKIPPED_HASH.lock() else {
+        return;
+    };
+    if *last != hash {
+        *last = hash.to_string();
+        logger::info("Commands", &format!("Skipped {} capture ({})", item_type, reason));
+    }
+}
+
+/// Why content of `item_type` isn't recorded, if it isn't.
+///
+/// A type turned off globally stays off; otherwise the source app's capture
+/// rule (`rule`: its app and whether it allows the type) decides.
+fn capture_skip_reason(item_type: &str, enabled: bool, rule: Option<(&str, bool)>) -> Option<String> {
+    if !enabled {
+        return Some(format!("{} capture is disabled", item_type));
+    }
+    match rule {
+        Some((app, false)) => Some(format!("{} capture is off for {}", item_type, app)),
+        _ => None,
+    }
+}
 
-use super::image::IMAGE_CACHE;
+/// Hash of the last content saved by `check_clipboard`.
+static LAST_CAPTURED_HASH: Mutex<String> = Mutex::new(String::new());
 
-/// Get clipboard history.
+/// Remember the captured hash; returns true if it differs from the previous capture.
+fn note_captured_hash(hash: &str) -> bool {
+    let Ok(mut last) = LAST_CAPTURED_HASH.lock() else {
+        return false;
+    };
+    if *last == hash {
+        return false;
+    }
+    *last = hash.to_string();
+    true
+}
+
+/// Hash of content written without recording: text from `set_clipboard` with
+/// `record: false`, or image pixels downscaled for pasting (`max_paste_dimension`).
+/// It isn't captured while it stays on the clipboard.
+static UNRECORDED_HASH: Mutex<Option<String>> = Mutex::new(None);
+
+/// Whether captured content with `hash` was written without recording.
+/// Any other content ends the exemption.
+fn is_unrecorded(hash: &str) -> bool {
+    let Ok(mut unrecorded) = UNRECORDED_HASH.lock() else {
+        return false;
+    };
+    if unrecorded.as_deref() == Some(hash) {
+        return true;
+    }
+    *unrecorded = None;
+    false
+}
+
+/// Like `is_unrecorded` for image pixels, which are only hashed while something is exempt.
+fn is_unrecorded_image(pixels: &[u8]) -> bool {
+    let exempt = UNRECORDED_HASH.lock().is_ok_and(|unrecorded| unrecorded.is_some());
+    exempt && is_unrecorded(&db::calculate_hash(pixels))
+}
+
+pub(crate) fn set_unrecorded(hash: Option<String>) {
+    if let Ok(mut unrecorded) = UNRECORDED_HASH.lock() {
+        *unrecorded = hash;
+    }
+}
+
+/// Text as `check_clipboard` stores it: line endings normalized and capture transforms applied.
+fn captured_text(text: String) -> String {
+    let text = if monitor::normalizes_line_endings() {
+        clipboard::normalize_line_endings(&text).into_owned()
+    } else {
+        text
+    };
+    let transforms = monitor::capture_transforms();
+    if transforms.is_empty() {
+        text
+    } else {
+        clipboard::transforms::apply(&text, &transforms)
+    }
+}
+
+/// Get clipboard history, optionally restricted to a single item type and
+/// ordered by one of `db::HISTORY_SORTS` (favorites first, then newest, by default).
 #[tauri::command]
 pub async fn get_history(
     state: tauri::State<'_, crate::DatabaseState>,
     limit: i64,
+    item_type: Option<String>,
+    sort: Option<String>,
 ) -> Result<Vec<ClipboardItem>, String> {
+    let query = db::HistoryQuery { item_type, limit: Some(limit), sort, ..Default::default() };
+    query.validate()?;
+    let conn = state.conn.lock().map_err(|e| e.to_string())?;
+    db::query_history(&conn, &query).map_err(|e| e.to_string())
+}
+
+/// Like `get_history`, but text longer than `preview_max_length` characters is
+/// cut short and flagged `truncated`, so long entries aren't sent in full.
+/// Fetch the full content with `get_item`.
+#[tauri::command]
+pub async fn get_history_previews(
+    state: tauri::State<'_, crate::DatabaseState>,
+    limit: i64,
+    item_type: Option<String>,
+    sort: Option<String>,
+) -> Result<Vec<HistoryPreview>, String> {
+    let items = get_history(state, limit, item_type, sort).await?;
+    let max_chars = PREVIEW_MAX_LENGTH.load(Ordering::Relaxed);
+    Ok(items.into_iter().map(|item| item.into_preview(max_chars)).collect())
+}
+
+/// Get a single history item, e.g. to refresh one row after it changed.
+///
+/// Returns `None` if the item has been deleted.
+#[tauri::command]
+pub async fn get_item(
+    state: tauri::State<'_, crate::DatabaseState>,
+    id: i64,
+) -> Result<Option<ClipboardItem>, String> {
     let conn = state.conn.lock().map_err(|e| e.to_string())?;
-    db::get_history(&conn, limit).map_err(|e| e.to_string())
+    db::get_item_by_id(&conn, id).map_err(|e| e.to_string())
 }
 
 /// Get clipboard history filtered by item type.
@@ -65,6 +201,17 @@ pub async fn get_history_since(
     db::get_history_since(&conn, &since, limit).map_err(|e| e.to_string())
 }
 
+/// Query history by any combination of text, type, tag, date range, paging and sort.
+#[tauri::command]
+pub async fn query_history(
+    state: tauri::State<'_, crate::DatabaseState>,
+    params: db::HistoryQuery,
+) -> Result<Vec<ClipboardItem>, String> {
+    params.validate()?;
+    let conn = state.conn.lock().map_err(|e| e.to_string())?;
+    db::query_history(&conn, &params).map_err(|e| e.to_string())
+}
+
 /// Get clipboard history filtered by both type and time period.
 #[tauri::command]
 pub async fn get_history_filtered(
@@ -98,21 +245,188 @@ pub async fn get_history_filtered(
             db::get_history_since(&conn, &since, limit).map_err(|e| e.to_string())
         }
         (None, None) => {
-            db::get_history(&conn, limit).map_err(|e| e.to_string())
+            db::get_history(&conn, limit, None).map_err(|e| e.to_string())
         }
     }
 }
 
+/// Copy history item `id` back to the system clipboard (see `copy_item`).
+///
+/// The item is read from the database, since the list may only hold a
+/// preview of its text (see `get_history_previews`).
+#[tauri::command]
+pub async fn copy_to_clipboard(app: tauri::AppHandle, id: i64) -> Result<(), String> {
+    let item = load_item(&app, id)?;
+    copy_item(app, item).await
+}
+
 /// Copy a history item back to the system clipboard.
+///
+/// Updates the item's use count and `last_used_at`, then emits
+/// `powerclip:item-copied` (`{ id, last_used_at }`) on success or
+/// `powerclip:copy-failed` (`{ id, error, missing }`) so the UI can confirm the copy.
+pub(crate) async fn copy_item(app: tauri::AppHandle, item: ClipboardItem) -> Result<(), String> {
+    match write_item_to_clipboard(&app, &item) {
+        Ok(()) => {
+            let last_used_at = record_use(&app, item.id);
+            let payload = serde_json::json!({ "id": item.id, "last_used_at": last_used_at });
+            let _ = app.emit("powerclip:item-copied", payload);
+            Ok(())
+        }
+        Err(e) => {
+            logger::error("Commands", &format!("Failed to copy item {}: {}", item.id, e));
+            // `missing` lets the UI offer to remove a row whose image file is gone
+            let missing = e == IMAGE_UNAVAILABLE;
+            let _ = app.emit(
+                "powerclip:copy-failed",
+                serde_json::json!({ "id": item.id, "error": e, "missing": missing }),
+            );
+            Err(e)
+        }
+    }
+}
+
+/// Save a history item to a file: the image for image items, the content for text items.
+///
+/// `dest_path` is the file to write, or an existing folder to save into under
+/// a name built from the item's time (`powerclip-20240301-083000.png`), never
+/// replacing a file already there. Returns the path written.
 #[tauri::command]
-pub async fn copy_to_clipboard(item: ClipboardItem) -> Result<(), String> {
-    if item.item_type == "image" {
-        if let Some(image_data) = IMAGE_CACHE.get(&item.hash) {
-            return super::image::copy_image_from_bytes(&image_data);
+pub async fn export_item(app: tauri::AppHandle, id: i64, dest_path: String) -> Result<String, String> {
+    let item = load_item(&app, id)?;
+
+    let (data, extension) = match item.item_type.as_str() {
+        "image" => {
+            let data = load_image_bytes(&app, &item)?;
+            let extension = image_extension(&data);
+            (data, extension)
+        }
+        "text" => (item.content.clone().into_bytes(), "txt"),
+        other => return Err(format!("{} items can't be exported to a file", other)),
+    };
+
+    let dest = std::path::PathBuf::from(dest_path.trim());
+    if dest.as_os_str().is_empty() {
+        return Err("No destination given".to_string());
+    }
+    let path = if dest.is_dir() { unused_export_path(&dest, &item, extension) } else { dest };
+
+    crate::config::write_atomic(&path, &data).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    logger::info("Commands", &format!("Exported item {} to {:?}", id, path));
+    Ok(path.to_string_lossy().into_owned())
+}
+
+/// A path in `dir` for `item` that no file uses yet, e.g. `powerclip-20240301-083000.txt`
+/// or `powerclip-20240301-083000-2.txt`.
+fn unused_export_path(dir: &std::path::Path, item: &ClipboardItem, extension: &str) -> std::path::PathBuf {
+    let stamp: String = item.created_at.chars().filter(|c| c.is_ascii_digit()).collect();
+    let stem = match stamp.len() {
+        14.. => format!("powerclip-{}-{}", &stamp[..8], &stamp[8..14]),
+        _ => format!("powerclip-{}", item.id),
+    };
+    let mut path = dir.join(format!("{}.{}", stem, extension));
+    let mut n = 2;
+    while path.exists() {
+        path = dir.join(format!("{}-{}.{}", stem, n, extension));
+        n += 1;
+    }
+    path
+}
+
+/// Put arbitrary text on the clipboard, without a history item.
+///
+/// With `record` (the default) the monitor captures it like any other copy.
+/// With `record: false` it stays out of history for as long as it is on the clipboard.
+#[tauri::command]
+pub async fn set_clipboard(text: String, record: Option<bool>) -> Result<(), String> {
+    let record = record.unwrap_or(true);
+    if !record {
+        // Set before writing, or the monitor could capture it in between
+        set_unrecorded(Some(db::calculate_hash(captured_text(text.clone()).as_bytes())));
+    }
+
+    if let Err(e) = clipboard::set_clipboard_text(&text) {
+        if !record {
+            set_unrecorded(None);
+        }
+        logger::error("Commands", &format!("Failed to set clipboard text: {}", e));
+        return Err(e);
+    }
+    logger::debug("Commands", &format!("Set clipboard text ({} chars, record: {})", text.chars().count(), record));
+    Ok(())
+}
+
+/// Copy item `id` picked in the main window, then follow the `after_select` policy.
+#[tauri::command]
+pub async fn select_item(app: tauri::AppHandle, id: i64) -> Result<(), String> {
+    copy_to_clipboard(app.clone(), id).await?;
+    finish_selection(&app, false).await?;
+    Ok(())
+}
+
+/// Hide the window and/or paste according to the `after_select` setting.
+///
+/// Emits `powerclip:after-select` with the applied action so the frontend can
+/// follow along. `wait_for_modifiers` delays the paste when the selection came
+/// from a shortcut whose modifiers may still be held.
+pub(crate) async fn finish_selection(
+    app: &tauri::AppHandle,
+    wait_for_modifiers: bool,
+) -> Result<AfterSelect, String> {
+    let policy = app_settings::load_settings_simple()?.after_select_policy();
+
+    if policy != AfterSelect::StayOpen {
+        crate::window::commands::hide_window(app.clone()).await?;
+    }
+    let _ = app.emit("powerclip:after-select", serde_json::json!({ "action": policy.as_str() }));
+
+    if policy == AfterSelect::HideAndPaste {
+        if wait_for_modifiers {
+            tokio::time::sleep(std::time::Duration::from_millis(HOTKEY_PASTE_DELAY_MS)).await;
+        }
+        super::paste::simulate_paste().await?;
+    }
+    Ok(policy)
+}
+
+/// Count a copy and stamp `last_used_at`, returning the new timestamp.
+///
+/// Failure only costs ranking accuracy, so it is logged rather than returned.
+fn record_use(app: &tauri::AppHandle, item_id: i64) -> Option<String> {
+    let state = app.state::<crate::DatabaseState>();
+    let result = match state.conn.lock() {
+        Ok(conn) => db::record_use(&conn, item_id).map_err(|e| e.to_string()),
+        Err(e) => Err(e.to_string()),
+    };
+    match result {
+        Ok(used_at) => Some(used_at),
+        Err(e) => {
+            logger::warning("Commands", &format!("Failed to record use of item {}: {}", item_id, e));
+            None
         }
-        return copy_image_to_clipboard(&item.content);
     }
+}
 
+/// History item `id` with its full content, or an error if it was deleted.
+pub(crate) fn load_item(app: &tauri::AppHandle, id: i64) -> Result<ClipboardItem, String> {
+    let state = app.state::<crate::DatabaseState>();
+    let conn = state.conn.lock().map_err(|e| e.to_string())?;
+    db::get_item_by_id(&conn, id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Item {} not found", id))
+}
+
+/// Write a history item back to the clipboard, without recording its use.
+pub(crate) fn write_item_to_clipboard(app: &tauri::AppHandle, item: &ClipboardItem) -> Result<(), String> {
+    if item.item_type == "image" {
+        let image_data = load_image_bytes(app, item)?;
+        return copy_image_from_bytes(&image_data);
+    }
+    write_text_or_files(item)
+}
+
+/// Write a text or file item back to the clipboard.
+pub(crate) fn write_text_or_files(item: &ClipboardItem) -> Result<(), String> {
     if item.item_type == "file" {
         // Parse JSON array of file paths
         let paths: Vec<String> = serde_json::from_str(&item.content)
@@ -120,24 +434,105 @@ pub async fn copy_to_clipboard(item: ClipboardItem) -> Result<(), String> {
         return clipboard::set_clipboard_files(&paths);
     }
 
+    // Stored text had its \r\n stripped; give Windows apps back what they expect
+    if cfg!(target_os = "windows") && monitor::normalizes_line_endings() {
+        return clipboard::set_clipboard_text(&clipboard::to_crlf(&item.content));
+    }
     clipboard::set_clipboard_text(&item.content).map_err(|e| e.to_string())
 }
 
-/// Copy image to clipboard from a file path relative to data_dir.
-fn copy_image_to_clipboard(relative_path: &str) -> Result<(), String> {
-    use image::{GenericImageView, ImageReader};
+/// Re-copy the most recent history item, then paste it if `after_select` is "hide_and_paste".
+///
+/// Backs the copy-latest hotkey. Returns the copied item, or `None` when history is empty.
+#[tauri::command]
+pub async fn copy_latest_item(app: tauri::AppHandle) -> Result<Option<ClipboardItem>, String> {
+    let latest = {
+        let state = app.state::<crate::DatabaseState>();
+        let conn = state.conn.lock().map_err(|e: std::sync::PoisonError<_>| e.to_string())?;
+        db::get_history(&conn, 1, None).map_err(|e| e.to_string())?.into_iter().next()
+    };
+    let Some(item) = latest else {
+        logger::info("Commands", "History is empty, nothing to copy");
+        return Ok(None);
+    };
 
-    let image_path = data_dir().join(relative_path);
+    copy_item(app.clone(), item.clone()).await?;
 
-    let img = ImageReader::open(&image_path)
-        .map_err(|e| e.to_string())?
-        .decode()
-        .map_err(|e| e.to_string())?;
+    // The window isn't involved here, so only the paste part of the policy applies
+    if app_settings::load_settings_simple()?.after_select_policy() == AfterSelect::HideAndPaste {
+        tokio::time::sleep(std::time::Duration::from_millis(HOTKEY_PASTE_DELAY_MS)).await;
+        super::paste::simulate_paste().await?;
+    }
+    Ok(Some(item))
+}
 
-    let (width, height) = img.dimensions();
-    let rgba = img.to_rgba8();
+/// Empty the system clipboard, then delete the newest `clear_clipboard_delete_recent`
+/// history items (favorites are kept).
+///
+/// Backs the clear-clipboard hotkey. Emits `powerclip:clipboard-cleared` with the
+/// number of deleted items and returns that number.
+#[tauri::command]
+pub async fn clear_clipboard(app: tauri::AppHandle) -> Result<usize, String> {
+    // Cleared first, or the monitor would capture the content again right after it's deleted
+    clipboard::clear_clipboard()?;
+    if let Ok(mut last) = LAST_CAPTURED_HASH.lock() {
+        last.clear();
+    }
+
+    let count = app_settings::load_settings_simple()?.clear_clipboard_delete_recent;
+    let ids = if count > 0 {
+        let state = app.state::<crate::DatabaseState>();
+        let conn = state.conn.lock().map_err(|e| e.to_string())?;
+        db::delete_newest_items(&conn, count).map_err(|e| e.to_string())?
+    } else {
+        Vec::new()
+    };
+
+    if let Some(sem_state) = app.try_state::<crate::semantic::SemanticState>() {
+        if let Ok(mut index) = sem_state.index.write() {
+            for id in &ids {
+                index.remove(*id);
+            }
+        }
+        if let Ok(mut status) = sem_state.status.write() {
+            status.indexed_count = status.indexed_count.saturating_sub(ids.len());
+        }
+    }
+
+    crate::window::forget_selected_items(&ids);
+    logger::info("Commands", &format!("Cleared clipboard and deleted {} history items", ids.len()));
+    let _ = app.emit("powerclip:clipboard-cleared", ids.len());
+    if !ids.is_empty() {
+        crate::tray::refresh_menu(&app);
+    }
+    Ok(ids.len())
+}
+
+/// Copy the item at 1-based position `n` among the newest history items,
+/// then follow the `after_select` policy.
+///
+/// Backs the digit shortcuts in the main window. Positions follow the default
+/// history order (favorites first, then newest).
+#[tauri::command]
+pub async fn copy_by_position(app: tauri::AppHandle, n: usize) -> Result<ClipboardItem, String> {
+    if !(1..=QUICK_SELECT_POSITIONS).contains(&n) {
+        return Err(format!("Position must be between 1 and {}, got {}", QUICK_SELECT_POSITIONS, n));
+    }
+
+    let items = {
+        let state = app.state::<crate::DatabaseState>();
+        let conn = state.conn.lock().map_err(|e| e.to_string())?;
+        db::get_history(&conn, QUICK_SELECT_POSITIONS as i64, None).map_err(|e| e.to_string())?
+    };
+    let count = items.len();
+    let Some(item) = items.into_iter().nth(n - 1) else {
+        return Err(format!("No item at position {} (history has {} items)", n, count));
+    };
 
-    clipboard::set_clipboard_image(width, height, &rgba).map_err(|e| e.to_string())
+    copy_item(app.clone(), item.clone()).await?;
+    // The shortcut's modifier may still be held
+    finish_selection(&app, true).await?;
+    Ok(item)
 }
 
 /// Check clipboard for new content and save to database.
@@ -149,45 +544,92 @@ pub async fn check_clipboard(app: tauri::AppHandle) -> Result<(), String> {
         return Ok(());
     };
 
+    // Looked up before the content is consumed below
+    let rule = monitor::capture_rule_for(&content);
+
     let state = app.state::<crate::DatabaseState>();
-    let conn = state.conn.lock().map_err(|e: std::sync::PoisonError<_>| e.to_string())?;
+    let conn = monitor::wait_for_database(|| state.conn.lock())
+        .map_err(|e: std::sync::PoisonError<_>| e.to_string())?;
+    // Paused while this check waited for the lock (e.g. the data folder is moving)
+    if monitor::is_paused() {
+        return Ok(());
+    }
 
-    let saved_item = match content {
+    let (hash, outcome) = match content {
         ClipboardContent::Text(text) => {
+            let text = captured_text(text);
+            if text.is_empty() {
+                return Ok(());
+            }
             let hash = db::calculate_hash(text.as_bytes());
-            db::save_item(&conn, "text", &text, &hash).map_err(|e| e.to_string())?
+            if is_unrecorded(&hash) {
+                return Ok(());
+            }
+            let text_rule = rule.as_ref().map(|r| (r.app.as_str(), r.capture_text));
+            if let Some(reason) = capture_skip_reason("text", monitor::captures_text(), text_rule) {
+                log_skipped_capture("text", &hash, &reason);
+                return Ok(());
+            }
+            let saved = db::save_item(&conn, "text", &text, &hash).map_err(|e| e.to_string())?;
+            (hash, saved)
         }
         ClipboardContent::Image(image) => {
-            let hash = db::calculate_hash(&image.bytes);
-            let relative_path = format!("images/{}.png", hash);
-
-            // Save image file if it doesn't exist yet
-            let image_path = images_dir().join(format!("{}.png", hash));
-            if !image_path.exists() {
-                fs::create_dir_all(images_dir()).map_err(|e| e.to_string())?;
-
-                let rgba = RgbaImage::from_vec(image.width, image.height, image.bytes)
-                    .ok_or_else(|| "Failed to create image buffer".to_string())?;
-
-                rgba.save_with_format(&image_path, ImageFormat::Png)
-                    .map_err(|e| e.to_string())?;
-
-                let image_data = fs::read(&image_path).map_err(|e| e.to_string())?;
-                IMAGE_CACHE.insert(hash.clone(), image_data);
+            if is_unrecorded_image(&image.bytes) {
+                return Ok(());
+            }
+            let image_rule = rule.as_ref().map(|r| (r.app.as_str(), r.capture_images));
+            if let Some(reason) = capture_skip_reason("image", monitor::captures_images(), image_rule) {
+                log_skipped_capture("image", &db::calculate_hash(&image.bytes), &reason);
+                return Ok(());
             }
 
-            db::save_item(&conn, "image", &relative_path, &hash).map_err(|e| e.to_string())?
+            if let Some(gif) = image.gif {
+                save_clipboard_gif(&conn, &images_dir(), gif, monitor::stores_image_inline)?
+            } else {
+                // The canonical hash is both the dedup key and the filename
+                let canonical = canonicalize_clipboard_image(image, monitor::max_image_pixels())?;
+                let hash = canonical.hash.clone();
+                let saved = save_clipboard_image(&conn, &images_dir(), canonical, monitor::stores_image_inline)?;
+                (hash, saved)
+            }
         }
         ClipboardContent::Files(files) => {
+            set_unrecorded(None);
             // Store file paths as JSON array
             let content = serde_json::to_string(&files.paths)
                 .map_err(|e| format!("Failed to serialize file paths: {}", e))?;
             let hash = db::calculate_hash(content.as_bytes());
-            db::save_item(&conn, "file", &content, &hash).map_err(|e| e.to_string())?
+            let saved = db::save_item(&conn, "file", &content, &hash).map_err(|e| e.to_string())?;
+            (hash, saved)
         }
     };
 
-    if let Some(item) = saved_item {
+    super::stack::record_copy(&app, &hash, outcome.id());
+
+    // The monitor re-saves unchanged content on every poll; only a changed
+    // clipboard is worth telling the UI about
+    let changed = note_captured_hash(&hash);
+
+    if let SaveOutcome::Updated { id, created_at } = &outcome {
+        if changed {
+            // Already in history: the UI moves the existing row to the top
+            let payload = serde_json::json!({ "id": id, "created_at": created_at });
+            app.emit_to("main", "powerclip:item-updated", payload).ok();
+        }
+    }
+
+    let saved_item = outcome.inserted();
+    let saved = saved_item.is_some();
+
+    if let Some(mut item) = saved_item {
+        // Tag likely code with a language hint (only for new items; the scan is bounded)
+        if item.item_type == "text" {
+            if let Some(language) = clipboard::code::detect_language(&item.content) {
+                db::set_code_language(&conn, item.id, Some(language)).map_err(|e| e.to_string())?;
+                item.code_language = Some(language.to_string());
+            }
+        }
+
         app.emit_to("main", "powerclip:new-item", &item).ok();
 
         // Index for semantic search (runtime-controlled)
@@ -205,16 +647,158 @@ pub async fn check_clipboard(app: tauri::AppHandle) -> Result<(), String> {
         }
 
         let settings = app_settings::load_settings_simple().unwrap_or_default();
-        if settings.auto_cleanup_enabled && settings.max_items > 0 {
-            if let Ok(deleted) = db::cleanup_old_items(&conn, settings.max_items) {
-                if deleted > 0 {
-                    logger::info("Commands", &format!("Auto-cleanup: deleted {} old items", deleted));
-                }
+        enforce_history_limits(&conn, &settings);
+    }
+
+    // Keep the tray's recent items current (needs the connection lock released)
+    drop(conn);
+    if saved || changed {
+        crate::tray::refresh_menu(&app);
+    }
+
+    Ok(())
+}
+
+/// Delete items beyond `max_items` (when auto-cleanup is on) and items older
+/// than `max_age_days`, with their image files. Favorites are always kept.
+///
+/// Returns the number of items deleted.
+fn enforce_history_limits(conn: &rusqlite::Connection, settings: &app_settings::AppSettings) -> i64 {
+    let mut total = 0;
+    if settings.auto_cleanup_enabled && settings.max_items > 0 {
+        if let Ok(deleted) = db::cleanup_old_items(conn, settings.max_items) {
+            if deleted > 0 {
+                logger::info("Commands", &format!("Auto-cleanup: deleted {} old items", deleted));
+            }
+            total += deleted;
+        }
+    }
+    if settings.max_age_days > 0 {
+        let now = chrono::Local::now().naive_local();
+        if let Ok(deleted) = db::cleanup_items_older_than(conn, settings.max_age_days, now) {
+            if deleted > 0 {
+                logger::info(
+                    "Commands",
+                    &format!("Auto-cleanup: deleted {} items older than {} days", deleted, settings.max_age_days),
+                );
             }
+            total += deleted;
         }
     }
+    total
+}
 
-    Ok(())
+/// Trim history to the limits in `settings` right away, rather than on the next capture.
+///
+/// Called when settings are saved or reloaded, so lowering `max_items` takes
+/// effect immediately. Emits `powerclip:history-changed` when items were deleted.
+pub fn apply_history_limits(app: &tauri::AppHandle, settings: &app_settings::AppSettings) {
+    let Some(state) = app.try_state::<crate::DatabaseState>() else {
+        return;
+    };
+    let deleted = match state.conn.lock() {
+        Ok(conn) => enforce_history_limits(&conn, settings),
+        Err(e) => {
+            logger::error("Commands", &format!("Failed to lock database: {}", e));
+            return;
+        }
+    };
+
+    if deleted > 0 {
+        let _ = app.emit("powerclip:history-changed", ());
+        crate::tray::refresh_menu(app);
+    }
+}
+
+/// A set of history items that look like copies of each other.
+#[derive(Debug, Clone, Serialize)]
+pub struct DuplicateGroup {
+    pub group_id: usize,
+    /// Member ids, newest first
+    pub item_ids: Vec<i64>,
+}
+
+/// List the format identifiers the clipboard currently offers (diagnostic).
+#[tauri::command]
+pub async fn list_clipboard_formats() -> Result<Vec<String>, String> {
+    Ok(clipboard::list_clipboard_formats())
+}
+
+/// Find groups of duplicate text items.
+///
+/// With `threshold`, items whose embeddings have at least that similarity are
+/// grouped (needs semantic search; only the newest `DUPLICATE_SCAN_MAX_ITEMS`
+/// embeddings are compared). Without it, items identical after normalizing
+/// case and whitespace are grouped.
+#[tauri::command]
+pub async fn find_duplicates(app: tauri::AppHandle, threshold: Option<f32>) -> Result<Vec<DuplicateGroup>, String> {
+    let groups = match threshold {
+        Some(threshold) => {
+            if !(0.0..=1.0).contains(&threshold) {
+                return Err(format!("Threshold must be between 0 and 1, got {}", threshold));
+            }
+            let state = app
+                .try_state::<crate::semantic::SemanticState>()
+                .ok_or_else(|| "Semantic search is not available".to_string())?;
+            if !state.status.read().map_err(|e| e.to_string())?.enabled {
+                return Err("Semantic search is not enabled".to_string());
+            }
+
+            state.touch();
+            {
+                let db_state = app.state::<crate::DatabaseState>();
+                let conn = db_state.conn.lock().map_err(|e| e.to_string())?;
+                state.ensure_index_loaded(&conn)?;
+            }
+            let snapshot = state.index.read().map_err(|e| e.to_string())?.snapshot();
+            tokio::task::spawn_blocking(move || snapshot.similar_groups(threshold, DUPLICATE_SCAN_MAX_ITEMS))
+                .await
+                .map_err(|e| e.to_string())?
+        }
+        None => {
+            let state = app.state::<crate::DatabaseState>();
+            let conn = state.conn.lock().map_err(|e| e.to_string())?;
+            db::find_text_duplicates(&conn).map_err(|e| e.to_string())?
+        }
+    };
+
+    logger::info("Commands", &format!("Found {} duplicate groups", groups.len()));
+    Ok(groups
+        .into_iter()
+        .enumerate()
+        .map(|(group_id, item_ids)| DuplicateGroup { group_id, item_ids })
+        .collect())
+}
+
+/// Group image items that look alike, such as repeated screenshots.
+///
+/// Images whose perceptual hashes differ in at most `distance` of 64 bits
+/// (default `SIMILAR_IMAGE_MAX_DISTANCE`) are grouped. Images not hashed yet
+/// are hashed first, off the async runtime.
+#[tauri::command]
+pub async fn group_similar_images(app: tauri::AppHandle, distance: Option<u32>) -> Result<Vec<DuplicateGroup>, String> {
+    let distance = distance.unwrap_or(SIMILAR_IMAGE_MAX_DISTANCE);
+    if distance > 64 {
+        return Err(format!("Distance must be between 0 and 64, got {}", distance));
+    }
+
+    let app_clone = app.clone();
+    tokio::task::spawn_blocking(move || hash_missing_images(&app_clone))
+        .await
+        .map_err(|e| e.to_string())??;
+
+    let groups = {
+        let state = app.state::<crate::DatabaseState>();
+        let conn = state.conn.lock().map_err(|e| e.to_string())?;
+        db::find_similar_images(&conn, distance, DUPLICATE_SCAN_MAX_ITEMS).map_err(|e| e.to_string())?
+    };
+
+    logger::info("Commands", &format!("Found {} similar image groups", groups.len()));
+    Ok(groups
+        .into_iter()
+        .enumerate()
+        .map(|(group_id, item_ids)| DuplicateGroup { group_id, item_ids })
+        .collect())
 }
 
 /// Toggle favorite status of a history item.
@@ -229,6 +813,18 @@ pub async fn toggle_favorite(
     db::toggle_favorite(&conn, item_id).map_err(|e| e.to_string())
 }
 
+/// Toggle concealed status of a history item.
+///
+/// Returns the new concealed state (true = concealed, false = visible).
+#[tauri::command]
+pub async fn toggle_conceal(
+    state: tauri::State<'_, crate::DatabaseState>,
+    item_id: i64,
+) -> Result<bool, String> {
+    let conn = state.conn.lock().map_err(|e| e.to_string())?;
+    db::toggle_conceal(&conn, item_id).map_err(|e| e.to_string())
+}
+
 /// Delete a history item by ID.
 ///
 /// Also deletes the associated image file if the item is an image.
@@ -258,8 +854,15 @@ pub async fn delete_history_item(
         // Note: We don't have the hash here, so we clear the entire cache entry
         // This is acceptable as the cache will be repopulated on demand
 
+        // Don't restore the selection to the deleted item
+        crate::window::forget_selected_items(&[item_id]);
         logger::info("Commands", &format!("Deleted item {}", item_id));
     }
 
+    drop(conn);
+    if deleted {
+        crate::tray::refresh_menu(&app);
+    }
+
     Ok(())
 }
@@ -1,61 +1,544 @@
 //! Image commands - Image asset serving and in-memory cache
 
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
+use std::fs;
 use std::io::Cursor;
+use std::path::{Component, Path};
+use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::{LazyLock, Mutex};
 
-use image::{GenericImageView, ImageReader};
+use image::imageops::FilterType;
+use image::{DynamicImage, GenericImageView, ImageFormat, ImageReader, RgbaImage};
 
-use crate::clipboard;
-use crate::config::data_dir;
+use tauri::Manager;
 
-/// In-memory cache for clipboard images.
+use crate::clipboard::ImageData;
+use crate::db::{ClipboardItem, SaveOutcome};
+use crate::{clipboard, db, logger};
+use crate::config::{
+    self, data_path, images_dir, IMAGE_CACHE_MAX_BYTES, IMAGE_CACHE_PREWARM_ITEMS, IMAGE_CACHE_PREWARM_MAX_BYTES,
+    TEMP_FILE_SUFFIX,
+};
+
+/// In-memory cache for clipboard images, holding at most `max_bytes`.
+///
+/// Inserting evicts the least recently used images until the new one fits.
 pub(crate) struct ImageCache {
-    images: Mutex<HashMap<String, Vec<u8>>>,
+    max_bytes: usize,
+    entries: Mutex<CacheEntries>,
+}
+
+#[derive(Default)]
+struct CacheEntries {
+    images: HashMap<String, Vec<u8>>,
+    /// Hashes from least to most recently used
+    order: VecDeque<String>,
+    total_bytes: usize,
+}
+
+impl CacheEntries {
+    fn remove(&mut self, hash: &str) {
+        if let Some(data) = self.images.remove(hash) {
+            self.total_bytes -= data.len();
+            self.order.retain(|h| h != hash);
+        }
+    }
 }
 
 impl ImageCache {
-    fn new() -> Self {
+    fn new(max_bytes: usize) -> Self {
         Self {
-            images: Mutex::new(HashMap::new()),
+            max_bytes,
+            entries: Mutex::new(CacheEntries::default()),
         }
     }
 
     pub fn get(&self, hash: &str) -> Option<Vec<u8>> {
-        self.images.lock().unwrap().get(hash).cloned()
+        let mut entries = self.entries.lock().unwrap();
+        let data = entries.images.get(hash).cloned()?;
+        if let Some(pos) = entries.order.iter().position(|h| h == hash) {
+            let used = entries.order.remove(pos).expect("position is in range");
+            entries.order.push_back(used);
+        }
+        Some(data)
     }
 
     pub fn insert(&self, hash: String, data: Vec<u8>) {
-        self.images.lock().unwrap().insert(hash, data);
+        // It would push out everything else and still not fit
+        if data.len() > self.max_bytes {
+            return;
+        }
+        let mut entries = self.entries.lock().unwrap();
+        entries.remove(&hash);
+        while entries.total_bytes + data.len() > self.max_bytes {
+            let Some(oldest) = entries.order.front().cloned() else {
+                break;
+            };
+            entries.remove(&oldest);
+        }
+        entries.total_bytes += data.len();
+        entries.order.push_back(hash.clone());
+        entries.images.insert(hash, data);
+    }
+
+    pub fn contains(&self, hash: &str) -> bool {
+        self.entries.lock().unwrap().images.contains_key(hash)
+    }
+
+    /// Combined size of the cached images in bytes.
+    pub fn total_bytes(&self) -> usize {
+        self.entries.lock().unwrap().total_bytes
+    }
+}
+
+pub(crate) static IMAGE_CACHE: LazyLock<ImageCache> = LazyLock::new(|| ImageCache::new(IMAGE_CACHE_MAX_BYTES));
+
+/// Dimensions scaled down (keeping the aspect ratio) to at most `max_pixels`,
+/// or `None` if the image already fits. `max_pixels == 0` means no limit.
+fn fit_within(width: u32, height: u32, max_pixels: u64) -> Option<(u32, u32)> {
+    let pixels = width as u64 * height as u64;
+    if max_pixels == 0 || pixels <= max_pixels {
+        return None;
+    }
+    let scale = (max_pixels as f64 / pixels as f64).sqrt();
+    let new_height = ((height as f64 * scale).floor() as u64).max(1);
+    // Clamping a thin side to 1px can overshoot the limit, so bound the other side too
+    let new_width = ((width as f64 * scale).floor() as u64).clamp(1, (max_pixels / new_height).max(1));
+    Some((new_width as u32, new_height as u32))
+}
+
+/// Dimensions scaled down (keeping the aspect ratio) so neither side exceeds `max_dim`,
+/// or `None` if the image already fits. `max_dim == 0` means no limit.
+fn fit_dimension(width: u32, height: u32, max_dim: u32) -> Option<(u32, u32)> {
+    if max_dim == 0 || (width <= max_dim && height <= max_dim) {
+        return None;
+    }
+    let scale = max_dim as f64 / width.max(height) as f64;
+    let scaled = |side: u32| ((side as f64 * scale).round() as u32).clamp(1, max_dim);
+    Some((scaled(width), scaled(height)))
+}
+
+/// Longest side, in pixels, of images placed on the clipboard (`max_paste_dimension`, 0 = no limit).
+static MAX_PASTE_DIMENSION: AtomicU32 = AtomicU32::new(0);
+
+/// Update the paste size limit (called when settings change).
+pub fn set_max_paste_dimension(max_dim: u32) {
+    MAX_PASTE_DIMENSION.store(max_dim, Ordering::Relaxed);
+}
+
+/// A clipboard image normalized for storage.
+pub(crate) struct CanonicalImage {
+    /// Hash of the stored RGBA pixels: the history dedup key and the image filename.
+    ///
+    /// Hashing decoded pixels (never file bytes) makes the same picture copied
+    /// from a PNG or a JPEG source, or re-copied from history, collapse into one entry.
+    pub hash: String,
+    /// Pixels to encode, or `None` when the image is known to be saved already
+    pub pixels: Option<RgbaImage>,
+}
+
+/// Last downscaled clipboard image as (hash of the original pixels, canonical hash).
+///
+/// The monitor re-reads the clipboard on every poll; this avoids resizing the
+/// same large image again each time.
+static LAST_DOWNSCALED: Mutex<Option<(String, String)>> = Mutex::new(None);
+
+/// Downscale an image over `max_pixels` and compute its canonical hash.
+pub(crate) fn canonicalize_clipboard_image(image: ImageData, max_pixels: u64) -> Result<CanonicalImage, String> {
+    let (width, height) = (image.width, image.height);
+
+    let Some((new_width, new_height)) = fit_within(width, height, max_pixels) else {
+        let hash = db::calculate_hash(&image.bytes);
+        let pixels = RgbaImage::from_vec(width, height, image.bytes)
+            .ok_or_else(|| "Failed to create image buffer".to_string())?;
+        return Ok(CanonicalImage { hash, pixels: Some(pixels) });
+    };
+
+    let source_hash = db::calculate_hash(&image.bytes);
+    let cached = LAST_DOWNSCALED.lock().map_err(|e| e.to_string())?.clone();
+    if let Some((last_source, hash)) = cached {
+        if last_source == source_hash && images_dir().join(format!("{}.png", hash)).exists() {
+            return Ok(CanonicalImage { hash, pixels: None });
+        }
     }
+
+    let rgba = RgbaImage::from_vec(width, height, image.bytes)
+        .ok_or_else(|| "Failed to create image buffer".to_string())?;
+    logger::info(
+        "Image",
+        &format!("Downscaling {}x{} image to {}x{}", width, height, new_width, new_height),
+    );
+    let pixels = image::imageops::resize(&rgba, new_width, new_height, FilterType::Triangle);
+    let hash = db::calculate_hash(pixels.as_raw());
+
+    *LAST_DOWNSCALED.lock().map_err(|e| e.to_string())? = Some((source_hash, hash.clone()));
+    Ok(CanonicalImage { hash, pixels: Some(pixels) })
 }
 
-pub(crate) static IMAGE_CACHE: LazyLock<ImageCache> = LazyLock::new(ImageCache::new);
+/// Store a canonical clipboard image and record it in history.
+///
+/// The PNG is written only if no file or inline copy with this hash exists,
+/// so copying the same image again just moves its row to the top. Images for
+/// which `store_inline` returns true (given the PNG size) go into the database
+/// instead of `dir`. The encoded bytes are cached so they aren't read back.
+pub(crate) fn save_clipboard_image(
+    conn: &rusqlite::Connection,
+    dir: &Path,
+    canonical: CanonicalImage,
+    store_inline: impl Fn(usize) -> bool,
+) -> Result<SaveOutcome, String> {
+    let pixels = canonical.pixels;
+    let encode = || encode_png(&pixels.ok_or_else(|| "Image pixels unavailable".to_string())?);
+    save_encoded_image(conn, dir, &canonical.hash, "png", encode, store_inline)
+}
+
+/// Store a copied GIF verbatim (so animation survives) and record it in history.
+///
+/// Unlike other images, GIFs are keyed by the hash of their file bytes, since
+/// different animations can share a first frame; they are never downscaled.
+/// Returns the hash along with the outcome.
+pub(crate) fn save_clipboard_gif(
+    conn: &rusqlite::Connection,
+    dir: &Path,
+    gif: Vec<u8>,
+    store_inline: impl Fn(usize) -> bool,
+) -> Result<(String, SaveOutcome), String> {
+    let hash = db::calculate_hash(&gif);
+    let saved = save_encoded_image(conn, dir, &hash, "gif", || Ok(gif), store_inline)?;
+    Ok((hash, saved))
+}
+
+/// Record an image item stored as `images/<hash>.<extension>`, writing the
+/// file from `encode` only if no file or inline copy with this hash exists.
+fn save_encoded_image(
+    conn: &rusqlite::Connection,
+    dir: &Path,
+    hash: &str,
+    extension: &str,
+    encode: impl FnOnce() -> Result<Vec<u8>, String>,
+    store_inline: impl Fn(usize) -> bool,
+) -> Result<SaveOutcome, String> {
+    let relative_path = format!("images/{}.{}", hash, extension);
+    let image_path = dir.join(format!("{}.{}", hash, extension));
+
+    let mut inline_data = None;
+    if !image_path.exists() && !db::has_inline_image(conn, hash).map_err(|e| e.to_string())? {
+        let data = encode()?;
+
+        if store_inline(data.len()) {
+            inline_data = Some(data.clone());
+        } else {
+            fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+            // Readers never see a half-written file, even after a crash
+            config::write_atomic(&image_path, &data).map_err(|e| e.to_string())?;
+        }
+        IMAGE_CACHE.insert(hash.to_string(), data);
+    }
+
+    let saved = db::save_item(conn, "image", &relative_path, hash).map_err(|e| e.to_string())?;
+    if let Some(data) = inline_data {
+        db::set_inline_image(conn, saved.id(), &data).map_err(|e| e.to_string())?;
+    }
+    Ok(saved)
+}
+
+/// Encode RGBA pixels as PNG.
+pub(crate) fn encode_png(pixels: &RgbaImage) -> Result<Vec<u8>, String> {
+    let mut png = Vec::new();
+    pixels.write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+        .map_err(|e| e.to_string())?;
+    Ok(png)
+}
+
+/// Delete `.tmp` files left in `dir` by image writes a crash interrupted.
+///
+/// Returns the number of files removed.
+pub(crate) fn remove_partial_images(dir: &Path) -> usize {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return 0;
+    };
+
+    let mut removed = 0;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let is_partial = path.file_name().is_some_and(|n| n.to_string_lossy().ends_with(TEMP_FILE_SUFFIX));
+        if !is_partial || !path.is_file() {
+            continue;
+        }
+        match fs::remove_file(&path) {
+            Ok(()) => removed += 1,
+            Err(e) => logger::warning("Image", &format!("Failed to delete partial image {:?}: {}", path, e)),
+        }
+    }
+
+    if removed > 0 {
+        logger::info("Image", &format!("Deleted {} partially written images", removed));
+    }
+    removed
+}
+
+/// Read the newest image items into `IMAGE_CACHE` (run on a background thread at startup).
+///
+/// Stops once the cache would grow past `IMAGE_CACHE_PREWARM_MAX_BYTES`.
+pub(crate) fn prewarm_image_cache(app: &tauri::AppHandle) {
+    let items = {
+        let Some(state) = app.try_state::<crate::DatabaseState>() else {
+            return;
+        };
+        let Ok(conn) = state.conn.lock() else {
+            return;
+        };
+        match db::get_history_by_type(&conn, "image", IMAGE_CACHE_PREWARM_ITEMS) {
+            Ok(items) => items,
+            Err(e) => {
+                logger::warning("Image", &format!("Failed to list images to prewarm: {}", e));
+                return;
+            }
+        }
+    };
+
+    let cached = prewarm_cache(&IMAGE_CACHE, &items, IMAGE_CACHE_PREWARM_MAX_BYTES, |item| load_image_bytes(app, item));
+    logger::info("Image", &format!("Prewarmed image cache with {} of {} recent images", cached, items.len()));
+}
+
+/// Cache the bytes of `items` in order, skipping those already cached, until
+/// the cache would hold more than `max_bytes`. Returns the number added.
+fn prewarm_cache(
+    cache: &ImageCache,
+    items: &[ClipboardItem],
+    max_bytes: usize,
+    load: impl Fn(&ClipboardItem) -> Result<Vec<u8>, String>,
+) -> usize {
+    let mut total = cache.total_bytes();
+    let mut added = 0;
+    for item in items {
+        if cache.contains(&item.hash) {
+            continue;
+        }
+        let data = match load(item) {
+            Ok(data) => data,
+            Err(e) => {
+                logger::debug("Image", &format!("Skipped prewarming {}: {}", item.content, e));
+                continue;
+            }
+        };
+        if total + data.len() > max_bytes {
+            break;
+        }
+        total += data.len();
+        cache.insert(item.hash.clone(), data);
+        added += 1;
+    }
+    added
+}
+
+/// Perceptual difference hash (dHash) of an image.
+///
+/// The image is shrunk to 9x8 grayscale and each bit records whether a pixel
+/// is brighter than its right neighbour, so resized or re-encoded copies of
+/// an image get the same or a nearby hash.
+pub(crate) fn difference_hash(image: &DynamicImage) -> u64 {
+    let small = image.resize_exact(9, 8, FilterType::Triangle).to_luma8();
+    let mut hash = 0u64;
+    for y in 0..8 {
+        for x in 0..8 {
+            hash <<= 1;
+            if small.get_pixel(x, y)[0] > small.get_pixel(x + 1, y)[0] {
+                hash |= 1;
+            }
+        }
+    }
+    hash
+}
+
+/// Compute and store the perceptual hash of every image item that lacks one.
+///
+/// Decoding is slow, so this runs when similar images are first looked for
+/// rather than when an image is captured. Images that can't be loaded or
+/// decoded are marked so they aren't tried again. Returns the number of images hashed.
+pub(crate) fn hash_missing_images(app: &tauri::AppHandle) -> Result<usize, String> {
+    let state = app.state::<crate::DatabaseState>();
+    let items = {
+        let conn = state.conn.lock().map_err(|e| e.to_string())?;
+        let ids = db::get_images_without_phash(&conn).map_err(|e| e.to_string())?;
+        db::get_items_by_ids(&conn, &ids).map_err(|e| e.to_string())?
+    };
+
+    let mut hashed = 0;
+    for item in &items {
+        let decoded = load_image_bytes(app, item)
+            .and_then(|bytes| image::load_from_memory(&bytes).map_err(|e| e.to_string()));
+        let phash = match decoded {
+            Ok(image) => difference_hash(&image),
+            Err(e) => {
+                logger::debug("Image", &format!("Can't hash {}: {}", item.content, e));
+                let conn = state.conn.lock().map_err(|e| e.to_string())?;
+                db::set_image_phash_failed(&conn, item.id).map_err(|e| e.to_string())?;
+                continue;
+            }
+        };
+        let conn = state.conn.lock().map_err(|e| e.to_string())?;
+        db::set_image_phash(&conn, item.id, phash).map_err(|e| e.to_string())?;
+        hashed += 1;
+    }
+
+    if hashed > 0 {
+        logger::info("Image", &format!("Computed perceptual hashes for {} images", hashed));
+    }
+    Ok(hashed)
+}
+
+/// Load an image item's encoded bytes from the cache, the database, or the images directory.
+pub(crate) fn load_image_bytes(app: &tauri::AppHandle, item: &ClipboardItem) -> Result<Vec<u8>, String> {
+    match app.try_state::<crate::DatabaseState>() {
+        Some(state) => {
+            let conn = state.conn.lock().map_err(|e| e.to_string())?;
+            load_stored_image(Some(&conn), item)
+        }
+        None => load_stored_image(None, item),
+    }
+}
+
+/// Load an image item's encoded bytes; inline copies are only looked up when `conn` is given.
+pub(crate) fn load_stored_image(conn: Option<&rusqlite::Connection>, item: &ClipboardItem) -> Result<Vec<u8>, String> {
+    if let Some(image_data) = IMAGE_CACHE.get(&item.hash) {
+        return Ok(image_data);
+    }
+
+    if let Some(conn) = conn {
+        if let Some(image_data) = db::get_inline_image(conn, &item.content).map_err(|e| e.to_string())? {
+            return Ok(image_data);
+        }
+    }
+
+    read_image_file(&item.content)
+}
+
+/// Error returned when an image's file was deleted from the images directory.
+pub(crate) const IMAGE_UNAVAILABLE: &str = "This image is no longer available.";
+
+/// Error returned for an image path that points outside the images directory.
+const IMAGE_ACCESS_DENIED: &str = "Permission denied: image path is outside the images directory";
+
+/// Check that the stored image path `relative_path`, resolved to `full_path`,
+/// stays inside `images`: no `..`, absolute paths, drive prefixes, or symlinks
+/// pointing elsewhere. A file that doesn't exist is only checked lexically.
+fn check_image_path(relative_path: &str, full_path: &Path, images: &Path) -> Result<(), String> {
+    let plain_components = relative_path.split(['/', '\\']).filter(|c| !c.is_empty()).all(|c| {
+        let mut parts = Path::new(c).components();
+        matches!((parts.next(), parts.next()), (Some(Component::Normal(_)), None))
+    });
+    if !plain_components || !full_path.starts_with(images) {
+        return Err(IMAGE_ACCESS_DENIED.to_string());
+    }
+
+    if let (Ok(real_path), Ok(real_images)) = (full_path.canonicalize(), images.canonicalize()) {
+        if !real_path.starts_with(real_images) {
+            return Err(IMAGE_ACCESS_DENIED.to_string());
+        }
+    }
+    Ok(())
+}
+
+/// Read a stored image file, reporting a deleted file as `IMAGE_UNAVAILABLE`.
+fn read_image_file(relative_path: &str) -> Result<Vec<u8>, String> {
+    let full_path = data_path(relative_path);
+    check_image_path(relative_path, &full_path, &images_dir()).inspect_err(|_| {
+        logger::warning("Image", &format!("Refused image path outside the images directory: {:?}", relative_path));
+    })?;
+    match std::fs::read(&full_path) {
+        Ok(image_data) => Ok(image_data),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            logger::warning("Image", &format!("Image file missing: {:?}", full_path));
+            Err(IMAGE_UNAVAILABLE.to_string())
+        }
+        Err(e) => Err(format!("Failed to read image: {}", e)),
+    }
+}
 
 /// Copy image from raw bytes to clipboard.
+///
+/// GIFs are offered both as their first frame and as the original file, so
+/// apps that support it paste the animation.
 pub(crate) fn copy_image_from_bytes(image_bytes: &[u8]) -> Result<(), String> {
+    let paste = prepare_paste(image_bytes, MAX_PASTE_DIMENSION.load(Ordering::Relaxed))?;
+    if let Some(hash) = paste.unrecorded_hash() {
+        super::history::set_unrecorded(Some(hash));
+    }
+
+    let (width, height) = paste.rgba.dimensions();
+    match paste.gif {
+        Some(gif) => clipboard::set_clipboard_gif(gif, width, height, &paste.rgba),
+        None => clipboard::set_clipboard_image(width, height, &paste.rgba).map_err(|e| e.to_string()),
+    }
+}
+
+/// An image ready to be placed on the clipboard.
+struct PasteImage<'a> {
+    rgba: RgbaImage,
+    /// Original GIF to offer alongside the pixels
+    gif: Option<&'a [u8]>,
+    /// Whether `rgba` was made smaller than the stored image
+    downscaled: bool,
+}
+
+impl PasteImage<'_> {
+    /// Hash of the bitmap the monitor must not record, if any.
+    ///
+    /// A smaller copy isn't history content. A GIF may land as its bitmap
+    /// alone (always on Linux, or when the platform refuses the GIF bytes),
+    /// which would otherwise be captured as a new PNG next to the GIF item.
+    fn unrecorded_hash(&self) -> Option<String> {
+        (self.downscaled || self.gif.is_some()).then(|| db::calculate_hash(self.rgba.as_raw()))
+    }
+}
+
+/// Decode a stored image for pasting, downscaled to `max_dim` (0 = no limit).
+fn prepare_paste(image_bytes: &[u8], max_dim: u32) -> Result<PasteImage<'_>, String> {
     let img = ImageReader::new(Cursor::new(image_bytes))
         .with_guessed_format()
         .map_err(|e| e.to_string())?
         .decode()
         .map_err(|e| e.to_string())?;
 
-    let (width, height) = img.dimensions();
-    let rgba = img.to_rgba8();
+    // Only the clipboard copy is downscaled; the stored image keeps its size
+    let Some((new_width, new_height)) = fit_dimension(img.width(), img.height(), max_dim) else {
+        let gif = clipboard::gif::is_gif(image_bytes).then_some(image_bytes);
+        return Ok(PasteImage { rgba: img.to_rgba8(), gif, downscaled: false });
+    };
 
-    clipboard::set_clipboard_image(width, height, &rgba).map_err(|e| e.to_string())
+    logger::info(
+        "Image",
+        &format!("Downscaling {}x{} image to {}x{} for pasting", img.width(), img.height(), new_width, new_height),
+    );
+    let rgba = img.resize_exact(new_width, new_height, FilterType::Triangle).to_rgba8();
+    // The original GIF would bring back the full size
+    Ok(PasteImage { rgba, gif: None, downscaled: true })
 }
 
-/// Get a base64 data URL for an image stored on disk.
+/// Get a base64 data URL for a stored image (inline in the database or on disk).
+///
+/// Images belonging to concealed items are only returned when `reveal` is true.
 #[tauri::command]
-pub async fn get_image_asset_url(relative_path: String) -> Result<String, String> {
-    let full_path = data_dir().join(&relative_path);
-
-    if !full_path.exists() {
-        return Err(format!("Image file not found: {:?}", full_path));
-    }
+pub async fn get_image_asset_url(
+    state: tauri::State<'_, crate::DatabaseState>,
+    relative_path: String,
+    reveal: Option<bool>,
+) -> Result<String, String> {
+    let inline_data = {
+        let conn = state.conn.lock().map_err(|e| e.to_string())?;
+        if !reveal.unwrap_or(false)
+            && db::is_content_concealed(&conn, &relative_path).map_err(|e| e.to_string())?
+        {
+            return Err("Image is concealed".to_string());
+        }
+        db::get_inline_image(&conn, &relative_path).map_err(|e| e.to_string())?
+    };
 
-    let image_data = std::fs::read(&full_path).map_err(|e| e.to_string())?;
+    let image_data = match inline_data {
+        Some(image_data) => image_data,
+        None => read_image_file(&relative_path)?,
+    };
 
     let mime_type = detect_image_mime(&image_data);
     let base64_data = base64::Engine::encode(&base64::engine::general_purpose::STANDARD, &image_data);
@@ -75,3 +558,309 @@ fn detect_image_mime(data: &[u8]) -> &'static str {
         "image/png"
     }
 }
+
+/// File extension for encoded image bytes, by the same detection as `detect_image_mime`.
+pub(crate) fn image_extension(data: &[u8]) -> &'static str {
+    match detect_image_mime(data) {
+        "image/jpeg" => "jpg",
+        "image/gif" => "gif",
+        _ => "png",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::resolve_stored_path;
+
+    #[test]
+    fn test_fit_within() {
+        assert_eq!(fit_within(100, 100, 0), None);
+        assert_eq!(fit_within(100, 100, 10_000), None);
+        assert_eq!(fit_within(200, 100, 5_000), Some((100, 50)));
+        // Extreme aspect ratios never collapse to zero
+        assert_eq!(fit_within(100_000, 1, 100), Some((100, 1)));
+    }
+
+    #[test]
+    fn test_image_extension() {
+        assert_eq!(image_extension(&[0x89, 0x50, 0x4E, 0x47, 0x0D]), "png");
+        assert_eq!(image_extension(&[0xFF, 0xD8, 0xFF, 0xE0]), "jpg");
+        assert_eq!(image_extension(b"GIF89a"), "gif");
+        assert_eq!(image_extension(b"unknown"), "png");
+    }
+
+    #[test]
+    fn test_fit_dimension() {
+        assert_eq!(fit_dimension(4000, 3000, 0), None);
+        assert_eq!(fit_dimension(2000, 1000, 2000), None);
+        assert_eq!(fit_dimension(4000, 3000, 2000), Some((2000, 1500)));
+        assert_eq!(fit_dimension(1000, 3000, 1500), Some((500, 1500)));
+        // Thin images keep at least one pixel
+        assert_eq!(fit_dimension(100_000, 1, 100), Some((100, 1)));
+    }
+
+    #[test]
+    fn test_prepare_paste() {
+        let mut png = Vec::new();
+        DynamicImage::ImageRgba8(RgbaImage::new(400, 200))
+            .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+            .unwrap();
+
+        let paste = prepare_paste(&png, 0).unwrap();
+        assert_eq!(paste.rgba.dimensions(), (400, 200));
+        assert!(!paste.downscaled);
+
+        let paste = prepare_paste(&png, 100).unwrap();
+        assert_eq!(paste.rgba.dimensions(), (100, 50));
+        assert!(paste.downscaled);
+        assert!(paste.gif.is_none());
+    }
+
+    #[test]
+    fn test_pasted_gif_bitmap_is_unrecorded() {
+        let frame = RgbaImage::from_fn(8, 4, |x, y| image::Rgba([x as u8 * 30, y as u8 * 60, 90, 255]));
+        let mut gif = Vec::new();
+        DynamicImage::ImageRgba8(frame).write_to(&mut Cursor::new(&mut gif), ImageFormat::Gif).unwrap();
+
+        let paste = prepare_paste(&gif, 0).unwrap();
+        assert!(paste.gif.is_some());
+        let unrecorded = paste.unrecorded_hash().expect("GIF bitmap must be exempt");
+
+        // What the monitor sees when only the bitmap made it to the clipboard
+        let (width, height) = paste.rgba.dimensions();
+        let bitmap = ImageData { bytes: paste.rgba.as_raw().clone(), width, height, gif: None };
+        assert_eq!(db::calculate_hash(&bitmap.bytes), unrecorded);
+        assert_eq!(canonicalize_clipboard_image(bitmap, 0).unwrap().hash, unrecorded);
+
+        let mut png = Vec::new();
+        DynamicImage::ImageRgba8(RgbaImage::new(8, 4))
+            .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+            .unwrap();
+        assert!(prepare_paste(&png, 0).unwrap().unrecorded_hash().is_none());
+    }
+
+    #[test]
+    fn test_canonical_hash_is_pixel_hash() {
+        let bytes: Vec<u8> = (0..16 * 4).map(|i| i as u8).collect();
+        let image = ImageData { bytes: bytes.clone(), width: 4, height: 4, gif: None };
+
+        let canonical = canonicalize_clipboard_image(image, 0).unwrap();
+        assert_eq!(canonical.hash, db::calculate_hash(&bytes));
+    }
+
+    #[test]
+    fn test_canonical_image_survives_png_round_trip() {
+        // Copying a saved image back from history must hash to the same entry
+        let bytes: Vec<u8> = (0..40 * 20 * 4).map(|i| (i % 251) as u8).collect();
+        let image = ImageData { bytes, width: 40, height: 20, gif: None };
+
+        let canonical = canonicalize_clipboard_image(image, 200).unwrap();
+        let pixels = canonical.pixels.unwrap();
+        assert_eq!(pixels.dimensions(), (20, 10));
+
+        let decoded = image::load_from_memory(&encode_png(&pixels).unwrap()).unwrap().to_rgba8();
+        assert_eq!(db::calculate_hash(decoded.as_raw()), canonical.hash);
+    }
+
+    #[test]
+    fn test_check_image_path() {
+        let root = std::env::temp_dir().join(format!("powerclip-image-path-test-{}", std::process::id()));
+        let images = root.join("images");
+        fs::create_dir_all(&images).unwrap();
+        fs::write(images.join("a.png"), b"png").unwrap();
+        fs::write(root.join("secret.txt"), b"secret").unwrap();
+        let check = |relative: &str| check_image_path(relative, &resolve_stored_path(&root, relative), &images);
+
+        assert_eq!(check("images/a.png"), Ok(()));
+        // Not written yet: allowed, the read reports it as unavailable
+        assert_eq!(check("images/b.png"), Ok(()));
+
+        let denied = Err(IMAGE_ACCESS_DENIED.to_string());
+        assert_eq!(check("images/../secret.txt"), denied);
+        assert_eq!(check("../secret.txt"), denied);
+        assert_eq!(check("images\\..\\..\\secret.txt"), denied);
+        assert_eq!(check("secret.txt"), denied);
+        assert_eq!(check("/etc/passwd"), denied);
+        assert_eq!(check(&root.join("secret.txt").to_string_lossy()), denied);
+        assert_eq!(check("C:/Windows/win.ini"), denied);
+
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(root.join("secret.txt"), images.join("link.png")).unwrap();
+            assert_eq!(check("images/link.png"), denied);
+        }
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_missing_image_file_is_unavailable() {
+        let result = read_image_file("images/powerclip-test-missing-image.png");
+        assert_eq!(result, Err(IMAGE_UNAVAILABLE.to_string()));
+    }
+
+    fn test_image(seed: u8) -> CanonicalImage {
+        let bytes: Vec<u8> = (0..8 * 8 * 4).map(|i| (i as u8).wrapping_mul(seed)).collect();
+        canonicalize_clipboard_image(ImageData { bytes, width: 8, height: 8, gif: None }, 0).unwrap()
+    }
+
+    #[test]
+    fn test_save_clipboard_image_dedups() {
+        let dir = std::env::temp_dir().join(format!("powerclip-image-save-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        db::create_history_table(&conn).unwrap();
+
+        let first = save_clipboard_image(&conn, &dir, test_image(3), |_| false).unwrap();
+        let id = first.inserted().expect("first copy is a new item").id;
+        let path = dir.join(format!("{}.png", test_image(3).hash));
+        let png = fs::read(&path).unwrap();
+        assert_eq!(IMAGE_CACHE.get(&test_image(3).hash), Some(png.clone()));
+
+        // The same pixels again reuse the stored file and row
+        let again = save_clipboard_image(&conn, &dir, test_image(3), |_| false).unwrap();
+        assert!(matches!(again, SaveOutcome::Updated { id: updated, .. } if updated == id));
+        assert_eq!(fs::read(&path).unwrap(), png);
+
+        // An image stored inline is not also written to disk, and dedups too
+        let inline = test_image(5);
+        let hash = inline.hash.clone();
+        let saved = save_clipboard_image(&conn, &dir, inline, |_| true).unwrap();
+        assert!(saved.inserted().is_some());
+        assert!(db::has_inline_image(&conn, &hash).unwrap());
+        assert!(!dir.join(format!("{}.png", hash)).exists());
+        let again = save_clipboard_image(&conn, &dir, test_image(5), |_| true).unwrap();
+        assert!(matches!(again, SaveOutcome::Updated { .. }));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_save_clipboard_gif_keeps_bytes() {
+        let dir = std::env::temp_dir().join(format!("powerclip-gif-save-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        db::create_history_table(&conn).unwrap();
+
+        let frame = RgbaImage::from_pixel(2, 2, image::Rgba([255, 0, 0, 255]));
+        let mut gif = Vec::new();
+        DynamicImage::ImageRgba8(frame).write_to(&mut Cursor::new(&mut gif), ImageFormat::Gif).unwrap();
+
+        let (hash, saved) = save_clipboard_gif(&conn, &dir, gif.clone(), |_| false).unwrap();
+        let item = saved.inserted().expect("a new item");
+        assert_eq!(item.content, format!("images/{}.gif", hash));
+        assert_eq!(fs::read(dir.join(format!("{}.gif", hash))).unwrap(), gif);
+        assert_eq!(image_extension(&gif), "gif");
+
+        let (_, again) = save_clipboard_gif(&conn, &dir, gif, |_| false).unwrap();
+        assert!(matches!(again, SaveOutcome::Updated { id, .. } if id == item.id));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_remove_partial_images() {
+        let dir = std::env::temp_dir().join(format!("powerclip-partial-image-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        // The saved image is written through a temp file that is renamed away
+        let saved = save_clipboard_image(&rusqlite::Connection::open_in_memory().map(|conn| {
+            db::create_history_table(&conn).unwrap();
+            conn
+        }).unwrap(), &dir, test_image(7), |_| false).unwrap();
+        assert!(saved.inserted().is_some());
+        let png = dir.join(format!("{}.png", test_image(7).hash));
+        assert!(png.exists());
+        assert!(!config::temp_path_for(&png).exists());
+
+        // A write cut short by a crash leaves only the temp file behind
+        fs::write(dir.join("crashed.png.tmp"), b"partial").unwrap();
+        assert_eq!(remove_partial_images(&dir), 1);
+        assert!(!dir.join("crashed.png.tmp").exists());
+        assert!(png.exists());
+        assert_eq!(remove_partial_images(&dir.join("missing")), 0);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_prewarm_cache_respects_byte_budget() {
+        let item = |hash: &str| ClipboardItem {
+            id: 0,
+            item_type: "image".to_string(),
+            content: format!("images/{}.png", hash),
+            hash: hash.to_string(),
+            created_at: String::new(),
+            is_favorited: false,
+            is_concealed: false,
+            code_language: None,
+            use_count: 0,
+            last_used_at: None,
+            created_at_relative: None,
+        };
+        let cache = ImageCache::new(1024);
+        cache.insert("cached".to_string(), vec![0; 4]);
+        let items = [item("cached"), item("missing"), item("a"), item("b"), item("c")];
+
+        // 4 bytes already cached, so only two more 4-byte images fit in 12
+        let added = prewarm_cache(&cache, &items, 12, |item| match item.hash.as_str() {
+            "missing" => Err(IMAGE_UNAVAILABLE.to_string()),
+            _ => Ok(vec![1; 4]),
+        });
+        assert_eq!(added, 2);
+        assert!(cache.contains("a") && cache.contains("b"));
+        assert!(!cache.contains("c") && !cache.contains("missing"));
+        assert_eq!(cache.total_bytes(), 12);
+    }
+
+    #[test]
+    fn test_image_cache_evicts_least_recently_used() {
+        let cache = ImageCache::new(12);
+        cache.insert("a".to_string(), vec![0; 4]);
+        cache.insert("b".to_string(), vec![0; 4]);
+        cache.insert("c".to_string(), vec![0; 4]);
+        assert_eq!(cache.total_bytes(), 12);
+
+        // Reading "a" makes "b" the least recently used
+        assert!(cache.get("a").is_some());
+        cache.insert("d".to_string(), vec![0; 4]);
+        assert!(!cache.contains("b"));
+        assert!(cache.contains("a") && cache.contains("c") && cache.contains("d"));
+        assert_eq!(cache.total_bytes(), 12);
+
+        // Replacing an entry counts only its new size, evicting as needed
+        cache.insert("c".to_string(), vec![0; 8]);
+        assert!(!cache.contains("a"));
+        assert!(cache.contains("c") && cache.contains("d"));
+        assert_eq!(cache.total_bytes(), 12);
+
+        // Too big for the whole budget: not cached, nothing evicted
+        cache.insert("huge".to_string(), vec![0; 13]);
+        assert!(!cache.contains("huge") && cache.contains("c") && cache.contains("d"));
+        assert_eq!(cache.total_bytes(), 12);
+    }
+
+    #[test]
+    fn test_difference_hash() {
+        let gradient = RgbaImage::from_fn(90, 80, |x, y| {
+            let v = ((x * 7 + y * 3) % 256) as u8;
+            image::Rgba([v, v, v, 255])
+        });
+        let hash = difference_hash(&DynamicImage::ImageRgba8(gradient.clone()));
+
+        // A resized copy looks the same
+        let resized = image::imageops::resize(&gradient, 45, 40, FilterType::Triangle);
+        assert!((hash ^ difference_hash(&DynamicImage::ImageRgba8(resized))).count_ones() <= 4);
+
+        // Mirroring flips the brightness order of neighbours
+        let mirrored = image::imageops::flip_horizontal(&gradient);
+        assert!((hash ^ difference_hash(&DynamicImage::ImageRgba8(mirrored))).count_ones() > 32);
+    }
+
+    #[test]
+    fn test_canonicalize_rejects_bad_buffer() {
+        let image = ImageData { bytes: vec![0; 3], width: 10, height: 10, gif: None };
+        assert!(canonicalize_clipboard_image(image, 0).is_err());
+    }
+}
@@ -2,10 +2,13 @@
 //!
 //! Each sub-module groups related commands by domain.
 
+pub mod database;
+pub mod diagnostics;
 pub mod extensions;
 pub mod history;
 pub mod image;
 pub mod paste;
 pub mod settings;
 pub mod snippets;
+pub mod stack;
 
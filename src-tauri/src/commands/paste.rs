@@ -1,8 +1,114 @@
 //! Paste simulation - Platform-specific keyboard event generation
 
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Error returned by `simulate_paste` when macOS blocks synthetic key events.
+/// The frontend matches on this prefix to guide the user to System Settings.
+pub const ACCESSIBILITY_NOT_GRANTED_ERROR: &str =
+    "Accessibility permission not granted: enable PowerClip in System Settings > Privacy & Security > Accessibility";
+
+/// Set once the Accessibility check has passed, so later pastes skip it.
+static ACCESSIBILITY_TRUSTED: AtomicBool = AtomicBool::new(false);
+
+/// Whether this process may post keyboard events to other apps.
+///
+/// With `prompt`, macOS shows its "grant access" dialog if not yet trusted.
+#[cfg(target_os = "macos")]
+fn is_accessibility_trusted(prompt: bool) -> bool {
+    use std::ffi::c_void;
+
+    #[link(name = "ApplicationServices", kind = "framework")]
+    extern "C" {
+        fn AXIsProcessTrustedWithOptions(options: *const c_void) -> bool;
+        static kAXTrustedCheckOptionPrompt: *const c_void;
+    }
+
+    #[link(name = "CoreFoundation", kind = "framework")]
+    extern "C" {
+        fn CFDictionaryCreate(
+            allocator: *const c_void,
+            keys: *const *const c_void,
+            values: *const *const c_void,
+            num_values: isize,
+            key_callbacks: *const c_void,
+            value_callbacks: *const c_void,
+        ) -> *const c_void;
+        fn CFRelease(cf: *const c_void);
+        static kCFBooleanTrue: *const c_void;
+        static kCFTypeDictionaryKeyCallBacks: c_void;
+        static kCFTypeDictionaryValueCallBacks: c_void;
+    }
+
+    unsafe {
+        if !prompt {
+            return AXIsProcessTrustedWithOptions(std::ptr::null());
+        }
+
+        let keys = [kAXTrustedCheckOptionPrompt];
+        let values = [kCFBooleanTrue];
+        let options = CFDictionaryCreate(
+            std::ptr::null(),
+            keys.as_ptr(),
+            values.as_ptr(),
+            1,
+            &kCFTypeDictionaryKeyCallBacks,
+            &kCFTypeDictionaryValueCallBacks,
+        );
+        let trusted = AXIsProcessTrustedWithOptions(options);
+        if !options.is_null() {
+            CFRelease(options);
+        }
+        trusted
+    }
+}
+
+/// Only macOS gates synthetic key events behind a permission.
+#[cfg(not(target_os = "macos"))]
+fn is_accessibility_trusted(_prompt: bool) -> bool {
+    true
+}
+
+/// Check whether PowerClip has the Accessibility permission needed for auto-paste.
+///
+/// Pass `prompt: true` to let macOS show its permission dialog. Always true on other platforms.
+#[tauri::command]
+pub async fn check_accessibility_permission(prompt: Option<bool>) -> Result<bool, String> {
+    let trusted = is_accessibility_trusted(prompt.unwrap_or(false));
+    if trusted {
+        ACCESSIBILITY_TRUSTED.store(true, Ordering::Relaxed);
+    }
+    Ok(trusted)
+}
+
+/// Open the Accessibility pane of System Settings (macOS only).
+#[tauri::command]
+pub async fn open_accessibility_settings() -> Result<(), String> {
+    #[cfg(target_os = "macos")]
+    {
+        open::that("x-apple.systempreferences:com.apple.preference.security?Privacy_Accessibility")
+            .map_err(|e| format!("Failed to open Accessibility settings: {}", e))
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    {
+        Err("Accessibility settings are only available on macOS".to_string())
+    }
+}
+
 /// Simulate paste action (Cmd+V on macOS, Ctrl+V on Windows).
+///
+/// Fails with `ACCESSIBILITY_NOT_GRANTED_ERROR` instead of silently doing
+/// nothing when macOS hasn't granted the Accessibility permission.
 #[tauri::command]
 pub async fn simulate_paste() -> Result<(), String> {
+    if !ACCESSIBILITY_TRUSTED.load(Ordering::Relaxed) {
+        if !is_accessibility_trusted(false) {
+            crate::logger::warning("Paste", "Accessibility permission not granted, cannot paste");
+            return Err(ACCESSIBILITY_NOT_GRANTED_ERROR.to_string());
+        }
+        ACCESSIBILITY_TRUSTED.store(true, Ordering::Relaxed);
+    }
+
     #[cfg(target_os = "macos")]
     {
         use std::ffi::c_void;
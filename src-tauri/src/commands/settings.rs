@@ -1,15 +1,19 @@
 //! Settings commands - Application preferences management
 
-use tauri::Manager;
+use tauri::{Emitter, Manager};
 
 use crate::app_settings::{self, AppSettings};
 use crate::config::settings_path;
 use crate::logger;
 
 /// Get current application settings.
+///
+/// `launch_at_login` reflects the OS login item, which may have been changed outside the app.
 #[tauri::command]
 pub async fn get_settings() -> Result<AppSettings, String> {
-    app_settings::load_settings_simple()
+    let mut settings = app_settings::load_settings_simple()?;
+    settings.launch_at_login = crate::autostart::is_enabled();
+    Ok(settings)
 }
 
 /// Save application settings and re-register hotkey.
@@ -32,12 +36,63 @@ pub async fn save_settings(
             &settings.hotkey_modifiers,
             &settings.hotkey_key,
         )?;
+
+        if let Err(e) = crate::window::apply_opacity(&window, settings.window_opacity) {
+            logger::warning("Settings", &format!("Failed to apply window opacity: {}", e));
+        }
     }
+    drop(manager);
+
+    // Don't wait for the file watcher: a lowered max_items should trim history now
+    crate::commands::history::apply_history_limits(&app, &settings);
 
     logger::info("Settings", "Settings saved and hotkey updated");
     Ok(())
 }
 
+/// Reset settings to defaults, backing up the old file to `settings.json.bak`.
+///
+/// Re-registers hotkeys and notifies the frontend with the fresh settings.
+#[tauri::command]
+pub async fn reset_settings(app: tauri::AppHandle) -> Result<AppSettings, String> {
+    let settings = app_settings::reset_settings()?;
+
+    // The file is already reset, so a hotkey that can't be registered (e.g. taken
+    // by another app) is logged rather than failing the whole reset
+    let state = app.state::<crate::HotkeyState>();
+    match state.manager.lock() {
+        Ok(manager) => {
+            let window = app.get_webview_window("main");
+            crate::hotkey::register_all_hotkeys(&manager, &state, window.as_ref(), &settings);
+        }
+        Err(e) => logger::error("Settings", &format!("Failed to lock hotkey manager: {}", e)),
+    }
+    crate::commands::stack::set_stack_mode(&app, settings.stack_mode_enabled);
+
+    let _ = app.emit("powerclip:settings-changed", ());
+    logger::info("Settings", "Settings reset and hotkeys re-registered");
+    Ok(settings)
+}
+
+/// Register or unregister PowerClip as a login item and persist the choice.
+#[tauri::command]
+pub async fn set_autostart(enabled: bool) -> Result<(), String> {
+    crate::autostart::set_enabled(enabled)?;
+
+    let mut settings = app_settings::load_settings_simple()?;
+    if settings.launch_at_login != enabled {
+        settings.launch_at_login = enabled;
+        app_settings::save_settings(&settings)?;
+    }
+    Ok(())
+}
+
+/// Mark the welcome tour as done so `powerclip:first-run` isn't emitted again.
+#[tauri::command]
+pub async fn complete_onboarding() -> Result<(), String> {
+    crate::onboarding::mark_completed()
+}
+
 /// Set whether settings dialog is open (prevents hide-on-blur).
 #[tauri::command]
 pub async fn set_settings_dialog_open(
@@ -49,7 +104,7 @@ pub async fn set_settings_dialog_open(
     Ok(())
 }
 
-/// Get the settings file path (so the frontend can display it).
+/// Get the settings file path for display (lossy if the path isn't valid UTF-8).
 #[tauri::command]
 pub async fn get_settings_path() -> Result<String, String> {
     Ok(settings_path().to_string_lossy().to_string())
@@ -67,3 +122,17 @@ pub async fn open_settings_file() -> Result<(), String> {
 
     open::that(&path).map_err(|e| format!("Failed to open settings file: {}", e))
 }
+
+/// Open the data directory (database, settings, logs) in the system file manager.
+#[tauri::command]
+pub async fn open_data_dir() -> Result<(), String> {
+    crate::config::ensure_dirs();
+    open::that(crate::config::data_dir()).map_err(|e| format!("Failed to open data directory: {}", e))
+}
+
+/// Open the directory holding saved clipboard images in the system file manager.
+#[tauri::command]
+pub async fn open_images_dir() -> Result<(), String> {
+    crate::config::ensure_dirs();
+    open::that(crate::config::images_dir()).map_err(|e| format!("Failed to open images directory: {}", e))
+}
@@ -1,6 +1,6 @@
 //! Snippets commands - Tauri command handlers for quick commands
 
-use tauri::State;
+use tauri::{Manager, State};
 
 use crate::db::{DatabaseState, snippets};
 
@@ -40,3 +40,21 @@ pub fn delete_snippet(db: State<DatabaseState>, id: i64) -> Result<bool, String>
     let conn = db.conn.lock().map_err(|e| e.to_string())?;
     snippets::delete_snippet(&conn, id).map_err(|e| e.to_string())
 }
+
+/// Copy a snippet picked in the main window, then follow the `after_select` policy.
+///
+/// Unlike `select_item`, this doesn't touch history usage: snippet ids are not
+/// history ids.
+#[tauri::command]
+pub async fn select_snippet(app: tauri::AppHandle, id: i64) -> Result<(), String> {
+    let snippet = {
+        let state = app.state::<DatabaseState>();
+        let conn = state.conn.lock().map_err(|e| e.to_string())?;
+        snippets::get_snippet(&conn, id).map_err(|e| e.to_string())?
+    }
+    .ok_or_else(|| format!("Snippet {} not found", id))?;
+
+    super::history::set_clipboard(snippet.content, None).await?;
+    super::history::finish_selection(&app, false).await?;
+    Ok(())
+}
@@ -0,0 +1,90 @@
+//! Stack commands - Paste stack (clipboard queue) management
+
+use tauri::Manager;
+
+use crate::config::HOTKEY_PASTE_DELAY_MS;
+use crate::db::{self, ClipboardItem};
+use crate::logger;
+
+/// Turn stack mode on or off (called when settings change).
+pub(crate) fn set_stack_mode(app: &tauri::AppHandle, enabled: bool) {
+    let state = app.state::<crate::AppState>();
+    let Ok(mut stack) = state.paste_stack.lock() else {
+        return;
+    };
+    if stack.is_enabled() != enabled {
+        stack.set_enabled(enabled);
+        logger::info("Stack", if enabled { "Stack mode enabled" } else { "Stack mode disabled" });
+    }
+}
+
+/// Queue a real clipboard copy while stack mode is on.
+///
+/// Called by `check_clipboard` on every poll with the saved item's id.
+pub(crate) fn record_copy(app: &tauri::AppHandle, hash: &str, item_id: i64) {
+    let state = app.state::<crate::AppState>();
+    let Ok(mut stack) = state.paste_stack.lock() else {
+        return;
+    };
+    if stack.observe(hash) {
+        stack.push(item_id);
+        logger::debug("Stack", &format!("Queued item {} ({} in stack)", item_id, stack.len()));
+    }
+}
+
+/// Push a history item onto the paste stack.
+#[tauri::command]
+pub async fn stack_push(
+    state: tauri::State<'_, crate::AppState>,
+    item_id: i64,
+) -> Result<(), String> {
+    let mut stack = state.paste_stack.lock().map_err(|e| e.to_string())?;
+    stack.push(item_id);
+    Ok(())
+}
+
+/// Pop the oldest item from the paste stack, copy it, and paste it.
+///
+/// Items deleted from history since they were queued are skipped.
+/// Returns the pasted item, or `None` when the stack is empty.
+#[tauri::command]
+pub async fn stack_pop_and_paste(app: tauri::AppHandle) -> Result<Option<ClipboardItem>, String> {
+    let item = {
+        let app_state = app.state::<crate::AppState>();
+        let db_state = app.state::<crate::DatabaseState>();
+        // Same lock order as `check_clipboard`: database first, then the stack
+        let conn = db_state.conn.lock().map_err(|e| e.to_string())?;
+        let mut stack = app_state.paste_stack.lock().map_err(|e| e.to_string())?;
+
+        let mut next = None;
+        while let Some(id) = stack.pop() {
+            if let Some(item) = db::get_item_by_id(&conn, id).map_err(|e| e.to_string())? {
+                next = Some(item);
+                break;
+            }
+            logger::debug("Stack", &format!("Skipping deleted item {}", id));
+        }
+        let Some(item) = next else {
+            logger::info("Stack", "Paste stack is empty");
+            return Ok(None);
+        };
+
+        // Don't let the monitor queue the item we're about to write
+        stack.mark_written(&item.hash);
+        item
+    };
+
+    super::history::copy_item(app.clone(), item.clone()).await?;
+    // The stack hotkey's modifiers may still be held
+    tokio::time::sleep(std::time::Duration::from_millis(HOTKEY_PASTE_DELAY_MS)).await;
+    super::paste::simulate_paste().await?;
+    Ok(Some(item))
+}
+
+/// Remove all items from the paste stack.
+#[tauri::command]
+pub async fn stack_clear(state: tauri::State<'_, crate::AppState>) -> Result<(), String> {
+    state.paste_stack.lock().map_err(|e| e.to_string())?.clear();
+    logger::info("Stack", "Paste stack cleared");
+    Ok(())
+}
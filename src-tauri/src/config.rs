@@ -3,7 +3,9 @@
 //! This module provides unified access to all application paths and constants.
 //! All paths are derived from the platform-appropriate data directory.
 
-use std::path::PathBuf;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::OnceLock;
 
 /// Application name
@@ -12,30 +14,229 @@ pub const APP_NAME: &str = "PowerClip";
 /// Clipboard polling interval in milliseconds
 pub const CLIPBOARD_POLL_INTERVAL_MS: u64 = 100;
 
+/// Retries for a clipboard read that failed with a transient error (e.g. another app holds it open)
+pub const CLIPBOARD_READ_RETRIES: u32 = 3;
+
+/// Delay before the first clipboard read retry; doubles on each further retry (milliseconds)
+pub const CLIPBOARD_RETRY_BACKOFF_MS: u64 = 10;
+
+/// Consecutive failed clipboard reads before a warning is logged
+pub const CLIPBOARD_FAILURE_WARN_THRESHOLD: u32 = 20;
+
+/// Share of U+FFFD replacement characters above which clipboard text is treated as
+/// mis-decoded and not recorded
+pub const GARBLED_TEXT_REPLACEMENT_RATIO: f64 = 0.5;
+/// Share of control characters (other than tab, newline, carriage return and form feed)
+/// above which clipboard text is treated as binary data and not recorded
+pub const BINARY_TEXT_CONTROL_RATIO: f64 = 0.1;
+
+/// Quiet period after the last settings file event before reloading (milliseconds)
+pub const SETTINGS_RELOAD_DEBOUNCE_MS: u64 = 300;
+
+/// How often the monitor checks whether another program changed the database (milliseconds)
+pub const EXTERNAL_CHANGE_CHECK_INTERVAL_MS: u64 = 1000;
+/// Clipboard polling interval while the user is idle (milliseconds)
+pub const IDLE_POLL_INTERVAL_MS: u64 = 1000;
+/// How often the monitor reads the user's idle time (milliseconds)
+pub const IDLE_CHECK_INTERVAL_MS: u64 = 2000;
+/// Times the clipboard monitor is restarted right away after it stalls; further
+/// restarts wait `MONITOR_RETRY_INTERVAL_MS` apart
+pub const MONITOR_MAX_RESTARTS: u32 = 1;
+/// Minimum time between restarts of a stalled clipboard monitor once
+/// `MONITOR_MAX_RESTARTS` is used up (milliseconds)
+pub const MONITOR_RETRY_INTERVAL_MS: u64 = 5 * 60 * 1000;
+/// Time the clipboard monitor must run without stalling before its
+/// `MONITOR_MAX_RESTARTS` are available again (milliseconds)
+pub const MONITOR_RESTART_RESET_MS: u64 = 10 * 60 * 1000;
+/// Time without a poll, or without a pending clipboard check finishing, after
+/// which the clipboard monitor counts as stalled (milliseconds)
+pub const MONITOR_STALL_TIMEOUT_MS: u64 = 30_000;
+
+/// Wait before pasting from a global hotkey, so its modifiers are released
+/// and don't combine with the simulated paste (milliseconds)
+pub const HOTKEY_PASTE_DELAY_MS: u64 = 150;
+/// Number of newest history items reachable with `copy_by_position` (keys 1-9)
+pub const QUICK_SELECT_POSITIONS: usize = 9;
+
+/// How often the automatic backup task checks whether a backup is due (seconds)
+pub const AUTO_BACKUP_CHECK_INTERVAL_SECS: u64 = 3600;
+/// Minimum time between automatic backups (hours)
+pub const AUTO_BACKUP_INTERVAL_HOURS: i64 = 24;
+
+/// Longest time quitting waits for an in-flight clipboard save to finish (milliseconds)
+pub const SHUTDOWN_DRAIN_TIMEOUT_MS: u64 = 2000;
+/// Longest time a second launch waits for the running instance to answer (milliseconds)
+pub const INSTANCE_SIGNAL_TIMEOUT_MS: u64 = 1000;
+/// Longest a fire-and-forget extension (timeout 0) may run before it is killed (seconds)
+pub const EXTENSION_DETACHED_MAX_SECS: u64 = 60;
+/// Newest image items read into the image cache at startup when `prewarm_image_cache` is on
+pub const IMAGE_CACHE_PREWARM_ITEMS: i64 = 20;
+/// Prewarming stops before the image cache holds more than this many bytes
+pub const IMAGE_CACHE_PREWARM_MAX_BYTES: usize = 64 * 1024 * 1024;
+/// Most bytes the image cache holds; the least recently used images are evicted beyond it
+pub const IMAGE_CACHE_MAX_BYTES: usize = 256 * 1024 * 1024;
+
+/// Half-life of the recency part of the "frecency" history sort (hours).
+/// An item's score is `(use_count + 1) * 0.5^(age / half-life)`, where age is the
+/// time since it was last copied: after 72 hours an item needs twice the uses to
+/// rank level with a fresh one.
+pub const FRECENCY_HALF_LIFE_HOURS: f64 = 72.0;
+
+/// Window size in logical pixels before any size is saved (matches tauri.conf.json)
+pub const DEFAULT_WINDOW_WIDTH: u32 = 450;
+pub const DEFAULT_WINDOW_HEIGHT: u32 = 400;
+/// Gap between an anchored window and the edges of the screen work area (logical pixels)
+pub const WINDOW_ANCHOR_MARGIN: f64 = 8.0;
+
+/// Id of the system tray icon
+pub const TRAY_ID: &str = "main";
+/// Number of recent items listed in the tray menu
+pub const TRAY_RECENT_ITEMS: i64 = 5;
+/// Maximum characters of an item preview in the tray menu
+pub const TRAY_LABEL_MAX_CHARS: usize = 40;
+
+/// Maximum results printed by `powerclip --search`
+pub const CLI_SEARCH_LIMIT: i64 = 50;
+
 /// Maximum embeddings to keep in memory (LRU eviction)
 pub const MAX_EMBEDDINGS_IN_MEMORY: usize = 50_000;
+/// Embeddings per index segment; a write during a search copies one segment
+pub const INDEX_SEGMENT_LEN: usize = 1024;
+/// Newest embeddings compared pairwise by `find_duplicates` (bounds the O(n²) scan)
+pub const DUPLICATE_SCAN_MAX_ITEMS: usize = 2000;
+/// Default number of differing perceptual hash bits (out of 64) for images to count as similar
+pub const SIMILAR_IMAGE_MAX_DISTANCE: u32 = 10;
 /// Minimum similarity score to include in results (0.0 - 1.0)
 pub const MIN_SIMILARITY_SCORE: f32 = 0.2;
 /// Batch size for bulk database operations
 pub const EMBEDDING_BATCH_SIZE: usize = 100;
+/// Number of texts sent per embeddings API request during bulk indexing
+pub const EMBEDDING_REQUEST_BATCH_SIZE: usize = 32;
+/// Maximum characters of a single text sent for embedding (keeps inputs under model token limits)
+pub const MAX_EMBEDDING_INPUT_CHARS: usize = 8_000;
+/// Minimum interval between indexing progress events (milliseconds)
+pub const INDEXING_PROGRESS_EMIT_INTERVAL_MS: u64 = 250;
+/// Maximum characters in a semantic search match snippet
+pub const SNIPPET_MAX_CHARS: usize = 160;
+/// Maximum sentences per item considered when choosing a snippet
+pub const MAX_SNIPPET_SENTENCES: usize = 20;
+/// How often to check whether the embedding index has been idle long enough to unload
+pub const SEMANTIC_IDLE_CHECK_INTERVAL_SECS: u64 = 60;
+/// How long a semantic search waits for a newer query before calling the embeddings API (milliseconds)
+pub const SEMANTIC_SEARCH_SETTLE_MS: u64 = 150;
+
+/// A known embedding model and the vector dimension its API returns
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EmbeddingModelInfo {
+    pub name: &'static str,
+    pub dim: usize,
+}
+
+/// Embedding models whose dimension is known, so `embedding_api_dim` needn't be set by hand
+pub const KNOWN_EMBEDDING_MODELS: &[EmbeddingModelInfo] = &[
+    EmbeddingModelInfo { name: "text-embedding-3-small", dim: 1536 },
+    EmbeddingModelInfo { name: "text-embedding-3-large", dim: 3072 },
+    EmbeddingModelInfo { name: "text-embedding-ada-002", dim: 1536 },
+    EmbeddingModelInfo { name: "nomic-embed-text", dim: 768 },
+    EmbeddingModelInfo { name: "mxbai-embed-large", dim: 1024 },
+    EmbeddingModelInfo { name: "bge-m3", dim: 1024 },
+];
+
+/// Look up a model in `KNOWN_EMBEDDING_MODELS` by name
+pub fn known_embedding_model(name: &str) -> Option<&'static EmbeddingModelInfo> {
+    KNOWN_EMBEDDING_MODELS.iter().find(|m| m.name == name)
+}
 
 /// Cache the data directory path
 static DATA_DIR: OnceLock<PathBuf> = OnceLock::new();
+/// Relocated data directory that was missing at startup (see `missing_data_dir`)
+static MISSING_DATA_DIR: OnceLock<PathBuf> = OnceLock::new();
+/// Set once the data was copied to a new folder; nothing may be written to the old one until restart
+static DATA_DIR_MOVED: AtomicBool = AtomicBool::new(false);
+
+/// File in the default data directory holding the path of a relocated data directory
+pub const DATA_DIR_OVERRIDE_FILE: &str = "data_dir_override";
 
 /// Get the application data directory
-/// Returns the platform-appropriate data directory:
-/// - Linux: ~/.local/share/PowerClip
-/// - macOS: ~/Library/Application Support/PowerClip
-/// - Windows: %APPDATA%/PowerClip
+/// Returns the directory the data was moved to with `migrate_data_dir`, or the
+/// platform-appropriate default (see `default_data_dir`).
+///
+/// Resolved once per run, so a relocation takes effect after a restart.
 #[inline]
 pub fn data_dir() -> &'static PathBuf {
     DATA_DIR.get_or_init(|| {
-        dirs::data_dir()
-            .unwrap_or(PathBuf::from("."))
-            .join(APP_NAME)
+        let default = default_data_dir();
+        match read_data_dir_override(&default) {
+            Some(dir) if dir.is_dir() => dir,
+            Some(dir) => {
+                // A relocated directory on a disconnected drive falls back to the default.
+                // Reported at startup, since logging itself needs the data directory.
+                let _ = MISSING_DATA_DIR.set(dir);
+                default
+            }
+            None => default,
+        }
     })
 }
 
+/// The relocated data directory `data_dir` fell back from because it didn't exist, if any.
+pub fn missing_data_dir() -> Option<&'static Path> {
+    MISSING_DATA_DIR.get().map(PathBuf::as_path)
+}
+
+/// Record that the data was copied to a new folder, used from the next start.
+pub fn set_data_dir_moved() {
+    DATA_DIR_MOVED.store(true, Ordering::SeqCst);
+}
+
+/// Whether the data was copied to a new folder during this run.
+pub fn data_dir_moved() -> bool {
+    DATA_DIR_MOVED.load(Ordering::SeqCst)
+}
+
+/// Err once the data has moved: anything written to `data_dir` would be lost at the next start.
+pub fn check_data_dir_current() -> Result<(), String> {
+    if data_dir_moved() {
+        return Err("The data folder has moved; restart PowerClip to use it".to_string());
+    }
+    Ok(())
+}
+
+/// Get the platform-appropriate data directory:
+/// - Linux: ~/.local/share/PowerClip
+/// - macOS: ~/Library/Application Support/PowerClip
+/// - Windows: %APPDATA%/PowerClip
+pub fn default_data_dir() -> PathBuf {
+    dirs::data_dir()
+        .unwrap_or(PathBuf::from("."))
+        .join(APP_NAME)
+}
+
+/// The relocated data directory recorded in `default`, if any.
+pub fn read_data_dir_override(default: &Path) -> Option<PathBuf> {
+    let contents = std::fs::read_to_string(default.join(DATA_DIR_OVERRIDE_FILE)).ok()?;
+    let path = contents.trim();
+    (!path.is_empty()).then(|| PathBuf::from(path))
+}
+
+/// Record `dir` as the data directory from the next start.
+///
+/// Moving back to `default` removes the override.
+pub fn write_data_dir_override(default: &Path, dir: &Path) -> std::io::Result<()> {
+    let override_path = default.join(DATA_DIR_OVERRIDE_FILE);
+    if dir == default {
+        return match std::fs::remove_file(&override_path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        };
+    }
+    let path = dir.to_str().ok_or_else(|| {
+        std::io::Error::new(std::io::ErrorKind::InvalidInput, "the path is not valid UTF-8")
+    })?;
+    std::fs::create_dir_all(default)?;
+    write_atomic(&override_path, path.as_bytes())
+}
+
 /// Get the database file path
 #[inline]
 pub fn db_path() -> PathBuf {
@@ -60,12 +261,62 @@ pub fn settings_path() -> PathBuf {
     data_dir().join("settings.json")
 }
 
+/// Get the settings backup file path (written before a reset)
+#[inline]
+pub fn settings_backup_path() -> PathBuf {
+    data_dir().join("settings.json.bak")
+}
+
+/// Get the marker file written once onboarding is completed
+#[inline]
+pub fn onboarding_marker_path() -> PathBuf {
+    data_dir().join(".onboarding_complete")
+}
+
+/// Get the lock file that marks a running instance
+#[inline]
+pub fn instance_lock_path() -> PathBuf {
+    data_dir().join("powerclip.lock")
+}
+
+/// Get the file listing hashes of extension commands the user approved
+#[inline]
+pub fn approved_extensions_path() -> PathBuf {
+    data_dir().join("approved_extensions.json")
+}
+
 /// Get the images directory path
 #[inline]
 pub fn images_dir() -> PathBuf {
     data_dir().join("images")
 }
 
+/// Get the database backups directory path
+#[inline]
+pub fn backups_dir() -> PathBuf {
+    data_dir().join("backups")
+}
+
+/// Resolve a path stored relative to `data_dir()`, such as an image item's
+/// "images/<hash>.png".
+///
+/// Stored paths are strings separated by `/` (`\` is accepted too). They
+/// are joined one component at a time so the platform's separator is used
+/// and the data directory keeps its exact `OsString`, even when it isn't
+/// valid UTF-8.
+#[inline]
+pub fn data_path(relative: &str) -> PathBuf {
+    resolve_stored_path(data_dir(), relative)
+}
+
+/// Join the components of a stored relative path onto `root`.
+pub fn resolve_stored_path(root: &Path, relative: &str) -> PathBuf {
+    relative
+        .split(['/', '\\'])
+        .filter(|component| !component.is_empty())
+        .fold(root.to_path_buf(), |path, component| path.join(component))
+}
+
 /// Ensure all required directories exist
 #[inline]
 pub fn ensure_dirs() {
@@ -74,6 +325,33 @@ pub fn ensure_dirs() {
     let _ = std::fs::create_dir_all(images_dir());
 }
 
+/// Suffix for the temporary sibling file used by `write_atomic`
+pub const TEMP_FILE_SUFFIX: &str = ".tmp";
+
+/// Get the temporary path used while atomically writing `path`
+#[inline]
+pub fn temp_path_for(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(TEMP_FILE_SUFFIX);
+    path.with_file_name(name)
+}
+
+/// Write a file atomically.
+///
+/// Contents go to a temp file in the same directory which is then renamed over
+/// the target, so a crash mid-write never leaves a truncated file behind.
+pub fn write_atomic(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    let tmp = temp_path_for(path);
+    {
+        let mut file = std::fs::File::create(&tmp)?;
+        file.write_all(contents)?;
+        file.sync_all()?;
+    }
+    std::fs::rename(&tmp, path).inspect_err(|_| {
+        let _ = std::fs::remove_file(&tmp);
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -83,12 +361,63 @@ mod tests {
         assert_eq!(APP_NAME, "PowerClip");
     }
 
+    #[test]
+    fn test_known_embedding_model() {
+        assert_eq!(known_embedding_model("text-embedding-3-large").map(|m| m.dim), Some(3072));
+        assert!(known_embedding_model("my-custom-model").is_none());
+    }
+
     #[test]
     fn test_constants() {
         assert_eq!(CLIPBOARD_POLL_INTERVAL_MS, 100);
+        assert!(SETTINGS_RELOAD_DEBOUNCE_MS > 0);
+        assert!(TRAY_RECENT_ITEMS > 0);
+        assert!(QUICK_SELECT_POSITIONS > 0);
+        assert!(TRAY_LABEL_MAX_CHARS > 0);
+        assert!(CLI_SEARCH_LIMIT > 0);
+        assert!(INSTANCE_SIGNAL_TIMEOUT_MS > 0);
+        assert!(EXTENSION_DETACHED_MAX_SECS > 0);
+        assert!(IMAGE_CACHE_PREWARM_ITEMS > 0);
+        assert!(IMAGE_CACHE_PREWARM_MAX_BYTES > 0);
+        assert!(IMAGE_CACHE_MAX_BYTES >= IMAGE_CACHE_PREWARM_MAX_BYTES);
+        assert!(EXTERNAL_CHANGE_CHECK_INTERVAL_MS > 0);
+        assert!(MONITOR_MAX_RESTARTS > 0);
+        assert!(MONITOR_STALL_TIMEOUT_MS > IDLE_POLL_INTERVAL_MS);
+        assert!(MONITOR_RETRY_INTERVAL_MS > MONITOR_STALL_TIMEOUT_MS);
+        assert!(MONITOR_RESTART_RESET_MS > MONITOR_STALL_TIMEOUT_MS);
+        assert!(IDLE_POLL_INTERVAL_MS > CLIPBOARD_POLL_INTERVAL_MS);
+        assert!(IDLE_CHECK_INTERVAL_MS > 0);
         assert!(MAX_EMBEDDINGS_IN_MEMORY > 0);
+        assert!(INDEX_SEGMENT_LEN > 0);
+        assert!(DUPLICATE_SCAN_MAX_ITEMS > 1);
+        assert!(SIMILAR_IMAGE_MAX_DISTANCE < 64);
         assert!(MIN_SIMILARITY_SCORE >= 0.0 && MIN_SIMILARITY_SCORE <= 1.0);
         assert!(EMBEDDING_BATCH_SIZE > 0);
+        assert!(EMBEDDING_REQUEST_BATCH_SIZE > 0);
+        assert!(MAX_EMBEDDING_INPUT_CHARS > 0);
+        assert!(INDEXING_PROGRESS_EMIT_INTERVAL_MS > 0);
+        assert!(SEMANTIC_IDLE_CHECK_INTERVAL_SECS > 0);
+        assert!(SEMANTIC_SEARCH_SETTLE_MS > 0);
+        assert!(SNIPPET_MAX_CHARS > 0);
+        assert!(MAX_SNIPPET_SENTENCES > 1);
+    }
+
+    #[test]
+    fn test_data_dir_override() {
+        let default = std::env::temp_dir().join(format!("powerclip-override-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&default);
+        assert_eq!(read_data_dir_override(&default), None);
+
+        let moved = Path::new("/mnt/data/PowerClip");
+        write_data_dir_override(&default, moved).unwrap();
+        assert_eq!(read_data_dir_override(&default).as_deref(), Some(moved));
+
+        // Moving back to the default directory removes the override
+        write_data_dir_override(&default, &default).unwrap();
+        assert_eq!(read_data_dir_override(&default), None);
+        write_data_dir_override(&default, &default).unwrap();
+
+        std::fs::remove_dir_all(&default).unwrap();
     }
 
     #[test]
@@ -122,12 +451,45 @@ mod tests {
         assert!(path.to_string_lossy().ends_with("settings.json"));
     }
 
+    #[test]
+    fn test_settings_backup_path() {
+        let path = settings_backup_path();
+        assert!(path.to_string_lossy().ends_with("settings.json.bak"));
+    }
+
     #[test]
     fn test_images_dir() {
         let path = images_dir();
         assert!(path.to_string_lossy().ends_with("images"));
     }
 
+    #[test]
+    fn test_resolve_stored_path() {
+        let root = Path::new("data");
+        let expected = root.join("images").join("bild-ü.png");
+        assert_eq!(resolve_stored_path(root, "images/bild-ü.png"), expected);
+        assert_eq!(resolve_stored_path(root, "images\\bild-ü.png"), expected);
+        assert_eq!(resolve_stored_path(root, "images//bild-ü.png"), expected);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_resolve_stored_path_keeps_non_utf8_root() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        // A data directory whose name isn't valid UTF-8 must not be mangled
+        let dir = std::env::temp_dir().join(OsStr::from_bytes(b"powerclip-\xff-data"));
+        let path = resolve_stored_path(&dir, "images/画像.png");
+        assert!(path.starts_with(&dir));
+        assert!(path.to_str().is_none());
+
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, b"png").unwrap();
+        assert_eq!(std::fs::read(resolve_stored_path(&dir, "images/画像.png")).unwrap(), b"png");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
     #[test]
     fn test_paths_are_consistent() {
         let data = data_dir();
@@ -135,9 +497,31 @@ mod tests {
         assert!(log_path().starts_with(data));
         assert!(window_config_path().starts_with(data));
         assert!(settings_path().starts_with(data));
+        assert!(approved_extensions_path().starts_with(data));
         assert!(images_dir().starts_with(data));
     }
 
+    #[test]
+    fn test_temp_path_for() {
+        let tmp = temp_path_for(Path::new("/some/dir/settings.json"));
+        assert_eq!(tmp, PathBuf::from("/some/dir/settings.json.tmp"));
+    }
+
+    #[test]
+    fn test_write_atomic_replaces_file() {
+        let dir = std::env::temp_dir().join(format!("powerclip_atomic_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("target.json");
+
+        write_atomic(&path, b"first").expect("Failed to write");
+        write_atomic(&path, b"second").expect("Failed to overwrite");
+
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "second");
+        assert!(!temp_path_for(&path).exists());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
     #[test]
     fn test_data_dir_is_cached() {
         let dir1 = data_dir();
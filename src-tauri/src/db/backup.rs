@@ -0,0 +1,139 @@
+//! Database backups - Timestamped copies of `clipboard.db` with rotation
+//!
+//! Backups use SQLite's online backup API, so they are consistent snapshots
+//! even while the clipboard monitor keeps writing.
+
+use std::path::{Path, PathBuf};
+
+use chrono::NaiveDateTime;
+use rusqlite::{Connection, DatabaseName};
+
+use crate::config::temp_path_for;
+use crate::logger;
+
+const BACKUP_PREFIX: &str = "clipboard-";
+const BACKUP_EXTENSION: &str = ".db";
+const BACKUP_TIME_FORMAT: &str = "%Y%m%d-%H%M%S";
+
+/// Copy the database to `dir/clipboard-<timestamp>.db`.
+///
+/// The copy is written to a temp file first, so an interrupted backup never
+/// looks like a complete one.
+pub fn create_backup(conn: &Connection, dir: &Path, now: NaiveDateTime) -> Result<PathBuf, String> {
+    std::fs::create_dir_all(dir).map_err(|e| format!("Failed to create backups directory: {}", e))?;
+
+    let path = dir.join(format!("{}{}{}", BACKUP_PREFIX, now.format(BACKUP_TIME_FORMAT), BACKUP_EXTENSION));
+    let temp_path = temp_path_for(&path);
+
+    if let Err(e) = conn.backup(DatabaseName::Main, &temp_path, None) {
+        let _ = std::fs::remove_file(&temp_path);
+        return Err(format!("Failed to back up database: {}", e));
+    }
+    std::fs::rename(&temp_path, &path).map_err(|e| format!("Failed to finalize backup: {}", e))?;
+
+    logger::info("Database", &format!("Backup written to {:?}", path));
+    Ok(path)
+}
+
+/// Backup files in `dir`, oldest first.
+pub fn list_backups(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut backups: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| backup_time(path).is_some())
+        .collect();
+    // Timestamps are zero-padded, so name order is chronological
+    backups.sort();
+    backups
+}
+
+/// Time a backup was taken, parsed from its file name.
+fn backup_time(path: &Path) -> Option<NaiveDateTime> {
+    let name = path.file_name()?.to_str()?;
+    let stamp = name.strip_prefix(BACKUP_PREFIX)?.strip_suffix(BACKUP_EXTENSION)?;
+    NaiveDateTime::parse_from_str(stamp, BACKUP_TIME_FORMAT).ok()
+}
+
+/// Time of the most recent backup in `dir`, if any.
+pub fn latest_backup_time(dir: &Path) -> Option<NaiveDateTime> {
+    list_backups(dir).last().and_then(|path| backup_time(path))
+}
+
+/// Delete all but the newest `keep` backups (0 = keep all). Returns the number deleted.
+pub fn prune_backups(dir: &Path, keep: usize) -> usize {
+    if keep == 0 {
+        return 0;
+    }
+    let backups = list_backups(dir);
+    let excess = backups.len().saturating_sub(keep);
+
+    backups
+        .iter()
+        .take(excess)
+        .filter(|path| match std::fs::remove_file(path) {
+            Ok(()) => true,
+            Err(e) => {
+                logger::warning("Database", &format!("Failed to delete old backup {:?}: {}", path, e));
+                false
+            }
+        })
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("powerclip-backup-test-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn at(stamp: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(stamp, "%Y-%m-%d %H:%M:%S").unwrap()
+    }
+
+    #[test]
+    fn test_create_backup_copies_data() {
+        let dir = temp_dir("create");
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute("CREATE TABLE t (v TEXT)", ()).unwrap();
+        conn.execute("INSERT INTO t VALUES ('hello')", ()).unwrap();
+
+        let path = create_backup(&conn, &dir, at("2024-03-01 08:30:00")).unwrap();
+        assert_eq!(path.file_name().unwrap(), "clipboard-20240301-083000.db");
+        assert!(!temp_path_for(&path).exists());
+
+        let copy = Connection::open(&path).unwrap();
+        let value: String = copy.query_row("SELECT v FROM t", [], |row| row.get(0)).unwrap();
+        assert_eq!(value, "hello");
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_prune_keeps_newest() {
+        let dir = temp_dir("prune");
+        let conn = Connection::open_in_memory().unwrap();
+        for day in 1..=4 {
+            create_backup(&conn, &dir, at(&format!("2024-03-0{} 12:00:00", day))).unwrap();
+        }
+        std::fs::write(dir.join("notes.txt"), "not a backup").unwrap();
+
+        assert_eq!(prune_backups(&dir, 2), 2);
+
+        let names: Vec<String> = list_backups(&dir)
+            .iter()
+            .map(|p| p.file_name().unwrap().to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(names, vec!["clipboard-20240303-120000.db", "clipboard-20240304-120000.db"]);
+        assert_eq!(latest_backup_time(&dir), Some(at("2024-03-04 12:00:00")));
+        assert!(dir.join("notes.txt").exists());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -1,11 +1,16 @@
 //! Database module - SQLite operations for clipboard history
 
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
 use rusqlite::Connection;
 use serde::{Deserialize, Serialize};
 
-use crate::config::db_path;
+use crate::config::{db_path, FRECENCY_HALF_LIFE_HOURS};
 use crate::logger;
 
+pub mod backup;
+pub mod relocate;
 pub mod snippets;
 
 /// Clipboard history item stored in database.
@@ -18,6 +23,47 @@ pub struct ClipboardItem {
     pub created_at: String,
     #[serde(default)]
     pub is_favorited: bool,
+    #[serde(default)]
+    pub is_concealed: bool,
+    /// Guessed language for text that looks like code ("unknown" if unclear), `None` otherwise
+    #[serde(default)]
+    pub code_language: Option<String>,
+    /// Times the item was copied back from history
+    #[serde(default)]
+    pub use_count: i64,
+    /// When the item was last copied back from history (unlike `created_at`, not
+    /// touched by the monitor re-capturing it); `None` if never
+    #[serde(default)]
+    pub last_used_at: Option<String>,
+    /// Human-readable age of `created_at` ("5 min ago", "Yesterday"), computed when read
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_at_relative: Option<String>,
+}
+
+/// A history item for the list, with long text cut short.
+#[derive(Debug, Clone, Serialize)]
+pub struct HistoryPreview {
+    #[serde(flatten)]
+    pub item: ClipboardItem,
+    /// Whether `content` was cut; fetch the full item with `get_item` when needed
+    pub truncated: bool,
+}
+
+impl ClipboardItem {
+    /// Cut text content to at most `max_chars` characters (0 = no limit).
+    ///
+    /// Images and file lists are kept whole, since their content is a
+    /// reference rather than something shown as text.
+    pub fn into_preview(mut self, max_chars: usize) -> HistoryPreview {
+        let cut = match self.content.char_indices().nth(max_chars) {
+            Some((end, _)) if max_chars > 0 && self.item_type == "text" => Some(end),
+            _ => None,
+        };
+        if let Some(end) = cut {
+            self.content.truncate(end);
+        }
+        HistoryPreview { item: self, truncated: cut.is_some() }
+    }
 }
 
 /// Database connection state.
@@ -57,16 +103,54 @@ impl DatabaseState {
         conn.execute("PRAGMA foreign_keys = ON", ())?;
 
         // Migration: add is_favorited column if missing
-        let has_favorited: bool = conn
-            .prepare("PRAGMA table_info(history)")?
-            .query_map([], |row| row.get::<_, String>(1))?
-            .filter_map(|r| r.ok())
-            .any(|name| name == "is_favorited");
-        if !has_favorited {
+        if !has_column(&conn, "history", "is_favorited")? {
             conn.execute("ALTER TABLE history ADD COLUMN is_favorited INTEGER NOT NULL DEFAULT 0", ())?;
             logger::info("Database", "Migrated: added is_favorited column");
         }
 
+        // Migration: add is_concealed column if missing
+        if !has_column(&conn, "history", "is_concealed")? {
+            conn.execute("ALTER TABLE history ADD COLUMN is_concealed INTEGER NOT NULL DEFAULT 0", ())?;
+            logger::info("Database", "Migrated: added is_concealed column");
+        }
+
+        // Migration: add code_language column if missing
+        if !has_column(&conn, "history", "code_language")? {
+            conn.execute("ALTER TABLE history ADD COLUMN code_language TEXT", ())?;
+            logger::info("Database", "Migrated: added code_language column");
+        }
+
+        // Migration: add image storage columns if missing ("file" = images dir, "inline" = image_data)
+        if !has_column(&conn, "history", "storage")? {
+            conn.execute("ALTER TABLE history ADD COLUMN storage TEXT NOT NULL DEFAULT 'file'", ())?;
+            conn.execute("ALTER TABLE history ADD COLUMN image_data BLOB", ())?;
+            logger::info("Database", "Migrated: added storage and image_data columns");
+        }
+
+        // Migration: add use_count column if missing
+        if !has_column(&conn, "history", "use_count")? {
+            conn.execute("ALTER TABLE history ADD COLUMN use_count INTEGER NOT NULL DEFAULT 0", ())?;
+            logger::info("Database", "Migrated: added use_count column");
+        }
+
+        // Migration: add last_used_at column if missing
+        if !has_column(&conn, "history", "last_used_at")? {
+            conn.execute("ALTER TABLE history ADD COLUMN last_used_at TEXT", ())?;
+            logger::info("Database", "Migrated: added last_used_at column");
+        }
+
+        // Migration: add image_phash column if missing
+        if !has_column(&conn, "history", "image_phash")? {
+            conn.execute("ALTER TABLE history ADD COLUMN image_phash INTEGER", ())?;
+            logger::info("Database", "Migrated: added image_phash column");
+        }
+
+        // Migration: add image_phash_failed column if missing (images that can't be decoded)
+        if !has_column(&conn, "history", "image_phash_failed")? {
+            conn.execute("ALTER TABLE history ADD COLUMN image_phash_failed INTEGER NOT NULL DEFAULT 0", ())?;
+            logger::info("Database", "Migrated: added image_phash_failed column");
+        }
+
         // Snippets table for quick commands
         conn.execute(
             "CREATE TABLE IF NOT EXISTS snippets (
@@ -88,21 +172,59 @@ impl DatabaseState {
     }
 }
 
+/// Check whether `table` already has a column named `column`.
+fn has_column(conn: &Connection, table: &str, column: &str) -> Result<bool, rusqlite::Error> {
+    let exists = conn
+        .prepare(&format!("PRAGMA table_info({})", table))?
+        .query_map([], |row| row.get::<_, String>(1))?
+        .filter_map(|r| r.ok())
+        .any(|name| name == column);
+    Ok(exists)
+}
+
 /// Calculate MD5 hash of content.
 #[inline]
 pub fn calculate_hash(content: &[u8]) -> String {
     format!("{:x}", md5::compute(content))
 }
 
+/// Result of `save_item`.
+#[derive(Debug)]
+pub enum SaveOutcome {
+    /// New content: a row was inserted
+    Inserted(ClipboardItem),
+    /// Content already in history: only its timestamp was bumped
+    Updated { id: i64, created_at: String },
+}
+
+impl SaveOutcome {
+    /// The newly inserted item, if this was an insert.
+    pub fn inserted(self) -> Option<ClipboardItem> {
+        match self {
+            SaveOutcome::Inserted(item) => Some(item),
+            SaveOutcome::Updated { .. } => None,
+        }
+    }
+
+    /// Id of the saved row, whether inserted or updated.
+    pub fn id(&self) -> i64 {
+        match self {
+            SaveOutcome::Inserted(item) => item.id,
+            SaveOutcome::Updated { id, .. } => *id,
+        }
+    }
+}
+
 /// Insert or update a clipboard item.
 ///
-/// Returns `Some(ClipboardItem)` if a new item was inserted, `None` if only the timestamp was updated.
+/// Content with a known hash isn't duplicated; the existing row's timestamp
+/// is bumped instead so it moves to the top of the history.
 pub fn save_item(
     conn: &Connection,
     item_type: &str,
     content: &str,
     hash: &str,
-) -> Result<Option<ClipboardItem>, rusqlite::Error> {
+) -> Result<SaveOutcome, rusqlite::Error> {
     let created_at = chrono::Local::now().format("%Y-%m-%dT%H:%M:%S").to_string();
 
     let existing_id: Result<Option<i64>, _> = conn.query_row(
@@ -117,7 +239,7 @@ pub fn save_item(
                 "UPDATE history SET created_at = ? WHERE id = ?",
                 rusqlite::params![&created_at, id],
             )?;
-            Ok(None)
+            Ok(SaveOutcome::Updated { id, created_at })
         }
         _ => {
             conn.execute(
@@ -129,47 +251,272 @@ pub fn save_item(
             let hash_preview = if hash.len() > 8 { &hash[..8] } else { hash };
             logger::debug("Database", &format!("New item hash={}", hash_preview));
 
-            Ok(Some(ClipboardItem {
+            Ok(SaveOutcome::Inserted(ClipboardItem {
                 id,
                 item_type: item_type.to_string(),
                 content: content.to_string(),
                 hash: hash.to_string(),
                 created_at,
                 is_favorited: false,
+                is_concealed: false,
+                code_language: None,
+                use_count: 0,
+                last_used_at: None,
+                created_at_relative: Some("Just now".to_string()),
             }))
         }
     }
 }
 
 
-/// Read a ClipboardItem from a row with columns: id, type, content, hash, created_at, is_favorited.
-fn row_to_item(row: &rusqlite::Row) -> rusqlite::Result<ClipboardItem> {
+/// Read a ClipboardItem from a row with columns:
+/// id, type, content, hash, created_at, is_favorited, is_concealed, code_language.
+pub(crate) fn row_to_item(row: &rusqlite::Row) -> rusqlite::Result<ClipboardItem> {
+    let created_at: String = row.get(4)?;
+    let created_at_relative = format_relative_time(&created_at, chrono::Local::now().naive_local());
     Ok(ClipboardItem {
         id: row.get(0)?,
         item_type: row.get(1)?,
         content: row.get(2)?,
         hash: row.get(3)?,
-        created_at: row.get(4)?,
+        created_at,
         is_favorited: row.get::<_, i64>(5).unwrap_or(0) != 0,
+        is_concealed: row.get::<_, i64>(6).unwrap_or(0) != 0,
+        code_language: row.get(7).unwrap_or(None),
+        use_count: row.get(8).unwrap_or(0),
+        last_used_at: row.get(9).unwrap_or(None),
+        created_at_relative,
     })
 }
 
-const SELECT_COLS: &str = "id, type, content, hash, created_at, is_favorited";
+/// Parse a stored local timestamp. Current rows use "T"; legacy rows used a space.
+fn parse_timestamp(timestamp: &str) -> Option<chrono::NaiveDateTime> {
+    chrono::NaiveDateTime::parse_from_str(timestamp, "%Y-%m-%dT%H:%M:%S")
+        .or_else(|_| chrono::NaiveDateTime::parse_from_str(timestamp, "%Y-%m-%d %H:%M:%S"))
+        .ok()
+}
+
+/// Describe a stored local timestamp relative to `now`.
+///
+/// Uses the same wording as the frontend's `formatTime`; items older than a
+/// week show their date. Returns `None` if `created_at` can't be parsed.
+pub fn format_relative_time(created_at: &str, now: chrono::NaiveDateTime) -> Option<String> {
+    let created = parse_timestamp(created_at)?;
+
+    let minutes = (now - created).num_minutes().max(0);
+    let text = match minutes {
+        0 => "Just now".to_string(),
+        1..=59 => format!("{} min ago", minutes),
+        60..=1439 => {
+            let hours = minutes / 60;
+            format!("{} {} ago", hours, if hours == 1 { "hour" } else { "hours" })
+        }
+        _ => match minutes / 1440 {
+            1 => "Yesterday".to_string(),
+            days @ 2..=6 => format!("{} days ago", days),
+            _ => created.format("%Y-%m-%d").to_string(),
+        },
+    };
+    Some(text)
+}
+
+pub(crate) const SELECT_COLS: &str =
+    "id, type, content, hash, created_at, is_favorited, is_concealed, code_language, use_count, last_used_at";
+
+/// Default page size for `query_history` when no limit is given.
+pub const DEFAULT_QUERY_LIMIT: i64 = 100;
+
+/// Item flags usable as `HistoryQuery::tag` (there are no user-defined tags).
+pub const HISTORY_TAGS: &[&str] = &["favorite", "concealed", "code"];
+
+/// Sort orders usable as `HistoryQuery::sort`.
+pub const HISTORY_SORTS: &[&str] = &["default", "newest", "oldest", "frecency"];
+
+/// Combined filters for `query_history`. Every field is optional.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HistoryQuery {
+    /// Search terms, each a case-insensitive substring of the content (see `search_terms`)
+    pub text: Option<String>,
+    /// "text", "image", or "file"
+    pub item_type: Option<String>,
+    /// One of `HISTORY_TAGS`
+    pub tag: Option<String>,
+    /// Inclusive lower bound on `created_at` ("YYYY-MM-DD" or "YYYY-MM-DDTHH:MM:SS")
+    pub after: Option<String>,
+    /// Exclusive upper bound on `created_at`
+    pub before: Option<String>,
+    /// Defaults to `DEFAULT_QUERY_LIMIT`; negative means no limit
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+    /// One of `HISTORY_SORTS`; "default" puts favorites first, then newest;
+    /// "frecency" ranks by use count weighted by recency (see `frecency_rank`)
+    pub sort: Option<String>,
+}
+
+impl HistoryQuery {
+    /// Reject unknown tags and sort orders instead of silently ignoring them.
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(tag) = self.tag.as_deref() {
+            if !HISTORY_TAGS.contains(&tag) {
+                return Err(format!("Invalid tag: {}. Use one of: {}", tag, HISTORY_TAGS.join(", ")));
+            }
+        }
+        if let Some(sort) = self.sort.as_deref() {
+            if !HISTORY_SORTS.contains(&sort) {
+                return Err(format!("Invalid sort: {}. Use one of: {}", sort, HISTORY_SORTS.join(", ")));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Escape `%`, `_` and `\` so user text matches literally in a `LIKE ... ESCAPE '\'` pattern.
+fn escape_like(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Split search text into terms: words separated by whitespace, or a "quoted phrase"
+/// kept whole. An unclosed quote runs to the end of the text.
+///
+/// Each term matches as a substring, so a partly typed word ("conf") already
+/// matches ("configuration") and results narrow as the user keeps typing.
+/// No word tokenizer is involved, so scripts written without spaces (Chinese,
+/// Japanese) match any run of characters. The ideographic space (U+3000) typed
+/// by CJK input methods separates terms like an ASCII space.
+fn search_terms(text: &str) -> Vec<&str> {
+    let mut terms = Vec::new();
+    let mut rest = text;
+    loop {
+        rest = rest.trim_start();
+        if let Some(quoted) = rest.strip_prefix('"') {
+            let end = quoted.find('"').unwrap_or(quoted.len());
+            terms.push(&quoted[..end]);
+            rest = quoted.get(end + 1..).unwrap_or("");
+        } else if rest.is_empty() {
+            break;
+        } else {
+            let end = rest.find(|c: char| c.is_whitespace() || c == '"').unwrap_or(rest.len());
+            terms.push(&rest[..end]);
+            rest = &rest[end..];
+        }
+    }
+    terms.retain(|term| !term.trim().is_empty());
+    terms
+}
+
+/// Query history with any combination of filters.
+///
+/// The SQL is assembled from fixed fragments only; every user value is a bound parameter.
+pub fn query_history(conn: &Connection, query: &HistoryQuery) -> Result<Vec<ClipboardItem>, rusqlite::Error> {
+    use rusqlite::types::Value;
+
+    let mut conditions: Vec<&str> = Vec::new();
+    let mut params: Vec<Value> = Vec::new();
+
+    // Every term must match, in any order
+    for term in query.text.as_deref().map(search_terms).unwrap_or_default() {
+        conditions.push("content LIKE ? ESCAPE '\\'");
+        params.push(Value::Text(format!("%{}%", escape_like(term))));
+    }
+    if let Some(item_type) = &query.item_type {
+        conditions.push("type = ?");
+        params.push(Value::Text(item_type.clone()));
+    }
+    match query.tag.as_deref() {
+        Some("favorite") => conditions.push("is_favorited != 0"),
+        Some("concealed") => conditions.push("is_concealed != 0"),
+        Some("code") => conditions.push("code_language IS NOT NULL"),
+        Some(_) => conditions.push("0"),
+        None => {}
+    }
+    if let Some(after) = &query.after {
+        conditions.push("created_at >= ?");
+        params.push(Value::Text(after.clone()));
+    }
+    if let Some(before) = &query.before {
+        conditions.push("created_at < ?");
+        params.push(Value::Text(before.clone()));
+    }
+
+    let where_clause = if conditions.is_empty() {
+        String::new()
+    } else {
+        format!("WHERE {}", conditions.join(" AND "))
+    };
+    let frecency = query.sort.as_deref() == Some("frecency");
+    let order_by = match query.sort.as_deref() {
+        Some("newest") | Some("frecency") => "created_at DESC, id DESC",
+        Some("oldest") => "created_at ASC, id ASC",
+        _ => "is_favorited DESC, created_at DESC",
+    };
+
+    // A negative limit means "no limit" in SQLite. Frecency is scored in Rust,
+    // so it reads every match and pages afterwards
+    let limit = query.limit.unwrap_or(DEFAULT_QUERY_LIMIT);
+    let offset = query.offset.unwrap_or(0).max(0);
+    params.push(Value::Integer(if frecency { -1 } else { limit }));
+    params.push(Value::Integer(if frecency { 0 } else { offset }));
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM history {} ORDER BY {} LIMIT ? OFFSET ?",
+        SELECT_COLS, where_clause, order_by
+    ))?;
+
+    let mut items = stmt
+        .query_map(rusqlite::params_from_iter(params), row_to_item)?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if frecency {
+        sort_by_frecency(&mut items, chrono::Local::now().naive_local());
+        let limit = if limit < 0 { usize::MAX } else { limit as usize };
+        items = items.into_iter().skip(offset as usize).take(limit).collect();
+    }
+
+    Ok(items)
+}
+
+/// Frecency of an item as a base-2 log, so very old items don't all underflow to 0.
+///
+/// Equals `log2((use_count + 1) * 0.5^(age_hours / FRECENCY_HALF_LIFE_HOURS))`, with age
+/// measured from the later of `created_at` and `last_used_at`.
+/// Items with an unparseable timestamp rank as if they were a year old.
+fn frecency_rank(item: &ClipboardItem, now: chrono::NaiveDateTime) -> f64 {
+    let created = parse_timestamp(&item.created_at);
+    let last_used = item.last_used_at.as_deref().and_then(parse_timestamp);
+    let age_hours = created
+        .max(last_used)
+        .map(|created| (now - created).num_seconds().max(0) as f64 / 3600.0)
+        .unwrap_or(24.0 * 365.0);
+    ((item.use_count.max(0) + 1) as f64).log2() - age_hours / FRECENCY_HALF_LIFE_HOURS
+}
+
+/// Order items by frecency, highest first. The sort is stable, so ties keep their order.
+fn sort_by_frecency(items: &mut [ClipboardItem], now: chrono::NaiveDateTime) {
+    items.sort_by(|a, b| frecency_rank(b, now).total_cmp(&frecency_rank(a, now)));
+}
 
 /// Get clipboard history items. Favorites are sorted first, then by recency.
+///
+/// `item_type` restricts results to a single type (e.g. "text", "image", "file");
+/// `None` returns all types.
 pub fn get_history(
     conn: &Connection,
     limit: i64,
+    item_type: Option<&str>,
 ) -> Result<Vec<ClipboardItem>, rusqlite::Error> {
-    let mut stmt = conn.prepare(
-        &format!("SELECT {} FROM history ORDER BY is_favorited DESC, created_at DESC LIMIT ?", SELECT_COLS),
-    )?;
-
-    let items = stmt
-        .query_map([limit], row_to_item)?
-        .collect::<Result<Vec<_>, _>>()?;
-
-    Ok(items)
+    query_history(conn, &HistoryQuery {
+        item_type: item_type.map(str::to_string),
+        limit: Some(limit),
+        ..Default::default()
+    })
 }
 
 /// Get clipboard history items filtered by type.
@@ -178,15 +525,7 @@ pub fn get_history_by_type(
     item_type: &str,
     limit: i64,
 ) -> Result<Vec<ClipboardItem>, rusqlite::Error> {
-    let mut stmt = conn.prepare(
-        &format!("SELECT {} FROM history WHERE type = ? ORDER BY is_favorited DESC, created_at DESC LIMIT ?", SELECT_COLS),
-    )?;
-
-    let items = stmt
-        .query_map(rusqlite::params![item_type, limit], row_to_item)?
-        .collect::<Result<Vec<_>, _>>()?;
-
-    Ok(items)
+    get_history(conn, limit, Some(item_type))
 }
 
 /// Get clipboard history items created after a specific datetime.
@@ -196,15 +535,11 @@ pub fn get_history_since(
     since: &str,
     limit: i64,
 ) -> Result<Vec<ClipboardItem>, rusqlite::Error> {
-    let mut stmt = conn.prepare(
-        &format!("SELECT {} FROM history WHERE created_at >= ? ORDER BY is_favorited DESC, created_at DESC LIMIT ?", SELECT_COLS),
-    )?;
-
-    let items = stmt
-        .query_map(rusqlite::params![since, limit], row_to_item)?
-        .collect::<Result<Vec<_>, _>>()?;
-
-    Ok(items)
+    query_history(conn, &HistoryQuery {
+        after: Some(since.to_string()),
+        limit: Some(limit),
+        ..Default::default()
+    })
 }
 
 /// Get clipboard history items filtered by type and created after a specific datetime.
@@ -214,17 +549,65 @@ pub fn get_history_by_type_since(
     since: &str,
     limit: i64,
 ) -> Result<Vec<ClipboardItem>, rusqlite::Error> {
+    query_history(conn, &HistoryQuery {
+        item_type: Some(item_type.to_string()),
+        after: Some(since.to_string()),
+        limit: Some(limit),
+        ..Default::default()
+    })
+}
+
+/// Get the ids of items created within `[after, before)`.
+///
+/// Bounds are compared as strings against `created_at`, so both "YYYY-MM-DD" and
+/// "YYYY-MM-DDTHH:MM:SS" work. `None` leaves that side open.
+pub fn get_item_ids_in_range(
+    conn: &Connection,
+    after: Option<&str>,
+    before: Option<&str>,
+) -> Result<HashSet<i64>, rusqlite::Error> {
+    let mut stmt = conn.prepare(
+        "SELECT id FROM history WHERE (?1 IS NULL OR created_at >= ?1) AND (?2 IS NULL OR created_at < ?2)",
+    )?;
+
+    let ids = stmt
+        .query_map(rusqlite::params![after, before], |row| row.get(0))?
+        .collect::<Result<HashSet<i64>, _>>()?;
+
+    Ok(ids)
+}
+
+/// Fetch items by id in a single query. Ids with no matching row are skipped,
+/// and the result is in no particular order.
+pub fn get_items_by_ids(conn: &Connection, ids: &[i64]) -> Result<Vec<ClipboardItem>, rusqlite::Error> {
+    if ids.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let placeholders = vec!["?"; ids.len()].join(", ");
     let mut stmt = conn.prepare(
-        &format!("SELECT {} FROM history WHERE type = ? AND created_at >= ? ORDER BY is_favorited DESC, created_at DESC LIMIT ?", SELECT_COLS),
+        &format!("SELECT {} FROM history WHERE id IN ({})", SELECT_COLS, placeholders),
     )?;
 
     let items = stmt
-        .query_map(rusqlite::params![item_type, since, limit], row_to_item)?
+        .query_map(rusqlite::params_from_iter(ids), row_to_item)?
         .collect::<Result<Vec<_>, _>>()?;
 
     Ok(items)
 }
 
+/// Fetch a single item, or `None` if it no longer exists.
+pub fn get_item_by_id(conn: &Connection, id: i64) -> Result<Option<ClipboardItem>, rusqlite::Error> {
+    let mut stmt = conn.prepare(&format!("SELECT {} FROM history WHERE id = ?", SELECT_COLS))?;
+    let mut rows = stmt.query_map([id], row_to_item)?;
+    rows.next().transpose()
+}
+
+/// Whether an item with `id` is still in history.
+pub fn item_exists(conn: &Connection, id: i64) -> Result<bool, rusqlite::Error> {
+    conn.query_row("SELECT EXISTS(SELECT 1 FROM history WHERE id = ?)", [id], |row| row.get(0))
+}
+
 /// Clean up old items beyond the specified limit.
 ///
 /// Favorited items are never deleted by auto-cleanup.
@@ -274,6 +657,90 @@ pub fn cleanup_old_items(conn: &Connection, max_items: i64) -> Result<i64, rusql
     Ok(to_delete)
 }
 
+/// Delete items created more than `max_age_days` days before `now`, with their image files.
+///
+/// Favorited items are never deleted. `max_age_days <= 0` means no age limit.
+/// Returns the number of items deleted.
+pub fn cleanup_items_older_than(
+    conn: &Connection,
+    max_age_days: i64,
+    now: chrono::NaiveDateTime,
+) -> Result<i64, rusqlite::Error> {
+    if max_age_days <= 0 {
+        return Ok(0);
+    }
+
+    let cutoff = (now - chrono::Duration::days(max_age_days))
+        .format("%Y-%m-%dT%H:%M:%S")
+        .to_string();
+
+    let mut stmt = conn.prepare(
+        "SELECT type, content FROM history WHERE is_favorited = 0 AND created_at < ?",
+    )?;
+    let image_paths: Vec<String> = stmt
+        .query_map([&cutoff], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?
+        .filter_map(|r| r.ok())
+        .filter(|(item_type, _)| item_type == "image")
+        .filter_map(|(_, content)| content.strip_prefix("images/").map(|f| f.to_string()))
+        .collect();
+
+    let deleted = conn.execute(
+        "DELETE FROM history WHERE is_favorited = 0 AND created_at < ?",
+        [&cutoff],
+    )?;
+
+    for filename in image_paths {
+        let _ = std::fs::remove_file(crate::config::images_dir().join(&filename));
+    }
+
+    Ok(deleted as i64)
+}
+
+/// Delete all non-favorited items and their image files.
+///
+/// Returns the ids of the deleted items.
+pub fn clear_history(conn: &Connection) -> Result<Vec<i64>, rusqlite::Error> {
+    let mut stmt = conn.prepare("SELECT id, type, content FROM history WHERE is_favorited = 0")?;
+    let rows: Vec<(i64, String, String)> = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    conn.execute("DELETE FROM history WHERE is_favorited = 0", [])?;
+
+    for (_, item_type, content) in &rows {
+        if item_type == "image" {
+            if let Some(filename) = content.strip_prefix("images/") {
+                let _ = std::fs::remove_file(crate::config::images_dir().join(filename));
+            }
+        }
+    }
+
+    Ok(rows.into_iter().map(|(id, _, _)| id).collect())
+}
+
+/// Delete the `count` newest non-favorited items and their image files.
+///
+/// Returns the ids of the deleted items.
+pub fn delete_newest_items(conn: &Connection, count: i64) -> Result<Vec<i64>, rusqlite::Error> {
+    let mut stmt = conn.prepare(
+        "SELECT id, type, content, storage FROM history WHERE is_favorited = 0 ORDER BY created_at DESC, id DESC LIMIT ?",
+    )?;
+    let rows: Vec<(i64, String, String, String)> = stmt
+        .query_map([count.max(0)], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)))?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    for (id, item_type, content, storage) in &rows {
+        conn.execute("DELETE FROM history WHERE id = ?", [id])?;
+        if item_type == "image" && storage == "file" {
+            if let Some(filename) = content.strip_prefix("images/") {
+                let _ = std::fs::remove_file(crate::config::images_dir().join(filename));
+            }
+        }
+    }
+
+    Ok(rows.into_iter().map(|(id, _, _, _)| id).collect())
+}
+
 /// Toggle the favorite status of a clipboard item.
 ///
 /// Returns the new favorite state.
@@ -290,26 +757,146 @@ pub fn toggle_favorite(conn: &Connection, item_id: i64) -> Result<bool, rusqlite
     Ok(new_state)
 }
 
+/// Toggle the concealed status of a clipboard item.
+///
+/// Concealed items are still stored and searchable, but the frontend blurs them
+/// until explicitly revealed. Returns the new concealed state.
+pub fn toggle_conceal(conn: &Connection, item_id: i64) -> Result<bool, rusqlite::Error> {
+    conn.execute(
+        "UPDATE history SET is_concealed = CASE WHEN is_concealed = 0 THEN 1 ELSE 0 END WHERE id = ?",
+        [item_id],
+    )?;
+    let new_state: bool = conn.query_row(
+        "SELECT is_concealed FROM history WHERE id = ?",
+        [item_id],
+        |row| Ok(row.get::<_, i64>(0)? != 0),
+    )?;
+    Ok(new_state)
+}
+
+/// Store the detected code language of an item (`None` clears it).
+pub fn set_code_language(conn: &Connection, item_id: i64, language: Option<&str>) -> Result<(), rusqlite::Error> {
+    conn.execute(
+        "UPDATE history SET code_language = ? WHERE id = ?",
+        rusqlite::params![language, item_id],
+    )?;
+    Ok(())
+}
+
+/// Count a copy of an item back to the clipboard and stamp `last_used_at`.
+///
+/// Returns the new `last_used_at`. `created_at` is left alone.
+pub fn record_use(conn: &Connection, item_id: i64) -> Result<String, rusqlite::Error> {
+    let now = chrono::Local::now().format("%Y-%m-%dT%H:%M:%S").to_string();
+    conn.execute(
+        "UPDATE history SET use_count = use_count + 1, last_used_at = ? WHERE id = ?",
+        rusqlite::params![&now, item_id],
+    )?;
+    Ok(now)
+}
+
+/// Store an image item's encoded bytes in the database instead of the images directory.
+pub fn set_inline_image(conn: &Connection, item_id: i64, data: &[u8]) -> Result<(), rusqlite::Error> {
+    conn.execute(
+        "UPDATE history SET storage = 'inline', image_data = ? WHERE id = ?",
+        rusqlite::params![data, item_id],
+    )?;
+    Ok(())
+}
+
+/// Load the bytes of an inline-stored image by its content path.
+///
+/// Returns `None` for images stored as files.
+pub fn get_inline_image(conn: &Connection, content: &str) -> Result<Option<Vec<u8>>, rusqlite::Error> {
+    let mut stmt = conn.prepare(
+        "SELECT image_data FROM history WHERE content = ? AND storage = 'inline' AND image_data IS NOT NULL LIMIT 1",
+    )?;
+    let mut rows = stmt.query_map([content], |row| row.get::<_, Vec<u8>>(0))?;
+    rows.next().transpose()
+}
+
+/// Check whether an image with the given hash is stored inline.
+pub fn has_inline_image(conn: &Connection, hash: &str) -> Result<bool, rusqlite::Error> {
+    conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM history WHERE hash = ? AND storage = 'inline')",
+        [hash],
+        |row| Ok(row.get::<_, i64>(0)? != 0),
+    )
+}
+
+/// Check whether any item with the given content is concealed.
+///
+/// Used to guard image assets, which are addressed by their relative path.
+pub fn is_content_concealed(conn: &Connection, content: &str) -> Result<bool, rusqlite::Error> {
+    conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM history WHERE content = ? AND is_concealed != 0)",
+        [content],
+        |row| Ok(row.get::<_, i64>(0)? != 0),
+    )
+}
+
+/// Delete image files (PNG, or GIFs kept verbatim) in `images_dir` that no file-stored image item refers to.
+///
+/// Returns the number of files removed and their total size in bytes.
+pub fn prune_orphan_images(conn: &Connection, images_dir: &Path) -> Result<(usize, u64), rusqlite::Error> {
+    let mut stmt = conn.prepare("SELECT content FROM history WHERE type = 'image' AND storage = 'file'")?;
+    let referenced: HashSet<String> = stmt
+        .query_map([], |row| row.get::<_, String>(0))?
+        .filter_map(|r| r.ok())
+        .filter_map(|content| content.strip_prefix("images/").map(str::to_string))
+        .collect();
+
+    let Ok(entries) = std::fs::read_dir(images_dir) else {
+        return Ok((0, 0));
+    };
+
+    let mut removed = 0;
+    let mut bytes = 0;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+            continue;
+        };
+        if !(name.ends_with(".png") || name.ends_with(".gif")) || referenced.contains(name) {
+            continue;
+        }
+
+        let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+        match std::fs::remove_file(&path) {
+            Ok(()) => {
+                removed += 1;
+                bytes += size;
+            }
+            Err(e) => logger::warning("Database", &format!("Failed to delete orphan image {:?}: {}", path, e)),
+        }
+    }
+
+    if removed > 0 {
+        logger::info("Database", &format!("Pruned {} orphan images ({} bytes)", removed, bytes));
+    }
+    Ok((removed, bytes))
+}
+
 /// Delete a single item by ID.
 ///
 /// Also deletes the associated image file if the item is an image.
 /// Returns true if an item was deleted, false if not found.
 pub fn delete_item(conn: &Connection, item_id: i64) -> Result<bool, rusqlite::Error> {
     // First, get the item to check if it's an image
-    let item_info: Result<(String, String), _> = conn.query_row(
-        "SELECT type, content FROM history WHERE id = ?",
+    let item_info: Result<(String, String, String), _> = conn.query_row(
+        "SELECT type, content, storage FROM history WHERE id = ?",
         [item_id],
-        |row| Ok((row.get(0)?, row.get(1)?)),
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
     );
 
     let deleted = match item_info {
-        Ok((item_type, content)) => {
+        Ok((item_type, content, storage)) => {
             // Delete from database
             let affected = conn.execute("DELETE FROM history WHERE id = ?", [item_id])?;
 
             if affected > 0 {
-                // If it was an image, delete the file
-                if item_type == "image" {
+                // If it was an image stored on disk, delete the file
+                if item_type == "image" && storage == "file" {
                     if let Some(filename) = content.strip_prefix("images/") {
                         let image_path = crate::config::images_dir().join(filename);
                         if let Err(e) = std::fs::remove_file(&image_path) {
@@ -331,9 +918,128 @@ pub fn delete_item(conn: &Connection, item_id: i64) -> Result<bool, rusqlite::Er
     Ok(deleted)
 }
 
+/// Text compared by `find_text_duplicates`: trimmed, whitespace runs collapsed
+/// to one space, lowercased.
+fn normalize_for_duplicates(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase()
+}
+
+/// Group text items that are identical after normalization.
+///
+/// Exact copies already share one row (the hash is unique), so this catches
+/// the variants that differ only in case or whitespace. Groups have at least
+/// two ids, newest first, and are ordered by their newest item.
+pub fn find_text_duplicates(conn: &Connection) -> Result<Vec<Vec<i64>>, rusqlite::Error> {
+    let mut stmt = conn.prepare("SELECT id, content FROM history WHERE type = 'text' ORDER BY id DESC")?;
+    let rows = stmt.query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))?;
+
+    let mut groups: Vec<Vec<i64>> = Vec::new();
+    let mut by_text: HashMap<String, usize> = HashMap::new();
+    for row in rows {
+        let (id, content) = row?;
+        let normalized = normalize_for_duplicates(&content);
+        if normalized.is_empty() {
+            continue;
+        }
+        let group = *by_text.entry(normalized).or_insert_with(|| {
+            groups.push(Vec::new());
+            groups.len() - 1
+        });
+        groups[group].push(id);
+    }
+    groups.retain(|group| group.len() > 1);
+    Ok(groups)
+}
+
+/// Ids of image items that have no perceptual hash yet, newest first.
+///
+/// Images marked with `set_image_phash_failed` are left out, so they aren't retried.
+pub fn get_images_without_phash(conn: &Connection) -> Result<Vec<i64>, rusqlite::Error> {
+    let mut stmt = conn.prepare(
+        "SELECT id FROM history WHERE type = 'image' AND image_phash IS NULL AND image_phash_failed = 0 ORDER BY id DESC",
+    )?;
+    let ids = stmt.query_map([], |row| row.get(0))?.collect();
+    ids
+}
+
+/// Store the perceptual hash of an image item.
+pub fn set_image_phash(conn: &Connection, item_id: i64, phash: u64) -> Result<(), rusqlite::Error> {
+    // SQLite integers are signed; the bits are kept as they are
+    conn.execute("UPDATE history SET image_phash = ? WHERE id = ?", rusqlite::params![phash as i64, item_id])?;
+    Ok(())
+}
+
+/// Record that an image item can't be hashed (its image is missing or doesn't decode).
+pub fn set_image_phash_failed(conn: &Connection, item_id: i64) -> Result<(), rusqlite::Error> {
+    conn.execute("UPDATE history SET image_phash_failed = 1 WHERE id = ?", [item_id])?;
+    Ok(())
+}
+
+/// Group image items whose perceptual hashes differ in at most `max_distance` bits.
+///
+/// Unlike the exact hash, this catches images that look alike but differ in
+/// bytes, such as repeated screenshots of the same window. Only the newest
+/// `max_items` hashed images are compared. Groups have at least two ids,
+/// newest first, and are ordered by their newest item.
+pub fn find_similar_images(
+    conn: &Connection,
+    max_distance: u32,
+    max_items: usize,
+) -> Result<Vec<Vec<i64>>, rusqlite::Error> {
+    let mut stmt = conn.prepare(
+        "SELECT id, image_phash FROM history WHERE type = 'image' AND image_phash IS NOT NULL ORDER BY id DESC LIMIT ?",
+    )?;
+    let entries: Vec<(i64, u64)> = stmt
+        .query_map([max_items as i64], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)? as u64)))?
+        .collect::<Result<_, _>>()?;
+
+    // Union-find over entry positions
+    let mut parent: Vec<usize> = (0..entries.len()).collect();
+    fn find(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+
+    for i in 0..entries.len() {
+        for j in i + 1..entries.len() {
+            if (entries[i].1 ^ entries[j].1).count_ones() <= max_distance {
+                let (a, b) = (find(&mut parent, i), find(&mut parent, j));
+                if a != b {
+                    // Keep the newer (lower position) entry as the root
+                    parent[a.max(b)] = a.min(b);
+                }
+            }
+        }
+    }
+
+    let mut groups: Vec<Vec<i64>> = Vec::new();
+    let mut root_to_group: HashMap<usize, usize> = HashMap::new();
+    for (i, &(id, _)) in entries.iter().enumerate() {
+        let root = find(&mut parent, i);
+        let group = *root_to_group.entry(root).or_insert_with(|| {
+            groups.push(Vec::new());
+            groups.len() - 1
+        });
+        groups[group].push(id);
+    }
+    groups.retain(|group| group.len() > 1);
+    Ok(groups)
+}
+
+/// SQLite's `PRAGMA data_version` for this connection.
+///
+/// It changes when another connection commits to the database file, but
+/// never for this connection's own writes.
+pub fn data_version(conn: &Connection) -> Result<i64, rusqlite::Error> {
+    conn.query_row("PRAGMA data_version", [], |row| row.get(0))
+}
+
 /// Create the history table if it doesn't exist (for testing).
 #[cfg(test)]
-fn create_history_table(conn: &Connection) -> Result<(), rusqlite::Error> {
+pub(crate) fn create_history_table(conn: &Connection) -> Result<(), rusqlite::Error> {
     conn.execute(
         "CREATE TABLE IF NOT EXISTS history (
             id INTEGER PRIMARY KEY AUTOINCREMENT,
@@ -341,7 +1047,15 @@ fn create_history_table(conn: &Connection) -> Result<(), rusqlite::Error> {
             content TEXT NOT NULL,
             hash TEXT NOT NULL UNIQUE,
             created_at TEXT NOT NULL,
-            is_favorited INTEGER NOT NULL DEFAULT 0
+            is_favorited INTEGER NOT NULL DEFAULT 0,
+            is_concealed INTEGER NOT NULL DEFAULT 0,
+            code_language TEXT,
+            storage TEXT NOT NULL DEFAULT 'file',
+            image_data BLOB,
+            use_count INTEGER NOT NULL DEFAULT 0,
+            last_used_at TEXT,
+            image_phash INTEGER,
+            image_phash_failed INTEGER NOT NULL DEFAULT 0
         )",
         (),
     )?;
@@ -406,8 +1120,7 @@ mod tests {
         let result = save_item(&conn, "text", "Hello World", "hash123")
             .expect("Failed to save item");
 
-        assert!(result.is_some());
-        let item = result.unwrap();
+        let item = result.inserted().expect("Expected a new item");
         assert!(item.id > 0);
         assert_eq!(item.item_type, "text");
         assert_eq!(item.content, "Hello World");
@@ -422,8 +1135,7 @@ mod tests {
         // First save
         let result1 = save_item(&conn, "text", "Original content", "same_hash_value")
             .expect("Failed to save first item");
-        assert!(result1.is_some());
-        let item1 = result1.unwrap();
+        let item1 = result1.inserted().expect("Expected a new item");
 
         // Delay to ensure different timestamp (1 second precision)
         std::thread::sleep(std::time::Duration::from_millis(1100));
@@ -431,10 +1143,16 @@ mod tests {
         // Second save with same hash
         let result2 = save_item(&conn, "text", "Original content", "same_hash_value")
             .expect("Failed to update item");
-        assert!(result2.is_none()); // Should return None for update
+        match result2 {
+            SaveOutcome::Updated { id, created_at } => {
+                assert_eq!(id, item1.id);
+                assert_ne!(created_at, item1.created_at);
+            }
+            SaveOutcome::Inserted(_) => panic!("Duplicate hash should update, not insert"),
+        }
 
         // Verify only one item exists
-        let items = get_history(&conn, 10).expect("Failed to get history");
+        let items = get_history(&conn, 10, None).expect("Failed to get history");
         assert_eq!(items.len(), 1);
         assert_eq!(items[0].id, item1.id);
     }
@@ -447,7 +1165,7 @@ mod tests {
         save_item(&conn, "image", "images/test.png", "hash2").unwrap();
         save_item(&conn, "file", "/path/to/file", "hash3").unwrap();
 
-        let items = get_history(&conn, 10).expect("Failed to get history");
+        let items = get_history(&conn, 10, None).expect("Failed to get history");
         assert_eq!(items.len(), 3);
     }
 
@@ -457,7 +1175,7 @@ mod tests {
     fn test_get_history_empty() {
         let conn = setup_test_db();
 
-        let items = get_history(&conn, 10).expect("Failed to get history");
+        let items = get_history(&conn, 10, None).expect("Failed to get history");
         assert!(items.is_empty());
     }
 
@@ -470,7 +1188,7 @@ mod tests {
             save_item(&conn, "text", &format!("Content {}", i), &format!("hash{}", i)).unwrap();
         }
 
-        let items = get_history(&conn, 5).expect("Failed to get history");
+        let items = get_history(&conn, 5, None).expect("Failed to get history");
         assert_eq!(items.len(), 5);
     }
 
@@ -484,7 +1202,7 @@ mod tests {
             std::thread::sleep(std::time::Duration::from_millis(1100));
         }
 
-        let items = get_history(&conn, 10).expect("Failed to get history");
+        let items = get_history(&conn, 10, None).expect("Failed to get history");
         assert_eq!(items.len(), 3);
         // Most recent should be first
         assert_eq!(items[0].content, "Item 3");
@@ -492,6 +1210,428 @@ mod tests {
         assert_eq!(items[2].content, "Item 1");
     }
 
+    #[test]
+    fn test_get_history_filtered_by_type() {
+        let conn = setup_test_db();
+
+        save_item(&conn, "text", "Text content", "hash1").unwrap();
+        save_item(&conn, "image", "images/test.png", "hash2").unwrap();
+        save_item(&conn, "text", "More text", "hash3").unwrap();
+
+        let texts = get_history(&conn, 10, Some("text")).expect("Failed to get history");
+        assert_eq!(texts.len(), 2);
+        assert!(texts.iter().all(|i| i.item_type == "text"));
+
+        let images = get_history(&conn, 10, Some("image")).expect("Failed to get history");
+        assert_eq!(images.len(), 1);
+
+        let files = get_history(&conn, 10, Some("file")).expect("Failed to get history");
+        assert!(files.is_empty());
+
+        let all = get_history(&conn, 10, None).expect("Failed to get history");
+        assert_eq!(all.len(), 3);
+    }
+
+    #[test]
+    fn test_get_item_ids_in_range() {
+        let conn = setup_test_db();
+
+        for (id, created_at) in [(1, "2024-01-01T09:00:00"), (2, "2024-01-02T09:00:00"), (3, "2024-01-03T09:00:00")] {
+            conn.execute(
+                "INSERT INTO history (id, type, content, hash, created_at) VALUES (?1, 'text', 'x', ?1, ?2)",
+                rusqlite::params![id, created_at],
+            )
+            .unwrap();
+        }
+
+        let ids = get_item_ids_in_range(&conn, Some("2024-01-02"), None).unwrap();
+        assert_eq!(ids, HashSet::from([2, 3]));
+
+        // `before` is exclusive, so a bare date excludes that whole day
+        let ids = get_item_ids_in_range(&conn, None, Some("2024-01-02")).unwrap();
+        assert_eq!(ids, HashSet::from([1]));
+
+        let ids = get_item_ids_in_range(&conn, Some("2024-01-02"), Some("2024-01-03")).unwrap();
+        assert_eq!(ids, HashSet::from([2]));
+
+        assert_eq!(get_item_ids_in_range(&conn, None, None).unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_get_items_by_ids() {
+        let conn = setup_test_db();
+
+        let a = save_item(&conn, "text", "First", "hash1").unwrap().inserted().unwrap();
+        let b = save_item(&conn, "text", "Second", "hash2").unwrap().inserted().unwrap();
+
+        let mut items = get_items_by_ids(&conn, &[a.id, b.id, 999]).unwrap();
+        items.sort_by_key(|i| i.id);
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].content, "First");
+        assert_eq!(items[1].content, "Second");
+
+        assert!(get_items_by_ids(&conn, &[]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_get_item_by_id() {
+        let conn = setup_test_db();
+        let item = save_item(&conn, "text", "single", "hash-single").unwrap().inserted().unwrap();
+
+        let fetched = get_item_by_id(&conn, item.id).unwrap().expect("item exists");
+        assert_eq!(fetched.content, "single");
+        assert!(get_item_by_id(&conn, item.id + 1).unwrap().is_none());
+
+        assert!(item_exists(&conn, item.id).unwrap());
+        assert!(!item_exists(&conn, item.id + 1).unwrap());
+    }
+
+    #[test]
+    fn test_clear_history_keeps_favorites() {
+        let conn = setup_test_db();
+
+        let a = save_item(&conn, "text", "Keep me", "hash1").unwrap().inserted().unwrap();
+        let b = save_item(&conn, "text", "Delete me", "hash2").unwrap().inserted().unwrap();
+        toggle_favorite(&conn, a.id).unwrap();
+
+        let deleted = clear_history(&conn).expect("Failed to clear history");
+        assert_eq!(deleted, vec![b.id]);
+
+        let remaining = get_history(&conn, 10, None).unwrap();
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].id, a.id);
+    }
+
+    #[test]
+    fn test_delete_newest_items_keeps_favorites() {
+        let conn = setup_test_db();
+
+        let a = save_item(&conn, "text", "Oldest", "hash1").unwrap().inserted().unwrap();
+        let b = save_item(&conn, "text", "Favorite", "hash2").unwrap().inserted().unwrap();
+        let c = save_item(&conn, "text", "Password", "hash3").unwrap().inserted().unwrap();
+        conn.execute("UPDATE history SET created_at = '2024-01-01T09:00:00' WHERE id = ?", [a.id]).unwrap();
+        conn.execute("UPDATE history SET created_at = '2024-01-02T09:00:00' WHERE id = ?", [b.id]).unwrap();
+        conn.execute("UPDATE history SET created_at = '2024-01-03T09:00:00' WHERE id = ?", [c.id]).unwrap();
+        toggle_favorite(&conn, b.id).unwrap();
+
+        assert!(delete_newest_items(&conn, 0).unwrap().is_empty());
+        assert_eq!(delete_newest_items(&conn, 1).unwrap(), vec![c.id]);
+
+        let remaining: Vec<i64> = get_history(&conn, 10, None).unwrap().iter().map(|i| i.id).collect();
+        assert_eq!(remaining, vec![b.id, a.id]);
+
+        assert_eq!(delete_newest_items(&conn, 5).unwrap(), vec![a.id]);
+        assert_eq!(get_history(&conn, 10, None).unwrap().len(), 1);
+    }
+
+    // ========== query_history tests ==========
+
+    /// Seed rows with fixed timestamps: ids 1-4 on consecutive days.
+    fn setup_query_db() -> Connection {
+        let conn = setup_test_db();
+        let rows = [
+            (1, "text", "fn main() {}", "2024-01-01T09:00:00"),
+            (2, "text", "Meeting notes for 100% of the team", "2024-01-02T09:00:00"),
+            (3, "image", "images/cat.png", "2024-01-03T09:00:00"),
+            (4, "text", "notes_v2 draft", "2024-01-04T09:00:00"),
+        ];
+        for (id, item_type, content, created_at) in rows {
+            conn.execute(
+                "INSERT INTO history (id, type, content, hash, created_at) VALUES (?1, ?2, ?3, ?1, ?4)",
+                rusqlite::params![id, item_type, content, created_at],
+            )
+            .unwrap();
+        }
+        set_code_language(&conn, 1, Some("rust")).unwrap();
+        toggle_favorite(&conn, 2).unwrap();
+        conn
+    }
+
+    fn query_ids(conn: &Connection, query: HistoryQuery) -> Vec<i64> {
+        query_history(conn, &query).expect("Failed to query history").iter().map(|i| i.id).collect()
+    }
+
+    #[test]
+    fn test_query_history_no_filters() {
+        let conn = setup_query_db();
+
+        // Default sort: favorites first, then newest
+        assert_eq!(query_ids(&conn, HistoryQuery::default()), vec![2, 4, 3, 1]);
+    }
+
+    #[test]
+    fn test_query_history_text_is_case_insensitive_and_literal() {
+        let conn = setup_query_db();
+
+        let q = |text: &str| HistoryQuery { text: Some(text.to_string()), ..Default::default() };
+        assert_eq!(query_ids(&conn, q("NOTES")), vec![2, 4]);
+        // LIKE wildcards in user text match literally
+        assert_eq!(query_ids(&conn, q("100%")), vec![2]);
+        assert_eq!(query_ids(&conn, q("notes_")), vec![4]);
+        assert_eq!(query_ids(&conn, q("%")), vec![2]);
+        // Quotes are bound, not spliced into SQL
+        assert!(query_ids(&conn, q("'; DROP TABLE history; --")).is_empty());
+        assert_eq!(query_ids(&conn, HistoryQuery::default()).len(), 4);
+    }
+
+    #[test]
+    fn test_search_terms() {
+        assert_eq!(search_terms("  meeting   notes "), vec!["meeting", "notes"]);
+        assert_eq!(search_terms(r#"draft "meeting notes" v2"#), vec!["draft", "meeting notes", "v2"]);
+        assert_eq!(search_terms(r#"say"hi there"#), vec!["say", "hi there"]);
+        // Empty and blank phrases are dropped
+        assert_eq!(search_terms("\"\" \"  \" x"), vec!["x"]);
+        assert!(search_terms("   ").is_empty());
+    }
+
+    #[test]
+    fn test_query_history_prefix_and_phrase() {
+        let conn = setup_query_db();
+
+        let q = |text: &str| HistoryQuery { text: Some(text.to_string()), ..Default::default() };
+        // Partly typed words match, narrowing as more is typed
+        assert_eq!(query_ids(&conn, q("mee")), vec![2]);
+        assert_eq!(query_ids(&conn, q("not")), vec![2, 4]);
+        assert_eq!(query_ids(&conn, q("not dra")), vec![4]);
+        // Terms match in any order; a quoted phrase must appear as written
+        assert_eq!(query_ids(&conn, q("team meeting")), vec![2]);
+        assert!(query_ids(&conn, q(r#""team meeting""#)).is_empty());
+        assert_eq!(query_ids(&conn, q(r#""meeting notes" team"#)), vec![2]);
+        // Blank text doesn't filter
+        assert_eq!(query_ids(&conn, q("  ")).len(), 4);
+    }
+
+    #[test]
+    fn test_query_history_cjk() {
+        let conn = setup_test_db();
+        let rows = [
+            (1, "今天的会议记录：讨论配置文件", "2024-01-01T09:00:00"),
+            (2, "東京で会議があります", "2024-01-02T09:00:00"),
+            (3, "会议 notes 2024", "2024-01-03T09:00:00"),
+        ];
+        for (id, content, created_at) in rows {
+            conn.execute(
+                "INSERT INTO history (id, type, content, hash, created_at) VALUES (?1, 'text', ?2, ?1, ?3)",
+                rusqlite::params![id, content, created_at],
+            )
+            .unwrap();
+        }
+
+        let q = |text: &str| HistoryQuery { text: Some(text.to_string()), ..Default::default() };
+        // Any run of characters matches, without word boundaries
+        assert_eq!(query_ids(&conn, q("会议")), vec![3, 1]);
+        assert_eq!(query_ids(&conn, q("配置文件")), vec![1]);
+        assert_eq!(query_ids(&conn, q("会議")), vec![2]);
+        assert_eq!(query_ids(&conn, q("あります")), vec![2]);
+        // Ideographic and ASCII spaces both separate terms
+        assert_eq!(query_ids(&conn, q("会议\u{3000}配置")), vec![1]);
+        assert_eq!(query_ids(&conn, q("会议 NOTES")), vec![3]);
+        // A quoted phrase still has to appear as written
+        assert!(query_ids(&conn, q("\"会议配置\"")).is_empty());
+    }
+
+    #[test]
+    fn test_query_history_type_tag_and_dates() {
+        let conn = setup_query_db();
+
+        let by_type = HistoryQuery { item_type: Some("image".to_string()), ..Default::default() };
+        assert_eq!(query_ids(&conn, by_type), vec![3]);
+
+        let favorites = HistoryQuery { tag: Some("favorite".to_string()), ..Default::default() };
+        assert_eq!(query_ids(&conn, favorites), vec![2]);
+
+        let code = HistoryQuery { tag: Some("code".to_string()), ..Default::default() };
+        assert_eq!(query_ids(&conn, code), vec![1]);
+
+        let range = HistoryQuery {
+            after: Some("2024-01-02".to_string()),
+            before: Some("2024-01-04".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(query_ids(&conn, range), vec![2, 3]);
+    }
+
+    #[test]
+    fn test_query_history_combined_filters() {
+        let conn = setup_query_db();
+
+        let query = HistoryQuery {
+            text: Some("notes".to_string()),
+            item_type: Some("text".to_string()),
+            after: Some("2024-01-03".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(query_ids(&conn, query), vec![4]);
+
+        let query = HistoryQuery {
+            text: Some("notes".to_string()),
+            tag: Some("favorite".to_string()),
+            before: Some("2024-01-02".to_string()),
+            ..Default::default()
+        };
+        assert!(query_ids(&conn, query).is_empty());
+    }
+
+    #[test]
+    fn test_query_history_sort_and_paging() {
+        let conn = setup_query_db();
+
+        let oldest = HistoryQuery { sort: Some("oldest".to_string()), ..Default::default() };
+        assert_eq!(query_ids(&conn, oldest), vec![1, 2, 3, 4]);
+
+        let page = HistoryQuery {
+            sort: Some("newest".to_string()),
+            limit: Some(2),
+            offset: Some(1),
+            ..Default::default()
+        };
+        assert_eq!(query_ids(&conn, page), vec![3, 2]);
+    }
+
+    #[test]
+    fn test_query_history_frecency() {
+        let conn = setup_query_db();
+        // Item 1 is the oldest but has been reused a lot
+        for _ in 0..7 {
+            record_use(&conn, 1).unwrap();
+        }
+
+        let frecency = HistoryQuery { sort: Some("frecency".to_string()), ..Default::default() };
+        let items = query_history(&conn, &frecency).unwrap();
+        assert_eq!(items[0].use_count, 7);
+        assert_eq!(items.iter().map(|i| i.id).collect::<Vec<_>>(), vec![1, 4, 3, 2]);
+
+        let page = HistoryQuery { limit: Some(2), offset: Some(1), ..frecency };
+        assert_eq!(query_ids(&conn, page), vec![4, 3]);
+    }
+
+    #[test]
+    fn test_record_use_keeps_created_at() {
+        let conn = setup_test_db();
+        let item = save_item(&conn, "text", "reused", "hash-reused").unwrap().inserted().unwrap();
+        assert_eq!(item.last_used_at, None);
+
+        let used_at = record_use(&conn, item.id).unwrap();
+        record_use(&conn, item.id).unwrap();
+
+        let stored = get_item_by_id(&conn, item.id).unwrap().unwrap();
+        assert_eq!(stored.created_at, item.created_at);
+        assert_eq!(stored.use_count, 2);
+        assert!(stored.last_used_at.as_deref().is_some_and(|t| t >= used_at.as_str()));
+    }
+
+    #[test]
+    fn test_frecency_rank_decay() {
+        let now = chrono::NaiveDateTime::parse_from_str("2024-01-10T00:00:00", "%Y-%m-%dT%H:%M:%S").unwrap();
+        let item = |created_at: &str, use_count| ClipboardItem {
+            id: 1,
+            item_type: "text".to_string(),
+            content: String::new(),
+            hash: String::new(),
+            created_at: created_at.to_string(),
+            is_favorited: false,
+            is_concealed: false,
+            code_language: None,
+            use_count,
+            last_used_at: None,
+            created_at_relative: None,
+        };
+
+        let fresh = frecency_rank(&item("2024-01-10T00:00:00", 0), now);
+        let one_half_life = frecency_rank(&item("2024-01-07T00:00:00", 1), now);
+        assert!((fresh - one_half_life).abs() < 1e-9, "double the uses offsets one half-life");
+        assert!(frecency_rank(&item("2024-01-09T00:00:00", 0), now) < fresh);
+        assert!(frecency_rank(&item("bad", 100), now) < fresh);
+
+        // A recent copy-back counts as recent use even for an old item
+        let reused = ClipboardItem { last_used_at: Some("2024-01-10T00:00:00".to_string()), ..item("2024-01-01T00:00:00", 0) };
+        assert!((frecency_rank(&reused, now) - fresh).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_into_preview() {
+        let conn = Connection::open_in_memory().unwrap();
+        create_history_table(&conn).unwrap();
+        save_item(&conn, "text", "héllo wörld", "h1").unwrap();
+        save_item(&conn, "file", "[\"/tmp/a\",\"/tmp/b\"]", "h2").unwrap();
+        let items = get_history(&conn, 10, None).unwrap();
+        let text = items.iter().find(|i| i.item_type == "text").unwrap();
+        let file = items.iter().find(|i| i.item_type == "file").unwrap();
+
+        // Cut on a character boundary, not in the middle of "ö"
+        let preview = text.clone().into_preview(8);
+        assert_eq!(preview.item.content, "héllo wö");
+        assert!(preview.truncated);
+        assert_eq!(preview.item.id, text.id);
+
+        let short = text.clone().into_preview(100);
+        assert_eq!(short.item.content, "héllo wörld");
+        assert!(!short.truncated);
+        assert!(!text.clone().into_preview(0).truncated);
+
+        // File lists stay parseable
+        let files = file.clone().into_preview(3);
+        assert_eq!(files.item.content, file.content);
+        assert!(!files.truncated);
+
+        let json = serde_json::to_value(&preview).unwrap();
+        assert_eq!(json["content"], "héllo wö");
+        assert_eq!(json["truncated"], true);
+    }
+
+    #[test]
+    fn test_history_query_validate() {
+        assert!(HistoryQuery::default().validate().is_ok());
+        assert!(HistoryQuery { tag: Some("code".to_string()), ..Default::default() }.validate().is_ok());
+        assert!(HistoryQuery { tag: Some("work".to_string()), ..Default::default() }.validate().is_err());
+        assert!(HistoryQuery { sort: Some("random".to_string()), ..Default::default() }.validate().is_err());
+    }
+
+    // ========== find_text_duplicates tests ==========
+
+    #[test]
+    fn test_find_text_duplicates() {
+        let conn = setup_test_db();
+        let a = save_item(&conn, "text", "Hello  World", "h1").unwrap().id();
+        save_item(&conn, "text", "unique", "h2").unwrap();
+        let b = save_item(&conn, "text", "hello world\n", "h3").unwrap().id();
+        save_item(&conn, "image", "images/x.png", "h4").unwrap();
+        let c = save_item(&conn, "text", "  HELLO\tWORLD", "h5").unwrap().id();
+        let d = save_item(&conn, "text", "x", "h6").unwrap().id();
+        let e = save_item(&conn, "text", "X ", "h7").unwrap().id();
+
+        let groups = find_text_duplicates(&conn).unwrap();
+        assert_eq!(groups, vec![vec![e, d], vec![c, b, a]]);
+    }
+
+    #[test]
+    fn test_find_similar_images() {
+        let conn = setup_test_db();
+        let a = save_item(&conn, "image", "images/a.png", "h1").unwrap().id();
+        let b = save_item(&conn, "image", "images/b.png", "h2").unwrap().id();
+        let c = save_item(&conn, "image", "images/c.png", "h3").unwrap().id();
+        let d = save_item(&conn, "image", "images/d.png", "h4").unwrap().id();
+        let e = save_item(&conn, "image", "images/e.png", "h5").unwrap().id();
+        save_item(&conn, "text", "not an image", "h6").unwrap();
+        save_item(&conn, "image", "images/f.png", "h7").unwrap();
+        assert_eq!(get_images_without_phash(&conn).unwrap().len(), 6);
+
+        // An image that failed to decode isn't offered for hashing again
+        set_image_phash_failed(&conn, e).unwrap();
+        assert_eq!(get_images_without_phash(&conn).unwrap().len(), 5);
+
+        // a-b and b-c are 2 bits apart (a-c is 4); d is far from all of them
+        set_image_phash(&conn, a, 0b0000).unwrap();
+        set_image_phash(&conn, b, 0b0011).unwrap();
+        set_image_phash(&conn, c, 0b1111).unwrap();
+        set_image_phash(&conn, d, u64::MAX).unwrap();
+        assert_eq!(get_images_without_phash(&conn).unwrap().len(), 1);
+
+        assert_eq!(find_similar_images(&conn, 2, 100).unwrap(), vec![vec![c, b, a]]);
+        assert!(find_similar_images(&conn, 1, 100).unwrap().is_empty());
+        assert_eq!(find_similar_images(&conn, 2, 2).unwrap(), Vec::<Vec<i64>>::new());
+    }
+
     // ========== delete_item tests ==========
 
     #[test]
@@ -500,12 +1640,13 @@ mod tests {
 
         let item = save_item(&conn, "text", "To be deleted", "delete_hash")
             .expect("Failed to save item")
+            .inserted()
             .unwrap();
 
         let deleted = delete_item(&conn, item.id).expect("Failed to delete item");
         assert!(deleted);
 
-        let items = get_history(&conn, 10).expect("Failed to get history");
+        let items = get_history(&conn, 10, None).expect("Failed to get history");
         assert!(items.is_empty());
     }
 
@@ -521,21 +1662,126 @@ mod tests {
     fn test_delete_item_specific() {
         let conn = setup_test_db();
 
-        let item1 = save_item(&conn, "text", "Item 1", "hash1").unwrap().unwrap();
-        let item2 = save_item(&conn, "text", "Item 2", "hash2").unwrap().unwrap();
-        let item3 = save_item(&conn, "text", "Item 3", "hash3").unwrap().unwrap();
+        let item1 = save_item(&conn, "text", "Item 1", "hash1").unwrap().inserted().unwrap();
+        let item2 = save_item(&conn, "text", "Item 2", "hash2").unwrap().inserted().unwrap();
+        let item3 = save_item(&conn, "text", "Item 3", "hash3").unwrap().inserted().unwrap();
 
         // Delete middle item
         let deleted = delete_item(&conn, item2.id).expect("Failed to delete item");
         assert!(deleted);
 
-        let items = get_history(&conn, 10).expect("Failed to get history");
+        let items = get_history(&conn, 10, None).expect("Failed to get history");
         assert_eq!(items.len(), 2);
         assert!(items.iter().any(|i| i.id == item1.id));
         assert!(items.iter().any(|i| i.id == item3.id));
         assert!(!items.iter().any(|i| i.id == item2.id));
     }
 
+    // ========== toggle_conceal tests ==========
+
+    #[test]
+    fn test_toggle_conceal() {
+        let conn = setup_test_db();
+
+        let item = save_item(&conn, "text", "secret", "conceal_hash").unwrap().inserted().unwrap();
+        assert!(!item.is_concealed);
+
+        assert!(toggle_conceal(&conn, item.id).expect("Failed to toggle conceal"));
+        let items = get_history(&conn, 10, None).unwrap();
+        assert!(items[0].is_concealed);
+
+        assert!(!toggle_conceal(&conn, item.id).expect("Failed to toggle conceal"));
+        let items = get_history(&conn, 10, None).unwrap();
+        assert!(!items[0].is_concealed);
+    }
+
+    #[test]
+    fn test_format_relative_time() {
+        let now = chrono::NaiveDateTime::parse_from_str("2024-03-10T12:00:00", "%Y-%m-%dT%H:%M:%S").unwrap();
+        let rel = |created_at: &str| format_relative_time(created_at, now);
+
+        assert_eq!(rel("2024-03-10T11:59:30").as_deref(), Some("Just now"));
+        // Slightly in the future (clock skew) still reads as "Just now"
+        assert_eq!(rel("2024-03-10T12:00:05").as_deref(), Some("Just now"));
+        assert_eq!(rel("2024-03-10T11:55:00").as_deref(), Some("5 min ago"));
+        assert_eq!(rel("2024-03-10T11:00:00").as_deref(), Some("1 hour ago"));
+        assert_eq!(rel("2024-03-10T09:00:00").as_deref(), Some("3 hours ago"));
+        assert_eq!(rel("2024-03-09T10:00:00").as_deref(), Some("Yesterday"));
+        assert_eq!(rel("2024-03-07 12:00:00").as_deref(), Some("3 days ago"));
+        assert_eq!(rel("2024-02-01T08:00:00").as_deref(), Some("2024-02-01"));
+        assert_eq!(rel("not a date"), None);
+    }
+
+    #[test]
+    fn test_set_code_language() {
+        let conn = setup_test_db();
+
+        let item = save_item(&conn, "text", "fn main() {}", "code_hash").unwrap().inserted().unwrap();
+        assert_eq!(item.code_language, None);
+
+        set_code_language(&conn, item.id, Some("rust")).unwrap();
+        let items = get_history(&conn, 10, None).unwrap();
+        assert_eq!(items[0].code_language.as_deref(), Some("rust"));
+
+        set_code_language(&conn, item.id, None).unwrap();
+        let items = get_history(&conn, 10, None).unwrap();
+        assert_eq!(items[0].code_language, None);
+    }
+
+    #[test]
+    fn test_is_content_concealed() {
+        let conn = setup_test_db();
+
+        let item = save_item(&conn, "image", "images/abc.png", "img_hash").unwrap().inserted().unwrap();
+        assert!(!is_content_concealed(&conn, "images/abc.png").unwrap());
+
+        toggle_conceal(&conn, item.id).unwrap();
+        assert!(is_content_concealed(&conn, "images/abc.png").unwrap());
+        assert!(!is_content_concealed(&conn, "images/other.png").unwrap());
+    }
+
+    #[test]
+    fn test_inline_image_storage() {
+        let conn = setup_test_db();
+
+        let item = save_item(&conn, "image", "images/small.png", "small_hash").unwrap().inserted().unwrap();
+        assert!(!has_inline_image(&conn, "small_hash").unwrap());
+        assert_eq!(get_inline_image(&conn, "images/small.png").unwrap(), None);
+
+        set_inline_image(&conn, item.id, &[0x89, 0x50, 0x4E, 0x47]).unwrap();
+        assert!(has_inline_image(&conn, "small_hash").unwrap());
+        assert_eq!(
+            get_inline_image(&conn, "images/small.png").unwrap(),
+            Some(vec![0x89, 0x50, 0x4E, 0x47])
+        );
+
+        // Inline rows have no file to remove
+        assert!(delete_item(&conn, item.id).unwrap());
+        assert_eq!(get_inline_image(&conn, "images/small.png").unwrap(), None);
+    }
+
+    #[test]
+    fn test_prune_orphan_images() {
+        let conn = setup_test_db();
+        let dir = std::env::temp_dir().join(format!("powerclip-orphan-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+
+        save_item(&conn, "image", "images/kept.png", "kept_hash").unwrap();
+        std::fs::write(dir.join("kept.png"), b"kept").unwrap();
+        std::fs::write(dir.join("orphan.png"), b"orphan").unwrap();
+        std::fs::write(dir.join("orphan.gif"), b"orphan").unwrap();
+        std::fs::write(dir.join("notes.txt"), b"not an image").unwrap();
+
+        assert_eq!(prune_orphan_images(&conn, &dir).unwrap(), (2, 12));
+        assert!(dir.join("kept.png").exists());
+        assert!(!dir.join("orphan.png").exists());
+        assert!(!dir.join("orphan.gif").exists());
+        assert!(dir.join("notes.txt").exists());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
     // ========== cleanup_old_items tests ==========
 
     #[test]
@@ -557,10 +1803,26 @@ mod tests {
         let deleted = cleanup_old_items(&conn, 10).expect("Failed to cleanup");
         assert_eq!(deleted, 0);
 
-        let items = get_history(&conn, 100).expect("Failed to get history");
+        let items = get_history(&conn, 100, None).expect("Failed to get history");
         assert_eq!(items.len(), 5);
     }
 
+    #[test]
+    fn test_cleanup_has_no_hidden_cap() {
+        let conn = setup_test_db();
+
+        let first = save_item(&conn, "text", "Content 0", "hash0").unwrap().id();
+        for i in 1..1001 {
+            save_item(&conn, "text", &format!("Content {}", i), &format!("hash{}", i)).unwrap();
+        }
+
+        // Only the user's max_items limits history; nothing is capped at 1000
+        let deleted = cleanup_old_items(&conn, 2000).expect("Failed to cleanup");
+        assert_eq!(deleted, 0);
+        assert_eq!(get_history(&conn, 5000, None).unwrap().len(), 1001);
+        assert!(get_item_by_id(&conn, first).unwrap().is_some());
+    }
+
     #[test]
     fn test_cleanup_above_limit() {
         let conn = setup_test_db();
@@ -573,7 +1835,7 @@ mod tests {
         let deleted = cleanup_old_items(&conn, 10).expect("Failed to cleanup");
         assert_eq!(deleted, 5);
 
-        let items = get_history(&conn, 100).expect("Failed to get history");
+        let items = get_history(&conn, 100, None).expect("Failed to get history");
         assert_eq!(items.len(), 10);
     }
 
@@ -590,7 +1852,7 @@ mod tests {
         // Cleanup to keep only 3
         cleanup_old_items(&conn, 3).expect("Failed to cleanup");
 
-        let items = get_history(&conn, 100).expect("Failed to get history");
+        let items = get_history(&conn, 100, None).expect("Failed to get history");
         assert_eq!(items.len(), 3);
         // Should keep the newest items (4, 3, 2)
         assert!(items.iter().any(|i| i.content == "Content 4"));
@@ -600,6 +1862,41 @@ mod tests {
         assert!(!items.iter().any(|i| i.content == "Content 1"));
     }
 
+    #[test]
+    fn test_cleanup_items_older_than() {
+        let conn = setup_test_db();
+
+        let rows = [
+            (1, "2024-01-01T09:00:00"), // 40 days old
+            (2, "2024-01-20T09:00:00"), // 21 days old
+            (3, "2024-02-09T09:00:00"), // 1 day old
+            (4, "2023-12-01T09:00:00"), // old but favorited
+        ];
+        for (id, created_at) in rows {
+            conn.execute(
+                "INSERT INTO history (id, type, content, hash, created_at) VALUES (?1, 'text', 'x', ?1, ?2)",
+                rusqlite::params![id, created_at],
+            )
+            .unwrap();
+        }
+        toggle_favorite(&conn, 4).unwrap();
+
+        let now = chrono::NaiveDateTime::parse_from_str("2024-02-10T09:00:00", "%Y-%m-%dT%H:%M:%S").unwrap();
+
+        // 0 means no age limit
+        assert_eq!(cleanup_items_older_than(&conn, 0, now).unwrap(), 0);
+
+        assert_eq!(cleanup_items_older_than(&conn, 30, now).unwrap(), 1);
+        let mut ids: Vec<i64> = get_history(&conn, 100, None).unwrap().iter().map(|i| i.id).collect();
+        ids.sort();
+        assert_eq!(ids, vec![2, 3, 4]);
+
+        assert_eq!(cleanup_items_older_than(&conn, 7, now).unwrap(), 1);
+        let mut ids: Vec<i64> = get_history(&conn, 100, None).unwrap().iter().map(|i| i.id).collect();
+        ids.sort();
+        assert_eq!(ids, vec![3, 4]);
+    }
+
     // ========== Integration tests ==========
 
     #[test]
@@ -607,26 +1904,26 @@ mod tests {
         let conn = setup_test_db();
 
         // Add items
-        let item1 = save_item(&conn, "text", "First item", "hash1").unwrap().unwrap();
-        let item2 = save_item(&conn, "text", "Second item", "hash2").unwrap().unwrap();
-        let item3 = save_item(&conn, "text", "Third item", "hash3").unwrap().unwrap();
+        let item1 = save_item(&conn, "text", "First item", "hash1").unwrap().inserted().unwrap();
+        let item2 = save_item(&conn, "text", "Second item", "hash2").unwrap().inserted().unwrap();
+        let item3 = save_item(&conn, "text", "Third item", "hash3").unwrap().inserted().unwrap();
 
         // Get history
-        let items = get_history(&conn, 10).unwrap();
+        let items = get_history(&conn, 10, None).unwrap();
         assert_eq!(items.len(), 3);
 
         // Delete one
         delete_item(&conn, item2.id).unwrap();
 
         // Verify
-        let items = get_history(&conn, 10).unwrap();
+        let items = get_history(&conn, 10, None).unwrap();
         assert_eq!(items.len(), 2);
 
         // Update one (re-save with same hash)
         save_item(&conn, "text", "First item", "hash1").unwrap();
 
         // Verify still 2 items
-        let items = get_history(&conn, 10).unwrap();
+        let items = get_history(&conn, 10, None).unwrap();
         assert_eq!(items.len(), 2);
     }
 
@@ -639,9 +1936,9 @@ mod tests {
 
         let result = save_item(&conn, "text", special_content, &hash)
             .expect("Failed to save item with special chars");
-        assert!(result.is_some());
+        assert!(matches!(result, SaveOutcome::Inserted(_)));
 
-        let items = get_history(&conn, 10).expect("Failed to get history");
+        let items = get_history(&conn, 10, None).expect("Failed to get history");
         assert_eq!(items.len(), 1);
         assert_eq!(items[0].content, special_content);
     }
@@ -655,9 +1952,9 @@ mod tests {
 
         let result = save_item(&conn, "text", unicode_content, &hash)
             .expect("Failed to save item with unicode");
-        assert!(result.is_some());
+        assert!(matches!(result, SaveOutcome::Inserted(_)));
 
-        let items = get_history(&conn, 10).expect("Failed to get history");
+        let items = get_history(&conn, 10, None).expect("Failed to get history");
         assert_eq!(items[0].content, unicode_content);
     }
 
@@ -670,9 +1967,28 @@ mod tests {
 
         let result = save_item(&conn, "text", &large_content, &hash)
             .expect("Failed to save large item");
-        assert!(result.is_some());
+        assert!(matches!(result, SaveOutcome::Inserted(_)));
 
-        let items = get_history(&conn, 10).expect("Failed to get history");
+        let items = get_history(&conn, 10, None).expect("Failed to get history");
         assert_eq!(items[0].content.len(), 100000);
     }
+
+    #[test]
+    fn test_data_version_tracks_other_connections() {
+        let path = std::env::temp_dir().join(format!("powerclip_data_version_{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let ours = Connection::open(&path).unwrap();
+        create_history_table(&ours).unwrap();
+        let other = Connection::open(&path).unwrap();
+
+        let before = data_version(&ours).unwrap();
+        save_item(&ours, "text", "own write", "hash1").unwrap();
+        assert_eq!(data_version(&ours).unwrap(), before);
+
+        save_item(&other, "text", "external write", "hash2").unwrap();
+        assert_ne!(data_version(&ours).unwrap(), before);
+
+        drop((ours, other));
+        let _ = std::fs::remove_file(&path);
+    }
 }
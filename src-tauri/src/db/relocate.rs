@@ -0,0 +1,320 @@
+//! Data directory relocation - Copying all of PowerClip's data to a new folder
+//!
+//! The database is copied with SQLite's online backup API and checked with
+//! `PRAGMA integrity_check`; every other file is copied as is. The running app
+//! keeps using the old folder until it restarts, so the old files can only be
+//! deleted on the next start (`remove_previous_data_dir`).
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use rusqlite::{Connection, DatabaseName, OpenFlags};
+
+use crate::config::{temp_path_for, DATA_DIR_OVERRIDE_FILE, TEMP_FILE_SUFFIX};
+use crate::logger;
+
+/// Database file name inside the data directory
+const DB_FILE: &str = "clipboard.db";
+
+/// Top-level files never copied: the database and SQLite's side files (the
+/// database is copied through the backup API), the running instance's lock,
+/// and the pointer to a relocated directory.
+const SKIPPED_FILES: &[&str] = &[
+    DB_FILE,
+    "clipboard.db-wal",
+    "clipboard.db-shm",
+    "clipboard.db-journal",
+    "powerclip.lock",
+    DATA_DIR_OVERRIDE_FILE,
+];
+
+/// File in the new data directory naming the old one, whose files are deleted at the next start
+const PREVIOUS_DIR_MARKER: &str = "previous_data_dir";
+
+/// A file to copy, relative to the data directory.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DataFile {
+    pub relative: PathBuf,
+    pub size: u64,
+}
+
+/// Check that `target` can receive the data in `source`: an absolute path to
+/// an empty or missing directory that isn't `source` or inside it.
+pub fn check_target(source: &Path, target: &Path) -> Result<(), String> {
+    if !target.is_absolute() {
+        return Err(format!("{} is not an absolute path", target.display()));
+    }
+    let source = fs::canonicalize(source).unwrap_or_else(|_| source.to_path_buf());
+    let resolved = resolve_missing(target);
+    if resolved == source {
+        return Err("That folder already holds the PowerClip data".to_string());
+    }
+    if resolved.starts_with(&source) {
+        return Err("The new folder can't be inside the current data folder".to_string());
+    }
+    if target.exists() {
+        if !target.is_dir() {
+            return Err(format!("{} is not a folder", target.display()));
+        }
+        let mut entries = fs::read_dir(target).map_err(|e| format!("Can't read {}: {}", target.display(), e))?;
+        if entries.next().is_some() {
+            return Err(format!("{} is not empty", target.display()));
+        }
+    }
+    Ok(())
+}
+
+/// `path` with its longest existing ancestor canonicalized, so a missing
+/// directory compares correctly against canonical paths.
+fn resolve_missing(path: &Path) -> PathBuf {
+    let mut missing = Vec::new();
+    let mut current = path;
+    loop {
+        if let Ok(canonical) = fs::canonicalize(current) {
+            return missing.iter().rev().fold(canonical, |path, name| path.join(name));
+        }
+        match (current.parent(), current.file_name()) {
+            (Some(parent), Some(name)) => {
+                missing.push(name.to_os_string());
+                current = parent;
+            }
+            _ => return path.to_path_buf(),
+        }
+    }
+}
+
+/// Every file in `source` to copy, except the database and `SKIPPED_FILES`.
+/// Leftover temp files and anything that isn't a regular file or directory are skipped too.
+pub fn list_data_files(source: &Path) -> std::io::Result<Vec<DataFile>> {
+    let mut files = Vec::new();
+    collect_files(source, Path::new(""), &mut files)?;
+    files.sort_by(|a, b| a.relative.cmp(&b.relative));
+    Ok(files)
+}
+
+fn collect_files(root: &Path, relative: &Path, files: &mut Vec<DataFile>) -> std::io::Result<()> {
+    for entry in fs::read_dir(root.join(relative))? {
+        let entry = entry?;
+        let name = entry.file_name();
+        let path = relative.join(&name);
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            collect_files(root, &path, files)?;
+        } else if file_type.is_file() {
+            let top_level = relative.as_os_str().is_empty();
+            let name = name.to_string_lossy();
+            if (top_level && SKIPPED_FILES.contains(&name.as_ref())) || name.ends_with(TEMP_FILE_SUFFIX) {
+                continue;
+            }
+            files.push(DataFile { relative: path, size: entry.metadata()?.len() });
+        }
+    }
+    Ok(())
+}
+
+/// Copy `files` from `source` to `target`, calling `progress` with the bytes copied so far.
+pub fn copy_data_files(
+    source: &Path,
+    target: &Path,
+    files: &[DataFile],
+    mut progress: impl FnMut(u64),
+) -> Result<u64, String> {
+    let mut copied = 0;
+    for file in files {
+        let to = target.join(&file.relative);
+        if let Some(parent) = to.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+        }
+        let bytes = fs::copy(source.join(&file.relative), &to)
+            .map_err(|e| format!("Failed to copy {}: {}", file.relative.display(), e))?;
+        let written = fs::metadata(&to).map(|m| m.len()).unwrap_or(0);
+        if written != bytes {
+            return Err(format!("{} was not copied completely", file.relative.display()));
+        }
+        copied += bytes;
+        progress(copied);
+    }
+    Ok(copied)
+}
+
+/// Copy the database to `target/clipboard.db` and verify the copy.
+///
+/// Returns the number of history items in the copy.
+pub fn copy_database(conn: &Connection, target: &Path) -> Result<i64, String> {
+    let path = target.join(DB_FILE);
+    let temp_path = temp_path_for(&path);
+
+    if let Err(e) = conn.backup(DatabaseName::Main, &temp_path, None) {
+        let _ = fs::remove_file(&temp_path);
+        return Err(format!("Failed to copy database: {}", e));
+    }
+    let verified = verify_database(conn, &temp_path);
+    if let Err(e) = verified {
+        let _ = fs::remove_file(&temp_path);
+        return Err(e);
+    }
+    fs::rename(&temp_path, &path).map_err(|e| format!("Failed to finalize database copy: {}", e))?;
+    verified
+}
+
+/// Check that the copy at `path` passes an integrity check and holds as many history items as `conn`.
+fn verify_database(conn: &Connection, path: &Path) -> Result<i64, String> {
+    let count_items = |conn: &Connection| {
+        conn.query_row("SELECT COUNT(*) FROM history", [], |row| row.get::<_, i64>(0))
+    };
+    let copy = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|e| format!("Failed to open database copy: {}", e))?;
+
+    let integrity: String = copy
+        .query_row("PRAGMA integrity_check", [], |row| row.get(0))
+        .map_err(|e| format!("Failed to check database copy: {}", e))?;
+    if integrity != "ok" {
+        return Err(format!("Database copy is damaged: {}", integrity));
+    }
+
+    let expected = count_items(conn).map_err(|e| e.to_string())?;
+    let copied = count_items(&copy).map_err(|e| format!("Failed to read database copy: {}", e))?;
+    if copied != expected {
+        return Err(format!("Database copy has {} of {} history items", copied, expected));
+    }
+    Ok(copied)
+}
+
+/// Ask for the files of `previous` to be deleted when the app next starts from `target`.
+pub fn schedule_previous_removal(target: &Path, previous: &Path) -> Result<(), String> {
+    let previous = previous.to_str().ok_or("The current data folder path is not valid UTF-8")?;
+    crate::config::write_atomic(&target.join(PREVIOUS_DIR_MARKER), previous.as_bytes())
+        .map_err(|e| format!("Failed to record the old data folder: {}", e))
+}
+
+/// Delete the old data directory's files after a move with `remove_old`.
+///
+/// Only files that were copied into `data_dir` are deleted, so nothing is lost
+/// if the move was interrupted; folders left empty are removed too.
+/// Returns the number of files deleted.
+pub fn remove_previous_data_dir(data_dir: &Path) -> usize {
+    let marker = data_dir.join(PREVIOUS_DIR_MARKER);
+    let Ok(previous) = fs::read_to_string(&marker) else {
+        return 0;
+    };
+    let _ = fs::remove_file(&marker);
+    let previous = PathBuf::from(previous.trim());
+    if previous.as_os_str().is_empty() || previous == data_dir || data_dir.starts_with(&previous) {
+        return 0;
+    }
+
+    let mut files: Vec<PathBuf> = match list_data_files(&previous) {
+        Ok(files) => files.into_iter().map(|file| file.relative).collect(),
+        Err(e) => {
+            logger::warning("Database", &format!("Can't read old data folder {:?}: {}", previous, e));
+            return 0;
+        }
+    };
+    files.extend(["clipboard.db", "clipboard.db-wal", "clipboard.db-shm"].map(PathBuf::from));
+
+    let mut removed = 0;
+    for relative in &files {
+        let copied = if relative == Path::new(DB_FILE) || relative.starts_with("clipboard.db-") {
+            data_dir.join(DB_FILE).exists()
+        } else {
+            data_dir.join(relative).exists()
+        };
+        if copied && fs::remove_file(previous.join(relative)).is_ok() {
+            removed += 1;
+        }
+    }
+    remove_empty_dirs(&previous);
+
+    logger::info("Database", &format!("Deleted {} files from the old data folder {:?}", removed, previous));
+    removed
+}
+
+/// Remove `dir` and its subdirectories if they hold no files.
+fn remove_empty_dirs(dir: &Path) {
+    if let Ok(entries) = fs::read_dir(dir) {
+        for entry in entries.flatten() {
+            if entry.file_type().is_ok_and(|t| t.is_dir()) {
+                remove_empty_dirs(&entry.path());
+            }
+        }
+    }
+    // Fails while anything is left, which is what we want
+    let _ = fs::remove_dir(dir);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("powerclip-relocate-test-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn write(path: PathBuf, contents: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+
+    #[test]
+    fn test_check_target() {
+        let source = temp_dir("check-source");
+        fs::create_dir_all(&source).unwrap();
+        let target = temp_dir("check-target");
+
+        assert!(check_target(&source, Path::new("relative/dir")).is_err());
+        assert!(check_target(&source, &source).unwrap_err().contains("already"));
+        assert!(check_target(&source, &source.join("nested")).unwrap_err().contains("inside"));
+        // Missing and empty folders are fine, others aren't
+        assert!(check_target(&source, &target).is_ok());
+        fs::create_dir_all(&target).unwrap();
+        assert!(check_target(&source, &target).is_ok());
+        write(target.join("file.txt"), "x");
+        assert!(check_target(&source, &target).unwrap_err().contains("not empty"));
+
+        fs::remove_dir_all(&source).unwrap();
+        fs::remove_dir_all(&target).unwrap();
+    }
+
+    #[test]
+    fn test_copy_and_remove_previous() {
+        let source = temp_dir("copy-source");
+        let target = temp_dir("copy-target");
+        write(source.join("settings.json"), "{}");
+        write(source.join("images/abc.png"), "png");
+        write(source.join("images/partial.png.tmp"), "half");
+        write(source.join("powerclip.lock"), "1234");
+        write(source.join("notes.txt"), "kept");
+
+        let conn = Connection::open(source.join(DB_FILE)).unwrap();
+        crate::db::create_history_table(&conn).unwrap();
+        crate::db::save_item(&conn, "text", "hello", "hash1").unwrap();
+
+        let files = list_data_files(&source).unwrap();
+        let names: Vec<&Path> = files.iter().map(|f| f.relative.as_path()).collect();
+        assert_eq!(names, vec![Path::new("images/abc.png"), Path::new("notes.txt"), Path::new("settings.json")]);
+
+        let mut reported = Vec::new();
+        let copied = copy_data_files(&source, &target, &files, |bytes| reported.push(bytes)).unwrap();
+        assert_eq!(copied, 9);
+        assert_eq!(reported, vec![3, 7, 9]);
+        assert_eq!(fs::read_to_string(target.join("images/abc.png")).unwrap(), "png");
+
+        assert_eq!(copy_database(&conn, &target).unwrap(), 1);
+        assert!(!temp_path_for(&target.join(DB_FILE)).exists());
+        drop(conn);
+
+        // A file added to the old folder after the copy is kept
+        write(source.join("late.txt"), "new");
+        schedule_previous_removal(&target, &source).unwrap();
+        assert_eq!(remove_previous_data_dir(&target), 4);
+        assert!(!target.join(PREVIOUS_DIR_MARKER).exists());
+        assert!(source.join("late.txt").exists());
+        assert!(!source.join("images/abc.png").exists());
+        assert!(target.join("settings.json").exists());
+
+        fs::remove_dir_all(&source).unwrap();
+        fs::remove_dir_all(&target).unwrap();
+    }
+}
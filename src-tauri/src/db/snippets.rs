@@ -34,6 +34,25 @@ pub fn get_snippets(conn: &Connection) -> Result<Vec<Snippet>, rusqlite::Error>
     Ok(items)
 }
 
+/// Get a snippet by ID.
+pub fn get_snippet(conn: &Connection, id: i64) -> Result<Option<Snippet>, rusqlite::Error> {
+    let mut stmt = conn.prepare(
+        "SELECT id, content, alias, created_at, updated_at FROM snippets WHERE id = ?",
+    )?;
+
+    let mut rows = stmt.query_map([id], |row| {
+        Ok(Snippet {
+            id: row.get(0)?,
+            content: row.get(1)?,
+            alias: row.get(2)?,
+            created_at: row.get(3)?,
+            updated_at: row.get(4)?,
+        })
+    })?;
+
+    rows.next().transpose()
+}
+
 /// Add a new snippet.
 ///
 /// Returns the created Snippet with its assigned ID.
@@ -217,6 +236,22 @@ mod tests {
         assert!(!updated); // Should return false for non-existent snippet
     }
 
+    #[test]
+    fn test_get_snippet() {
+        let conn = setup_test_db();
+
+        let snippet = add_snippet(&conn, "git status", Some("Status"))
+            .expect("Failed to add snippet");
+
+        let found = get_snippet(&conn, snippet.id)
+            .expect("Failed to get snippet")
+            .expect("Snippet should exist");
+        assert_eq!(found.content, "git status");
+        assert_eq!(found.alias.as_deref(), Some("Status"));
+
+        assert!(get_snippet(&conn, 999).expect("Failed to get snippet").is_none());
+    }
+
     #[test]
     fn test_delete_snippet() {
         let conn = setup_test_db();
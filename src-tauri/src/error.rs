@@ -0,0 +1,100 @@
+//! Errors returned to the frontend with a machine-readable code
+//!
+//! Most commands still return `Result<_, String>`. Commands whose failures the
+//! UI needs to tell apart return `PowerClipError`, which reaches JavaScript as
+//! `{ code, message }` so the frontend can branch on `code` instead of
+//! matching message text.
+
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+
+/// Error returned by commands whose failure kinds the UI handles differently.
+#[derive(Debug, thiserror::Error)]
+pub enum PowerClipError {
+    /// Semantic search is turned off in settings
+    #[error("Semantic search is not enabled")]
+    SemanticDisabled,
+    /// A newer query superseded the search, or it was cancelled
+    #[error("Search was cancelled")]
+    Cancelled,
+    /// `embedding_api_url` or `embedding_api_key` is empty
+    #[error("Embedding API is not configured")]
+    ApiNotConfigured,
+    /// The embeddings API request failed (network, HTTP or response errors)
+    #[error("{0}")]
+    Api(String),
+    #[error("Database error: {0}")]
+    Database(#[from] rusqlite::Error),
+    #[error("{0}")]
+    InvalidInput(String),
+    /// Anything else (lock poisoning, task failures, ...)
+    #[error("{0}")]
+    Internal(String),
+}
+
+impl PowerClipError {
+    /// Stable identifier the frontend branches on.
+    pub fn code(&self) -> &'static str {
+        match self {
+            PowerClipError::SemanticDisabled => "semantic_disabled",
+            PowerClipError::Cancelled => "cancelled",
+            PowerClipError::ApiNotConfigured => "api_not_configured",
+            PowerClipError::Api(_) => "api_error",
+            PowerClipError::Database(_) => "database_error",
+            PowerClipError::InvalidInput(_) => "invalid_input",
+            PowerClipError::Internal(_) => "internal_error",
+        }
+    }
+}
+
+impl Serialize for PowerClipError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("PowerClipError", 2)?;
+        state.serialize_field("code", self.code())?;
+        state.serialize_field("message", &self.to_string())?;
+        state.end()
+    }
+}
+
+/// Errors from helpers that still return `String` carry no kind.
+impl From<String> for PowerClipError {
+    fn from(message: String) -> Self {
+        PowerClipError::Internal(message)
+    }
+}
+
+impl<T> From<std::sync::PoisonError<T>> for PowerClipError {
+    fn from(e: std::sync::PoisonError<T>) -> Self {
+        PowerClipError::Internal(e.to_string())
+    }
+}
+
+impl From<tokio::task::JoinError> for PowerClipError {
+    fn from(e: tokio::task::JoinError) -> Self {
+        PowerClipError::Internal(format!("Task join error: {}", e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_serializes_code_and_message() {
+        let json = serde_json::to_value(PowerClipError::SemanticDisabled).unwrap();
+        assert_eq!(json, serde_json::json!({ "code": "semantic_disabled", "message": "Semantic search is not enabled" }));
+
+        let json = serde_json::to_value(PowerClipError::Api("API error (401): bad key".to_string())).unwrap();
+        assert_eq!(json["code"], "api_error");
+        assert_eq!(json["message"], "API error (401): bad key");
+    }
+
+    #[test]
+    fn test_conversions() {
+        let e: PowerClipError = "lock failed".to_string().into();
+        assert_eq!(e.code(), "internal_error");
+
+        let e: PowerClipError = rusqlite::Error::QueryReturnedNoRows.into();
+        assert_eq!(e.code(), "database_error");
+        assert!(e.to_string().starts_with("Database error: "));
+    }
+}
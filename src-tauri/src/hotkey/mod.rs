@@ -16,6 +16,9 @@ pub struct HotkeyState {
     pub manager: std::sync::Mutex<GlobalHotKeyManager>,
     pub current_hotkey: std::sync::Mutex<Option<HotKey>>,
     pub add_to_snippets_hotkey: std::sync::Mutex<Option<HotKey>>,
+    pub copy_latest_hotkey: std::sync::Mutex<Option<HotKey>>,
+    pub stack_pop_hotkey: std::sync::Mutex<Option<HotKey>>,
+    pub clear_clipboard_hotkey: std::sync::Mutex<Option<HotKey>>,
     pub handler_installed: std::sync::Mutex<bool>,
 }
 
@@ -36,6 +39,9 @@ impl HotkeyState {
             manager: std::sync::Mutex::new(manager),
             current_hotkey: std::sync::Mutex::new(None),
             add_to_snippets_hotkey: std::sync::Mutex::new(None),
+            copy_latest_hotkey: std::sync::Mutex::new(None),
+            stack_pop_hotkey: std::sync::Mutex::new(None),
+            clear_clipboard_hotkey: std::sync::Mutex::new(None),
             handler_installed: std::sync::Mutex::new(false),
         })
     }
@@ -139,6 +145,9 @@ fn parse_key_code(key: &str) -> Option<Code> {
 /// Active hotkey IDs for the global event handler.
 static ACTIVE_HOTKEY_ID: AtomicU32 = AtomicU32::new(0);
 static ADD_TO_SNIPPETS_HOTKEY_ID: AtomicU32 = AtomicU32::new(0);
+static COPY_LATEST_HOTKEY_ID: AtomicU32 = AtomicU32::new(0);
+static STACK_POP_HOTKEY_ID: AtomicU32 = AtomicU32::new(0);
+static CLEAR_CLIPBOARD_HOTKEY_ID: AtomicU32 = AtomicU32::new(0);
 
 /// Register a global hotkey with the given modifier and key settings.
 ///
@@ -187,12 +196,15 @@ pub fn register_hotkey_with_settings(
         GlobalHotKeyEvent::set_event_handler(Some(move |event: GlobalHotKeyEvent| {
             let main_id = ACTIVE_HOTKEY_ID.load(Ordering::SeqCst);
             let snippets_id = ADD_TO_SNIPPETS_HOTKEY_ID.load(Ordering::SeqCst);
+            let copy_latest_id = COPY_LATEST_HOTKEY_ID.load(Ordering::SeqCst);
+            let stack_pop_id = STACK_POP_HOTKEY_ID.load(Ordering::SeqCst);
+            let clear_clipboard_id = CLEAR_CLIPBOARD_HOTKEY_ID.load(Ordering::SeqCst);
 
             logger::debug(
                 "Hotkey",
                 &format!(
-                    "Event received: id={}, main_id={}, snippets_id={}, state={:?}",
-                    event.id, main_id, snippets_id, event.state
+                    "Event received: id={}, main_id={}, snippets_id={}, copy_latest_id={}, stack_pop_id={}, clear_clipboard_id={}, state={:?}",
+                    event.id, main_id, snippets_id, copy_latest_id, stack_pop_id, clear_clipboard_id, event.state
                 ),
             );
 
@@ -215,6 +227,30 @@ pub fn register_hotkey_with_settings(
                     } else {
                         logger::info("Hotkey", "No text content in clipboard, skipping add-to-snippets");
                     }
+                } else if event.id == copy_latest_id {
+                    logger::info("Hotkey", "Copy latest item hotkey triggered");
+                    let app_handle = win.app_handle().clone();
+                    tauri::async_runtime::spawn(async move {
+                        if let Err(e) = crate::commands::history::copy_latest_item(app_handle).await {
+                            logger::error("Hotkey", &format!("Failed to copy latest item: {}", e));
+                        }
+                    });
+                } else if event.id == stack_pop_id {
+                    logger::info("Hotkey", "Paste stack hotkey triggered");
+                    let app_handle = win.app_handle().clone();
+                    tauri::async_runtime::spawn(async move {
+                        if let Err(e) = crate::commands::stack::stack_pop_and_paste(app_handle).await {
+                            logger::error("Hotkey", &format!("Failed to paste from stack: {}", e));
+                        }
+                    });
+                } else if event.id == clear_clipboard_id {
+                    logger::info("Hotkey", "Clear clipboard hotkey triggered");
+                    let app_handle = win.app_handle().clone();
+                    tauri::async_runtime::spawn(async move {
+                        if let Err(e) = crate::commands::history::clear_clipboard(app_handle).await {
+                            logger::error("Hotkey", &format!("Failed to clear clipboard: {}", e));
+                        }
+                    });
                 }
             }
         }));
@@ -232,21 +268,176 @@ pub fn register_add_to_snippets_hotkey(
     modifiers: &str,
     key: &str,
 ) -> Result<(), String> {
-    let mut guard = add_to_snippets_hotkey.lock().map_err(|e| e.to_string())?;
+    register_optional_hotkey(
+        manager,
+        add_to_snippets_hotkey,
+        &ADD_TO_SNIPPETS_HOTKEY_ID,
+        "add-to-snippets",
+        enabled,
+        modifiers,
+        key,
+    )
+}
+
+/// Register the "copy latest item" hotkey.
+pub fn register_copy_latest_hotkey(
+    manager: &GlobalHotKeyManager,
+    copy_latest_hotkey: &std::sync::Mutex<Option<HotKey>>,
+    enabled: bool,
+    modifiers: &str,
+    key: &str,
+) -> Result<(), String> {
+    register_optional_hotkey(
+        manager,
+        copy_latest_hotkey,
+        &COPY_LATEST_HOTKEY_ID,
+        "copy-latest",
+        enabled,
+        modifiers,
+        key,
+    )
+}
+
+/// Register the paste stack hotkey (active only while stack mode is on).
+pub fn register_stack_pop_hotkey(
+    manager: &GlobalHotKeyManager,
+    stack_pop_hotkey: &std::sync::Mutex<Option<HotKey>>,
+    enabled: bool,
+    modifiers: &str,
+    key: &str,
+) -> Result<(), String> {
+    register_optional_hotkey(
+        manager,
+        stack_pop_hotkey,
+        &STACK_POP_HOTKEY_ID,
+        "paste-stack",
+        enabled,
+        modifiers,
+        key,
+    )
+}
+
+/// Register the clear-clipboard hotkey.
+pub fn register_clear_clipboard_hotkey(
+    manager: &GlobalHotKeyManager,
+    clear_clipboard_hotkey: &std::sync::Mutex<Option<HotKey>>,
+    enabled: bool,
+    modifiers: &str,
+    key: &str,
+) -> Result<(), String> {
+    register_optional_hotkey(
+        manager,
+        clear_clipboard_hotkey,
+        &CLEAR_CLIPBOARD_HOTKEY_ID,
+        "clear-clipboard",
+        enabled,
+        modifiers,
+        key,
+    )
+}
+
+/// Register every hotkey in `settings`, logging each failure and carrying on, so
+/// one conflicting shortcut doesn't leave the rest unregistered.
+///
+/// The main hotkey is skipped without a `window`. Returns the number of failures.
+pub fn register_all_hotkeys(
+    manager: &GlobalHotKeyManager,
+    state: &HotkeyState,
+    window: Option<&tauri::WebviewWindow>,
+    settings: &crate::app_settings::AppSettings,
+) -> usize {
+    let main = window.map(|window| {
+        register_hotkey_with_settings(
+            manager,
+            &state.current_hotkey,
+            &state.handler_installed,
+            window,
+            &settings.hotkey_modifiers,
+            &settings.hotkey_key,
+        )
+    });
+    let results = [
+        ("main", main.unwrap_or(Ok(()))),
+        (
+            "add-to-snippets",
+            register_add_to_snippets_hotkey(
+                manager,
+                &state.add_to_snippets_hotkey,
+                settings.add_to_snippets_hotkey_enabled,
+                &settings.add_to_snippets_hotkey_modifiers,
+                &settings.add_to_snippets_hotkey_key,
+            ),
+        ),
+        (
+            "copy-latest",
+            register_copy_latest_hotkey(
+                manager,
+                &state.copy_latest_hotkey,
+                settings.copy_latest_hotkey_enabled,
+                &settings.copy_latest_hotkey_modifiers,
+                &settings.copy_latest_hotkey_key,
+            ),
+        ),
+        (
+            "stack-pop",
+            register_stack_pop_hotkey(
+                manager,
+                &state.stack_pop_hotkey,
+                settings.stack_mode_enabled,
+                &settings.stack_pop_hotkey_modifiers,
+                &settings.stack_pop_hotkey_key,
+            ),
+        ),
+        (
+            "clear-clipboard",
+            register_clear_clipboard_hotkey(
+                manager,
+                &state.clear_clipboard_hotkey,
+                settings.clear_clipboard_hotkey_enabled,
+                &settings.clear_clipboard_hotkey_modifiers,
+                &settings.clear_clipboard_hotkey_key,
+            ),
+        ),
+    ];
+
+    let mut failed = 0;
+    for (name, result) in results {
+        if let Err(e) = result {
+            logger::error("Hotkey", &format!("Failed to register {} hotkey: {}", name, e));
+            failed += 1;
+        }
+    }
+    failed
+}
+
+/// Register a secondary hotkey that can be turned off, replacing its previous binding.
+///
+/// `active_id` is what the global event handler matches against; it is reset to 0
+/// while the hotkey is disabled.
+fn register_optional_hotkey(
+    manager: &GlobalHotKeyManager,
+    slot: &std::sync::Mutex<Option<HotKey>>,
+    active_id: &AtomicU32,
+    name: &str,
+    enabled: bool,
+    modifiers: &str,
+    key: &str,
+) -> Result<(), String> {
+    let mut guard = slot.lock().map_err(|e| e.to_string())?;
 
     // Unregister old hotkey if exists
     if let Some(old_hotkey) = guard.take() {
         if let Err(e) = manager.unregister(old_hotkey) {
             logger::error(
                 "Hotkey",
-                &format!("Failed to unregister old add-to-snippets hotkey: {}", e),
+                &format!("Failed to unregister old {} hotkey: {}", name, e),
             );
         }
     }
 
     if !enabled {
-        logger::info("Hotkey", "Add to snippets hotkey disabled");
-        ADD_TO_SNIPPETS_HOTKEY_ID.store(0, Ordering::SeqCst);
+        logger::info("Hotkey", &format!("{} hotkey disabled", name));
+        active_id.store(0, Ordering::SeqCst);
         return Ok(());
     }
 
@@ -256,7 +447,7 @@ pub fn register_add_to_snippets_hotkey(
 
     logger::info(
         "Hotkey",
-        &format!("Registering add-to-snippets hotkey: {}+{}", modifiers, key),
+        &format!("Registering {} hotkey: {}+{}", name, modifiers, key),
     );
 
     manager
@@ -264,13 +455,13 @@ pub fn register_add_to_snippets_hotkey(
         .map_err(|e: global_hotkey::Error| {
             logger::error(
                 "Hotkey",
-                &format!("Failed to register add-to-snippets hotkey: {}", e),
+                &format!("Failed to register {} hotkey: {}", name, e),
             );
             e.to_string()
         })?;
 
     *guard = Some(hotkey);
-    ADD_TO_SNIPPETS_HOTKEY_ID.store(hotkey.id(), Ordering::SeqCst);
+    active_id.store(hotkey.id(), Ordering::SeqCst);
 
     Ok(())
 }
@@ -0,0 +1,113 @@
+//! User idle time - How long since the last keyboard or mouse input
+//!
+//! Used by the clipboard monitor to poll less often while nobody is at the
+//! computer. Where idle time can't be read (Wayland, or Linux without
+//! `xprintidle`) the monitor keeps polling at full speed.
+
+#[cfg(target_os = "linux")]
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+/// Time since the user last pressed a key or moved the mouse, if known.
+#[cfg(target_os = "macos")]
+pub fn idle_time() -> Option<Duration> {
+    // CGEventSourceStateID / CGEventType from CoreGraphics
+    const COMBINED_SESSION_STATE: i32 = 0;
+    const ANY_INPUT_EVENT_TYPE: u32 = u32::MAX;
+
+    #[link(name = "CoreGraphics", kind = "framework")]
+    extern "C" {
+        fn CGEventSourceSecondsSinceLastEventType(state_id: i32, event_type: u32) -> f64;
+    }
+
+    let seconds = unsafe { CGEventSourceSecondsSinceLastEventType(COMBINED_SESSION_STATE, ANY_INPUT_EVENT_TYPE) };
+    (seconds.is_finite() && seconds >= 0.0).then(|| Duration::from_secs_f64(seconds))
+}
+
+#[cfg(target_os = "windows")]
+pub fn idle_time() -> Option<Duration> {
+    use windows::Win32::System::SystemInformation::GetTickCount;
+    use windows::Win32::UI::Input::KeyboardAndMouse::{GetLastInputInfo, LASTINPUTINFO};
+
+    let mut info = LASTINPUTINFO { cbSize: std::mem::size_of::<LASTINPUTINFO>() as u32, dwTime: 0 };
+    unsafe {
+        if !GetLastInputInfo(&mut info).as_bool() {
+            return None;
+        }
+        // Both are milliseconds since boot and wrap after ~49 days
+        Some(Duration::from_millis(GetTickCount().wrapping_sub(info.dwTime) as u64))
+    }
+}
+
+/// Set once `xprintidle` turned out not to be installed, so it isn't looked up on every check.
+#[cfg(target_os = "linux")]
+static XPRINTIDLE_MISSING: AtomicBool = AtomicBool::new(false);
+
+/// Reads the X11 screensaver idle time through `xprintidle`.
+#[cfg(target_os = "linux")]
+pub fn idle_time() -> Option<Duration> {
+    use std::process::{Command, Stdio};
+
+    let display = uses_x11_idle(std::env::var_os("WAYLAND_DISPLAY").is_some(), std::env::var_os("DISPLAY").is_some());
+    if !display || XPRINTIDLE_MISSING.load(Ordering::Relaxed) {
+        return None;
+    }
+    let output = match Command::new("xprintidle").stdin(Stdio::null()).stderr(Stdio::null()).output() {
+        Ok(output) => output,
+        Err(e) => {
+            if e.kind() == std::io::ErrorKind::NotFound {
+                XPRINTIDLE_MISSING.store(true, Ordering::Relaxed);
+                crate::logger::debug("Idle", "xprintidle not found; polling at full speed");
+            }
+            return None;
+        }
+    };
+    if !output.status.success() {
+        return None;
+    }
+    parse_xprintidle(&String::from_utf8_lossy(&output.stdout))
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows", target_os = "linux")))]
+pub fn idle_time() -> Option<Duration> {
+    None
+}
+
+/// Whether the X11 idle time reflects the user's input. Under Wayland, XWayland
+/// sets `DISPLAY` too but only sees input to X11 windows.
+#[cfg(target_os = "linux")]
+fn uses_x11_idle(wayland: bool, x11: bool) -> bool {
+    x11 && !wayland
+}
+
+/// Idle time from `xprintidle` output (milliseconds).
+#[cfg(target_os = "linux")]
+fn parse_xprintidle(output: &str) -> Option<Duration> {
+    output.trim().parse().ok().map(Duration::from_millis)
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_parse_xprintidle() {
+        use super::*;
+
+        assert_eq!(parse_xprintidle("1500\n"), Some(Duration::from_millis(1500)));
+        assert_eq!(parse_xprintidle("0"), Some(Duration::ZERO));
+        assert_eq!(parse_xprintidle(""), None);
+        assert_eq!(parse_xprintidle("couldn't open display"), None);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_uses_x11_idle() {
+        use super::*;
+
+        assert!(uses_x11_idle(false, true));
+        // XWayland's DISPLAY doesn't see native Wayland input
+        assert!(!uses_x11_idle(true, true));
+        assert!(!uses_x11_idle(true, false));
+        assert!(!uses_x11_idle(false, false));
+    }
+}
@@ -1,11 +1,12 @@
 //! Logger module - Simple logging to file
 //!
 //! Provides structured logging to file with configurable log levels.
+//! Callers only format and enqueue lines; a background thread does the file I/O.
 
 use std::fs::OpenOptions;
-use std::io::Write;
-use std::sync::{Mutex, OnceLock};
-use std::time::SystemTime;
+use std::io::{BufWriter, Write};
+use std::sync::{mpsc, Mutex, OnceLock};
+use std::time::{Duration, SystemTime};
 
 /// Log level enumeration
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -38,10 +39,93 @@ impl LogLevel {
     }
 }
 
+/// Log line format, chosen by the `log_format` setting
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    /// `[ts] [LEVEL] [module] message`
+    Text,
+    /// One JSON object per line with `ts`, `level`, `module`, `msg`
+    Json,
+}
+
+impl LogFormat {
+    /// Parse the `log_format` setting; anything but "json" means text
+    pub fn from_setting(value: &str) -> Self {
+        if value.eq_ignore_ascii_case("json") {
+            Self::Json
+        } else {
+            Self::Text
+        }
+    }
+
+    /// Format one log line (including the trailing newline)
+    fn format_line(self, record: &LogRecord) -> String {
+        match self {
+            LogFormat::Text => format!(
+                "[{:.3}] [#{}] [{}] [{}] [{}] {}\n",
+                record.timestamp,
+                record.seq,
+                record.level.as_str(),
+                record.thread,
+                record.module,
+                record.message
+            ),
+            LogFormat::Json => {
+                let line = serde_json::json!({
+                    "ts": (record.timestamp * 1000.0).round() / 1000.0,
+                    "seq": record.seq,
+                    "level": record.level.as_str(),
+                    "thread": record.thread,
+                    "module": record.module,
+                    "msg": record.message,
+                });
+                format!("{}\n", line)
+            }
+        }
+    }
+}
+
+/// Everything written for one log call
+struct LogRecord<'a> {
+    timestamp: f64,
+    /// Position in write order, so lines reordered by a later merge or flush stand out
+    seq: u64,
+    /// Calling thread, e.g. "Thread-3" or "Thread-main"
+    thread: String,
+    level: LogLevel,
+    module: &'a str,
+    message: &'a str,
+}
+
+/// Label for the current thread: its name if it has one, else its numeric id.
+fn thread_label() -> String {
+    let thread = std::thread::current();
+    if let Some(name) = thread.name() {
+        return format!("Thread-{}", name);
+    }
+    // `ThreadId` only exposes its number through Debug ("ThreadId(7)")
+    let id = format!("{:?}", thread.id());
+    let number = id.trim_start_matches("ThreadId(").trim_end_matches(')');
+    format!("Thread-{}", number)
+}
+
+/// Longest time `flush` waits for the writer thread to catch up
+const FLUSH_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Work for the writer thread
+enum WriterMessage {
+    Line(String),
+    /// Write everything queued so far to disk, then reply
+    Flush(mpsc::Sender<()>),
+}
+
 /// Internal logger state
 struct LoggerInner {
     level: LogLevel,
-    file: std::fs::File,
+    format: LogFormat,
+    /// Lines enqueued so far
+    seq: u64,
+    sender: mpsc::Sender<WriterMessage>,
 }
 
 /// Thread-safe logger wrapper
@@ -68,10 +152,16 @@ impl Logger {
                 .open(&log_path)
                 .expect("Failed to open log file");
 
+            let (sender, receiver) = mpsc::channel();
+            std::thread::Builder::new()
+                .name("logger".to_string())
+                .spawn(move || run_writer(file, receiver))
+                .expect("Failed to start log writer thread");
+
             let level = LogLevel::effective();
 
             Logger {
-                inner: Mutex::new(LoggerInner { level, file }),
+                inner: Mutex::new(LoggerInner { level, format: LogFormat::Text, seq: 0, sender }),
             }
         })
     }
@@ -88,19 +178,68 @@ impl Logger {
         // Format log line - use SystemTime to avoid timezone issues during shutdown
         let timestamp = SystemTime::now()
             .duration_since(SystemTime::UNIX_EPOCH)
-            .map(|d| format!("{:.3}", d.as_secs_f64()))
-            .unwrap_or_else(|_| "0.000".to_string());
-        let log_line = format!(
-            "[{}] [{}] [{}] {}\n",
+            .map(|d| d.as_secs_f64())
+            .unwrap_or(0.0);
+        inner.seq += 1;
+        let record = LogRecord {
             timestamp,
-            level.as_str(),
+            seq: inner.seq,
+            thread: thread_label(),
+            level,
             module,
-            message
-        );
+            message,
+        };
+        let log_line = inner.format.format_line(&record);
+
+        // Sent under the lock, so lines reach the file in sequence order
+        let _ = inner.sender.send(WriterMessage::Line(log_line));
+    }
 
-        // Write to file
-        let _ = inner.file.write_all(log_line.as_bytes());
-        let _ = inner.file.flush();
+    fn set_format(&self, format: LogFormat) {
+        if let Ok(mut inner) = self.inner.lock() {
+            inner.format = format;
+        }
+    }
+
+    /// Wait until everything logged so far has reached the disk
+    fn sync(&self) {
+        let (ack, done) = mpsc::channel();
+        let sent = match self.inner.lock() {
+            Ok(inner) => inner.sender.send(WriterMessage::Flush(ack)).is_ok(),
+            Err(_) => false,
+        };
+        if sent {
+            let _ = done.recv_timeout(FLUSH_TIMEOUT);
+        }
+    }
+}
+
+/// Writer thread: append queued lines to the log file.
+///
+/// Lines are buffered and flushed whenever the queue runs empty, so the file
+/// stays current without a write call per line under load.
+fn run_writer(file: std::fs::File, receiver: mpsc::Receiver<WriterMessage>) {
+    let mut out = BufWriter::new(file);
+
+    while let Ok(message) = receiver.recv() {
+        handle_message(message, &mut out);
+        while let Ok(message) = receiver.try_recv() {
+            handle_message(message, &mut out);
+        }
+        let _ = out.flush();
+    }
+}
+
+fn handle_message(message: WriterMessage, out: &mut BufWriter<std::fs::File>) {
+    match message {
+        WriterMessage::Line(line) => {
+            let _ = out.write_all(line.as_bytes());
+        }
+        WriterMessage::Flush(ack) => {
+            let _ = out.flush();
+            let _ = out.get_ref().sync_data();
+            let _ = ack.send(());
+        }
     }
 }
 
@@ -132,6 +271,16 @@ pub fn error(module: &str, message: &str) {
     Logger::global().log(LogLevel::Error, module, message);
 }
 
+/// Switch the log line format (called when settings change)
+pub fn set_format(format: LogFormat) {
+    Logger::global().set_format(format);
+}
+
+/// Flush the log file to disk (called on shutdown)
+pub fn flush() {
+    Logger::global().sync();
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -163,6 +312,51 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_log_format_lines() {
+        assert_eq!(LogFormat::from_setting("json"), LogFormat::Json);
+        assert_eq!(LogFormat::from_setting("text"), LogFormat::Text);
+        assert_eq!(LogFormat::from_setting("bogus"), LogFormat::Text);
+
+        let record = |level, message| LogRecord {
+            timestamp: 12.5,
+            seq: 42,
+            thread: "Thread-7".to_string(),
+            level,
+            module: "Main",
+            message,
+        };
+
+        let text = LogFormat::Text.format_line(&record(LogLevel::Info, "hello"));
+        assert_eq!(text, "[12.500] [#42] [INFO] [Thread-7] [Main] hello\n");
+
+        let json = LogFormat::Json.format_line(&record(LogLevel::Warning, "say \"hi\"\nagain"));
+        assert!(json.ends_with('\n'));
+        assert_eq!(json.lines().count(), 1);
+        let value: serde_json::Value = serde_json::from_str(json.trim_end()).unwrap();
+        assert_eq!(value["ts"], 12.5);
+        assert_eq!(value["seq"], 42);
+        assert_eq!(value["level"], "WARNING");
+        assert_eq!(value["thread"], "Thread-7");
+        assert_eq!(value["module"], "Main");
+        assert_eq!(value["msg"], "say \"hi\"\nagain");
+    }
+
+    #[test]
+    fn test_thread_label() {
+        let label = std::thread::spawn(thread_label).join().unwrap();
+        let number = label.strip_prefix("Thread-").unwrap();
+        assert!(number.parse::<u64>().is_ok(), "unexpected label {}", label);
+
+        let named = std::thread::Builder::new()
+            .name("monitor".to_string())
+            .spawn(thread_label)
+            .unwrap()
+            .join()
+            .unwrap();
+        assert_eq!(named, "Thread-monitor");
+    }
+
     #[test]
     fn test_log_functions_dont_panic() {
         // These should not panic even if called multiple times
@@ -170,6 +364,7 @@ mod tests {
         info("TestModule", "Test info message");
         warning("TestModule", "Test warning message");
         error("TestModule", "Test error message");
+        flush();
     }
 
     #[test]
@@ -4,16 +4,25 @@
 )]
 
 pub mod logger;
+mod cli;
 mod clipboard;
 mod commands;
 mod config;
 mod db;
+mod error;
 mod hotkey;
+mod idle;
 mod monitor;
+mod onboarding;
 mod window;
 mod app_settings;
+mod autostart;
 mod semantic;
+mod shutdown;
+mod single_instance;
+mod stack;
 mod quick_menu;
+mod tray;
 
 pub use db::DatabaseState;
 pub use hotkey::HotkeyState;
@@ -22,88 +31,37 @@ pub use quick_menu::QuickMenuState;
 use std::sync::{Arc, Mutex};
 
 use tauri::{
-    image::Image as TauriImage,
-    tray::TrayIconBuilder,
-    menu::MenuBuilder,
     Manager,
     Emitter,
-    Size, PhysicalSize,
-    Position, PhysicalPosition,
 };
 
 use crate::config::APP_NAME;
 use crate::app_settings::AppSettings;
 
-/// Tracks whether the settings dialog is open (prevents hide-on-blur).
+/// Shared runtime state: the settings dialog flag (prevents hide-on-blur)
+/// and the paste stack.
 #[derive(Clone)]
 pub struct AppState {
     pub settings_open: Arc<Mutex<bool>>,
+    pub paste_stack: Arc<Mutex<stack::PasteStack>>,
 }
 
 impl Default for AppState {
     fn default() -> Self {
         Self {
             settings_open: Arc::new(Mutex::new(false)),
+            paste_stack: Arc::new(Mutex::new(stack::PasteStack::default())),
         }
     }
 }
 
-/// Initialize system tray.
-fn setup_tray(app: &tauri::App) -> Result<(), String> {
-    let icon_data = include_bytes!("../icons/icon.png");
-    let icon = TauriImage::from_bytes(icon_data).map_err(|e| e.to_string())?;
-
-    let tray_menu = MenuBuilder::new(app)
-        .text("show", "Show Window")
-        .text("settings", "Open Settings")
-        .separator()
-        .text("quit", "Quit")
-        .build()
-        .map_err(|e| e.to_string())?;
-
-    let _tray = TrayIconBuilder::new()
-        .icon(icon)
-        .menu(&tray_menu)
-        .tooltip(format!("{} - Clipboard History", APP_NAME))
-        .show_menu_on_left_click(false)
-        .on_menu_event(move |app, event| {
-            match event.id.as_ref() {
-                "show" => {
-                    if let Some(window) = app.get_webview_window("main") {
-                        let _ = window::show_and_notify(app, &window);
-                    }
-                }
-                "settings" => {
-                    // Open settings file in default editor
-                    let settings_path = crate::config::settings_path();
-                    if let Err(e) = open::that(settings_path) {
-                        logger::error("Tray", &format!("Failed to open settings: {}", e));
-                    }
-                }
-                "quit" => std::process::exit(0),
-                _ => {}
-            }
-        })
-        .on_tray_icon_event(|tray, event| {
-            if let tauri::tray::TrayIconEvent::Click {
-                button: tauri::tray::MouseButton::Left,
-                ..
-            } = event
-            {
-                if let Some(window) = tray.app_handle().get_webview_window("main") {
-                    let _ = window::show_and_notify(tray.app_handle(), &window);
-                }
-            }
-        })
-        .build(app)
-        .map_err(|e| e.to_string())?;
-
-    Ok(())
-}
-
 /// Initialize application state, database, hotkey, tray, and window.
 fn initialize_app(app: &tauri::App) -> Result<(), String> {
     config::ensure_dirs();
+    // Only the running instance gets here, so no image write is in progress
+    commands::image::remove_partial_images(&config::images_dir());
+    // Finish a data folder move made with remove_old
+    db::relocate::remove_previous_data_dir(config::data_dir());
 
     // Database
     let conn = DatabaseState::new().map_err(|e| e.to_string())?;
@@ -121,7 +79,7 @@ fn initialize_app(app: &tauri::App) -> Result<(), String> {
     app.manage(quick_menu_state);
 
     // System tray
-    setup_tray(app)?;
+    tray::setup_tray(app)?;
 
     // Clipboard monitor event listener
     {
@@ -131,6 +89,7 @@ fn initialize_app(app: &tauri::App) -> Result<(), String> {
             let app = app_handle.clone();
             tauri::async_runtime::spawn(async move {
                 let _ = commands::history::check_clipboard(app).await;
+                monitor::finish_check();
             });
         });
     }
@@ -138,19 +97,14 @@ fn initialize_app(app: &tauri::App) -> Result<(), String> {
     // Start clipboard polling (interval is set later once settings are loaded)
     monitor::start_clipboard_monitor(app.handle().clone());
 
-    // Restore window geometry
     let window = app.get_webview_window("main").unwrap();
-    if let Ok(config) = window::config::load_window_config() {
-        let _ = window.set_size(Size::Physical(PhysicalSize::new(config.width, config.height)));
-        let _ = window.set_position(Position::Physical(PhysicalPosition::new(config.x, config.y)));
-    }
 
     // Register hotkey from saved settings
     let state = app.state::<HotkeyState>();
     let guard = state.manager.lock().map_err(|e: std::sync::PoisonError<_>| e.to_string())?;
     let (settings, settings_error) = app_settings::load_settings().unwrap_or_else(|e| {
         logger::error("Main", &format!("Failed to load settings: {}", e));
-        (AppSettings::default(), Some(e))
+        (AppSettings::default(), Some(e.into()))
     });
 
     // Emit settings error to frontend if there was an issue
@@ -159,31 +113,56 @@ fn initialize_app(app: &tauri::App) -> Result<(), String> {
         let _ = app.emit("powerclip:settings-error", error_msg.clone());
     }
 
-    hotkey::register_hotkey_with_settings(
-        &guard,
-        &state.current_hotkey,
-        &state.handler_installed,
-        &window,
-        &settings.hotkey_modifiers,
-        &settings.hotkey_key,
-    )?;
-
-    // Register add-to-snippets hotkey
-    hotkey::register_add_to_snippets_hotkey(
-        &guard,
-        &state.add_to_snippets_hotkey,
-        settings.add_to_snippets_hotkey_enabled,
-        &settings.add_to_snippets_hotkey_modifiers,
-        &settings.add_to_snippets_hotkey_key,
-    )?;
+    // The moved data folder may be on a drive that isn't connected
+    if let Some(missing) = config::missing_data_dir() {
+        let message = format!(
+            "Data folder {} not found; using {} until it is available again",
+            missing.display(),
+            config::data_dir().display()
+        );
+        logger::warning("Main", &message);
+        let _ = app.emit("powerclip:settings-error", app_settings::SettingsError::from(message));
+    }
+
+    // A shortcut taken by another app shouldn't keep PowerClip from starting
+    hotkey::register_all_hotkeys(&guard, &state, Some(&window), &settings);
 
     drop(guard);
 
-    // Apply clipboard poll interval from settings
+    // Restore window geometry
+    window::set_remember_size(settings.remember_window_size);
+    window::anchor::set_anchor(window::anchor::WindowAnchor::from_setting(&settings.window_anchor));
+    window::monitors::set_target_monitor(&settings.target_monitor);
+    window::set_remember_selection(settings.remember_selection);
+    window::restore_geometry(&window, &settings);
+
+    // Apply clipboard poll interval and capture types from settings
     monitor::set_poll_interval(settings.clipboard_poll_interval_ms);
+    monitor::set_idle_threshold_secs(settings.idle_threshold_secs);
+    monitor::set_capture_types(settings.capture_text, settings.capture_images);
+    monitor::set_normalize_line_endings(settings.normalize_line_endings);
+    monitor::set_capture_transforms(&settings.capture_transforms);
+    monitor::set_capture_rules(&settings.capture_rules);
+    commands::stack::set_stack_mode(app.handle(), settings.stack_mode_enabled);
+    monitor::set_max_image_megapixels(settings.max_image_megapixels);
+    commands::image::set_max_paste_dimension(settings.max_paste_dimension);
+    monitor::set_inline_image_max_kb(settings.inline_image_max_kb);
+    commands::history::set_preview_max_length(settings.preview_max_length);
+    tray::apply_settings(app.handle(), &settings);
+    logger::set_format(logger::LogFormat::from_setting(&settings.log_format));
 
     // Initialize semantic enabled tracker before starting settings watcher
     app_settings::init_semantic_tracker(settings.semantic_search_enabled);
+    app_settings::init_embedding_model_tracker(&settings.embedding_api_model);
+    app_settings::init_launch_at_login_tracker(settings.launch_at_login);
+
+    // Daily database backups (checks `auto_backup_enabled` on each run)
+    commands::database::start_auto_backup(app.handle().clone());
+
+    if settings.prewarm_image_cache {
+        let app_handle = app.handle().clone();
+        std::thread::spawn(move || commands::image::prewarm_image_cache(&app_handle));
+    }
 
     // Start settings file watcher
     app_settings::start_settings_watcher(app.handle().clone())?;
@@ -193,7 +172,10 @@ fn initialize_app(app: &tauri::App) -> Result<(), String> {
         let api_configured = !settings.embedding_api_key.is_empty()
             && !settings.embedding_api_url.is_empty();
 
-        let semantic_state = semantic::SemanticState::new(settings.embedding_api_dim);
+        let semantic_state = semantic::SemanticState::with_config(
+            settings.embedding_dim(),
+            settings.max_embeddings_in_memory,
+        );
 
         // Sync enabled state and API configuration from settings
         if let Ok(mut status) = semantic_state.status.write() {
@@ -201,33 +183,22 @@ fn initialize_app(app: &tauri::App) -> Result<(), String> {
             status.api_configured = api_configured;
         }
 
-        // Update text count and load existing embeddings
+        // Update text count
         if let Some(db_state) = app.try_state::<DatabaseState>() {
             if let Ok(conn) = db_state.conn.lock() {
                 semantic_state.update_text_count(&conn);
-
-                if settings.semantic_search_enabled {
-                    let mut index = semantic_state.index.write().unwrap();
-                    match semantic::db::load_embeddings_into_index(
-                        &conn,
-                        &mut index,
-                        settings.embedding_api_dim,
-                    ) {
-                        Ok(count) => {
-                            logger::info("Main", &format!("Loaded {} embeddings into memory", count));
-                            if let Ok(mut status) = semantic_state.status.write() {
-                                status.indexed_count = count;
-                            }
-                        }
-                        Err(e) => {
-                            logger::error("Main", &format!("Failed to load embeddings: {}", e));
-                        }
-                    }
-                }
+                semantic_state.update_dimension_mismatch_count(&conn);
             }
         }
 
         app.manage(semantic_state.clone());
+        semantic::idle::start_idle_unloader(app.handle().clone());
+
+        // Load existing embeddings in the background so the first search is fast
+        if settings.semantic_search_enabled {
+            let app_handle = app.handle().clone();
+            std::thread::spawn(move || semantic::idle::warm_index(&app_handle));
+        }
 
         logger::info("Main", &format!(
             "Semantic search initialized (enabled={}, api_configured={})",
@@ -247,6 +218,16 @@ fn initialize_app(app: &tauri::App) -> Result<(), String> {
     // Window behavior
     window::setup_window_behavior(app)?;
     window::setup_window_transparency(app)?;
+    if let Err(e) = window::apply_opacity(&window, settings.window_opacity) {
+        logger::warning("Main", &format!("Failed to apply window opacity: {}", e));
+    }
+
+    // Show the window on first launch so the welcome tour is visible
+    // (`powerclip:first-run` is emitted once the page has loaded)
+    if onboarding::is_first_run() {
+        logger::info("Main", "First run, showing onboarding");
+        let _ = window::show_and_notify(app.handle(), &window);
+    }
 
     logger::info("Main", "Initialization complete");
     Ok(())
@@ -260,46 +241,116 @@ async fn main() {
     #[cfg(target_os = "macos")]
     std::env::set_var("OBJC2_DEBUG", "0");
 
+    // Headless command-line operations exit here, before any window exists
+    if let Some(code) = cli::run_from_args(std::env::args().skip(1)) {
+        logger::flush();
+        std::process::exit(code);
+    }
+
     logger::info("Main", &format!("=== {} Starting ===", APP_NAME));
 
+    config::ensure_dirs();
+    let instance_listener = match single_instance::acquire() {
+        Ok(single_instance::Instance::Primary(listener)) => Some(listener),
+        Ok(single_instance::Instance::Secondary) => {
+            logger::flush();
+            return;
+        }
+        Err(e) => {
+            logger::warning("Main", &format!("Single-instance check failed, starting anyway: {}", e));
+            None
+        }
+    };
+
     tauri::Builder::default()
         .plugin(tauri_plugin_shell::init())
-        .setup(|app| {
+        .setup(move |app| {
             initialize_app(app)?;
+            // Launches made during setup wait in the listener's backlog and are shown now
+            if let Some(listener) = instance_listener {
+                single_instance::serve(app.handle().clone(), listener);
+            }
             Ok(())
         })
+        .on_page_load(|webview, payload| {
+            // Emitting from setup would fire before the frontend is listening
+            if webview.label() == "main"
+                && payload.event() == tauri::webview::PageLoadEvent::Finished
+                && onboarding::is_first_run()
+            {
+                let _ = webview.emit("powerclip:first-run", ());
+            }
+        })
         .invoke_handler(tauri::generate_handler![
             commands::history::get_history,
+            commands::history::get_item,
+            commands::history::get_history_previews,
             commands::history::get_history_by_type,
             commands::history::get_history_since,
             commands::history::get_history_filtered,
+            commands::history::query_history,
             commands::history::copy_to_clipboard,
+            commands::history::set_clipboard,
+            commands::history::export_item,
+            commands::history::select_item,
+            commands::history::copy_latest_item,
+            commands::history::clear_clipboard,
+            commands::history::copy_by_position,
+            commands::history::find_duplicates,
+            commands::history::group_similar_images,
+            commands::stack::stack_push,
+            commands::stack::stack_pop_and_paste,
+            commands::stack::stack_clear,
             commands::history::check_clipboard,
+            commands::history::list_clipboard_formats,
             commands::history::delete_history_item,
             commands::history::toggle_favorite,
+            commands::history::toggle_conceal,
             commands::image::get_image_asset_url,
             commands::paste::simulate_paste,
+            commands::paste::check_accessibility_permission,
+            commands::paste::open_accessibility_settings,
             commands::settings::get_settings,
             commands::settings::save_settings,
+            commands::settings::reset_settings,
             commands::settings::set_settings_dialog_open,
             commands::settings::get_settings_path,
             commands::settings::open_settings_file,
+            commands::settings::open_data_dir,
+            commands::settings::open_images_dir,
+            commands::database::backup_database,
+            commands::database::compact_database,
+            commands::database::migrate_data_dir,
+            commands::diagnostics::run_diagnostics,
+            commands::settings::set_autostart,
+            commands::settings::complete_onboarding,
             window::commands::save_window_state,
             window::commands::get_window_state,
             window::commands::move_window,
             window::commands::resize_window,
             window::commands::hide_window,
+            window::commands::set_window_opacity,
+            window::commands::get_system_theme,
+            window::commands::list_monitors,
+            window::commands::set_selected_item,
             commands::extensions::run_extension,
+            commands::extensions::kill_all_extensions,
+            commands::extensions::confirm_extension,
             semantic::commands::get_semantic_status,
             semantic::commands::semantic_search,
+            semantic::commands::cancel_semantic_search,
             semantic::commands::set_semantic_enabled,
             semantic::commands::rebuild_semantic_index,
             semantic::commands::start_bulk_indexing,
+            semantic::commands::cancel_bulk_indexing,
             semantic::commands::full_rebuild_index,
+            semantic::commands::get_indexed_item_ids,
+            semantic::commands::remove_embedding,
             commands::snippets::get_snippets,
             commands::snippets::add_snippet,
             commands::snippets::update_snippet,
             commands::snippets::delete_snippet,
+            commands::snippets::select_snippet,
             quick_menu::show_quick_menu,
             quick_menu::hide_quick_menu,
             quick_menu::quick_menu_select_next,
@@ -308,6 +359,12 @@ async fn main() {
             quick_menu::quick_menu_copy_selected,
             quick_menu::is_quick_menu_visible,
         ])
-        .run(tauri::generate_context!())
-        .expect("Fatal error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("Fatal error while building tauri application")
+        .run(|app, event| {
+            // Covers every way out (last window closed, OS logout, app.exit)
+            if let tauri::RunEvent::Exit = event {
+                shutdown::prepare(app);
+            }
+        });
 }
@@ -1,39 +1,499 @@
 //! Clipboard monitor - Background polling thread
 
-use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::sync::Mutex;
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
-use tauri::{AppHandle, Emitter};
+use tauri::{AppHandle, Emitter, Manager};
 
-use crate::config::CLIPBOARD_POLL_INTERVAL_MS;
-use crate::logger;
+use crate::config::{
+    CLIPBOARD_POLL_INTERVAL_MS, EXTERNAL_CHANGE_CHECK_INTERVAL_MS, IDLE_CHECK_INTERVAL_MS, IDLE_POLL_INTERVAL_MS,
+    MONITOR_MAX_RESTARTS, MONITOR_RESTART_RESET_MS, MONITOR_RETRY_INTERVAL_MS, MONITOR_STALL_TIMEOUT_MS,
+};
+use crate::app_settings::CaptureRule;
+use crate::clipboard::ClipboardContent;
+use crate::{db, logger};
 
 static MONITOR_RUNNING: AtomicBool = AtomicBool::new(false);
+/// Bumped when the poll thread is replaced, so a stuck one exits if it ever wakes up.
+static POLL_GENERATION: AtomicU64 = AtomicU64::new(0);
+/// Poll loop iterations, paused or not.
+static POLL_TICKS: AtomicU64 = AtomicU64::new(0);
+/// Clipboard checks requested by the poll loop, and those that finished (see `finish_check`).
+static CHECKS_SENT: AtomicU64 = AtomicU64::new(0);
+static CHECKS_DONE: AtomicU64 = AtomicU64::new(0);
+/// Clipboard checks waiting for the database lock (see `wait_for_database`).
+static CHECKS_WAITING_FOR_DB: AtomicU32 = AtomicU32::new(0);
+/// When set, clipboard changes are not recorded (toggled from the tray).
+static RECORDING_PAUSED: AtomicBool = AtomicBool::new(false);
 /// Current polling interval in milliseconds (hot-reloadable from settings).
 static POLL_INTERVAL_MS: AtomicU64 = AtomicU64::new(CLIPBOARD_POLL_INTERVAL_MS);
+/// Seconds without user input before polling slows down, 0 = never (hot-reloadable from settings).
+static IDLE_THRESHOLD_SECS: AtomicU64 = AtomicU64::new(0);
+/// Whether text / image clipboard content is recorded (hot-reloadable from settings).
+static CAPTURE_TEXT: AtomicBool = AtomicBool::new(true);
+static CAPTURE_IMAGES: AtomicBool = AtomicBool::new(true);
+/// Per-app overrides of the capture toggles (hot-reloadable from settings).
+static CAPTURE_RULES: Mutex<Vec<CaptureRule>> = Mutex::new(Vec::new());
+/// Whether stored text has its line endings normalized to `\n` (hot-reloadable from settings).
+static NORMALIZE_LINE_ENDINGS: AtomicBool = AtomicBool::new(false);
+/// Named transforms applied to captured text, in order (hot-reloadable from settings).
+static CAPTURE_TRANSFORMS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+/// Pixel limit for saved images, 0 = unlimited (hot-reloadable from settings).
+static MAX_IMAGE_PIXELS: AtomicU64 = AtomicU64::new(0);
+/// Largest encoded image stored inline in the database, 0 = never (hot-reloadable from settings).
+static INLINE_IMAGE_MAX_BYTES: AtomicU64 = AtomicU64::new(0);
 
 /// Update the clipboard polling interval (called when settings change).
 pub fn set_poll_interval(ms: u64) {
     POLL_INTERVAL_MS.store(ms, Ordering::Relaxed);
 }
 
+/// Update the idle time after which polling slows down (called when settings change).
+pub fn set_idle_threshold_secs(secs: u64) {
+    IDLE_THRESHOLD_SECS.store(secs, Ordering::Relaxed);
+}
+
+/// Update which content types are recorded (called when settings change).
+pub fn set_capture_types(text: bool, images: bool) {
+    CAPTURE_TEXT.store(text, Ordering::Relaxed);
+    CAPTURE_IMAGES.store(images, Ordering::Relaxed);
+}
+
+/// Update the per-app capture rules (called when settings change).
+pub fn set_capture_rules(rules: &[CaptureRule]) {
+    if let Ok(mut current) = CAPTURE_RULES.lock() {
+        *current = rules.to_vec();
+    }
+}
+
+/// The first capture rule for the app that put `content` on the clipboard.
+///
+/// The source app is only looked up while rules are configured.
+pub fn capture_rule_for(content: &ClipboardContent) -> Option<CaptureRule> {
+    let rules = CAPTURE_RULES.lock().ok()?;
+    if rules.is_empty() {
+        return None;
+    }
+    let source = crate::clipboard::source_app(content)?;
+    rules.iter().find(|rule| rule.matches(&source)).cloned()
+}
+
+/// Update whether stored text gets `\n` line endings (called when settings change).
+pub fn set_normalize_line_endings(enabled: bool) {
+    NORMALIZE_LINE_ENDINGS.store(enabled, Ordering::Relaxed);
+}
+
+/// Whether stored text gets `\n` line endings.
+pub fn normalizes_line_endings() -> bool {
+    NORMALIZE_LINE_ENDINGS.load(Ordering::Relaxed)
+}
+
+/// Update the transforms applied to captured text (called when settings change).
+pub fn set_capture_transforms(transforms: &[String]) {
+    let unknown = crate::clipboard::transforms::unknown(transforms);
+    if !unknown.is_empty() {
+        logger::warning("Monitor", &format!("Ignoring unknown capture transforms: {}", unknown.join(", ")));
+    }
+    if let Ok(mut current) = CAPTURE_TRANSFORMS.lock() {
+        *current = transforms.to_vec();
+    }
+}
+
+/// Transforms applied to captured text, in order.
+pub fn capture_transforms() -> Vec<String> {
+    CAPTURE_TRANSFORMS.lock().map(|t| t.clone()).unwrap_or_default()
+}
+
+/// Update the image size limit in megapixels, 0 = unlimited (called when settings change).
+pub fn set_max_image_megapixels(megapixels: f64) {
+    let pixels = (megapixels.max(0.0) * 1_000_000.0) as u64;
+    MAX_IMAGE_PIXELS.store(pixels, Ordering::Relaxed);
+}
+
+/// Maximum pixel count for saved images (0 = unlimited).
+pub fn max_image_pixels() -> u64 {
+    MAX_IMAGE_PIXELS.load(Ordering::Relaxed)
+}
+
+/// Update the inline image size limit in KB, 0 = always store files (called when settings change).
+pub fn set_inline_image_max_kb(kb: u64) {
+    INLINE_IMAGE_MAX_BYTES.store(kb.saturating_mul(1024), Ordering::Relaxed);
+}
+
+/// Whether an encoded image of `len` bytes should be stored inline in the database.
+pub fn stores_image_inline(len: usize) -> bool {
+    let max = INLINE_IMAGE_MAX_BYTES.load(Ordering::Relaxed);
+    max > 0 && len as u64 <= max
+}
+
+/// Whether text clipboard content is recorded.
+pub fn captures_text() -> bool {
+    CAPTURE_TEXT.load(Ordering::Relaxed)
+}
+
+/// Whether image clipboard content is recorded.
+pub fn captures_images() -> bool {
+    CAPTURE_IMAGES.load(Ordering::Relaxed)
+}
+
+/// Pause or resume recording clipboard changes.
+///
+/// Recording can't be resumed once the data folder has moved: new items would
+/// only be saved to the old folder.
+pub fn set_paused(paused: bool) {
+    if !paused && crate::config::data_dir_moved() {
+        logger::warning("Monitor", "Recording stays paused until PowerClip restarts with the moved data folder");
+        return;
+    }
+    RECORDING_PAUSED.store(paused, Ordering::SeqCst);
+    logger::info("Monitor", if paused { "Recording paused" } else { "Recording resumed" });
+}
+
+/// Whether recording is currently paused.
+pub fn is_paused() -> bool {
+    RECORDING_PAUSED.load(Ordering::SeqCst)
+}
+
+/// Whether the monitor thread is polling.
+pub fn is_running() -> bool {
+    MONITOR_RUNNING.load(Ordering::SeqCst)
+}
+
 /// Start the clipboard monitor thread.
 ///
 /// Polls clipboard at the configured interval (see `set_poll_interval`) and
 /// emits Tauri events to trigger the actual clipboard check on the main thread.
+/// Also emits `powerclip:history-changed` when another program writes to the
+/// database (see `ExternalChangeWatch`).
+///
+/// A watchdog thread checks that polls keep running and that each check
+/// finishes. If the monitor stalls, `powerclip:monitor-stopped` is emitted and
+/// polling is restarted (see `RestartBudget`).
+/// Panics can't be recovered from: release builds abort on panic.
 pub fn start_clipboard_monitor(app: AppHandle) {
     if MONITOR_RUNNING.swap(true, Ordering::SeqCst) {
         logger::warning("Monitor", "Clipboard monitor already running");
         return;
     }
 
-    thread::spawn(move || {
-        logger::info("Monitor", &format!("Started (interval: {}ms)", POLL_INTERVAL_MS.load(Ordering::Relaxed)));
+    logger::info("Monitor", &format!("Started (interval: {}ms)", POLL_INTERVAL_MS.load(Ordering::Relaxed)));
+    spawn_poll_thread(app.clone());
+    thread::spawn(move || watch_monitor(&app));
+}
+
+/// Start a poll thread, retiring any previous one.
+fn spawn_poll_thread(app: AppHandle) {
+    let generation = POLL_GENERATION.fetch_add(1, Ordering::SeqCst) + 1;
+    thread::spawn(move || poll_clipboard(&app, generation));
+}
+
+/// Poll until the monitor is stopped or this thread is replaced.
+fn poll_clipboard(app: &AppHandle, generation: u64) {
+    let mut external_changes = ExternalChangeWatch::default();
+    let mut idle = IdleWatch::default();
+    while MONITOR_RUNNING.load(Ordering::SeqCst) && POLL_GENERATION.load(Ordering::SeqCst) == generation {
+        thread::sleep(idle.poll_interval());
+        POLL_TICKS.fetch_add(1, Ordering::SeqCst);
+        external_changes.poll(app);
+        if is_paused() {
+            continue;
+        }
+        // One check at a time, so a stuck check doesn't pile up more behind it
+        if CHECKS_SENT.load(Ordering::SeqCst) != CHECKS_DONE.load(Ordering::SeqCst) {
+            continue;
+        }
+        CHECKS_SENT.fetch_add(1, Ordering::SeqCst);
+        if app.emit("powerclip:check-clipboard", ()).is_err() {
+            finish_check();
+        }
+    }
+}
+
+/// Record that a clipboard check requested by the poll loop finished, whatever its outcome.
+pub fn finish_check() {
+    CHECKS_DONE.fetch_add(1, Ordering::SeqCst);
+}
+
+/// Run `lock` (taking the database lock) for a clipboard check.
+///
+/// The watchdog doesn't count the wait as a stall: a VACUUM or backup can
+/// hold the database longer than `MONITOR_STALL_TIMEOUT_MS`.
+pub fn wait_for_database<T>(lock: impl FnOnce() -> T) -> T {
+    CHECKS_WAITING_FOR_DB.fetch_add(1, Ordering::SeqCst);
+    let result = lock();
+    CHECKS_WAITING_FOR_DB.fetch_sub(1, Ordering::SeqCst);
+    result
+}
+
+/// Restart polling whenever the monitor stops making progress.
+fn watch_monitor(app: &AppHandle) {
+    let mut last = Liveness::now();
+    let mut budget = RestartBudget::default();
+    // Whether the user was told restarts are being spaced out
+    let mut reported = false;
+    while MONITOR_RUNNING.load(Ordering::SeqCst) {
+        // Never shorter than a few polls, however slow the configured interval
+        let poll_ms = POLL_INTERVAL_MS.load(Ordering::Relaxed).max(IDLE_POLL_INTERVAL_MS);
+        thread::sleep(Duration::from_millis(MONITOR_STALL_TIMEOUT_MS.max(poll_ms * 3)));
+        if !MONITOR_RUNNING.load(Ordering::SeqCst) {
+            break;
+        }
+
+        let now = Liveness::now();
+        let Some(message) = now.stall_since(&last) else {
+            budget.healthy(Instant::now());
+            last = now;
+            continue;
+        };
+
+        let restarting = budget.stall(Instant::now());
+        if !restarting {
+            // Still stalled; tell the user once, then keep watching until a retry is due
+            if !reported {
+                let retry_mins = MONITOR_RETRY_INTERVAL_MS / 60_000;
+                logger::error(
+                    "Monitor",
+                    &format!("Clipboard monitor stalled: {} (retrying every {} minutes)", message, retry_mins),
+                );
+                let payload = serde_json::json!({ "message": message, "restarting": false });
+                let _ = app.emit("powerclip:monitor-stopped", payload);
+                reported = true;
+            }
+            continue;
+        }
+        reported = false;
+        logger::error("Monitor", &format!("Clipboard monitor stalled: {} (restarting)", message));
+        let payload = serde_json::json!({ "message": message, "restarting": true });
+        let _ = app.emit("powerclip:monitor-stopped", payload);
+
+        // Give up on the stuck check so the new thread sends checks again
+        CHECKS_DONE.store(CHECKS_SENT.load(Ordering::SeqCst), Ordering::SeqCst);
+        spawn_poll_thread(app.clone());
+        last = Liveness::now();
+    }
+}
+
+/// Progress counters of the monitor, compared between watchdog rounds.
+#[derive(Debug, Clone, Copy)]
+struct Liveness {
+    ticks: u64,
+    sent: u64,
+    done: u64,
+    /// Whether a check is waiting for the database lock
+    waiting_for_db: bool,
+}
+
+impl Liveness {
+    fn now() -> Self {
+        Self {
+            ticks: POLL_TICKS.load(Ordering::SeqCst),
+            sent: CHECKS_SENT.load(Ordering::SeqCst),
+            done: CHECKS_DONE.load(Ordering::SeqCst),
+            waiting_for_db: CHECKS_WAITING_FOR_DB.load(Ordering::SeqCst) > 0,
+        }
+    }
+
+    /// Why the monitor made no progress since `earlier`, if it didn't: the poll
+    /// loop stopped running, or a check pending then still hasn't finished and
+    /// isn't just waiting for the database.
+    fn stall_since(&self, earlier: &Liveness) -> Option<String> {
+        if self.ticks == earlier.ticks {
+            Some("polling stopped responding".to_string())
+        } else if earlier.sent > earlier.done && self.done == earlier.done && !self.waiting_for_db {
+            Some("a clipboard check stopped responding".to_string())
+        } else {
+            None
+        }
+    }
+}
+
+/// Decides when a stalled monitor is restarted: right away up to
+/// `MONITOR_MAX_RESTARTS` times, then at most every `MONITOR_RETRY_INTERVAL_MS`.
+/// Running `MONITOR_RESTART_RESET_MS` without a stall makes the quick restarts
+/// available again.
+#[derive(Debug, Default)]
+struct RestartBudget {
+    used: u32,
+    last_stall: Option<Instant>,
+    last_restart: Option<Instant>,
+}
+
+impl RestartBudget {
+    /// A watchdog round found the monitor making progress.
+    fn healthy(&mut self, now: Instant) {
+        let reset_after = Duration::from_millis(MONITOR_RESTART_RESET_MS);
+        if self.last_stall.is_some_and(|at| now.duration_since(at) >= reset_after) {
+            *self = Self::default();
+        }
+    }
+
+    /// A watchdog round found the monitor stalled; returns whether to restart it now.
+    fn stall(&mut self, now: Instant) -> bool {
+        self.last_stall = Some(now);
+        let retry_after = Duration::from_millis(MONITOR_RETRY_INTERVAL_MS);
+        let retry_due = self.last_restart.is_none_or(|at| now.duration_since(at) >= retry_after);
+        if self.used >= MONITOR_MAX_RESTARTS && !retry_due {
+            return false;
+        }
+        self.used = self.used.saturating_add(1);
+        self.last_restart = Some(now);
+        true
+    }
+}
+
+/// Poll interval in milliseconds: `base_ms`, or `IDLE_POLL_INTERVAL_MS` if slower
+/// once the user has been idle for `threshold_secs` (0 = never slow down).
+fn effective_poll_interval(base_ms: u64, idle: Option<Duration>, threshold_secs: u64) -> u64 {
+    let away = threshold_secs > 0 && idle.is_some_and(|idle| idle.as_secs() >= threshold_secs);
+    if away {
+        base_ms.max(IDLE_POLL_INTERVAL_MS)
+    } else {
+        base_ms
+    }
+}
+
+/// Slows polling while the user is away, reading the idle time at most
+/// every `IDLE_CHECK_INTERVAL_MS`.
+#[derive(Default)]
+struct IdleWatch {
+    idle: Option<Duration>,
+    last_check: Option<Instant>,
+    throttled: bool,
+}
+
+impl IdleWatch {
+    /// How long to sleep before the next poll.
+    fn poll_interval(&mut self) -> Duration {
+        let base = POLL_INTERVAL_MS.load(Ordering::Relaxed);
+        let threshold = IDLE_THRESHOLD_SECS.load(Ordering::Relaxed);
 
-        while MONITOR_RUNNING.load(Ordering::SeqCst) {
-            thread::sleep(Duration::from_millis(POLL_INTERVAL_MS.load(Ordering::Relaxed)));
-            let _ = app.emit("powerclip:check-clipboard", ());
+        let interval = Duration::from_millis(IDLE_CHECK_INTERVAL_MS);
+        if threshold > 0 && !self.last_check.is_some_and(|checked| checked.elapsed() < interval) {
+            self.last_check = Some(Instant::now());
+            self.idle = crate::idle::idle_time();
         }
-    });
+
+        let ms = effective_poll_interval(base, self.idle, threshold);
+        let throttled = ms != base;
+        if throttled != self.throttled {
+            self.throttled = throttled;
+            let state = if throttled { "User idle, polling less often" } else { "User active, polling at full speed" };
+            logger::debug("Monitor", state);
+        }
+        Duration::from_millis(ms)
+    }
+}
+
+/// Notices commits to the database made by other programs (such as
+/// `powerclip --search` or a SQLite browser) through `PRAGMA data_version`.
+///
+/// Only writes through other connections are detected; the app's own writes
+/// never change the version, and send their own events.
+#[derive(Default)]
+struct ExternalChangeWatch {
+    version: Option<i64>,
+    last_check: Option<Instant>,
+}
+
+impl ExternalChangeWatch {
+    /// Emit `powerclip:history-changed` if another connection committed since the last check.
+    fn poll(&mut self, app: &AppHandle) {
+        let interval = Duration::from_millis(EXTERNAL_CHANGE_CHECK_INTERVAL_MS);
+        if self.last_check.is_some_and(|checked| checked.elapsed() < interval) {
+            return;
+        }
+        self.last_check = Some(Instant::now());
+
+        let Some(state) = app.try_state::<crate::DatabaseState>() else {
+            return;
+        };
+        // Don't wait behind a save in progress; check again next time
+        let Ok(conn) = state.conn.try_lock() else {
+            return;
+        };
+        let Ok(version) = db::data_version(&conn) else {
+            return;
+        };
+        drop(conn);
+
+        if self.update(version) {
+            logger::debug("Monitor", "Database changed by another program");
+            let _ = app.emit("powerclip:history-changed", ());
+        }
+    }
+
+    /// Record `version`, returning whether it differs from the previous one.
+    fn update(&mut self, version: i64) -> bool {
+        let changed = self.version.is_some_and(|previous| previous != version);
+        self.version = Some(version);
+        changed
+    }
+}
+
+/// Stop the clipboard monitor thread after its current poll.
+pub fn stop_clipboard_monitor() {
+    MONITOR_RUNNING.store(false, Ordering::SeqCst);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_effective_poll_interval() {
+        let idle = |secs| Some(Duration::from_secs(secs));
+        assert_eq!(effective_poll_interval(100, idle(5), 120), 100);
+        assert_eq!(effective_poll_interval(100, idle(120), 120), IDLE_POLL_INTERVAL_MS);
+        // Unknown idle time or a disabled threshold keeps full speed
+        assert_eq!(effective_poll_interval(100, None, 120), 100);
+        assert_eq!(effective_poll_interval(100, idle(3600), 0), 100);
+        // Never faster than the configured interval
+        assert_eq!(effective_poll_interval(5000, idle(3600), 120), 5000);
+    }
+
+    #[test]
+    fn test_stall_since() {
+        let at = |ticks, sent, done| Liveness { ticks, sent, done, waiting_for_db: false };
+
+        // Polling and checks both moving
+        assert!(at(12, 5, 5).stall_since(&at(10, 4, 3)).is_none());
+        // Paused: the loop runs without sending checks
+        assert!(at(12, 4, 4).stall_since(&at(10, 4, 4)).is_none());
+        // The loop itself hung
+        assert!(at(10, 4, 4).stall_since(&at(10, 4, 4)).is_some());
+        // A check pending last round never finished
+        let stalled = at(30, 5, 4).stall_since(&at(20, 5, 4));
+        assert_eq!(stalled.as_deref(), Some("a clipboard check stopped responding"));
+        // A check sent since then is still within its window
+        assert!(at(30, 5, 4).stall_since(&at(20, 4, 4)).is_none());
+        // A check waiting behind a VACUUM or backup
+        let waiting = Liveness { waiting_for_db: true, ..at(30, 5, 4) };
+        assert!(waiting.stall_since(&at(20, 5, 4)).is_none());
+    }
+
+    #[test]
+    fn test_restart_budget() {
+        let start = Instant::now();
+        let ms = |ms: u64| start + Duration::from_millis(ms);
+        let mut budget = RestartBudget::default();
+
+        for n in 0..MONITOR_MAX_RESTARTS as u64 {
+            assert!(budget.stall(ms(n * MONITOR_STALL_TIMEOUT_MS)));
+        }
+        // Used up: the next restarts are spaced out, never given up on
+        let exhausted = MONITOR_MAX_RESTARTS as u64 * MONITOR_STALL_TIMEOUT_MS;
+        assert!(!budget.stall(ms(exhausted)));
+        let retry = exhausted + MONITOR_RETRY_INTERVAL_MS;
+        assert!(budget.stall(ms(retry)));
+        assert!(!budget.stall(ms(retry + MONITOR_STALL_TIMEOUT_MS)));
+
+        // Healthy long enough: quick restarts are back
+        let last_stall = retry + MONITOR_STALL_TIMEOUT_MS;
+        budget.healthy(ms(last_stall + MONITOR_RESTART_RESET_MS - 1));
+        assert_eq!(budget.used, MONITOR_MAX_RESTARTS + 1);
+        budget.healthy(ms(last_stall + MONITOR_RESTART_RESET_MS));
+        assert!(budget.stall(ms(last_stall + MONITOR_RESTART_RESET_MS + MONITOR_STALL_TIMEOUT_MS)));
+    }
 }
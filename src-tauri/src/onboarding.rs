@@ -0,0 +1,20 @@
+//! Onboarding module - First-run state
+//!
+//! A marker file in the data directory records that the welcome tour was
+//! completed, so it survives settings resets.
+
+use crate::config::onboarding_marker_path;
+use crate::logger;
+
+/// Returns true until onboarding has been completed.
+pub fn is_first_run() -> bool {
+    !onboarding_marker_path().exists()
+}
+
+/// Record that onboarding was completed.
+pub fn mark_completed() -> Result<(), String> {
+    std::fs::write(onboarding_marker_path(), chrono::Local::now().to_rfc3339())
+        .map_err(|e| format!("Failed to write onboarding marker: {}", e))?;
+    logger::info("Onboarding", "Onboarding completed");
+    Ok(())
+}
@@ -7,7 +7,6 @@ use std::sync::Mutex;
 
 use tauri::{Manager, Emitter};
 
-use crate::db::ClipboardItem;
 use crate::logger;
 
 /// Quick menu state
@@ -128,41 +127,19 @@ pub fn quick_menu_get_selected(
 #[tauri::command]
 pub async fn quick_menu_copy_selected(
     app: tauri::AppHandle,
-    items: Vec<ClipboardItem>,
+    item_ids: Vec<i64>,
 ) -> Result<bool, String> {
     let state = app.state::<QuickMenuState>();
 
     let index = *state.selected_index.lock().unwrap();
 
-    if index >= items.len() {
+    let Some(&id) = item_ids.get(index) else {
         return Err("Invalid selection index".to_string());
-    }
+    };
 
-    let item = &items[index];
-
-    // Copy to clipboard
-    if item.item_type == "image" {
-        // For images, load from file
-        let relative_path = &item.content;
-        let image_path = crate::config::data_dir().join(relative_path);
-
-        use image::{ImageReader, GenericImageView};
-        let img = ImageReader::open(&image_path)
-            .map_err(|e| format!("Failed to open image: {}", e))?
-            .decode()
-            .map_err(|e| format!("Failed to decode image: {}", e))?;
-
-        let (width, height) = img.dimensions();
-        let rgba = img.to_rgba8();
-        crate::clipboard::set_clipboard_image(width, height, &rgba)
-            .map_err(|e| format!("Failed to set clipboard image: {}", e))?;
-    } else if item.item_type == "file" {
-        let paths: Vec<String> = serde_json::from_str(&item.content)
-            .map_err(|e| format!("Failed to parse file paths: {}", e))?;
-        crate::clipboard::set_clipboard_files(&paths)?;
-    } else {
-        crate::clipboard::set_clipboard_text(&item.content)?;
-    }
+    // The menu only has previews; copy the full item from the database
+    let item = crate::commands::history::load_item(&app, id)?;
+    crate::commands::history::write_item_to_clipboard(&app, &item)?;
 
     // Hide quick menu
     hide_quick_menu(app.clone()).await?;
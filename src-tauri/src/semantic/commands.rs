@@ -1,11 +1,16 @@
 //! Tauri commands for semantic search
 
+use std::collections::HashMap;
+use std::time::Duration;
+
 use serde::{Deserialize, Serialize};
 use tauri::Manager;
 
 use crate::db::ClipboardItem;
+use crate::error::PowerClipError;
 use crate::logger;
 
+use super::EmbeddingIndex;
 use super::SemanticState;
 use super::SemanticStatus;
 
@@ -14,37 +19,72 @@ use super::SemanticStatus;
 pub struct SemanticSearchResult {
     pub item: ClipboardItem,
     pub score: f32,
+    /// Sentence of a long text that best matches the query (only when requested)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snippet: Option<String>,
+}
+
+/// Fail with `Cancelled` once search `id` has been superseded.
+fn ensure_current(state: &SemanticState, id: u64) -> Result<(), PowerClipError> {
+    if state.is_search_stale(id) {
+        return Err(PowerClipError::Cancelled);
+    }
+    Ok(())
 }
 
 /// Get semantic search status
 #[tauri::command]
 pub async fn get_semantic_status(
     state: tauri::State<'_, SemanticState>,
-) -> Result<SemanticStatus, String> {
-    let status = state.status.read().map_err(|e| e.to_string())?.clone();
+) -> Result<SemanticStatus, PowerClipError> {
+    let mut status = state.status.read()?.clone();
+
+    // Read live so the figures follow loads, unloads, eviction and resizes
+    let index = state.index.read()?;
+    status.index_memory_bytes = index.memory_usage();
+    status.index_capacity = index.capacity();
+
     Ok(status)
 }
 
 /// Perform semantic search
+///
+/// `after`/`before` optionally restrict results to items created in `[after, before)`
+/// ("YYYY-MM-DD" or "YYYY-MM-DDTHH:MM:SS"). Filtered-out items don't count against `limit`.
+///
+/// With `with_snippets`, long text results also carry the best-matching sentence.
+/// This costs an extra embeddings request, so it is off by default.
+///
+/// Each call supersedes the previous one: a search that is overtaken by a newer
+/// query, or by `cancel_semantic_search`, fails with `Cancelled` at its next stage.
 #[tauri::command]
 pub async fn semantic_search(
     app: tauri::AppHandle,
     query: String,
     limit: usize,
     min_score: Option<f32>,
-) -> Result<Vec<SemanticSearchResult>, String> {
+    after: Option<String>,
+    before: Option<String>,
+    with_snippets: Option<bool>,
+) -> Result<Vec<SemanticSearchResult>, PowerClipError> {
     let state = app.state::<SemanticState>();
 
     {
-        let status = state.status.read().map_err(|e| e.to_string())?;
+        let status = state.status.read()?;
         if !status.enabled {
-            return Err("Semantic search is not enabled".to_string());
+            return Err(PowerClipError::SemanticDisabled);
         }
         if !status.api_configured {
-            return Err("Embedding API is not configured".to_string());
+            return Err(PowerClipError::ApiNotConfigured);
         }
     }
 
+    // Typing sends a query per keystroke; wait briefly so only the last one
+    // reaches the embeddings API
+    let search_id = state.begin_search();
+    tokio::time::sleep(Duration::from_millis(crate::config::SEMANTIC_SEARCH_SETTLE_MS)).await;
+    ensure_current(&state, search_id)?;
+
     let min_score = match min_score {
         Some(score) => score,
         None => {
@@ -53,17 +93,43 @@ pub async fn semantic_search(
         }
     };
 
+    // Reload the index if it was unloaded after a period of inactivity
+    state.touch();
+    {
+        let db_state = app.state::<crate::DatabaseState>();
+        let conn = db_state.conn.lock()?;
+        let loaded = state.ensure_index_loaded(&conn)?;
+        if loaded > 0 {
+            logger::info("Semantic", &format!("Reloaded {} embeddings into memory", loaded));
+        }
+    }
+    ensure_current(&state, search_id)?;
+
     // Compute query embedding (blocking API call wrapped in spawn_blocking)
     let query_embedding = tokio::task::spawn_blocking(move || {
         super::embedding::compute_embedding(&query)
     })
-    .await
-    .map_err(|e| format!("Task join error: {}", e))??;
+    .await?
+    .map_err(PowerClipError::Api)?;
+    ensure_current(&state, search_id)?;
 
-    // Search in memory index
-    let search_results = {
-        let index = state.index.read().map_err(|e| e.to_string())?;
-        index.search(&query_embedding, limit, min_score)
+    let db_state = app.state::<crate::DatabaseState>();
+
+    // Restrict candidates to the time window before ranking, so the window
+    // doesn't eat into `limit`
+    let in_range = if after.is_some() || before.is_some() {
+        let conn = db_state.conn.lock()?;
+        Some(crate::db::get_item_ids_in_range(&conn, after.as_deref(), before.as_deref())?)
+    } else {
+        None
+    };
+
+    // Search in memory index. The read lock is only held to take the
+    // snapshot, so indexing can keep writing while we scan
+    let snapshot = state.index.read()?.snapshot();
+    let search_results = match &in_range {
+        Some(ids) => snapshot.search_filtered(&query_embedding, limit, min_score, |id| ids.contains(&id)),
+        None => snapshot.search(&query_embedding, limit, min_score),
     };
 
     if search_results.is_empty() {
@@ -71,32 +137,23 @@ pub async fn semantic_search(
     }
 
     // Fetch full items from database
-    let db_state = app.state::<crate::DatabaseState>();
-    let conn = db_state.conn.lock().map_err(|e| e.to_string())?;
+    let ids: Vec<i64> = search_results.iter().map(|sr| sr.item_id).collect();
+    let mut items: HashMap<i64, ClipboardItem> = {
+        let conn = db_state.conn.lock()?;
+        crate::db::get_items_by_ids(&conn, &ids)?
+            .into_iter()
+            .map(|item| (item.id, item))
+            .collect()
+    };
 
     let mut results = Vec::with_capacity(search_results.len());
 
     for sr in search_results {
-        let item_result: Result<ClipboardItem, _> = conn.query_row(
-            "SELECT id, type, content, hash, created_at, is_favorited FROM history WHERE id = ?1",
-            [sr.item_id],
-            |row| {
-                Ok(ClipboardItem {
-                    id: row.get(0)?,
-                    item_type: row.get(1)?,
-                    content: row.get(2)?,
-                    hash: row.get(3)?,
-                    created_at: row.get(4)?,
-                    is_favorited: row.get::<_, i64>(5).unwrap_or(0) != 0,
-                })
-            },
-        );
-
-        match item_result {
-            Ok(item) => {
-                results.push(SemanticSearchResult { item, score: sr.score });
+        match items.remove(&sr.item_id) {
+            Some(item) => {
+                results.push(SemanticSearchResult { item, score: sr.score, snippet: None });
             }
-            Err(rusqlite::Error::QueryReturnedNoRows) => {
+            None => {
                 logger::warning(
                     "Semantic",
                     &format!("Item {} not found in database", sr.item_id),
@@ -105,26 +162,38 @@ pub async fn semantic_search(
                     index.remove(sr.item_id);
                 }
             }
-            Err(e) => {
-                logger::error(
-                    "Semantic",
-                    &format!("Failed to fetch item {}: {}", sr.item_id, e),
-                );
-            }
         }
     }
 
+    if with_snippets.unwrap_or(false) {
+        ensure_current(&state, search_id)?;
+        results = tokio::task::spawn_blocking(move || {
+            super::snippet::attach_snippets(&mut results, &query_embedding);
+            results
+        })
+        .await?;
+    }
+
     logger::debug("Semantic", &format!("Search returned {} results", results.len()));
     Ok(results)
 }
 
+/// Cancel the running semantic search, if any.
+///
+/// The embeddings request already in flight still completes, but its results are dropped.
+#[tauri::command]
+pub async fn cancel_semantic_search(state: tauri::State<'_, SemanticState>) -> Result<(), PowerClipError> {
+    state.cancel_search();
+    Ok(())
+}
+
 /// Toggle semantic search enabled state
 #[tauri::command]
 pub async fn set_semantic_enabled(
     state: tauri::State<'_, SemanticState>,
     enabled: bool,
-) -> Result<(), String> {
-    let mut status = state.status.write().map_err(|e| e.to_string())?;
+) -> Result<(), PowerClipError> {
+    let mut status = state.status.write()?;
     status.enabled = enabled;
     logger::info(
         "Semantic",
@@ -135,26 +204,37 @@ pub async fn set_semantic_enabled(
 
 /// Rebuild the in-memory index from database
 #[tauri::command]
-pub async fn rebuild_semantic_index(app: tauri::AppHandle) -> Result<usize, String> {
+pub async fn rebuild_semantic_index(app: tauri::AppHandle) -> Result<usize, PowerClipError> {
     let state = app.state::<SemanticState>();
     let db_state = app.state::<crate::DatabaseState>();
 
     let settings = crate::app_settings::load_settings_simple().unwrap_or_default();
 
     {
-        let mut index = state.index.write().map_err(|e| e.to_string())?;
-        index.clear();
+        // Recreate rather than clear so dimension/capacity changes from settings apply
+        let mut index = state.index.write()?;
+        *index = EmbeddingIndex::with_config(
+            settings.embedding_dim(),
+            settings.max_embeddings_in_memory,
+            settings.min_similarity_score,
+        );
     }
 
     let count = {
-        let conn = db_state.conn.lock().map_err(|e| e.to_string())?;
-        let mut index = state.index.write().map_err(|e| e.to_string())?;
-        super::db::load_embeddings_into_index(&conn, &mut index, settings.embedding_api_dim)
-            .map_err(|e| e.to_string())?
+        let conn = db_state.conn.lock()?;
+        let mut index = state.index.write()?;
+        let count = super::db::load_embeddings_into_index(&conn, &mut index, settings.embedding_dim())?;
+        state.mark_index_loaded();
+        count
     };
 
     {
-        let mut status = state.status.write().map_err(|e| e.to_string())?;
+        let conn = db_state.conn.lock()?;
+        state.update_dimension_mismatch_count(&conn);
+    }
+
+    {
+        let mut status = state.status.write()?;
         status.indexed_count = count;
     }
 
@@ -162,32 +242,100 @@ pub async fn rebuild_semantic_index(app: tauri::AppHandle) -> Result<usize, Stri
     Ok(count)
 }
 
+/// Ids of the items in the in-memory index, newest first.
+///
+/// Loads the index first if it was unloaded while idle. Items evicted to stay
+/// within `max_embeddings_in_memory` are stored but not listed.
+#[tauri::command]
+pub async fn get_indexed_item_ids(app: tauri::AppHandle) -> Result<Vec<i64>, PowerClipError> {
+    let state = app.state::<SemanticState>();
+    {
+        let db_state = app.state::<crate::DatabaseState>();
+        let conn = db_state.conn.lock()?;
+        state.ensure_index_loaded(&conn)?;
+    }
+
+    let ids = state.index.read()?.item_ids();
+    Ok(ids)
+}
+
+/// Delete the embedding of one item from the database and the in-memory index.
+///
+/// The item itself stays in history and is embedded again by the next bulk
+/// indexing pass. Returns false if the item had no embedding.
+#[tauri::command]
+pub async fn remove_embedding(app: tauri::AppHandle, id: i64) -> Result<bool, PowerClipError> {
+    let state = app.state::<SemanticState>();
+    let db_state = app.state::<crate::DatabaseState>();
+
+    // Both are changed under the database lock, so a concurrent lazy load
+    // can't bring the embedding back into the index
+    let (stored, in_index) = {
+        let conn = db_state.conn.lock()?;
+        let stored = super::db::delete_embedding(&conn, id)?;
+        (stored, state.index.write()?.remove(id))
+    };
+
+    if stored {
+        let mut status = state.status.write()?;
+        status.indexed_count = status.indexed_count.saturating_sub(1);
+    }
+    if stored || in_index {
+        logger::info("Semantic", &format!("Removed embedding of item {}", id));
+    }
+    Ok(stored || in_index)
+}
+
 /// Start bulk indexing for all items without embeddings
 #[tauri::command]
-pub async fn start_bulk_indexing(app: tauri::AppHandle) -> Result<(), String> {
+pub async fn start_bulk_indexing(app: tauri::AppHandle) -> Result<(), PowerClipError> {
     super::embedding::index_all_items(app);
     Ok(())
 }
 
+/// Cancel a running bulk indexing pass.
+///
+/// Returns false if no indexing was in progress. Items indexed so far are kept.
+#[tauri::command]
+pub async fn cancel_bulk_indexing(state: tauri::State<'_, SemanticState>) -> Result<bool, PowerClipError> {
+    let in_progress = state.status.read()?.indexing_in_progress;
+
+    if in_progress {
+        super::embedding::cancel_bulk_indexing();
+    }
+    Ok(in_progress)
+}
+
 /// Fully rebuild the semantic index (clear all embeddings and re-index everything)
+///
+/// Required whenever the embedding dimension changes, since stored vectors of
+/// the old dimension can't be compared with new queries.
 #[tauri::command]
-pub async fn full_rebuild_index(app: tauri::AppHandle) -> Result<String, String> {
+pub async fn full_rebuild_index(app: tauri::AppHandle) -> Result<String, PowerClipError> {
     let state = app.state::<SemanticState>();
     let db_state = app.state::<crate::DatabaseState>();
+    let settings = crate::app_settings::load_settings_simple().unwrap_or_default();
 
     {
-        let mut index = state.index.write().map_err(|e| e.to_string())?;
-        index.clear();
+        let mut index = state.index.write()?;
+        *index = EmbeddingIndex::with_config(
+            settings.embedding_dim(),
+            settings.max_embeddings_in_memory,
+            settings.min_similarity_score,
+        );
+        // Empty index matches the cleared table
+        state.mark_index_loaded();
     }
 
     let cleared_count = {
-        let conn = db_state.conn.lock().map_err(|e| e.to_string())?;
-        super::db::clear_all_embeddings(&conn).map_err(|e| e.to_string())?
+        let conn = db_state.conn.lock()?;
+        super::db::clear_all_embeddings(&conn)?
     };
 
     {
-        let mut status = state.status.write().map_err(|e| e.to_string())?;
+        let mut status = state.status.write()?;
         status.indexed_count = 0;
+        status.dimension_mismatch_count = 0;
     }
 
     logger::info("Semantic", &format!("Full rebuild: cleared {} embeddings", cleared_count));
@@ -60,7 +60,6 @@ pub fn get_all_embeddings(conn: &Connection) -> Result<Vec<(i64, Vec<f32>)>, rus
 }
 
 /// Delete an embedding for an item
-#[allow(dead_code)]
 pub fn delete_embedding(conn: &Connection, item_id: i64) -> Result<bool, rusqlite::Error> {
     let affected = conn.execute(
         "DELETE FROM embeddings WHERE item_id = ?1",
@@ -76,6 +75,17 @@ pub fn get_embedding_count(conn: &Connection) -> Result<usize, rusqlite::Error>
     Ok(count as usize)
 }
 
+/// Count stored embeddings whose dimension differs from `expected_dim`.
+/// These are skipped by `load_embeddings_into_index` and unsearchable until a full rebuild.
+pub fn count_dimension_mismatches(conn: &Connection, expected_dim: usize) -> Result<usize, rusqlite::Error> {
+    let count: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM embeddings WHERE dim != ?1",
+        [expected_dim as i64],
+        |row| row.get(0),
+    )?;
+    Ok(count as usize)
+}
+
 /// Clear all embeddings from the database
 /// Use when embedding dimension changes or to force re-indexing
 pub fn clear_all_embeddings(conn: &Connection) -> Result<usize, rusqlite::Error> {
@@ -318,6 +328,18 @@ mod tests {
         assert_eq!(get_embedding_count(&conn).expect("Failed to count"), 0);
     }
 
+    #[test]
+    fn test_count_dimension_mismatches() {
+        let conn = setup_test_db();
+
+        save_embedding(&conn, 1, &[1.0, 0.0]).expect("Failed to save");
+        save_embedding(&conn, 2, &[1.0, 0.0, 0.0]).expect("Failed to save");
+        save_embedding(&conn, 3, &[0.0, 1.0, 0.0]).expect("Failed to save");
+
+        assert_eq!(count_dimension_mismatches(&conn, 3).expect("Failed to count"), 1);
+        assert_eq!(count_dimension_mismatches(&conn, 2).expect("Failed to count"), 2);
+    }
+
     #[test]
     fn test_clear_all_embeddings_empty() {
         let conn = setup_test_db();
@@ -2,20 +2,74 @@
 //!
 //! Provides text embedding via an external API for semantic search.
 
-use std::sync::mpsc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Mutex};
+use std::time::{Duration, Instant};
 
-use tauri::Manager;
+use tauri::{Emitter, Manager};
 
-use crate::config::EMBEDDING_BATCH_SIZE;
+use crate::config::{
+    EMBEDDING_BATCH_SIZE, EMBEDDING_REQUEST_BATCH_SIZE, INDEXING_PROGRESS_EMIT_INTERVAL_MS,
+};
 use crate::logger;
 
 use super::SemanticState;
 
+/// Set to request that a running bulk indexing pass stops early
+static BULK_INDEXING_CANCELLED: AtomicBool = AtomicBool::new(false);
+
+/// Held for the duration of each indexing request, so incremental and bulk
+/// indexing never run more than one inference at a time. Later requests wait
+/// their turn instead of competing for CPU with the UI.
+static INFERENCE_SLOT: Mutex<()> = Mutex::new(());
+
+/// Run an indexing inference once no other one is in flight.
+///
+/// Search queries don't go through here: they are interactive and short.
+fn with_inference_slot<T>(f: impl FnOnce() -> T) -> T {
+    // The guard protects no data, so a poisoned lock is still usable
+    let _slot = INFERENCE_SLOT.lock().unwrap_or_else(|e| e.into_inner());
+    f()
+}
+
+/// Ask the running bulk indexing pass to stop after the current batch.
+pub fn cancel_bulk_indexing() {
+    BULK_INDEXING_CANCELLED.store(true, Ordering::SeqCst);
+}
+
 /// Compute an embedding for the given text using the configured API.
 ///
 /// Loads API credentials from settings on each call so that settings changes
 /// take effect without a restart.
 pub fn compute_embedding(text: &str) -> Result<Vec<f32>, String> {
+    let settings = api_settings()?;
+
+    super::api::fetch_embedding(
+        text,
+        &settings.embedding_api_url,
+        &settings.embedding_api_key,
+        &settings.embedding_api_model,
+        &settings.embedding_api_proxy,
+    )
+}
+
+/// Compute embeddings for several texts in a single API request.
+///
+/// Returned vectors are in the same order as `texts`.
+pub fn compute_embeddings(texts: &[&str]) -> Result<Vec<Vec<f32>>, String> {
+    let settings = api_settings()?;
+
+    super::api::fetch_embeddings(
+        texts,
+        &settings.embedding_api_url,
+        &settings.embedding_api_key,
+        &settings.embedding_api_model,
+        &settings.embedding_api_proxy,
+    )
+}
+
+/// Load settings and ensure the embedding API is configured.
+fn api_settings() -> Result<crate::app_settings::AppSettings, String> {
     let settings = crate::app_settings::load_settings_simple()
         .map_err(|e| format!("Failed to load settings: {}", e))?;
 
@@ -26,18 +80,14 @@ pub fn compute_embedding(text: &str) -> Result<Vec<f32>, String> {
         );
     }
 
-    super::api::fetch_embedding(
-        text,
-        &settings.embedding_api_url,
-        &settings.embedding_api_key,
-        &settings.embedding_api_model,
-    )
+    Ok(settings)
 }
 
 /// Index a single clipboard item.
 ///
 /// Computes embedding via API, saves to database, and updates in-memory index.
-/// Called when new clipboard content is saved.
+/// Called when new clipboard content is saved; waits behind any inference
+/// already running, including a bulk indexing batch.
 pub fn index_single_item(app: &tauri::AppHandle, item_id: i64, content: &str) {
     let state = match app.try_state::<SemanticState>() {
         Some(s) => s,
@@ -47,19 +97,28 @@ pub fn index_single_item(app: &tauri::AppHandle, item_id: i64, content: &str) {
         }
     };
 
-    // Only index if semantic search is enabled and API is configured
+    // Only index if semantic search is enabled, API is configured and hasn't rejected us
     let should_index = state
         .status
         .read()
-        .map(|s| s.enabled && s.api_configured)
+        .map(|s| s.enabled && s.api_configured && !s.incremental_paused)
         .unwrap_or(false);
 
     if !should_index {
         return;
     }
+    state.touch();
 
-    let embedding = match compute_embedding(content) {
+    let embedding = match with_inference_slot(|| compute_embedding(content)) {
         Ok(e) => e,
+        Err(e) if super::api::is_rejection(&e) => {
+            // Every later item would fail the same way, so stop until the settings change
+            if state.pause_incremental() {
+                logger::warning("Semantic", &format!("Pausing indexing of new items: {}", e));
+                let _ = app.emit("powerclip:semantic-model-missing", serde_json::json!({ "message": e }));
+            }
+            return;
+        }
         Err(e) => {
             logger::debug("Semantic", &format!("Failed to index item {}: {}", item_id, e));
             return;
@@ -75,9 +134,7 @@ pub fn index_single_item(app: &tauri::AppHandle, item_id: i64, content: &str) {
         }
     }
 
-    if let Ok(mut index) = state.index.write() {
-        index.upsert(item_id, &embedding);
-    }
+    state.upsert_if_loaded(item_id, &embedding);
 
     if let Ok(mut status) = state.status.write() {
         status.indexed_count = status.indexed_count.saturating_add(1);
@@ -89,7 +146,8 @@ pub fn index_single_item(app: &tauri::AppHandle, item_id: i64, content: &str) {
 /// Bulk index all existing text items without embeddings.
 ///
 /// Called when semantic search is first enabled or when API is first configured.
-/// Runs in a background thread with batch database writes for efficiency.
+/// Runs in a background thread; texts are embedded `EMBEDDING_REQUEST_BATCH_SIZE`
+/// at a time per API request, and database writes are batched as well.
 pub fn index_all_items(app: tauri::AppHandle) {
     let state = match app.try_state::<SemanticState>() {
         Some(s) => s.inner().clone(),
@@ -104,6 +162,10 @@ pub fn index_all_items(app: tauri::AppHandle) {
         return;
     }
 
+    // New embeddings are upserted before they reach the database, so the index
+    // must be loaded first or a concurrent lazy load could miss them
+    super::idle::warm_index(&app);
+
     let items_to_index: Vec<(i64, String)> = match get_unindexed_items(&app) {
         Ok(items) => items,
         Err(e) => {
@@ -117,11 +179,13 @@ pub fn index_all_items(app: tauri::AppHandle) {
         return;
     }
 
-    logger::info("Semantic", &format!("Starting bulk indexing of {} items", items_to_index.len()));
+    let total = items_to_index.len();
+    logger::info("Semantic", &format!("Starting bulk indexing of {} items", total));
 
     if let Ok(mut status) = state.status.write() {
         status.indexing_in_progress = true;
     }
+    BULK_INDEXING_CANCELLED.store(false, Ordering::SeqCst);
 
     // Channel for batch database writes
     let (tx, rx) = mpsc::channel::<Vec<(i64, Vec<f32>)>>();
@@ -153,44 +217,61 @@ pub fn index_all_items(app: tauri::AppHandle) {
     std::thread::spawn(move || {
         let mut indexed = 0usize;
         let mut failed = 0usize;
+        let mut cancelled = false;
+        let progress_interval = Duration::from_millis(INDEXING_PROGRESS_EMIT_INTERVAL_MS);
+        let mut last_progress_emit = Instant::now();
+        emit_progress(&app, 0, total);
         let mut batch: Vec<(i64, Vec<f32>)> = Vec::with_capacity(EMBEDDING_BATCH_SIZE);
 
-        for (item_id, content) in items_to_index {
+        for chunk in items_to_index.chunks(EMBEDDING_REQUEST_BATCH_SIZE) {
+            if BULK_INDEXING_CANCELLED.swap(false, Ordering::SeqCst) {
+                logger::info("Semantic", "Bulk indexing cancelled");
+                cancelled = true;
+                break;
+            }
+
+            state.touch();
+
             let still_enabled = state.status.read().map(|s| s.enabled).unwrap_or(false);
             if !still_enabled {
                 logger::info("Semantic", "Semantic search disabled, stopping bulk indexing");
                 break;
             }
 
-            match compute_embedding(&content) {
-                Ok(embedding) => {
-                    if let Ok(mut idx) = state.index.write() {
-                        idx.upsert(item_id, &embedding);
-                    }
-                    batch.push((item_id, embedding));
-                    indexed += 1;
-
-                    if batch.len() >= EMBEDDING_BATCH_SIZE {
-                        if tx.send(std::mem::take(&mut batch)).is_err() {
-                            logger::warning("Semantic", "Failed to send batch to database writer");
+            for (item_id, result) in embed_chunk(chunk) {
+                match result {
+                    Ok(embedding) => {
+                        state.upsert_if_loaded(item_id, &embedding);
+                        batch.push((item_id, embedding));
+                        indexed += 1;
+
+                        if batch.len() >= EMBEDDING_BATCH_SIZE {
+                            if tx.send(std::mem::take(&mut batch)).is_err() {
+                                logger::warning("Semantic", "Failed to send batch to database writer");
+                            }
                         }
                     }
-                }
-                Err(e) => {
-                    logger::warning(
-                        "Semantic",
-                        &format!("Failed to embed item {}: {}", item_id, e),
-                    );
-                    failed += 1;
+                    Err(e) => {
+                        logger::warning(
+                            "Semantic",
+                            &format!("Failed to embed item {}: {}", item_id, e),
+                        );
+                        failed += 1;
+                    }
                 }
             }
 
-            if (indexed + failed) % 10 == 0 {
-                if let Ok(mut status) = state.status.write() {
-                    status.indexed_count = indexed;
-                }
+            if let Ok(mut status) = state.status.write() {
+                status.indexed_count = indexed;
+            }
+
+            // Throttled so fast batches don't flood the event channel
+            if last_progress_emit.elapsed() >= progress_interval {
+                emit_progress(&app, indexed + failed, total);
+                last_progress_emit = Instant::now();
             }
         }
+        emit_progress(&app, indexed + failed, total);
 
         if !batch.is_empty() {
             let _ = tx.send(batch);
@@ -201,6 +282,16 @@ pub fn index_all_items(app: tauri::AppHandle) {
             status.indexing_in_progress = false;
             status.indexed_count = indexed;
         }
+        if indexed > 0 {
+            state.resume_incremental();
+        }
+
+        if cancelled {
+            let _ = app.emit(
+                "powerclip:indexing-cancelled",
+                serde_json::json!({ "indexed": indexed }),
+            );
+        }
 
         logger::info(
             "Semantic",
@@ -209,6 +300,40 @@ pub fn index_all_items(app: tauri::AppHandle) {
     });
 }
 
+/// Emit a `powerclip:indexing-progress` event for the frontend progress bar.
+fn emit_progress(app: &tauri::AppHandle, done: usize, total: usize) {
+    let _ = app.emit(
+        "powerclip:indexing-progress",
+        serde_json::json!({ "done": done, "total": total }),
+    );
+}
+
+/// Embed a chunk of items with one API request.
+///
+/// If the batched request fails (e.g. one input is rejected), falls back to
+/// embedding each item individually so a single bad item doesn't sink the chunk.
+fn embed_chunk(chunk: &[(i64, String)]) -> Vec<(i64, Result<Vec<f32>, String>)> {
+    let texts: Vec<&str> = chunk.iter().map(|(_, content)| content.as_str()).collect();
+
+    match with_inference_slot(|| compute_embeddings(&texts)) {
+        Ok(embeddings) => chunk
+            .iter()
+            .zip(embeddings)
+            .map(|((item_id, _), embedding)| (*item_id, Ok(embedding)))
+            .collect(),
+        Err(e) => {
+            logger::warning(
+                "Semantic",
+                &format!("Batch embedding failed, retrying items individually: {}", e),
+            );
+            chunk
+                .iter()
+                .map(|(item_id, content)| (*item_id, with_inference_slot(|| compute_embedding(content))))
+                .collect()
+        }
+    }
+}
+
 /// Get all text items from database that don't have embeddings yet.
 fn get_unindexed_items(app: &tauri::AppHandle) -> Result<Vec<(i64, String)>, String> {
     let db_state = app
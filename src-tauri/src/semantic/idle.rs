@@ -0,0 +1,109 @@
+//! Background loading and idle unloading of the in-memory embedding index
+//!
+//! The index can hold hundreds of MB of vectors. It is loaded off the startup
+//! path, and when semantic search goes unused for `semantic_unload_idle_minutes`
+//! it is dropped and then reloaded from the database by the next search.
+
+use std::time::{Duration, Instant};
+
+use tauri::Manager;
+
+use crate::config::SEMANTIC_IDLE_CHECK_INTERVAL_SECS;
+use crate::logger;
+
+use super::SemanticState;
+
+/// Load the index from the database if it isn't loaded yet.
+///
+/// Run from a background thread at startup so neither launch nor the first
+/// search stalls on reading every embedding.
+pub fn warm_index(app: &tauri::AppHandle) {
+    let Some(state) = app.try_state::<SemanticState>() else {
+        return;
+    };
+    if state.is_index_loaded() {
+        return;
+    }
+    let Some(db_state) = app.try_state::<crate::DatabaseState>() else {
+        return;
+    };
+
+    let start = Instant::now();
+    let result = db_state
+        .conn
+        .lock()
+        .map_err(|e| e.to_string())
+        .and_then(|conn| state.ensure_index_loaded(&conn));
+
+    match result {
+        Ok(count) => {
+            // Start the idle countdown from now rather than from launch
+            state.touch();
+
+            // 0 also means another thread finished loading first
+            if count > 0 {
+                if let Ok(mut status) = state.status.write() {
+                    status.indexed_count = count;
+                }
+                logger::info(
+                    "Semantic",
+                    &format!("Loaded {} embeddings into memory in {:?}", count, start.elapsed()),
+                );
+            }
+        }
+        Err(e) => {
+            logger::error("Semantic", &format!("Failed to load embeddings: {}", e));
+        }
+    }
+}
+
+/// Start the background thread that unloads the index after inactivity.
+pub fn start_idle_unloader(app: tauri::AppHandle) {
+    std::thread::spawn(move || loop {
+        std::thread::sleep(Duration::from_secs(SEMANTIC_IDLE_CHECK_INTERVAL_SECS));
+
+        let Some(state) = app.try_state::<SemanticState>() else {
+            continue;
+        };
+
+        let idle_minutes = crate::app_settings::load_settings_simple()
+            .map(|s| s.semantic_unload_idle_minutes)
+            .unwrap_or(0);
+        let indexing = state
+            .status
+            .read()
+            .map(|s| s.indexing_in_progress)
+            .unwrap_or(true);
+
+        if should_unload(state.idle_for(), idle_minutes, indexing) && state.unload_index() {
+            logger::info(
+                "Semantic",
+                &format!("Unloaded embedding index after {} idle minutes", idle_minutes),
+            );
+        }
+    });
+}
+
+/// Whether the index has been idle long enough to unload.
+///
+/// `idle_minutes == 0` disables unloading; a running bulk index is never interrupted.
+fn should_unload(idle: Duration, idle_minutes: u64, indexing: bool) -> bool {
+    idle_minutes > 0 && !indexing && idle >= Duration::from_secs(idle_minutes * 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_should_unload() {
+        let hour = Duration::from_secs(3600);
+
+        assert!(should_unload(hour, 30, false));
+        assert!(!should_unload(Duration::from_secs(60), 30, false));
+        // Disabled
+        assert!(!should_unload(hour, 0, false));
+        // Never during bulk indexing
+        assert!(!should_unload(hour, 30, true));
+    }
+}
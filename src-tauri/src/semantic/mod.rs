@@ -3,7 +3,9 @@
 //! This module provides semantic search capabilities for clipboard content.
 //! It uses an OpenAI-compatible embeddings API for text embedding.
 
-use std::sync::{Arc, RwLock};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
 use serde::{Deserialize, Serialize};
 
 use crate::logger;
@@ -13,6 +15,8 @@ mod search;
 pub mod api;
 pub mod embedding;
 pub mod commands;
+pub mod idle;
+mod snippet;
 
 pub use search::EmbeddingIndex;
 
@@ -29,6 +33,20 @@ pub struct SemanticStatus {
     pub enabled: bool,
     /// Whether the embedding API is configured (key + url present)
     pub api_configured: bool,
+    /// Stored embeddings whose dimension doesn't match the current model.
+    /// They are excluded from search; run `full_rebuild_index` to re-embed them.
+    #[serde(default)]
+    pub dimension_mismatch_count: usize,
+    /// Approximate bytes used by the in-memory index (0 while unloaded)
+    #[serde(default)]
+    pub index_memory_bytes: usize,
+    /// Maximum embeddings the in-memory index holds before evicting
+    #[serde(default)]
+    pub index_capacity: usize,
+    /// New items aren't indexed because the API rejected the key or model.
+    /// Cleared when settings are saved or a re-index succeeds.
+    #[serde(default)]
+    pub incremental_paused: bool,
 }
 
 /// Global state for semantic search
@@ -38,11 +56,22 @@ pub struct SemanticState {
     pub index: Arc<RwLock<EmbeddingIndex>>,
     /// Current status for frontend queries
     pub status: Arc<RwLock<SemanticStatus>>,
+    /// Whether `index` mirrors the embeddings table (false until loaded or after an idle unload)
+    pub index_loaded: Arc<AtomicBool>,
+    /// Time of the last semantic query or indexing activity
+    pub last_activity: Arc<Mutex<Instant>>,
+    /// Id of the newest semantic search; older searches stop at their next stage
+    pub search_generation: Arc<AtomicU64>,
 }
 
 impl SemanticState {
     pub fn new(dim: usize) -> Self {
-        let index = EmbeddingIndex::new(dim);
+        Self::with_config(dim, crate::config::MAX_EMBEDDINGS_IN_MEMORY)
+    }
+
+    /// Create state with an index sized from settings.
+    pub fn with_config(dim: usize, max_items: usize) -> Self {
+        let index = EmbeddingIndex::with_config(dim, max_items, crate::config::MIN_SIMILARITY_SCORE);
         let status = SemanticStatus::default();
 
         logger::info("Semantic", "Initialized (API mode)");
@@ -50,6 +79,133 @@ impl SemanticState {
         Self {
             index: Arc::new(RwLock::new(index)),
             status: Arc::new(RwLock::new(status)),
+            index_loaded: Arc::new(AtomicBool::new(false)),
+            last_activity: Arc::new(Mutex::new(Instant::now())),
+            search_generation: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Update the count of stored embeddings that don't match the index dimension
+    pub fn update_dimension_mismatch_count(&self, db_conn: &rusqlite::Connection) {
+        let dim = match self.index.read() {
+            Ok(index) => index.dim(),
+            Err(_) => return,
+        };
+        let count = db::count_dimension_mismatches(db_conn, dim).unwrap_or(0);
+
+        if count > 0 {
+            logger::warning(
+                "Semantic",
+                &format!("{} stored embeddings don't match dimension {}; a full rebuild is needed", count, dim),
+            );
+        }
+        if let Ok(mut status) = self.status.write() {
+            status.dimension_mismatch_count = count;
+        }
+    }
+
+    /// Record semantic activity, postponing the idle unload.
+    pub fn touch(&self) {
+        if let Ok(mut last) = self.last_activity.lock() {
+            *last = Instant::now();
+        }
+    }
+
+    /// Time since the last semantic query or indexing activity.
+    pub fn idle_for(&self) -> Duration {
+        self.last_activity
+            .lock()
+            .map(|last| last.elapsed())
+            .unwrap_or_default()
+    }
+
+    /// Start a search, superseding any that is still running. Returns its id.
+    pub fn begin_search(&self) -> u64 {
+        self.search_generation.fetch_add(1, Ordering::SeqCst) + 1
+    }
+
+    /// Stop the running search at its next stage.
+    pub fn cancel_search(&self) {
+        self.search_generation.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// Whether the search `id` was superseded by a newer one or cancelled.
+    pub fn is_search_stale(&self, id: u64) -> bool {
+        self.search_generation.load(Ordering::SeqCst) != id
+    }
+
+    pub fn is_index_loaded(&self) -> bool {
+        self.index_loaded.load(Ordering::SeqCst)
+    }
+
+    /// Mark the index as mirroring the database (after an explicit load or rebuild).
+    pub fn mark_index_loaded(&self) {
+        self.index_loaded.store(true, Ordering::SeqCst);
+    }
+
+    /// Load embeddings from the database if the index isn't loaded.
+    ///
+    /// Returns the number of embeddings loaded (0 if already loaded).
+    pub fn ensure_index_loaded(&self, db_conn: &rusqlite::Connection) -> Result<usize, String> {
+        if self.is_index_loaded() {
+            return Ok(0);
+        }
+
+        let mut index = self.index.write().map_err(|e| e.to_string())?;
+        // Another thread may have loaded it while we waited for the lock
+        if self.is_index_loaded() {
+            return Ok(0);
+        }
+
+        let dim = index.dim();
+        let count = db::load_embeddings_into_index(db_conn, &mut index, dim)
+            .map_err(|e| e.to_string())?;
+        self.index_loaded.store(true, Ordering::SeqCst);
+
+        Ok(count)
+    }
+
+    /// Drop the in-memory index to reclaim RAM. It is reloaded lazily on the next search.
+    ///
+    /// Returns false if the index was not loaded.
+    pub fn unload_index(&self) -> bool {
+        let Ok(mut index) = self.index.write() else {
+            return false;
+        };
+        if !self.is_index_loaded() {
+            return false;
+        }
+
+        index.release();
+        self.index_loaded.store(false, Ordering::SeqCst);
+        true
+    }
+
+    /// Insert an embedding into the in-memory index, unless it is unloaded.
+    ///
+    /// While unloaded the database is the only copy; the embedding is picked up
+    /// by the next lazy load.
+    pub fn upsert_if_loaded(&self, item_id: i64, embedding: &[f32]) {
+        if let Ok(mut index) = self.index.write() {
+            if self.is_index_loaded() {
+                index.upsert(item_id, embedding);
+            }
+        }
+    }
+
+    /// Stop indexing new items until the settings change. Returns false if
+    /// indexing was already paused.
+    pub fn pause_incremental(&self) -> bool {
+        match self.status.write() {
+            Ok(mut status) => !std::mem::replace(&mut status.incremental_paused, true),
+            Err(_) => false,
+        }
+    }
+
+    /// Index new items again after `pause_incremental`.
+    pub fn resume_incremental(&self) {
+        if let Ok(mut status) = self.status.write() {
+            status.incremental_paused = false;
         }
     }
 
@@ -116,6 +272,10 @@ mod tests {
             indexing_in_progress: true,
             enabled: true,
             api_configured: true,
+            dimension_mismatch_count: 3,
+            index_memory_bytes: 4096,
+            index_capacity: 50_000,
+            incremental_paused: true,
         };
 
         let json = serde_json::to_string(&status).expect("Failed to serialize");
@@ -127,6 +287,23 @@ mod tests {
         assert_eq!(status.indexing_in_progress, deserialized.indexing_in_progress);
         assert_eq!(status.enabled, deserialized.enabled);
         assert_eq!(status.api_configured, deserialized.api_configured);
+        assert_eq!(status.dimension_mismatch_count, deserialized.dimension_mismatch_count);
+        assert_eq!(status.index_memory_bytes, deserialized.index_memory_bytes);
+        assert_eq!(status.index_capacity, deserialized.index_capacity);
+        assert_eq!(status.incremental_paused, deserialized.incremental_paused);
+    }
+
+    #[test]
+    fn test_pause_incremental() {
+        let state = SemanticState::new(3);
+        assert!(state.pause_incremental());
+        // Only the first pause reports, so the frontend is told once
+        assert!(!state.clone().pause_incremental());
+        assert!(state.status.read().unwrap().incremental_paused);
+
+        state.resume_incremental();
+        assert!(!state.status.read().unwrap().incremental_paused);
+        assert!(state.pause_incremental());
     }
 
     #[test]
@@ -137,6 +314,57 @@ mod tests {
         // Both should share the same underlying data
         assert!(Arc::ptr_eq(&state.index, &cloned.index));
         assert!(Arc::ptr_eq(&state.status, &cloned.status));
+        assert!(Arc::ptr_eq(&state.index_loaded, &cloned.index_loaded));
+    }
+
+    #[test]
+    fn test_search_generation() {
+        let state = SemanticState::new(3);
+        let first = state.begin_search();
+        assert!(!state.is_search_stale(first));
+
+        // A newer search supersedes the first
+        let second = state.begin_search();
+        assert!(state.is_search_stale(first));
+        assert!(!state.is_search_stale(second));
+
+        state.cancel_search();
+        assert!(state.is_search_stale(second));
+
+        // Clones share the generation, so cancelling from a command reaches the running search
+        let third = state.clone().begin_search();
+        assert!(!state.is_search_stale(third));
+    }
+
+    #[test]
+    fn test_unload_and_lazy_reload() {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        conn.execute(
+            "CREATE TABLE embeddings (item_id INTEGER PRIMARY KEY, embedding BLOB NOT NULL, dim INTEGER NOT NULL)",
+            [],
+        )
+        .unwrap();
+        db::save_embedding(&conn, 1, &[1.0, 0.0, 0.0]).unwrap();
+
+        let state = SemanticState::new(3);
+        assert_eq!(state.ensure_index_loaded(&conn).unwrap(), 1);
+        assert!(state.is_index_loaded());
+        assert_eq!(state.index.read().unwrap().len(), 1);
+
+        // Already loaded: no-op
+        assert_eq!(state.ensure_index_loaded(&conn).unwrap(), 0);
+
+        assert!(state.unload_index());
+        assert!(!state.is_index_loaded());
+        assert!(state.index.read().unwrap().is_empty());
+        assert!(!state.unload_index());
+
+        // Upserts while unloaded only live in the database
+        state.upsert_if_loaded(2, &[0.0, 1.0, 0.0]);
+        assert!(state.index.read().unwrap().is_empty());
+
+        assert_eq!(state.ensure_index_loaded(&conn).unwrap(), 1);
+        assert_eq!(state.index.read().unwrap().len(), 1);
     }
 
     #[test]
@@ -3,10 +3,16 @@
 //! Uses parallel arrays for CPU cache efficiency during similarity search.
 //! Supports O(1) upsert/remove and O(n) search with partial sorting.
 //! Implements LRU eviction to prevent unbounded memory growth.
+//!
+//! Vectors are split into fixed-size segments behind `Arc`s. A search takes a
+//! snapshot under a brief read lock and scans it after releasing the lock, so
+//! upserts from indexing don't wait for a full scan. A write that lands while
+//! a snapshot is alive copies only the segment it touches.
 
 use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
 
-use crate::config::{MAX_EMBEDDINGS_IN_MEMORY, MIN_SIMILARITY_SCORE};
+use crate::config::{INDEX_SEGMENT_LEN, MAX_EMBEDDINGS_IN_MEMORY, MIN_SIMILARITY_SCORE};
 
 /// Result item from semantic search
 #[derive(Debug, Clone)]
@@ -15,18 +21,36 @@ pub struct SearchResult {
     pub score: f32,
 }
 
+/// Up to `INDEX_SEGMENT_LEN` embeddings stored as parallel arrays.
+#[derive(Clone, Default)]
+struct Segment {
+    item_ids: Vec<i64>,
+    embeddings: Vec<f32>,
+}
+
+/// Immutable view of the indexed vectors, cheap to share across threads.
+///
+/// Position `i` lives in segment `i / INDEX_SEGMENT_LEN`; every segment but
+/// the last is full.
+#[derive(Clone)]
+pub struct IndexSnapshot {
+    dim: usize,
+    segments: Vec<Arc<Segment>>,
+}
+
 /// In-memory embedding index with LRU eviction.
 ///
 /// Memory layout is optimized for CPU cache efficiency:
-/// - `item_ids` and `embeddings` are parallel arrays
+/// - `item_ids` and `embeddings` are parallel arrays within each segment
 /// - Embeddings are stored flat (not vec of vecs)
+/// - Segments are copied on write, only while a search holds a snapshot
 /// - HashMap provides O(1) item lookup
 /// - LRU queue prevents unbounded memory growth
 pub struct EmbeddingIndex {
     dim: usize,
     max_items: usize,
-    item_ids: Vec<i64>,
-    embeddings: Vec<f32>,
+    len: usize,
+    vectors: Arc<IndexSnapshot>,
     id_to_idx: HashMap<i64, usize>,
     lru_queue: VecDeque<i64>,
 }
@@ -40,13 +64,31 @@ impl EmbeddingIndex {
         Self {
             dim,
             max_items,
-            item_ids: Vec::new(),
-            embeddings: Vec::new(),
+            len: 0,
+            vectors: Arc::new(IndexSnapshot { dim, segments: Vec::new() }),
             id_to_idx: HashMap::new(),
             lru_queue: VecDeque::new(),
         }
     }
 
+    /// Embedding dimension this index was built for.
+    pub fn dim(&self) -> usize {
+        self.dim
+    }
+
+    /// Change the capacity, evicting least-recently-used entries if over the new limit.
+    pub fn set_max_items(&mut self, max_items: usize) {
+        self.max_items = max_items.max(1);
+        while self.len > self.max_items {
+            self.evict_lru();
+        }
+    }
+
+    /// Segment `seg` for writing, copying it first if a snapshot shares it.
+    fn segment_mut(&mut self, seg: usize) -> &mut Segment {
+        Arc::make_mut(&mut Arc::make_mut(&mut self.vectors).segments[seg])
+    }
+
     pub fn upsert(&mut self, item_id: i64, embedding: &[f32]) {
         debug_assert_eq!(
             embedding.len(),
@@ -56,20 +98,27 @@ impl EmbeddingIndex {
             embedding.len()
         );
 
+        let dim = self.dim;
         if let Some(&idx) = self.id_to_idx.get(&item_id) {
-            let start = idx * self.dim;
-            self.embeddings[start..start + self.dim].copy_from_slice(embedding);
+            let start = (idx % INDEX_SEGMENT_LEN) * dim;
+            self.segment_mut(idx / INDEX_SEGMENT_LEN).embeddings[start..start + dim]
+                .copy_from_slice(embedding);
 
             self.lru_queue.retain(|&id| id != item_id);
             self.lru_queue.push_back(item_id);
         } else {
-            while self.item_ids.len() >= self.max_items {
+            while self.len >= self.max_items {
                 self.evict_lru();
             }
 
-            let idx = self.item_ids.len();
-            self.item_ids.push(item_id);
-            self.embeddings.extend_from_slice(embedding);
+            let idx = self.len;
+            if idx.is_multiple_of(INDEX_SEGMENT_LEN) {
+                Arc::make_mut(&mut self.vectors).segments.push(Arc::new(Segment::default()));
+            }
+            let segment = self.segment_mut(idx / INDEX_SEGMENT_LEN);
+            segment.item_ids.push(item_id);
+            segment.embeddings.extend_from_slice(embedding);
+            self.len += 1;
             self.id_to_idx.insert(item_id, idx);
             self.lru_queue.push_back(item_id);
         }
@@ -86,21 +135,27 @@ impl EmbeddingIndex {
             return false;
         };
 
-        let last_idx = self.item_ids.len() - 1;
+        let dim = self.dim;
+        let last_idx = self.len - 1;
+        let last_seg = last_idx / INDEX_SEGMENT_LEN;
+
+        let last = self.segment_mut(last_seg);
+        let last_id = last.item_ids.pop().unwrap_or_default();
+        let last_embedding = last.embeddings.split_off(last.embeddings.len() - dim);
+        let last_is_empty = last.item_ids.is_empty();
 
         if idx != last_idx {
-            let last_id = self.item_ids[last_idx];
-            self.item_ids[idx] = last_id;
+            let segment = self.segment_mut(idx / INDEX_SEGMENT_LEN);
+            let offset = idx % INDEX_SEGMENT_LEN;
+            segment.item_ids[offset] = last_id;
+            segment.embeddings[offset * dim..(offset + 1) * dim].copy_from_slice(&last_embedding);
             self.id_to_idx.insert(last_id, idx);
-
-            let start = idx * self.dim;
-            let last_start = last_idx * self.dim;
-            self.embeddings
-                .copy_within(last_start..last_start + self.dim, start);
         }
 
-        self.item_ids.pop();
-        self.embeddings.truncate(self.embeddings.len() - self.dim);
+        if last_is_empty {
+            Arc::make_mut(&mut self.vectors).segments.pop();
+        }
+        self.len -= 1;
 
         self.lru_queue.retain(|&id| id != item_id);
 
@@ -108,27 +163,126 @@ impl EmbeddingIndex {
     }
 
     pub fn clear(&mut self) {
-        self.item_ids.clear();
-        self.embeddings.clear();
+        // A fresh snapshot instead of make_mut, so clearing never copies
+        // vectors that a search is still reading
+        self.vectors = Arc::new(IndexSnapshot { dim: self.dim, segments: Vec::new() });
+        self.len = 0;
         self.id_to_idx.clear();
         self.lru_queue.clear();
     }
 
+    /// Drop all embeddings and return their memory to the allocator.
+    ///
+    /// Memory held by an in-flight search snapshot is freed when that search ends.
+    pub fn release(&mut self) {
+        self.clear();
+        self.id_to_idx.shrink_to_fit();
+        self.lru_queue.shrink_to_fit();
+    }
+
     /// Returns the number of embeddings currently stored.
     #[allow(dead_code)]
     pub fn len(&self) -> usize {
-        self.item_ids.len()
+        self.len
     }
 
     /// Returns true if the index contains no embeddings.
     #[allow(dead_code)]
     pub fn is_empty(&self) -> bool {
-        self.item_ids.is_empty()
+        self.len == 0
+    }
+
+    /// Ids of the indexed items, newest (highest id) first.
+    pub fn item_ids(&self) -> Vec<i64> {
+        let mut ids: Vec<i64> = self.id_to_idx.keys().copied().collect();
+        ids.sort_unstable_by(|a, b| b.cmp(a));
+        ids
     }
 
-    /// Returns a reference to the internal embedding vector for similarity.
-    pub fn get_embeddings(&self) -> &Vec<f32> {
-        &self.embeddings
+    /// Maximum number of embeddings kept before LRU eviction.
+    pub fn capacity(&self) -> usize {
+        self.max_items
+    }
+
+    /// Approximate heap bytes held by the index (vectors, lookup map and LRU queue).
+    ///
+    /// Segments still shared with an in-flight search snapshot are counted once.
+    pub fn memory_usage(&self) -> usize {
+        let vectors: usize = self
+            .vectors
+            .segments
+            .iter()
+            .map(|segment| {
+                segment.item_ids.capacity() * std::mem::size_of::<i64>()
+                    + segment.embeddings.capacity() * std::mem::size_of::<f32>()
+            })
+            .sum();
+        let segments = self.vectors.segments.capacity() * std::mem::size_of::<Arc<Segment>>();
+        // hashbrown stores one control byte per bucket next to each entry
+        let lookup = self.id_to_idx.capacity() * (std::mem::size_of::<(i64, usize)>() + 1);
+        let lru = self.lru_queue.capacity() * std::mem::size_of::<i64>();
+
+        vectors + segments + lookup + lru
+    }
+
+    /// Share the current vectors for searching outside the index lock.
+    ///
+    /// Only bumps a reference count. Writes made after this don't affect the
+    /// snapshot; the first write to each segment copies that segment instead.
+    pub fn snapshot(&self) -> Arc<IndexSnapshot> {
+        Arc::clone(&self.vectors)
+    }
+}
+
+impl IndexSnapshot {
+    /// Group the newest `max_items` embeddings whose similarity to another
+    /// group member is at least `threshold` (single-linkage clustering).
+    ///
+    /// Only the newest items are compared, since every pair is scored. Groups
+    /// have at least two ids, newest first, and are ordered by their newest item.
+    pub fn similar_groups(&self, threshold: f32, max_items: usize) -> Vec<Vec<i64>> {
+        let mut entries: Vec<(i64, &[f32])> = self
+            .segments
+            .iter()
+            .flat_map(|segment| segment.item_ids.iter().copied().zip(segment.embeddings.chunks_exact(self.dim)))
+            .collect();
+        entries.sort_unstable_by(|a, b| b.0.cmp(&a.0));
+        entries.truncate(max_items);
+
+        // Union-find over entry positions
+        let mut parent: Vec<usize> = (0..entries.len()).collect();
+        fn find(parent: &mut [usize], mut i: usize) -> usize {
+            while parent[i] != i {
+                parent[i] = parent[parent[i]];
+                i = parent[i];
+            }
+            i
+        }
+
+        for i in 0..entries.len() {
+            for j in i + 1..entries.len() {
+                if dot_product(entries[i].1, entries[j].1) >= threshold {
+                    let (a, b) = (find(&mut parent, i), find(&mut parent, j));
+                    if a != b {
+                        // Keep the newer (lower position) entry as the root
+                        parent[a.max(b)] = a.min(b);
+                    }
+                }
+            }
+        }
+
+        let mut groups: Vec<Vec<i64>> = Vec::new();
+        let mut root_to_group: HashMap<usize, usize> = HashMap::new();
+        for i in 0..entries.len() {
+            let root = find(&mut parent, i);
+            let group = *root_to_group.entry(root).or_insert_with(|| {
+                groups.push(Vec::new());
+                groups.len() - 1
+            });
+            groups[group].push(entries[i].0);
+        }
+        groups.retain(|group| group.len() > 1);
+        groups
     }
 
     pub fn search(
@@ -136,6 +290,18 @@ impl EmbeddingIndex {
         query: &[f32],
         k: usize,
         min_score: f32,
+    ) -> Vec<SearchResult> {
+        self.search_filtered(query, k, min_score, |_| true)
+    }
+
+    /// Like `search`, but only items accepted by `filter` are considered,
+    /// so filtered-out items don't count against `k`.
+    pub fn search_filtered<F: Fn(i64) -> bool>(
+        &self,
+        query: &[f32],
+        k: usize,
+        min_score: f32,
+        filter: F,
     ) -> Vec<SearchResult> {
         debug_assert_eq!(
             query.len(),
@@ -145,19 +311,21 @@ impl EmbeddingIndex {
             query.len()
         );
 
-        if self.item_ids.is_empty() || k == 0 {
+        if self.segments.is_empty() || k == 0 {
             return Vec::new();
         }
 
         let mut scores: Vec<(i64, f32)> = self
-            .item_ids
+            .segments
             .iter()
-            .enumerate()
-            .map(|(idx, &item_id)| {
-                let start = idx * self.dim;
-                let embedding = &self.embeddings[start..start + self.dim];
-                (item_id, dot_product(query, embedding))
+            .flat_map(|segment| {
+                segment
+                    .item_ids
+                    .iter()
+                    .zip(segment.embeddings.chunks_exact(self.dim))
             })
+            .filter(|(&item_id, _)| filter(item_id))
+            .map(|(&item_id, embedding)| (item_id, dot_product(query, embedding)))
             .filter(|(_, score)| *score >= min_score)
             .collect();
 
@@ -190,6 +358,123 @@ impl Default for EmbeddingIndex {
 }
 
 #[inline]
-fn dot_product(a: &[f32], b: &[f32]) -> f32 {
+pub(super) fn dot_product(a: &[f32], b: &[f32]) -> f32 {
     a.iter().zip(b.iter()).map(|(x, y)| x * y).sum()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unit(dim: usize, hot: usize) -> Vec<f32> {
+        let mut v = vec![0.0; dim];
+        v[hot] = 1.0;
+        v
+    }
+
+    #[test]
+    fn test_snapshot_unaffected_by_later_writes() {
+        let mut index = EmbeddingIndex::with_config(4, 10, 0.0);
+        index.upsert(1, &unit(4, 0));
+        index.upsert(2, &unit(4, 1));
+
+        let snapshot = index.snapshot();
+        index.upsert(3, &unit(4, 0));
+        index.upsert(1, &unit(4, 2));
+        assert!(index.remove(2));
+
+        let before: Vec<i64> = snapshot.search(&unit(4, 0), 10, 0.5).iter().map(|r| r.item_id).collect();
+        assert_eq!(before, vec![1]);
+
+        let after: Vec<i64> = index.snapshot().search(&unit(4, 0), 10, 0.5).iter().map(|r| r.item_id).collect();
+        assert_eq!(after, vec![3]);
+        assert_eq!(index.len(), 2);
+    }
+
+    #[test]
+    fn test_snapshot_survives_clear() {
+        let mut index = EmbeddingIndex::with_config(4, 10, 0.0);
+        index.upsert(1, &unit(4, 0));
+
+        let snapshot = index.snapshot();
+        index.release();
+
+        assert!(index.is_empty());
+        assert_eq!(snapshot.search(&unit(4, 0), 10, 0.5).len(), 1);
+    }
+
+    #[test]
+    fn test_remove_across_segments() {
+        let total = INDEX_SEGMENT_LEN * 2 + 10;
+        let mut index = EmbeddingIndex::with_config(2, total, 0.0);
+        for id in 0..total as i64 {
+            index.upsert(id, &[1.0, id as f32]);
+        }
+
+        let snapshot = index.snapshot();
+        for id in (0..total as i64).step_by(3) {
+            assert!(index.remove(id));
+        }
+        index.upsert(1, &[1.0, -1.0]);
+
+        let mut found: Vec<(i64, f32)> = index
+            .snapshot()
+            .search(&[1.0, 0.0], total, 0.0)
+            .iter()
+            .map(|r| (r.item_id, r.score))
+            .collect();
+        found.sort_by_key(|(id, _)| *id);
+        let expected: Vec<i64> = (0..total as i64).filter(|id| id % 3 != 0).collect();
+        assert_eq!(found.iter().map(|(id, _)| *id).collect::<Vec<_>>(), expected);
+        assert!(found.iter().all(|(_, score)| *score == 1.0));
+        assert_eq!(index.len(), expected.len());
+        assert_eq!(snapshot.search(&[1.0, 0.0], total, 0.0).len(), total);
+    }
+
+    #[test]
+    fn test_item_ids() {
+        let mut index = EmbeddingIndex::with_config(4, 3, 0.0);
+        for id in [5, 9, 2, 7] {
+            index.upsert(id, &unit(4, 0));
+        }
+        // 5 was evicted to stay within capacity
+        assert_eq!(index.item_ids(), vec![9, 7, 2]);
+
+        assert!(index.remove(9));
+        assert_eq!(index.item_ids(), vec![7, 2]);
+    }
+
+    #[test]
+    fn test_similar_groups() {
+        let mut index = EmbeddingIndex::with_config(3, 10, 0.0);
+        index.upsert(1, &[1.0, 0.0, 0.0]);
+        index.upsert(2, &[0.0, 1.0, 0.0]);
+        index.upsert(3, &[0.99, 0.14, 0.0]);
+        index.upsert(4, &[0.0, 0.0, 1.0]);
+        index.upsert(5, &[0.14, 0.99, 0.0]);
+        index.upsert(6, &[0.9, 0.43, 0.0]);
+
+        // 6 links to 3 (and 3 to 1), so single linkage joins all three
+        let groups = index.snapshot().similar_groups(0.95, 10);
+        assert_eq!(groups, vec![vec![6, 3, 1], vec![5, 2]]);
+
+        // Only the newest items are compared
+        assert_eq!(index.snapshot().similar_groups(0.95, 3), Vec::<Vec<i64>>::new());
+        assert_eq!(index.snapshot().similar_groups(0.95, 4), vec![vec![6, 3]]);
+    }
+
+    #[test]
+    fn test_memory_usage_tracks_contents() {
+        let mut index = EmbeddingIndex::with_config(8, 100, 0.0);
+        assert_eq!(index.capacity(), 100);
+        assert_eq!(index.memory_usage(), 0);
+
+        for id in 0..50 {
+            index.upsert(id, &[0.5; 8]);
+        }
+        assert!(index.memory_usage() >= 50 * 8 * std::mem::size_of::<f32>());
+
+        index.release();
+        assert_eq!(index.memory_usage(), 0);
+    }
+}
@@ -0,0 +1,147 @@
+//! Match snippets for semantic search results
+//!
+//! For long text items, picks the sentence most similar to the query so the
+//! UI can show why an item matched.
+
+use crate::config::{EMBEDDING_REQUEST_BATCH_SIZE, MAX_SNIPPET_SENTENCES, SNIPPET_MAX_CHARS};
+use crate::logger;
+
+use super::commands::SemanticSearchResult;
+use super::search::dot_product;
+
+/// Sentences shorter than this (in characters) are ignored
+const MIN_SENTENCE_CHARS: usize = 3;
+
+/// Fill in `snippet` for long text results.
+///
+/// All candidate sentences are embedded in as few API requests as possible.
+/// If embedding fails, results fall back to a content prefix.
+pub fn attach_snippets(results: &mut [SemanticSearchResult], query_embedding: &[f32]) {
+    // (result index, candidate sentences)
+    let mut candidates: Vec<(usize, Vec<String>)> = Vec::new();
+
+    for (i, result) in results.iter_mut().enumerate() {
+        let content = &result.item.content;
+        if result.item.item_type != "text" || content.chars().count() <= SNIPPET_MAX_CHARS {
+            continue;
+        }
+
+        let sentences: Vec<String> = split_sentences(content)
+            .into_iter()
+            .take(MAX_SNIPPET_SENTENCES)
+            .map(str::to_string)
+            .collect();
+
+        if sentences.len() < 2 {
+            result.snippet = Some(truncate_chars(content, SNIPPET_MAX_CHARS));
+        } else {
+            candidates.push((i, sentences));
+        }
+    }
+
+    if candidates.is_empty() {
+        return;
+    }
+
+    let texts: Vec<&str> = candidates
+        .iter()
+        .flat_map(|(_, sentences)| sentences.iter().map(String::as_str))
+        .collect();
+
+    let mut embeddings = Vec::with_capacity(texts.len());
+    for chunk in texts.chunks(EMBEDDING_REQUEST_BATCH_SIZE) {
+        match super::embedding::compute_embeddings(chunk) {
+            Ok(batch) => embeddings.extend(batch),
+            Err(e) => {
+                logger::warning("Semantic", &format!("Failed to embed snippet sentences: {}", e));
+                break;
+            }
+        }
+    }
+
+    if embeddings.len() != texts.len() {
+        for (i, _) in candidates {
+            let prefix = truncate_chars(&results[i].item.content, SNIPPET_MAX_CHARS);
+            results[i].snippet = Some(prefix);
+        }
+        return;
+    }
+
+    let mut embeddings = embeddings.into_iter();
+    for (i, sentences) in candidates {
+        let best = sentences
+            .iter()
+            .zip(embeddings.by_ref())
+            .map(|(sentence, embedding)| (sentence, dot_product(query_embedding, &embedding)))
+            .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal))
+            .map(|(sentence, _)| sentence.as_str());
+
+        results[i].snippet = best.map(|s| truncate_chars(s, SNIPPET_MAX_CHARS));
+    }
+}
+
+/// Split text into trimmed sentences on terminal punctuation and line breaks.
+///
+/// A '.' only ends a sentence when followed by whitespace or the end of the
+/// text, so numbers and abbreviations like "3.14" stay intact.
+fn split_sentences(text: &str) -> Vec<&str> {
+    let mut sentences = Vec::new();
+    let mut start = 0;
+    let mut chars = text.char_indices().peekable();
+
+    while let Some((i, c)) = chars.next() {
+        let is_end = match c {
+            '!' | '?' | '\n' | '。' | '！' | '？' => true,
+            '.' => chars.peek().map_or(true, |(_, next)| next.is_whitespace()),
+            _ => false,
+        };
+
+        if is_end {
+            let end = i + c.len_utf8();
+            push_sentence(&mut sentences, &text[start..end]);
+            start = end;
+        }
+    }
+    push_sentence(&mut sentences, &text[start..]);
+
+    sentences
+}
+
+fn push_sentence<'a>(sentences: &mut Vec<&'a str>, sentence: &'a str) {
+    let sentence = sentence.trim();
+    if sentence.chars().count() >= MIN_SENTENCE_CHARS {
+        sentences.push(sentence);
+    }
+}
+
+/// Truncate to at most `max` characters, adding an ellipsis when cut.
+fn truncate_chars(text: &str, max: usize) -> String {
+    match text.char_indices().nth(max) {
+        Some((end, _)) => format!("{}…", text[..end].trim_end()),
+        None => text.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_sentences() {
+        let sentences = split_sentences("First one. Pi is 3.14! Is it?\nLast line");
+        assert_eq!(sentences, vec!["First one.", "Pi is 3.14!", "Is it?", "Last line"]);
+    }
+
+    #[test]
+    fn test_split_sentences_cjk_and_short_fragments() {
+        let sentences = split_sentences("你好世界。这是测试！ a. okay");
+        assert_eq!(sentences, vec!["你好世界。", "这是测试！", "okay"]);
+    }
+
+    #[test]
+    fn test_truncate_chars() {
+        assert_eq!(truncate_chars("short", 10), "short");
+        assert_eq!(truncate_chars("hello world", 5), "hello…");
+        assert_eq!(truncate_chars("日本語テキスト", 3), "日本語…");
+    }
+}
@@ -0,0 +1,65 @@
+//! Shutdown - Stop background work and flush pending writes before exiting
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use tauri::{AppHandle, Manager};
+
+use crate::config::SHUTDOWN_DRAIN_TIMEOUT_MS;
+use crate::{logger, monitor};
+
+/// Set once shutdown has started, so cleanup runs only once.
+static SHUTTING_DOWN: AtomicBool = AtomicBool::new(false);
+
+/// Stop the clipboard monitor, wait for an in-flight save, and flush the log.
+///
+/// Runs on every exit path (tray "Quit" and Tauri's exit event); only the
+/// first call does anything.
+pub fn prepare(app: &AppHandle) {
+    if SHUTTING_DOWN.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    logger::info("Shutdown", "Shutting down");
+
+    monitor::stop_clipboard_monitor();
+    crate::semantic::embedding::cancel_bulk_indexing();
+    crate::commands::extensions::kill_all();
+    drain_database(app);
+    crate::single_instance::release();
+
+    logger::info("Shutdown", "Cleanup complete");
+    logger::flush();
+}
+
+/// Clean up, then exit the process.
+pub fn quit(app: &AppHandle) -> ! {
+    prepare(app);
+    std::process::exit(0);
+}
+
+/// Wait (bounded) for the database connection to be free, then keep it.
+///
+/// `check_clipboard` holds the connection while it writes image files and
+/// rows, so owning the lock means no write is half done. It is never
+/// released, so a poll that fires after this can't start a new write.
+fn drain_database(app: &AppHandle) {
+    let Some(state) = app.try_state::<crate::DatabaseState>() else {
+        return;
+    };
+
+    let deadline = Instant::now() + Duration::from_millis(SHUTDOWN_DRAIN_TIMEOUT_MS);
+    loop {
+        match state.conn.try_lock() {
+            Ok(guard) => {
+                std::mem::forget(guard);
+                return;
+            }
+            Err(std::sync::TryLockError::Poisoned(_)) => return,
+            Err(std::sync::TryLockError::WouldBlock) if Instant::now() >= deadline => {
+                logger::warning("Shutdown", "Timed out waiting for a database write to finish");
+                return;
+            }
+            Err(std::sync::TryLockError::WouldBlock) => std::thread::sleep(Duration::from_millis(10)),
+        }
+    }
+}
@@ -0,0 +1,285 @@
+//! Single instance - Keep one running app per data directory
+//!
+//! The first instance creates a lock file in `data_dir()` holding its process
+//! id and the localhost port it listens on. A later launch finds the lock,
+//! asks that instance to show its window, and exits. A lock left behind by a
+//! crashed run is recognized because nothing listens on its port, and is
+//! replaced. An instance that accepts the connection but doesn't answer is
+//! still starting up, and counts as running.
+
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, BufReader, ErrorKind, Write};
+use std::net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+use std::path::Path;
+use std::time::Duration;
+
+use tauri::Manager;
+
+use crate::config::{instance_lock_path, INSTANCE_SIGNAL_TIMEOUT_MS};
+use crate::logger;
+
+/// Request sent by a second launch, and the running instance's reply
+const SHOW_REQUEST: &str = "show";
+const SHOW_ACK: &str = "ok";
+
+/// Attempts at replacing a stale lock before giving up
+const MAX_ACQUIRE_ATTEMPTS: u32 = 3;
+
+/// Outcome of `acquire`.
+pub enum Instance {
+    /// No other instance is running; pass the listener to `serve`
+    Primary(TcpListener),
+    /// Another instance is running and was asked to show its window
+    Secondary,
+}
+
+/// What `signal_show` found on the port of a lock.
+#[derive(Debug, PartialEq)]
+enum Signal {
+    /// The instance showed its window
+    Shown,
+    /// Something listens but didn't answer in time: an instance still starting up
+    Busy,
+    /// Nothing listens, or whatever does isn't PowerClip
+    NotRunning,
+}
+
+/// Contents of the lock file: "<pid> <port>"
+#[derive(Debug, PartialEq)]
+struct LockInfo {
+    pid: u32,
+    port: u16,
+}
+
+impl LockInfo {
+    fn parse(contents: &str) -> Option<Self> {
+        let mut parts = contents.split_whitespace();
+        let pid = parts.next()?.parse().ok()?;
+        let port = parts.next()?.parse().ok()?;
+        Some(Self { pid, port })
+    }
+
+    fn to_contents(&self) -> String {
+        format!("{} {}\n", self.pid, self.port)
+    }
+}
+
+/// Become the running instance, or signal the one that already is.
+pub fn acquire() -> Result<Instance, String> {
+    acquire_at(&instance_lock_path())
+}
+
+fn acquire_at(path: &Path) -> Result<Instance, String> {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).map_err(|e| e.to_string())?;
+    let ours = LockInfo {
+        pid: std::process::id(),
+        port: listener.local_addr().map_err(|e| e.to_string())?.port(),
+    };
+
+    for _ in 0..MAX_ACQUIRE_ATTEMPTS {
+        match OpenOptions::new().write(true).create_new(true).open(path) {
+            Ok(mut file) => {
+                file.write_all(ours.to_contents().as_bytes())
+                    .map_err(|e| format!("Failed to write instance lock {:?}: {}", path, e))?;
+                logger::info("Instance", &format!(
+                    "Holding instance lock {:?} (pid {}, port {})",
+                    path, ours.pid, ours.port
+                ));
+                return Ok(Instance::Primary(listener));
+            }
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                let other = read_lock(path);
+                if let Some(other) = &other {
+                    match signal_show(other.port) {
+                        Signal::Shown => {
+                            logger::info("Instance", &format!(
+                                "Already running (pid {}, lock {:?}), asked it to show its window",
+                                other.pid, path
+                            ));
+                            return Ok(Instance::Secondary);
+                        }
+                        Signal::Busy => {
+                            logger::info("Instance", &format!(
+                                "Already running (pid {}, lock {:?}) but still starting up",
+                                other.pid, path
+                            ));
+                            return Ok(Instance::Secondary);
+                        }
+                        Signal::NotRunning => {}
+                    }
+                }
+
+                logger::warning("Instance", &format!(
+                    "Removing stale instance lock {:?} ({})",
+                    path,
+                    other.map(|o| format!("pid {} not responding", o.pid)).unwrap_or_else(|| "unreadable".to_string())
+                ));
+                let _ = fs::remove_file(path);
+            }
+            Err(e) => return Err(format!("Failed to create instance lock {:?}: {}", path, e)),
+        }
+    }
+
+    Err(format!("Could not replace stale instance lock {:?}", path))
+}
+
+/// Read the lock file, allowing a moment for an instance that is still writing it.
+fn read_lock(path: &Path) -> Option<LockInfo> {
+    for _ in 0..5 {
+        if let Some(info) = fs::read_to_string(path).ok().as_deref().and_then(LockInfo::parse) {
+            return Some(info);
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    }
+    None
+}
+
+/// Ask the instance listening on `port` to show its window.
+///
+/// The listener exists from the start of a launch but only answers once setup
+/// is done, so an accepted connection without a reply is a busy instance, not
+/// a stale lock.
+fn signal_show(port: u16) -> Signal {
+    let timeout = Duration::from_millis(INSTANCE_SIGNAL_TIMEOUT_MS);
+    let addr = SocketAddr::from((Ipv4Addr::LOCALHOST, port));
+
+    let Ok(mut stream) = TcpStream::connect_timeout(&addr, timeout) else {
+        return Signal::NotRunning;
+    };
+    let _ = stream.set_read_timeout(Some(timeout));
+    let _ = stream.set_write_timeout(Some(timeout));
+
+    if writeln!(stream, "{}", SHOW_REQUEST).is_err() {
+        return Signal::NotRunning;
+    }
+    let mut reply = String::new();
+    match BufReader::new(stream).read_line(&mut reply) {
+        Ok(_) if reply.trim() == SHOW_ACK => Signal::Shown,
+        // Closed without the expected reply: another program reused the port
+        Ok(_) => Signal::NotRunning,
+        Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => Signal::Busy,
+        Err(_) => Signal::NotRunning,
+    }
+}
+
+/// Show the main window whenever a later launch asks for it.
+pub fn serve(app: tauri::AppHandle, listener: TcpListener) {
+    serve_with(listener, move || {
+        let app_handle = app.clone();
+        let _ = app.run_on_main_thread(move || {
+            if let Some(window) = app_handle.get_webview_window("main") {
+                let _ = crate::window::show_and_notify(&app_handle, &window);
+            }
+        });
+    });
+}
+
+fn serve_with(listener: TcpListener, on_show: impl Fn() + Send + 'static) {
+    std::thread::spawn(move || {
+        let timeout = Duration::from_millis(INSTANCE_SIGNAL_TIMEOUT_MS);
+        for stream in listener.incoming() {
+            let Ok(mut stream) = stream else {
+                continue;
+            };
+            let _ = stream.set_read_timeout(Some(timeout));
+
+            let mut request = String::new();
+            let Ok(reader) = stream.try_clone() else {
+                continue;
+            };
+            if BufReader::new(reader).read_line(&mut request).is_err() || request.trim() != SHOW_REQUEST {
+                continue;
+            }
+
+            logger::info("Instance", "Another launch asked to show the window");
+            on_show();
+            let _ = writeln!(stream, "{}", SHOW_ACK);
+        }
+    });
+}
+
+/// Remove the lock file if this process holds it (called on shutdown).
+pub fn release() {
+    release_at(&instance_lock_path());
+}
+
+fn release_at(path: &Path) {
+    let held = fs::read_to_string(path)
+        .ok()
+        .and_then(|contents| LockInfo::parse(&contents))
+        .is_some_and(|info| info.pid == std::process::id());
+    if held {
+        let _ = fs::remove_file(path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    fn lock_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("powerclip_{}_{}.lock", name, std::process::id()))
+    }
+
+    #[test]
+    fn test_lock_info_parse() {
+        assert_eq!(LockInfo::parse("123 4567\n"), Some(LockInfo { pid: 123, port: 4567 }));
+        assert_eq!(LockInfo::parse("123"), None);
+        assert_eq!(LockInfo::parse(""), None);
+    }
+
+    #[test]
+    fn test_second_launch_signals_first() {
+        let path = lock_path("signal");
+        let _ = fs::remove_file(&path);
+
+        let Ok(Instance::Primary(listener)) = acquire_at(&path) else {
+            panic!("First launch should hold the lock");
+        };
+        let shown = Arc::new(AtomicUsize::new(0));
+        let shown_clone = shown.clone();
+        serve_with(listener, move || {
+            shown_clone.fetch_add(1, Ordering::SeqCst);
+        });
+
+        assert!(matches!(acquire_at(&path), Ok(Instance::Secondary)));
+        assert_eq!(shown.load(Ordering::SeqCst), 1);
+
+        release_at(&path);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_starting_instance_is_not_stale() {
+        let path = lock_path("starting");
+        let _ = fs::remove_file(&path);
+
+        // Bound but not served yet, as while the first launch runs its setup
+        let Ok(Instance::Primary(_listener)) = acquire_at(&path) else {
+            panic!("First launch should hold the lock");
+        };
+
+        assert!(matches!(acquire_at(&path), Ok(Instance::Secondary)));
+        let info = LockInfo::parse(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(info.pid, std::process::id());
+
+        release_at(&path);
+    }
+
+    #[test]
+    fn test_stale_lock_is_replaced() {
+        let path = lock_path("stale");
+        // A port nothing listens on any more, as after a crash
+        let dead_port = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap().local_addr().unwrap().port();
+        fs::write(&path, LockInfo { pid: 1, port: dead_port }.to_contents()).unwrap();
+
+        assert!(matches!(acquire_at(&path), Ok(Instance::Primary(_))));
+        let info = LockInfo::parse(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(info.pid, std::process::id());
+
+        release_at(&path);
+        assert!(!path.exists());
+    }
+}
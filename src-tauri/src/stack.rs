@@ -0,0 +1,120 @@
+//! Paste stack - Copy several items, then paste them back in order
+//!
+//! While stack mode is on, every clipboard change queues the copied item's id;
+//! the stack hotkey pops the oldest one and pastes it. The queue lives in
+//! memory only and is cleared when stack mode is turned off.
+
+use std::collections::VecDeque;
+
+/// Ordered queue of history item ids, managed in `AppState`.
+#[derive(Debug, Default)]
+pub struct PasteStack {
+    enabled: bool,
+    items: VecDeque<i64>,
+    /// Hash of the clipboard content last seen by the monitor
+    last_seen_hash: Option<String>,
+}
+
+impl PasteStack {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Turn stack mode on or off. Turning it off drops all queued items.
+    pub fn set_enabled(&mut self, enabled: bool) {
+        if self.enabled == enabled {
+            return;
+        }
+        self.enabled = enabled;
+        self.items.clear();
+        // Content already on the clipboard when stack mode starts isn't a new copy
+        self.last_seen_hash = None;
+    }
+
+    /// Record the clipboard content seen by the monitor.
+    ///
+    /// Returns true when it is a new copy that should be queued. The monitor
+    /// re-reads unchanged content on every poll, so only hash changes count.
+    pub fn observe(&mut self, hash: &str) -> bool {
+        if !self.enabled || self.last_seen_hash.as_deref() == Some(hash) {
+            return false;
+        }
+        let first = self.last_seen_hash.is_none();
+        self.last_seen_hash = Some(hash.to_string());
+        !first
+    }
+
+    /// Mark content we are about to write ourselves, so it isn't queued again.
+    pub fn mark_written(&mut self, hash: &str) {
+        self.last_seen_hash = Some(hash.to_string());
+    }
+
+    pub fn push(&mut self, id: i64) {
+        self.items.push_back(id);
+    }
+
+    /// Take the oldest queued item id.
+    pub fn pop(&mut self) -> Option<i64> {
+        self.items.pop_front()
+    }
+
+    pub fn clear(&mut self) {
+        self.items.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pops_in_copy_order() {
+        let mut stack = PasteStack::default();
+        stack.set_enabled(true);
+        stack.push(1);
+        stack.push(2);
+        stack.push(3);
+
+        assert_eq!(stack.pop(), Some(1));
+        assert_eq!(stack.pop(), Some(2));
+        assert_eq!(stack.len(), 1);
+        stack.clear();
+        assert!(stack.is_empty());
+        assert_eq!(stack.pop(), None);
+    }
+
+    #[test]
+    fn test_observe_only_counts_changes() {
+        let mut stack = PasteStack::default();
+        assert!(!stack.observe("a"), "disabled stack ignores copies");
+
+        stack.set_enabled(true);
+        assert!(!stack.observe("a"), "content present when enabling is not queued");
+        assert!(!stack.observe("a"));
+        assert!(stack.observe("b"));
+        assert!(!stack.observe("b"));
+
+        stack.mark_written("c");
+        assert!(!stack.observe("c"), "our own writes are not queued");
+        assert!(stack.observe("a"));
+    }
+
+    #[test]
+    fn test_disabling_clears_queue() {
+        let mut stack = PasteStack::default();
+        stack.set_enabled(true);
+        stack.push(1);
+
+        stack.set_enabled(false);
+        assert!(!stack.is_enabled());
+        assert!(stack.is_empty());
+    }
+}
@@ -0,0 +1,366 @@
+//! Tray module - System tray icon and menu
+
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use tauri::{
+    image::Image as TauriImage,
+    menu::{CheckMenuItemBuilder, Menu, MenuBuilder, SubmenuBuilder},
+    tray::TrayIconBuilder,
+    AppHandle, Emitter, Manager, Wry,
+};
+
+use crate::config::{APP_NAME, TRAY_ID, TRAY_LABEL_MAX_CHARS, TRAY_RECENT_ITEMS};
+use crate::db::{self, ClipboardItem};
+use crate::{logger, monitor, shutdown, window};
+
+/// Menu id prefix for recent-item entries ("recent:<item id>")
+const RECENT_ITEM_PREFIX: &str = "recent:";
+
+/// What a left click on the tray icon does (`tray_left_click` setting).
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[repr(u8)]
+pub enum LeftClickAction {
+    ToggleWindow = 0,
+    ShowMenu = 1,
+    Nothing = 2,
+}
+
+impl LeftClickAction {
+    /// Parse the setting value, defaulting to `ToggleWindow` for unknown values.
+    pub fn from_setting(value: &str) -> Self {
+        match value {
+            "show_menu" => Self::ShowMenu,
+            "none" => Self::Nothing,
+            _ => Self::ToggleWindow,
+        }
+    }
+
+    fn from_u8(value: u8) -> Self {
+        match value {
+            1 => Self::ShowMenu,
+            2 => Self::Nothing,
+            _ => Self::ToggleWindow,
+        }
+    }
+}
+
+/// Current left-click action (hot-reloadable from settings).
+static LEFT_CLICK_ACTION: AtomicU8 = AtomicU8::new(LeftClickAction::ToggleWindow as u8);
+
+/// Tray icon for the `tray_icon_theme` setting, and whether it is a template image.
+///
+/// "light"/"dark" pick a monochrome glyph that contrasts with a light/dark tray.
+/// "auto" uses the app icon, except on macOS where the glyph is a template
+/// image that the menu bar recolors for its current appearance.
+fn tray_icon(theme: &str) -> Result<(TauriImage<'static>, bool), String> {
+    let (bytes, template): (&'static [u8], bool) = match theme {
+        "light" => (include_bytes!("../../icons/tray-light.png"), false),
+        "dark" => (include_bytes!("../../icons/tray-dark.png"), false),
+        _ if cfg!(target_os = "macos") => (include_bytes!("../../icons/tray-light.png"), true),
+        _ => (include_bytes!("../../icons/icon.png"), false),
+    };
+    let icon = TauriImage::from_bytes(bytes).map_err(|e| e.to_string())?;
+    Ok((icon, template))
+}
+
+/// Apply the tray settings (left-click action and icon theme).
+pub fn apply_settings(app: &AppHandle, settings: &crate::app_settings::AppSettings) {
+    let action = LeftClickAction::from_setting(&settings.tray_left_click);
+    LEFT_CLICK_ACTION.store(action as u8, Ordering::Relaxed);
+
+    let Some(tray) = app.tray_by_id(TRAY_ID) else {
+        return;
+    };
+
+    if let Err(e) = tray.set_show_menu_on_left_click(action == LeftClickAction::ShowMenu) {
+        logger::warning("Tray", &format!("Failed to set left-click behavior: {}", e));
+    }
+
+    match tray_icon(&settings.tray_icon_theme) {
+        Ok((icon, template)) => {
+            let _ = tray.set_icon(Some(icon));
+            let _ = tray.set_icon_as_template(template);
+        }
+        Err(e) => logger::warning("Tray", &format!("Failed to load tray icon: {}", e)),
+    }
+}
+
+/// Initialize system tray.
+///
+/// Starts with default behavior; `apply_settings` is called once settings are loaded.
+pub fn setup_tray(app: &tauri::App) -> Result<(), String> {
+    let (icon, template) = tray_icon("auto")?;
+
+    let tray_menu = build_menu(app.handle())?;
+
+    let _tray = TrayIconBuilder::with_id(TRAY_ID)
+        .icon(icon)
+        .icon_as_template(template)
+        .menu(&tray_menu)
+        .tooltip(format!("{} - Clipboard History", APP_NAME))
+        .show_menu_on_left_click(false)
+        .on_menu_event(move |app, event| {
+            let id = event.id.as_ref();
+            match id {
+                "show" => {
+                    if let Some(window) = app.get_webview_window("main") {
+                        let _ = window::show_and_notify(app, &window);
+                    }
+                }
+                "settings" => {
+                    // Open settings file in default editor
+                    let settings_path = crate::config::settings_path();
+                    if let Err(e) = open::that(settings_path) {
+                        logger::error("Tray", &format!("Failed to open settings: {}", e));
+                    }
+                }
+                "pause_recording" => {
+                    // Disabled in the menu, but a stale menu could still send it
+                    if crate::config::data_dir_moved() {
+                        return;
+                    }
+                    let paused = !monitor::is_paused();
+                    monitor::set_paused(paused);
+                    refresh_menu(app);
+                }
+                "clear_history_confirm" => clear_history(app),
+                "quit" => shutdown::quit(app),
+                _ => {
+                    if let Some(item_id) = id.strip_prefix(RECENT_ITEM_PREFIX) {
+                        if let Ok(item_id) = item_id.parse::<i64>() {
+                            copy_recent_item(app, item_id);
+                        }
+                    }
+                }
+            }
+        })
+        .on_tray_icon_event(|tray, event| {
+            if let tauri::tray::TrayIconEvent::Click {
+                button: tauri::tray::MouseButton::Left,
+                button_state: tauri::tray::MouseButtonState::Up,
+                ..
+            } = event
+            {
+                let action = LeftClickAction::from_u8(LEFT_CLICK_ACTION.load(Ordering::Relaxed));
+                if action != LeftClickAction::ToggleWindow {
+                    return;
+                }
+                if let Some(window) = tray.app_handle().get_webview_window("main") {
+                    if window.is_visible().unwrap_or(false) {
+                        let _ = window::hide(&window);
+                    } else {
+                        let _ = window::show_and_notify(tray.app_handle(), &window);
+                    }
+                }
+            }
+        })
+        .build(app)
+        .map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+/// Rebuild the tray menu so the recent items and pause state are current.
+///
+/// Locks the database, so callers must not hold the connection lock.
+pub fn refresh_menu(app: &AppHandle) {
+    let Some(tray) = app.tray_by_id(TRAY_ID) else {
+        return;
+    };
+
+    match build_menu(app) {
+        Ok(menu) => {
+            if let Err(e) = tray.set_menu(Some(menu)) {
+                logger::warning("Tray", &format!("Failed to update menu: {}", e));
+            }
+        }
+        Err(e) => logger::warning("Tray", &format!("Failed to build menu: {}", e)),
+    }
+}
+
+fn build_menu(app: &AppHandle) -> Result<Menu<Wry>, String> {
+    let recent_items = app
+        .try_state::<crate::DatabaseState>()
+        .and_then(|state| {
+            let conn = state.conn.lock().ok()?;
+            db::get_history(&conn, TRAY_RECENT_ITEMS, None).ok()
+        })
+        .unwrap_or_default();
+
+    let mut recent = SubmenuBuilder::new(app, "Recent Items");
+    if recent_items.is_empty() {
+        recent = recent.item(
+            &tauri::menu::MenuItemBuilder::new("No items")
+                .enabled(false)
+                .build(app)
+                .map_err(|e| e.to_string())?,
+        );
+    }
+    for item in &recent_items {
+        recent = recent.text(format!("{}{}", RECENT_ITEM_PREFIX, item.id), menu_label(item));
+    }
+    let recent = recent.build().map_err(|e| e.to_string())?;
+
+    // Recording can't resume until PowerClip restarts with the moved data folder
+    let moved = crate::config::data_dir_moved();
+    let pause_label = if moved { "Recording Paused (restart to use the moved data)" } else { "Pause Recording" };
+    let pause = CheckMenuItemBuilder::with_id("pause_recording", pause_label)
+        .checked(monitor::is_paused())
+        .enabled(!moved)
+        .build(app)
+        .map_err(|e| e.to_string())?;
+
+    // Clearing is behind a submenu so it can't be triggered by a single misclick
+    let clear = SubmenuBuilder::new(app, "Clear History")
+        .text("clear_history_confirm", "Clear All (keeps favorites)")
+        .build()
+        .map_err(|e| e.to_string())?;
+
+    MenuBuilder::new(app)
+        .text("show", "Show Window")
+        .item(&recent)
+        .separator()
+        .item(&pause)
+        .item(&clear)
+        .text("settings", "Open Settings")
+        .separator()
+        .text("quit", "Quit")
+        .build()
+        .map_err(|e| e.to_string())
+}
+
+/// Short single-line label for a history item in the tray menu.
+fn menu_label(item: &ClipboardItem) -> String {
+    if item.is_concealed {
+        return "[Concealed]".to_string();
+    }
+
+    match item.item_type.as_str() {
+        "image" => "[Image]".to_string(),
+        "file" => {
+            let paths: Vec<String> = serde_json::from_str(&item.content).unwrap_or_default();
+            let name = paths
+                .first()
+                .and_then(|p| std::path::Path::new(p).file_name())
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default();
+            match paths.len() {
+                0 | 1 => format!("[File] {}", name),
+                n => format!("[Files] {} +{}", name, n - 1),
+            }
+        }
+        _ => {
+            let line = item.content.trim().lines().next().unwrap_or("").trim();
+            match line.char_indices().nth(TRAY_LABEL_MAX_CHARS) {
+                Some((end, _)) => format!("{}…", &line[..end]),
+                None => line.to_string(),
+            }
+        }
+    }
+}
+
+fn copy_recent_item(app: &AppHandle, item_id: i64) {
+    let item = app.try_state::<crate::DatabaseState>().and_then(|state| {
+        let conn = state.conn.lock().ok()?;
+        db::get_item_by_id(&conn, item_id).ok()?
+    });
+
+    let Some(item) = item else {
+        logger::warning("Tray", &format!("Recent item {} no longer exists", item_id));
+        refresh_menu(app);
+        return;
+    };
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = crate::commands::history::copy_item(app, item).await {
+            logger::error("Tray", &format!("Failed to copy item: {}", e));
+        }
+    });
+}
+
+fn clear_history(app: &AppHandle) {
+    let Some(state) = app.try_state::<crate::DatabaseState>() else {
+        return;
+    };
+
+    let deleted = match state.conn.lock() {
+        Ok(conn) => db::clear_history(&conn),
+        Err(e) => {
+            logger::error("Tray", &format!("Failed to lock database: {}", e));
+            return;
+        }
+    };
+
+    match deleted {
+        Ok(ids) => {
+            if let Some(sem_state) = app.try_state::<crate::semantic::SemanticState>() {
+                if let Ok(mut index) = sem_state.index.write() {
+                    for id in &ids {
+                        index.remove(*id);
+                    }
+                }
+            }
+            crate::window::forget_selected_items(&ids);
+            logger::info("Tray", &format!("Cleared {} history items", ids.len()));
+            let _ = app.emit("powerclip:history-cleared", ids.len());
+        }
+        Err(e) => logger::error("Tray", &format!("Failed to clear history: {}", e)),
+    }
+
+    refresh_menu(app);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(item_type: &str, content: &str) -> ClipboardItem {
+        ClipboardItem {
+            id: 1,
+            item_type: item_type.to_string(),
+            content: content.to_string(),
+            hash: String::new(),
+            created_at: String::new(),
+            is_favorited: false,
+            is_concealed: false,
+            code_language: None,
+            use_count: 0,
+            last_used_at: None,
+            created_at_relative: None,
+        }
+    }
+
+    #[test]
+    fn test_left_click_action_from_setting() {
+        assert_eq!(LeftClickAction::from_setting("toggle_window"), LeftClickAction::ToggleWindow);
+        assert_eq!(LeftClickAction::from_setting("show_menu"), LeftClickAction::ShowMenu);
+        assert_eq!(LeftClickAction::from_setting("none"), LeftClickAction::Nothing);
+        assert_eq!(LeftClickAction::from_setting("bogus"), LeftClickAction::ToggleWindow);
+
+        for action in [LeftClickAction::ToggleWindow, LeftClickAction::ShowMenu, LeftClickAction::Nothing] {
+            assert_eq!(LeftClickAction::from_u8(action as u8), action);
+        }
+    }
+
+    #[test]
+    fn test_menu_label_text() {
+        assert_eq!(menu_label(&item("text", "  hello\nworld")), "hello");
+
+        let long = "x".repeat(TRAY_LABEL_MAX_CHARS + 5);
+        let label = menu_label(&item("text", &long));
+        assert_eq!(label.chars().count(), TRAY_LABEL_MAX_CHARS + 1);
+        assert!(label.ends_with('…'));
+    }
+
+    #[test]
+    fn test_menu_label_other_types() {
+        assert_eq!(menu_label(&item("image", "images/abc.png")), "[Image]");
+        assert_eq!(menu_label(&item("file", r#"["/tmp/a.txt"]"#)), "[File] a.txt");
+        assert_eq!(menu_label(&item("file", r#"["/tmp/a.txt","/tmp/b.txt"]"#)), "[Files] a.txt +1");
+
+        let mut concealed = item("text", "secret");
+        concealed.is_concealed = true;
+        assert_eq!(menu_label(&concealed), "[Concealed]");
+    }
+}
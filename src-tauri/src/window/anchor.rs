@@ -0,0 +1,179 @@
+//! Window anchoring - Place the window at a fixed spot of the active monitor
+//!
+//! With `window_anchor` set to anything but "free", the saved position is
+//! ignored and the window is placed in the monitor's work area (the part not
+//! covered by the taskbar or menu bar) every time it is shown, so resolution
+//! and monitor changes are picked up on the next show. The monitor is the one
+//! pinned with `target_monitor`, or else the one under the cursor.
+
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use tauri::{PhysicalPosition, PhysicalSize};
+
+use crate::config::WINDOW_ANCHOR_MARGIN;
+use crate::window::monitors;
+
+/// Where the window is placed on show (`window_anchor` setting).
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[repr(u8)]
+pub enum WindowAnchor {
+    /// Restore the saved position
+    Free = 0,
+    Center = 1,
+    TopLeft = 2,
+    TopRight = 3,
+    BottomLeft = 4,
+    BottomRight = 5,
+}
+
+impl WindowAnchor {
+    /// Parse the setting value ("top_left" and "top-left" both work),
+    /// defaulting to `Free` for unknown values.
+    pub fn from_setting(value: &str) -> Self {
+        match value.replace('-', "_").as_str() {
+            "center" => Self::Center,
+            "top_left" => Self::TopLeft,
+            "top_right" => Self::TopRight,
+            "bottom_left" => Self::BottomLeft,
+            "bottom_right" => Self::BottomRight,
+            _ => Self::Free,
+        }
+    }
+
+    fn from_u8(value: u8) -> Self {
+        match value {
+            1 => Self::Center,
+            2 => Self::TopLeft,
+            3 => Self::TopRight,
+            4 => Self::BottomLeft,
+            5 => Self::BottomRight,
+            _ => Self::Free,
+        }
+    }
+}
+
+/// Current anchor (hot-reloadable from settings).
+static ANCHOR: AtomicU8 = AtomicU8::new(WindowAnchor::Free as u8);
+
+/// Update the window anchor (called when settings change).
+pub fn set_anchor(anchor: WindowAnchor) {
+    ANCHOR.store(anchor as u8, Ordering::Relaxed);
+}
+
+/// Current window anchor.
+pub fn anchor() -> WindowAnchor {
+    WindowAnchor::from_u8(ANCHOR.load(Ordering::Relaxed))
+}
+
+/// Top-left position for a window of `size` anchored in `area`, both in
+/// physical pixels. `margin` keeps corner anchors off the area's edges;
+/// a window larger than the area is pinned to its top-left corner.
+pub fn anchored_position(
+    anchor: WindowAnchor,
+    area_position: PhysicalPosition<i32>,
+    area_size: PhysicalSize<u32>,
+    size: PhysicalSize<u32>,
+    margin: i32,
+) -> Option<PhysicalPosition<i32>> {
+    let free_x = area_size.width as i32 - size.width as i32;
+    let free_y = area_size.height as i32 - size.height as i32;
+    let margin_x = margin.min(free_x / 2).max(0);
+    let margin_y = margin.min(free_y / 2).max(0);
+
+    let (x, y) = match anchor {
+        WindowAnchor::Free => return None,
+        WindowAnchor::Center => (free_x / 2, free_y / 2),
+        WindowAnchor::TopLeft => (margin_x, margin_y),
+        WindowAnchor::TopRight => (free_x - margin_x, margin_y),
+        WindowAnchor::BottomLeft => (margin_x, free_y - margin_y),
+        WindowAnchor::BottomRight => (free_x - margin_x, free_y - margin_y),
+    };
+    Some(PhysicalPosition::new(area_position.x + x.max(0), area_position.y + y.max(0)))
+}
+
+/// Position `window` before it is shown.
+///
+/// Anchored windows move to their spot on the pinned monitor, or on the one
+/// under the cursor (falling back to the window's current monitor). Free
+/// windows keep their position, pulled inside the pinned monitor if there is one.
+pub fn apply(app: &tauri::AppHandle, window: &tauri::WebviewWindow) -> Result<(), String> {
+    let anchor = anchor();
+    let pinned = monitors::target_monitor(app);
+    if anchor == WindowAnchor::Free && pinned.is_none() {
+        return Ok(());
+    }
+
+    let monitor = pinned.or_else(|| {
+        let cursor = app.cursor_position().ok()?;
+        app.monitor_from_point(cursor.x, cursor.y).ok().flatten()
+    });
+    let Some(monitor) = monitor.or(window.current_monitor().map_err(|e| e.to_string())?) else {
+        return Ok(());
+    };
+
+    // The window takes the target monitor's scale once moved there
+    let scale = monitor.scale_factor() / window.scale_factor().map_err(|e| e.to_string())?;
+    let size = window.outer_size().map_err(|e| e.to_string())?;
+    let size = PhysicalSize::new(
+        (size.width as f64 * scale).round() as u32,
+        (size.height as f64 * scale).round() as u32,
+    );
+    let margin = (WINDOW_ANCHOR_MARGIN * monitor.scale_factor()).round() as i32;
+
+    let area = monitor.work_area();
+    let position = match anchored_position(anchor, area.position, area.size, size, margin) {
+        Some(position) => position,
+        None => {
+            let current = window.outer_position().map_err(|e| e.to_string())?;
+            monitors::clamp_position(current, size, area.position, area.size)
+        }
+    };
+    window.set_position(position).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn place(anchor: WindowAnchor, size: (u32, u32)) -> Option<(i32, i32)> {
+        anchored_position(
+            anchor,
+            PhysicalPosition::new(100, 50),
+            PhysicalSize::new(1000, 800),
+            PhysicalSize::new(size.0, size.1),
+            10,
+        )
+        .map(|p| (p.x, p.y))
+    }
+
+    #[test]
+    fn test_from_setting() {
+        assert_eq!(WindowAnchor::from_setting("bottom_right"), WindowAnchor::BottomRight);
+        assert_eq!(WindowAnchor::from_setting("top-left"), WindowAnchor::TopLeft);
+        assert_eq!(WindowAnchor::from_setting("center"), WindowAnchor::Center);
+        assert_eq!(WindowAnchor::from_setting("free"), WindowAnchor::Free);
+        assert_eq!(WindowAnchor::from_setting("somewhere"), WindowAnchor::Free);
+        for anchor in [WindowAnchor::Center, WindowAnchor::BottomLeft, WindowAnchor::TopRight] {
+            assert_eq!(WindowAnchor::from_u8(anchor as u8), anchor);
+        }
+    }
+
+    #[test]
+    fn test_anchored_position() {
+        assert_eq!(place(WindowAnchor::Free, (400, 300)), None);
+        assert_eq!(place(WindowAnchor::TopLeft, (400, 300)), Some((110, 60)));
+        assert_eq!(place(WindowAnchor::TopRight, (400, 300)), Some((690, 60)));
+        assert_eq!(place(WindowAnchor::BottomLeft, (400, 300)), Some((110, 540)));
+        assert_eq!(place(WindowAnchor::BottomRight, (400, 300)), Some((690, 540)));
+        assert_eq!(place(WindowAnchor::Center, (400, 300)), Some((400, 300)));
+    }
+
+    #[test]
+    fn test_anchored_position_stays_in_area() {
+        // Barely fits: the margin shrinks instead of pushing the window off the area
+        assert_eq!(place(WindowAnchor::BottomRight, (990, 800)), Some((105, 50)));
+        // Too large: pinned to the top-left corner
+        assert_eq!(place(WindowAnchor::BottomRight, (1200, 900)), Some((100, 50)));
+        assert_eq!(place(WindowAnchor::Center, (1200, 900)), Some((100, 50)));
+    }
+}
@@ -1,6 +1,7 @@
 //! Window commands - Tauri command handlers for window operations
 
 use crate::window::config::WindowConfig;
+use crate::window::monitors::MonitorInfo;
 
 /// Save current window position and size.
 #[tauri::command]
@@ -13,6 +14,7 @@ pub async fn save_window_state(window: tauri::WebviewWindow) -> Result<(), Strin
         y: position.y,
         width: size.width,
         height: size.height,
+        scale_factor: Some(window.scale_factor().map_err(|e| e.to_string())?),
     };
 
     crate::window::config::save_window_config(&config)
@@ -33,6 +35,7 @@ pub async fn get_window_state(window: tauri::WebviewWindow) -> Result<WindowConf
         y: (position.y as f64 / scale) as i32,
         width: (size.width as f64 / scale).round() as u32,
         height: (size.height as f64 / scale).round() as u32,
+        scale_factor: Some(scale),
     })
 }
 
@@ -50,6 +53,27 @@ pub async fn resize_window(window: tauri::WebviewWindow, width: u32, height: u32
     window.set_size(size).map_err(|e| e.to_string())
 }
 
+/// Set window opacity natively so changes take effect without a reload.
+#[tauri::command]
+pub async fn set_window_opacity(window: tauri::WebviewWindow, value: f64) -> Result<(), String> {
+    crate::window::apply_opacity(&window, value)
+}
+
+/// Get the effective UI theme ("dark" or "light").
+///
+/// Honors the `theme_override` setting, falling back to the OS appearance.
+#[tauri::command]
+pub async fn get_system_theme(window: tauri::WebviewWindow) -> Result<String, String> {
+    let settings = crate::app_settings::load_settings_simple().unwrap_or_default();
+    Ok(crate::window::resolve_theme(&window, &settings.theme_override).to_string())
+}
+
+/// List connected displays (for the `target_monitor` setting).
+#[tauri::command]
+pub async fn list_monitors(app: tauri::AppHandle) -> Result<Vec<MonitorInfo>, String> {
+    crate::window::monitors::list(&app)
+}
+
 /// Hide window and restore focus to previous application.
 #[tauri::command]
 pub async fn hide_window(app: tauri::AppHandle) -> Result<(), String> {
@@ -59,3 +83,11 @@ pub async fn hide_window(app: tauri::AppHandle) -> Result<(), String> {
     }
     Ok(())
 }
+
+/// Record the selected item so it can be restored when the window reopens
+/// (`remember_selection`).
+#[tauri::command]
+pub async fn set_selected_item(item_id: Option<i64>) -> Result<(), String> {
+    crate::window::set_selected_item(item_id);
+    Ok(())
+}
@@ -2,20 +2,25 @@
 //!
 //! This module handles persistence of window geometry (position and size)
 //! using a JSON configuration file in the application data directory.
+//! Geometry is stored in physical pixels together with the display's scale
+//! factor, so the size can be converted when restoring on another display.
 
 use serde::{Deserialize, Serialize};
 use std::fs;
 
-use crate::config::window_config_path;
+use crate::config::{window_config_path, write_atomic, DEFAULT_WINDOW_HEIGHT, DEFAULT_WINDOW_WIDTH};
 use crate::logger;
 
 /// Window geometry configuration
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct WindowConfig {
     pub x: i32,
     pub y: i32,
     pub width: u32,
     pub height: u32,
+    /// Scale factor of the display when saved; `None` in files written before it was recorded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scale_factor: Option<f64>,
 }
 
 impl Default for WindowConfig {
@@ -23,8 +28,26 @@ impl Default for WindowConfig {
         Self {
             x: 100,
             y: 100,
-            width: 450,
-            height: 400,
+            width: DEFAULT_WINDOW_WIDTH,
+            height: DEFAULT_WINDOW_HEIGHT,
+            scale_factor: None,
+        }
+    }
+}
+
+impl WindowConfig {
+    /// Physical size to restore on a display with `scale_factor`, keeping the
+    /// saved logical size. Sizes without a recorded scale are used as-is.
+    pub fn size_at_scale(&self, scale_factor: f64) -> (u32, u32) {
+        match self.scale_factor {
+            Some(saved) if saved > 0.0 => {
+                let ratio = scale_factor / saved;
+                (
+                    (self.width as f64 * ratio).round() as u32,
+                    (self.height as f64 * ratio).round() as u32,
+                )
+            }
+            _ => (self.width, self.height),
         }
     }
 }
@@ -32,6 +55,7 @@ impl Default for WindowConfig {
 /// Save window configuration to file
 #[inline]
 pub fn save_window_config(config: &WindowConfig) -> Result<(), String> {
+    crate::config::check_data_dir_current()?;
     let config_path = window_config_path();
 
     // Ensure parent directory exists
@@ -41,7 +65,7 @@ pub fn save_window_config(config: &WindowConfig) -> Result<(), String> {
 
     // Serialize and write
     let json = serde_json::to_string_pretty(config).map_err(|e| e.to_string())?;
-    fs::write(&config_path, json).map_err(|e| e.to_string())?;
+    write_atomic(&config_path, json.as_bytes()).map_err(|e| e.to_string())?;
 
     logger::debug(
         "WindowConfig",
@@ -73,3 +97,32 @@ pub fn load_window_config() -> Result<WindowConfig, String> {
 
     Ok(config)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn saved_at(scale_factor: Option<f64>) -> WindowConfig {
+        WindowConfig { x: 0, y: 0, width: 900, height: 800, scale_factor }
+    }
+
+    #[test]
+    fn test_size_at_scale() {
+        // Saved on a 2x display, restored on a 1x display
+        assert_eq!(saved_at(Some(2.0)).size_at_scale(1.0), (450, 400));
+        assert_eq!(saved_at(Some(2.0)).size_at_scale(2.0), (900, 800));
+        assert_eq!(saved_at(Some(1.0)).size_at_scale(1.5), (1350, 1200));
+        // Older files have no scale and keep their physical size
+        assert_eq!(saved_at(None).size_at_scale(1.0), (900, 800));
+    }
+
+    #[test]
+    fn test_legacy_config_deserializes() {
+        let config: WindowConfig = serde_json::from_str(r#"{"x":10,"y":20,"width":450,"height":400}"#).unwrap();
+        assert_eq!(config.scale_factor, None);
+
+        let json = serde_json::to_string(&saved_at(Some(2.0))).unwrap();
+        let config: WindowConfig = serde_json::from_str(&json).unwrap();
+        assert_eq!(config.scale_factor, Some(2.0));
+    }
+}
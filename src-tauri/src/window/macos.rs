@@ -64,3 +64,16 @@ pub fn get_frontmost_bundle_id() -> Option<String> {
         Some(std::ffi::CStr::from_ptr(utf8).to_string_lossy().into_owned())
     }
 }
+
+/// Set the alpha value of an NSWindow (0.0 = transparent, 1.0 = opaque).
+///
+/// Must be called on the main thread.
+pub fn set_window_alpha(ns_window: *mut std::ffi::c_void, alpha: f64) {
+    if ns_window.is_null() {
+        return;
+    }
+    unsafe {
+        let ns_window = ns_window as *mut AnyObject;
+        let _: () = msg_send![ns_window, setAlphaValue: alpha];
+    }
+}
@@ -1,7 +1,9 @@
 //! Window module - Window management and setup
 
+pub mod anchor;
 pub mod commands;
 pub mod config;
+pub mod monitors;
 
 #[cfg(target_os = "macos")]
 pub mod macos;
@@ -9,12 +11,113 @@ pub mod macos;
 #[cfg(target_os = "windows")]
 pub mod windows;
 
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Mutex;
 
+use serde::Serialize;
+
+use crate::app_settings::AppSettings;
 use crate::logger;
 use crate::window::config::WindowConfig;
 
 static PREVIOUS_APP_BUNDLE_ID: Mutex<Option<String>> = Mutex::new(None);
+/// Whether resizing the window is saved (`remember_window_size`, hot-reloadable from settings).
+static REMEMBER_SIZE: AtomicBool = AtomicBool::new(true);
+
+/// Whether the selection is restored when the window reopens (`remember_selection`, hot-reloadable).
+static REMEMBER_SELECTION: AtomicBool = AtomicBool::new(false);
+/// Item the user last selected in the window, reported by the frontend.
+static SELECTED_ITEM: Mutex<Option<i64>> = Mutex::new(None);
+
+/// Payload of `powerclip:window-shown`.
+#[derive(Debug, Clone, Serialize)]
+pub struct WindowShown {
+    /// Item to select, when `remember_selection` is on and it still exists
+    pub selected_id: Option<i64>,
+}
+
+/// Update whether window resizes are saved (called when settings change).
+pub fn set_remember_size(enabled: bool) {
+    REMEMBER_SIZE.store(enabled, Ordering::Relaxed);
+}
+
+/// Update whether the selection is restored (called when settings change).
+pub fn set_remember_selection(enabled: bool) {
+    REMEMBER_SELECTION.store(enabled, Ordering::Relaxed);
+    if !enabled {
+        set_selected_item(None);
+    }
+}
+
+/// Record the item selected in the window.
+pub fn set_selected_item(item_id: Option<i64>) {
+    if let Ok(mut selected) = SELECTED_ITEM.lock() {
+        *selected = item_id;
+    }
+}
+
+/// Forget the remembered selection if it is one of `deleted` items.
+pub fn forget_selected_items(deleted: &[i64]) {
+    if let Ok(mut selected) = SELECTED_ITEM.lock() {
+        if selected.is_some_and(|id| deleted.contains(&id)) {
+            *selected = None;
+        }
+    }
+}
+
+/// Item to restore the selection to when the window is shown, if any.
+///
+/// Checked against the database, since some deletions don't report their ids
+/// (auto-cleanup, other programs writing to the database). If the database is
+/// busy the id is returned unchecked; the window falls back to the top item
+/// when it isn't in the list.
+fn selection_to_restore(app: &tauri::AppHandle) -> Option<i64> {
+    use tauri::Manager;
+
+    if !REMEMBER_SELECTION.load(Ordering::Relaxed) {
+        return None;
+    }
+    let id = SELECTED_ITEM.lock().ok().and_then(|selected| *selected)?;
+
+    let state = app.try_state::<crate::DatabaseState>()?;
+    // Runs on the main loop; don't wait behind a save, cleanup or VACUUM
+    let exists = state.conn.try_lock().ok().and_then(|conn| crate::db::item_exists(&conn, id).ok());
+    if exists == Some(false) {
+        forget_selected_items(&[id]);
+        return None;
+    }
+    Some(id)
+}
+
+/// Restore the saved window position, and the saved size if `remember_window_size`
+/// is on; otherwise size the window to `default_width` x `default_height`.
+///
+/// The saved size is converted to the scale of the display at the saved
+/// position, so it looks the same after moving between displays.
+pub fn restore_geometry(window: &tauri::WebviewWindow, settings: &AppSettings) {
+    use tauri::{LogicalSize, PhysicalPosition, PhysicalSize, Position, Size};
+
+    let saved = config::load_window_config().ok();
+    // Anchored windows are placed when shown
+    if let Some(config) = saved.as_ref().filter(|_| anchor::anchor() == anchor::WindowAnchor::Free) {
+        let _ = window.set_position(Position::Physical(PhysicalPosition::new(config.x, config.y)));
+    }
+
+    if !settings.remember_window_size {
+        let size = LogicalSize::new(settings.default_width as f64, settings.default_height as f64);
+        let _ = window.set_size(Size::Logical(size));
+    } else if let Some(config) = &saved {
+        let scale = window
+            .monitor_from_point(config.x as f64, config.y as f64)
+            .ok()
+            .flatten()
+            .map(|monitor| monitor.scale_factor())
+            .or_else(|| window.scale_factor().ok())
+            .unwrap_or(1.0);
+        let (width, height) = config.size_at_scale(scale);
+        let _ = window.set_size(Size::Physical(PhysicalSize::new(width, height)));
+    }
+}
 
 /// Hide window and restore focus to the previously active application.
 pub fn hide(window: &tauri::WebviewWindow) -> Result<(), String> {
@@ -56,6 +159,10 @@ pub fn show_and_notify(app: &tauri::AppHandle, window: &tauri::WebviewWindow) ->
         // This ensures proper rendering when awakened via global hotkey
     }
 
+    if let Err(e) = anchor::apply(app, window) {
+        logger::warning("Window", &format!("Failed to anchor window: {}", e));
+    }
+
     window.show().map_err(|e| {
         logger::error("Window", &format!("Failed to show window: {}", e));
         e.to_string()
@@ -71,7 +178,8 @@ pub fn show_and_notify(app: &tauri::AppHandle, window: &tauri::WebviewWindow) ->
     }
 
     use tauri::Emitter;
-    let _ = app.emit_to("main", "powerclip:window-shown", ());
+    let payload = WindowShown { selected_id: selection_to_restore(app) };
+    let _ = app.emit_to("main", "powerclip:window-shown", payload);
 
     Ok(())
 }
@@ -100,28 +208,37 @@ pub fn setup_window_behavior(app: &tauri::App) -> Result<(), String> {
         }
     });
 
-    // Save window geometry on move/resize
+    // Save window geometry on move/resize (resizes only with remember_window_size)
     let geometry_window = window.clone();
     window.on_window_event(move |event| {
         match event {
             tauri::WindowEvent::Moved(position) => {
+                // The anchor decides the position, so don't save it
+                if anchor::anchor() != anchor::WindowAnchor::Free {
+                    return;
+                }
                 if let Ok(size) = geometry_window.outer_size() {
                     let config = WindowConfig {
                         x: position.x,
                         y: position.y,
                         width: size.width,
                         height: size.height,
+                        scale_factor: geometry_window.scale_factor().ok(),
                     };
                     let _ = crate::window::config::save_window_config(&config);
                 }
             }
             tauri::WindowEvent::Resized(size) => {
+                if !REMEMBER_SIZE.load(Ordering::Relaxed) {
+                    return;
+                }
                 if let Ok(position) = geometry_window.outer_position() {
                     let config = WindowConfig {
                         x: position.x,
                         y: position.y,
                         width: size.width,
                         height: size.height,
+                        scale_factor: geometry_window.scale_factor().ok(),
                     };
                     let _ = crate::window::config::save_window_config(&config);
                 }
@@ -130,12 +247,89 @@ pub fn setup_window_behavior(app: &tauri::App) -> Result<(), String> {
         }
     });
 
+    // Re-anchor when the display configuration changes under the window
+    let anchor_window = window.clone();
+    let anchor_app = app.handle().clone();
+    window.on_window_event(move |event| {
+        if let tauri::WindowEvent::ScaleFactorChanged { .. } = event {
+            if anchor_window.is_visible().unwrap_or(false) {
+                let _ = anchor::apply(&anchor_app, &anchor_window);
+            }
+        }
+    });
+
+    // Forward OS appearance changes to the frontend (unless a theme is forced in settings)
+    let theme_app = app.handle().clone();
+    window.on_window_event(move |event| {
+        if let tauri::WindowEvent::ThemeChanged(theme) = event {
+            let settings = crate::app_settings::load_settings_simple().unwrap_or_default();
+            if settings.theme_override != "dark" && settings.theme_override != "light" {
+                let name = match theme {
+                    tauri::Theme::Dark => "dark",
+                    _ => "light",
+                };
+                logger::info("Window", &format!("System theme changed to {}", name));
+                use tauri::Emitter;
+                let _ = theme_app.emit("powerclip:theme-changed", name);
+            }
+        }
+    });
+
     let _ = window.set_skip_taskbar(true);
     logger::info("Window", "Window behavior configured");
 
     Ok(())
 }
 
+/// Resolve the effective UI theme ("dark" or "light").
+///
+/// An explicit `theme_override` of "dark"/"light" wins; otherwise the OS
+/// appearance reported for the window is used.
+pub fn resolve_theme(window: &tauri::WebviewWindow, theme_override: &str) -> &'static str {
+    match theme_override {
+        "dark" => "dark",
+        "light" => "light",
+        _ => match window.theme() {
+            Ok(tauri::Theme::Dark) => "dark",
+            _ => "light",
+        },
+    }
+}
+
+/// Lowest opacity accepted from settings; anything below is clamped so the
+/// window can't become invisible.
+const MIN_WINDOW_OPACITY: f64 = 0.5;
+
+/// Apply window opacity natively (NSWindow alpha on macOS, layered window on Windows).
+///
+/// Values are clamped to `MIN_WINDOW_OPACITY..=1.0`. No-op on other platforms.
+#[allow(unused_variables)]
+pub fn apply_opacity(window: &tauri::WebviewWindow, opacity: f64) -> Result<(), String> {
+    let opacity = opacity.clamp(MIN_WINDOW_OPACITY, 1.0);
+
+    #[cfg(target_os = "macos")]
+    {
+        // AppKit requires the main thread; raw pointers aren't Send, so pass the address
+        let ns_window = window.ns_window().map_err(|e| e.to_string())? as usize;
+        window
+            .run_on_main_thread(move || {
+                macos::set_window_alpha(ns_window as *mut std::ffi::c_void, opacity);
+            })
+            .map_err(|e| e.to_string())?;
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        let hwnd = window.hwnd().map_err(|e| e.to_string())?;
+        if !windows::set_window_opacity(hwnd.0, opacity) {
+            return Err("Failed to set window opacity".to_string());
+        }
+    }
+
+    logger::debug("Window", &format!("Applied window opacity {:.2}", opacity));
+    Ok(())
+}
+
 /// Set up platform-specific window transparency.
 pub fn setup_window_transparency(app: &tauri::App) -> Result<(), String> {
     use tauri::Manager;
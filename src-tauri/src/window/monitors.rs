@@ -0,0 +1,116 @@
+//! Monitor selection - Which display the window appears on
+//!
+//! The `target_monitor` setting pins the window to a display by name. When
+//! that display is disconnected the primary monitor is used instead.
+
+use std::sync::Mutex;
+
+use serde::Serialize;
+use tauri::{PhysicalPosition, PhysicalSize};
+
+use crate::logger;
+
+/// Name of the pinned monitor, empty = not pinned (hot-reloadable from settings).
+static TARGET_MONITOR: Mutex<String> = Mutex::new(String::new());
+
+/// Update the pinned monitor (called when settings change).
+pub fn set_target_monitor(name: &str) {
+    if let Ok(mut target) = TARGET_MONITOR.lock() {
+        *target = name.to_string();
+    }
+}
+
+/// A connected display, in logical (CSS) pixels like the other window commands.
+#[derive(Debug, Clone, Serialize)]
+pub struct MonitorInfo {
+    /// Name to use for `target_monitor`
+    pub name: String,
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub scale_factor: f64,
+    pub is_primary: bool,
+}
+
+/// Describe all connected displays.
+pub fn list(app: &tauri::AppHandle) -> Result<Vec<MonitorInfo>, String> {
+    let primary = app.primary_monitor().map_err(|e| e.to_string())?;
+
+    let monitors = app.available_monitors().map_err(|e| e.to_string())?;
+    Ok(monitors
+        .iter()
+        .map(|monitor| {
+            let scale = monitor.scale_factor();
+            MonitorInfo {
+                name: monitor.name().cloned().unwrap_or_default(),
+                x: (monitor.position().x as f64 / scale) as i32,
+                y: (monitor.position().y as f64 / scale) as i32,
+                width: (monitor.size().width as f64 / scale).round() as u32,
+                height: (monitor.size().height as f64 / scale).round() as u32,
+                scale_factor: scale,
+                is_primary: primary.as_ref().is_some_and(|p| p.position() == monitor.position()),
+            }
+        })
+        .collect())
+}
+
+/// The pinned monitor, or the primary one if it is disconnected.
+/// `None` when no monitor is pinned.
+pub fn target_monitor(app: &tauri::AppHandle) -> Option<tauri::Monitor> {
+    let target = TARGET_MONITOR.lock().ok()?.clone();
+    if target.is_empty() {
+        return None;
+    }
+
+    let monitors = app.available_monitors().unwrap_or_default();
+    if let Some(monitor) = monitors.into_iter().find(|m| m.name() == Some(&target)) {
+        return Some(monitor);
+    }
+    logger::debug("Window", &format!("Monitor {:?} not connected, using the primary monitor", target));
+    app.primary_monitor().ok().flatten()
+}
+
+/// Move a window of `size` at `position` the least distance needed to lie
+/// inside the area (all in physical pixels). A window larger than the area
+/// is aligned with its top-left corner.
+pub fn clamp_position(
+    position: PhysicalPosition<i32>,
+    size: PhysicalSize<u32>,
+    area_position: PhysicalPosition<i32>,
+    area_size: PhysicalSize<u32>,
+) -> PhysicalPosition<i32> {
+    let max_x = area_position.x + area_size.width as i32 - size.width as i32;
+    let max_y = area_position.y + area_size.height as i32 - size.height as i32;
+    PhysicalPosition::new(
+        position.x.min(max_x).max(area_position.x),
+        position.y.min(max_y).max(area_position.y),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn clamp(position: (i32, i32), size: (u32, u32)) -> (i32, i32) {
+        let p = clamp_position(
+            PhysicalPosition::new(position.0, position.1),
+            PhysicalSize::new(size.0, size.1),
+            PhysicalPosition::new(1920, 0),
+            PhysicalSize::new(1280, 1024),
+        );
+        (p.x, p.y)
+    }
+
+    #[test]
+    fn test_clamp_position() {
+        // Already inside: unchanged
+        assert_eq!(clamp((2000, 100), (400, 300)), (2000, 100));
+        // On another monitor to the left: pulled onto the left edge
+        assert_eq!(clamp((100, 100), (400, 300)), (1920, 100));
+        // Hanging off the bottom-right corner
+        assert_eq!(clamp((3000, 900), (400, 300)), (2800, 724));
+        // Larger than the monitor
+        assert_eq!(clamp((3000, 900), (1500, 1100)), (1920, 0));
+    }
+}
@@ -4,8 +4,11 @@
 
 use std::ffi::c_void;
 
-use windows::Win32::Foundation::HWND;
-use windows::Win32::UI::WindowsAndMessaging::{BringWindowToTop, SetForegroundWindow};
+use windows::Win32::Foundation::{COLORREF, HWND};
+use windows::Win32::UI::WindowsAndMessaging::{
+    BringWindowToTop, GetWindowLongPtrW, SetForegroundWindow, SetLayeredWindowAttributes,
+    SetWindowLongPtrW, GWL_EXSTYLE, LWA_ALPHA, WS_EX_LAYERED,
+};
 
 use crate::logger;
 
@@ -31,3 +34,22 @@ pub fn bring_to_foreground(hwnd: *mut c_void) -> bool {
         success
     }
 }
+
+/// Apply window opacity using the layered-window API.
+/// `opacity` ranges from 0.0 (transparent) to 1.0 (opaque).
+pub fn set_window_opacity(hwnd: *mut c_void, opacity: f64) -> bool {
+    unsafe {
+        let hwnd = HWND(hwnd);
+        let ex_style = GetWindowLongPtrW(hwnd, GWL_EXSTYLE);
+        if ex_style & WS_EX_LAYERED.0 as isize == 0 {
+            SetWindowLongPtrW(hwnd, GWL_EXSTYLE, ex_style | WS_EX_LAYERED.0 as isize);
+        }
+
+        let alpha = (opacity * 255.0).round() as u8;
+        let result = SetLayeredWindowAttributes(hwnd, COLORREF(0), alpha, LWA_ALPHA);
+        if let Err(e) = &result {
+            logger::warning("Window", &format!("SetLayeredWindowAttributes failed: {}", e));
+        }
+        result.is_ok()
+    }
+}